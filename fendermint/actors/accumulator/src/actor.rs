@@ -2,17 +2,23 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+use std::collections::HashMap;
+
 use cid::Cid;
-use fendermint_actor_machine::{ConstructorParams, MachineActor};
+use fendermint_actor_machine::{ensure_metadata_allowed, ConstructorParams, MachineActor};
 use fil_actors_runtime::{
     actor_dispatch, actor_error,
     runtime::{ActorCode, Runtime},
-    ActorDowncast, ActorError, FIRST_EXPORTED_METHOD_NUMBER, INIT_ACTOR_ADDR,
+    ActorDowncast, ActorError, EventBuilder, FIRST_EXPORTED_METHOD_NUMBER, INIT_ACTOR_ADDR,
 };
-use fvm_ipld_encoding::ipld_block::IpldBlock;
+use fvm_ipld_encoding::{ipld_block::IpldBlock, strict_bytes::ByteBuf};
 use fvm_shared::{error::ExitCode, MethodNum};
 
-use crate::{Method, PushParams, PushReturn, State, ACCUMULATOR_ACTOR_NAME};
+use crate::{
+    AccumulatorError, GetParams, GetWithProofReturn, ImportLeavesParams, LeafCodec, Method,
+    PushCidParams, PushParams, PushReturn, RootParams, RootScheme, State, VerifyPeakParams,
+    ACCUMULATOR_ACTOR_NAME, MAX_EVENT_LENGTH, MAX_NAMESPACE_LENGTH,
+};
 
 #[cfg(feature = "fil-actor")]
 fil_actors_runtime::wasm_trampoline!(Actor);
@@ -22,43 +28,215 @@ pub struct Actor;
 impl Actor {
     fn constructor(rt: &impl Runtime, params: ConstructorParams) -> Result<(), ActorError> {
         rt.validate_immediate_caller_is(std::iter::once(&INIT_ACTOR_ADDR))?;
-        let state = State::new(rt.store(), params.creator, params.write_access).map_err(|e| {
-            e.downcast_default(
-                ExitCode::USR_ILLEGAL_STATE,
-                "failed to construct empty store",
-            )
-        })?;
+        ensure_metadata_allowed(&params.metadata)?;
+        let mut state =
+            State::new(rt.store(), params.creator, params.write_access).map_err(|e| {
+                e.downcast_default(
+                    ExitCode::USR_ILLEGAL_STATE,
+                    "failed to construct empty store",
+                )
+            })?;
+        state.metadata = params.metadata;
         rt.create(&state)
     }
 
     fn push(rt: &impl Runtime, params: PushParams) -> Result<PushReturn, ActorError> {
         Self::ensure_write_allowed(rt)?;
-        rt.transaction(|st: &mut State, rt| {
-            st.push(rt.store(), params.0).map_err(|e| {
-                e.downcast_default(ExitCode::USR_ILLEGAL_STATE, "failed to push object")
-            })
-        })
+        if params.data.is_empty() {
+            return Err(actor_error!(illegal_argument; "cannot push an empty object"));
+        }
+        if params.data.len() > MAX_EVENT_LENGTH {
+            return Err(actor_error!(
+                illegal_argument;
+                "object length {} exceeds maximum {}",
+                params.data.len(),
+                MAX_EVENT_LENGTH
+            ));
+        }
+        if params.namespace.len() > MAX_NAMESPACE_LENGTH {
+            return Err(actor_error!(
+                illegal_argument;
+                "namespace length {} exceeds maximum {}",
+                params.namespace.len(),
+                MAX_NAMESPACE_LENGTH
+            ));
+        }
+        if !params.namespace.is_empty() && !params.metadata.is_empty() {
+            return Err(actor_error!(
+                illegal_argument;
+                "leaf metadata is only kept in the default namespace"
+            ));
+        }
+        if rt.state::<State>()?.is_frozen() {
+            return Err(actor_error!(forbidden; "accumulator is frozen"));
+        }
+        let namespace = params.namespace;
+        let ret = rt.transaction(|st: &mut State, rt| {
+            st.count_push(rt.curr_epoch())
+                .map_err(|e| to_actor_error(e.into(), "failed to push object"))?;
+            let mut ret = st
+                .push_bytes_in(rt.store(), &namespace, params.data)
+                .map_err(|e| to_actor_error(e, "failed to push object"))?;
+            if params.with_proof {
+                ret.proof = Some(
+                    st.get_proof_in(rt.store(), &namespace, ret.index)
+                        .map_err(|e| to_actor_error(e, "failed to get proof"))?,
+                );
+            }
+            // A deduplicated push is a retry, the metadata went with the original push.
+            if !params.metadata.is_empty() && !ret.deduplicated {
+                st.set_leaf_metadata(rt.store(), ret.index, params.metadata)
+                    .map_err(|e| to_actor_error(e, "failed to set metadata"))?;
+            }
+            Ok(ret)
+        })?;
+        if !ret.deduplicated {
+            Self::emit_push(rt, &ret, &namespace)?;
+        }
+        Ok(ret)
+    }
+
+    fn push_cid(rt: &impl Runtime, params: PushCidParams) -> Result<PushReturn, ActorError> {
+        Self::ensure_write_allowed(rt)?;
+        if rt.state::<State>()?.is_frozen() {
+            return Err(actor_error!(forbidden; "accumulator is frozen"));
+        }
+        let ret = rt.transaction(|st: &mut State, rt| {
+            st.count_push(rt.curr_epoch())
+                .map_err(|e| to_actor_error(e.into(), "failed to push cid"))?;
+            st.push_cid(rt.store(), params.cid)
+                .map_err(|e| to_actor_error(e, "failed to push cid"))
+        })?;
+        if !ret.deduplicated {
+            Self::emit_push(rt, &ret, &[])?;
+        }
+        Ok(ret)
+    }
+
+    /// Pushes into the default namespace emit the same event as before namespaces existed,
+    /// the others add the namespace.
+    fn emit_push(rt: &impl Runtime, ret: &PushReturn, namespace: &[u8]) -> Result<(), ActorError> {
+        let mut event = EventBuilder::new()
+            .typ("push")
+            .field_indexed("index", &ret.index)
+            .field_indexed("root", &ret.root);
+        if !namespace.is_empty() {
+            event = event.field_indexed("namespace", &ByteBuf(namespace.to_vec()));
+        }
+        rt.emit_event(&event.build()?)
     }
 
-    fn get_leaf_at(rt: &impl Runtime, index: u64) -> Result<Option<Vec<u8>>, ActorError> {
+    /// Replay a batch of exported leaves, see [`State::import_leaves`].
+    ///
+    /// Replacing the leaves of a non-empty accumulator with `force` is reserved to the owner.
+    /// Every imported leaf counts against the push limit.
+    fn import_leaves(rt: &impl Runtime, params: ImportLeavesParams) -> Result<Cid, ActorError> {
+        if params.force {
+            Self::ensure_owner(rt)?;
+        } else {
+            Self::ensure_write_allowed(rt)?;
+        }
+        ensure_leaf_codec_supported(params.leaf_codec)?;
+        if let Some(leaf) = params
+            .leaves
+            .iter()
+            .find(|l| l.0.is_empty() || l.0.len() > MAX_EVENT_LENGTH)
+        {
+            return Err(actor_error!(
+                illegal_argument;
+                "imported leaf length {} must be between 1 and {}",
+                leaf.0.len(),
+                MAX_EVENT_LENGTH
+            ));
+        }
+        let start = params.start;
+        let count = params.leaves.len() as u64;
+        let root = rt.transaction(|st: &mut State, rt| {
+            for _ in 0..count {
+                st.count_push(rt.curr_epoch())
+                    .map_err(|e| to_actor_error(e.into(), "failed to import leaves"))?;
+            }
+            st.import_leaves(rt.store(), params)
+                .map_err(|e| to_actor_error(e, "failed to import leaves"))
+        })?;
+        rt.emit_event(
+            &EventBuilder::new()
+                .typ("import")
+                .field_indexed("start", &start)
+                .field_indexed("count", &count)
+                .field_indexed("root", &root)
+                .build()?,
+        )?;
+        Ok(root)
+    }
+
+    fn get_leaf_at(rt: &impl Runtime, params: GetParams) -> Result<Option<Vec<u8>>, ActorError> {
+        rt.validate_immediate_caller_accept_any()?;
+        let st: State = rt.state()?;
+        st.get_leaf_bytes_in(rt.store(), &params.namespace, params.index)
+            .map_err(|e| to_actor_error(e, "failed to get leaf"))
+    }
+
+    fn get_leaf_cid_at(rt: &impl Runtime, index: u64) -> Result<Option<Cid>, ActorError> {
+        rt.validate_immediate_caller_accept_any()?;
+        let st: State = rt.state()?;
+        st.get_leaf_cid_at(rt.store(), index)
+            .map_err(|e| to_actor_error(e, "failed to get leaf CID"))
+    }
+
+    fn get_with_proof(
+        rt: &impl Runtime,
+        index: u64,
+    ) -> Result<Option<GetWithProofReturn>, ActorError> {
+        rt.validate_immediate_caller_accept_any()?;
+        let st: State = rt.state()?;
+        st.get_with_proof(rt.store(), index)
+            .map_err(|e| to_actor_error(e, "failed to get leaf with proof"))
+    }
+
+    fn verify_peak(rt: &impl Runtime, params: VerifyPeakParams) -> Result<bool, ActorError> {
+        rt.validate_immediate_caller_accept_any()?;
+        let st: State = rt.state()?;
+        if params.peak_index >= st.peak_count() as u64 {
+            return Err(actor_error!(
+                illegal_argument;
+                "peak index {} out of range for {} peaks",
+                params.peak_index,
+                st.peak_count()
+            ));
+        }
+        st.verify_peak(
+            rt.store(),
+            params.peak_index,
+            &params.expected_cid,
+            &params.leaves,
+        )
+        .map_err(|e| to_actor_error(e, "failed to verify peak"))
+    }
+
+    fn get_leaf_metadata(
+        rt: &impl Runtime,
+        index: u64,
+    ) -> Result<Option<HashMap<String, String>>, ActorError> {
         rt.validate_immediate_caller_accept_any()?;
         let st: State = rt.state()?;
-        st.get_leaf_at(rt.store(), index)
-            .map_err(|e| e.downcast_default(ExitCode::USR_ILLEGAL_STATE, "failed to get leaf"))
+        st.get_leaf_metadata(rt.store(), index).map_err(|e| {
+            e.downcast_default(ExitCode::USR_ILLEGAL_STATE, "failed to get leaf metadata")
+        })
     }
 
-    fn get_root(rt: &impl Runtime) -> Result<Cid, ActorError> {
+    fn get_root(rt: &impl Runtime, params: RootParams) -> Result<Cid, ActorError> {
         rt.validate_immediate_caller_accept_any()?;
         let st: State = rt.state()?;
-        st.get_root(rt.store())
-            .map_err(|e| e.downcast_default(ExitCode::USR_ILLEGAL_STATE, "failed to bag peaks"))
+        st.get_root_in(rt.store(), &params.namespace)
+            .map_err(|e| to_actor_error(e, "failed to bag peaks"))
     }
 
     fn get_peaks(rt: &impl Runtime) -> Result<Vec<Cid>, ActorError> {
         rt.validate_immediate_caller_accept_any()?;
         let st: State = rt.state()?;
         st.get_peaks(rt.store())
-            .map_err(|e| e.downcast_default(ExitCode::USR_ILLEGAL_STATE, "failed to get peaks"))
+            .map_err(|e| to_actor_error(e, "failed to get peaks"))
     }
 
     fn get_count(rt: &impl Runtime) -> Result<u64, ActorError> {
@@ -67,6 +245,67 @@ impl Actor {
         Ok(st.leaf_count)
     }
 
+    /// Returns what [`State::verify_integrity`] found wrong, or `None` if the state is sound.
+    /// The check doesn't fail the call, so that it can't be mistaken for a failure to run it.
+    fn verify_integrity(rt: &impl Runtime) -> Result<Option<String>, ActorError> {
+        rt.validate_immediate_caller_accept_any()?;
+        let st: State = rt.state()?;
+        Ok(st
+            .verify_integrity(rt.store())
+            .err()
+            .map(|e| format!("{:#}", e)))
+    }
+
+    fn set_leaf_codec(rt: &impl Runtime, leaf_codec: LeafCodec) -> Result<(), ActorError> {
+        Self::ensure_owner(rt)?;
+        ensure_leaf_codec_supported(leaf_codec)?;
+
+        rt.transaction(|st: &mut State, _| {
+            st.set_leaf_codec(leaf_codec).map_err(|e| {
+                e.downcast_default(ExitCode::USR_ILLEGAL_STATE, "failed to set leaf codec")
+            })
+        })
+    }
+
+    fn set_root_scheme(rt: &impl Runtime, scheme: RootScheme) -> Result<(), ActorError> {
+        Self::ensure_owner(rt)?;
+
+        rt.transaction(|st: &mut State, _| {
+            st.set_root_scheme(scheme).map_err(|e| {
+                e.downcast_default(ExitCode::USR_ILLEGAL_STATE, "failed to set root scheme")
+            })
+        })
+    }
+
+    fn set_max_pushes_per_epoch(rt: &impl Runtime, max: Option<u64>) -> Result<(), ActorError> {
+        Self::ensure_owner(rt)?;
+
+        rt.transaction(|st: &mut State, _| {
+            st.set_max_pushes_per_epoch(max).map_err(|e| {
+                e.downcast_default(ExitCode::USR_ILLEGAL_STATE, "failed to set push limit")
+            })
+        })
+    }
+
+    fn set_dedup_window(rt: &impl Runtime, window: u64) -> Result<(), ActorError> {
+        Self::ensure_owner(rt)?;
+
+        rt.transaction(|st: &mut State, _| {
+            st.set_dedup_window(window).map_err(|e| {
+                e.downcast_default(ExitCode::USR_ILLEGAL_STATE, "failed to set dedup window")
+            })
+        })
+    }
+
+    fn freeze(rt: &impl Runtime) -> Result<(), ActorError> {
+        Self::ensure_owner(rt)?;
+
+        rt.transaction(|st: &mut State, _| {
+            st.freeze();
+            Ok(())
+        })
+    }
+
     /// Fallback method for unimplemented method numbers.
     pub fn fallback(
         rt: &impl Runtime,
@@ -82,6 +321,26 @@ impl Actor {
     }
 }
 
+/// Map an [`AccumulatorError`] to its own exit code, and any other error to `USR_ILLEGAL_STATE`.
+fn to_actor_error(e: anyhow::Error, msg: &str) -> ActorError {
+    match e.downcast::<AccumulatorError>() {
+        Ok(e) => ActorError::unchecked(e.exit_code(), format!("{}: {}", msg, e)),
+        Err(e) => e.downcast_default(ExitCode::USR_ILLEGAL_STATE, msg),
+    }
+}
+
+/// Fail if `leaf_codec` is DAG-PB. The FVM only stores raw, DAG-CBOR and CBOR blocks, so
+/// DAG-PB leaves are off-chain only.
+fn ensure_leaf_codec_supported(leaf_codec: LeafCodec) -> Result<(), ActorError> {
+    if leaf_codec == LeafCodec::DagPb {
+        return Err(actor_error!(
+            illegal_argument;
+            "DAG-PB leaves are not supported on chain"
+        ));
+    }
+    Ok(())
+}
+
 impl MachineActor for Actor {
     type State = State;
 }
@@ -96,11 +355,748 @@ impl ActorCode for Actor {
     actor_dispatch! {
         Constructor => constructor,
         GetMetadata => get_metadata,
+        CanWrite => can_write,
+        SetMetadata => set_metadata,
         Push => push,
         Get => get_leaf_at,
         Root => get_root,
         Peaks => get_peaks,
         Count => get_count,
+        SetLeafCodec => set_leaf_codec,
+        GetMeta => get_leaf_metadata,
+        SetRootScheme => set_root_scheme,
+        Freeze => freeze,
+        GetWithProof => get_with_proof,
+        VerifyPeak => verify_peak,
+        SetMaxPushesPerEpoch => set_max_pushes_per_epoch,
+        ImportLeaves => import_leaves,
+        PushCid => push_cid,
+        GetCid => get_leaf_cid_at,
+        SetDedupWindow => set_dedup_window,
+        VerifyIntegrity => verify_integrity,
         _ => fallback,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{verify_proof, ERR_RATE_LIMITED, HASH_CODE};
+    use cid::multihash::{Code, MultihashDigest};
+    use fendermint_actor_machine::{
+        CanWriteParams, Metadata, SetMetadataParams, WriteAccess, MAX_MACHINE_METADATA_ENTRIES,
+    };
+    use fil_actors_runtime::test_utils::{
+        expect_empty, MockRuntime, ETHACCOUNT_ACTOR_CODE_ID, INIT_ACTOR_CODE_ID,
+    };
+    use fvm_ipld_blockstore::MemoryBlockstore;
+    use fvm_shared::address::Address;
+
+    pub fn construct_and_verify(write_access: WriteAccess) -> MockRuntime {
+        construct_with_metadata(write_access, HashMap::new())
+    }
+
+    fn construct_with_metadata(
+        write_access: WriteAccess,
+        metadata: HashMap<String, String>,
+    ) -> MockRuntime {
+        let rt = MockRuntime {
+            receiver: Address::new_id(10),
+            ..Default::default()
+        };
+
+        rt.set_caller(*INIT_ACTOR_CODE_ID, INIT_ACTOR_ADDR);
+        rt.expect_validate_caller_addr(vec![INIT_ACTOR_ADDR]);
+
+        let result = rt
+            .call::<Actor>(
+                Method::Constructor as u64,
+                IpldBlock::serialize_cbor(&ConstructorParams {
+                    creator: Address::new_id(100),
+                    write_access,
+                    metadata,
+                })
+                .unwrap(),
+            )
+            .unwrap();
+        expect_empty(result);
+        rt.verify();
+        rt.reset();
+
+        rt
+    }
+
+    #[test]
+    fn test_push_emits_event() {
+        let rt = construct_and_verify(WriteAccess::Public);
+
+        // Compute the expected return against an identical, separate state.
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::Public).unwrap();
+        let expected = state.push(&store, vec![1, 2, 3]).unwrap();
+
+        rt.set_caller(*ETHACCOUNT_ACTOR_CODE_ID, Address::new_id(110));
+        rt.expect_validate_caller_any();
+        rt.expect_emitted_event(
+            EventBuilder::new()
+                .typ("push")
+                .field_indexed("index", &expected.index)
+                .field_indexed("root", &expected.root)
+                .build()
+                .unwrap(),
+        );
+
+        let result = rt
+            .call::<Actor>(
+                Method::Push as u64,
+                IpldBlock::serialize_cbor(&PushParams {
+                    data: vec![1, 2, 3],
+                    metadata: HashMap::new(),
+                    with_proof: false,
+                    namespace: vec![],
+                })
+                .unwrap(),
+            )
+            .unwrap()
+            .unwrap()
+            .deserialize::<PushReturn>()
+            .unwrap();
+        assert_eq!(result.index, 0);
+        assert_eq!(result.root, expected.root);
+        rt.verify();
+    }
+
+    #[test]
+    fn test_push_namespace() {
+        let rt = construct_and_verify(WriteAccess::Public);
+        let push = |data: Vec<u8>, namespace: &[u8]| {
+            rt.call::<Actor>(
+                Method::Push as u64,
+                IpldBlock::serialize_cbor(&PushParams {
+                    data,
+                    metadata: HashMap::new(),
+                    with_proof: false,
+                    namespace: namespace.to_vec(),
+                })
+                .unwrap(),
+            )
+        };
+
+        let store = MemoryBlockstore::default();
+        let mut expected = State::new(&store, Address::new_id(100), WriteAccess::Public).unwrap();
+        let expected = expected.push_bytes(&store, vec![7]).unwrap();
+
+        rt.set_caller(*ETHACCOUNT_ACTOR_CODE_ID, Address::new_id(110));
+        rt.expect_validate_caller_any();
+        rt.expect_emitted_event(
+            EventBuilder::new()
+                .typ("push")
+                .field_indexed("index", &0u64)
+                .field_indexed("root", &expected.root)
+                .field_indexed("namespace", &ByteBuf(b"events".to_vec()))
+                .build()
+                .unwrap(),
+        );
+        let result = push(vec![7], b"events")
+            .unwrap()
+            .unwrap()
+            .deserialize::<PushReturn>()
+            .unwrap();
+        assert_eq!(result.index, 0);
+        assert_eq!(result.root, expected.root);
+        rt.verify();
+        assert_eq!(rt.get_state::<State>().leaf_count(), 0);
+
+        // Reads take the namespace, where the empty one is the default.
+        rt.expect_validate_caller_any();
+        let leaf = rt
+            .call::<Actor>(
+                Method::Get as u64,
+                IpldBlock::serialize_cbor(&GetParams {
+                    index: 0,
+                    namespace: b"events".to_vec(),
+                })
+                .unwrap(),
+            )
+            .unwrap()
+            .unwrap()
+            .deserialize::<Option<Vec<u8>>>()
+            .unwrap();
+        assert_eq!(leaf, Some(vec![7]));
+        rt.expect_validate_caller_any();
+        let root = rt
+            .call::<Actor>(
+                Method::Root as u64,
+                IpldBlock::serialize_cbor(&RootParams::default()).unwrap(),
+            )
+            .unwrap()
+            .unwrap()
+            .deserialize::<Cid>()
+            .unwrap();
+        assert_ne!(root, expected.root);
+        rt.verify();
+
+        // Named namespaces don't keep metadata, and their names are bounded.
+        rt.expect_validate_caller_any();
+        let err = rt
+            .call::<Actor>(
+                Method::Push as u64,
+                IpldBlock::serialize_cbor(&PushParams {
+                    data: vec![8],
+                    metadata: HashMap::from([("k".to_string(), "v".to_string())]),
+                    with_proof: false,
+                    namespace: b"events".to_vec(),
+                })
+                .unwrap(),
+            )
+            .unwrap_err();
+        assert_eq!(err.exit_code(), ExitCode::USR_ILLEGAL_ARGUMENT);
+        rt.expect_validate_caller_any();
+        let err = push(vec![8], &[0; MAX_NAMESPACE_LENGTH + 1]).unwrap_err();
+        assert_eq!(err.exit_code(), ExitCode::USR_ILLEGAL_ARGUMENT);
+        rt.verify();
+    }
+
+    #[test]
+    fn test_push_cid() {
+        let rt = construct_and_verify(WriteAccess::Public);
+        let cid = Cid::new_v1(
+            fvm_ipld_encoding::DAG_CBOR,
+            Code::Blake2b256.digest(&fvm_ipld_encoding::to_vec(&vec![1u8, 2, 3]).unwrap()),
+        );
+
+        rt.set_caller(*ETHACCOUNT_ACTOR_CODE_ID, Address::new_id(110));
+        rt.expect_validate_caller_any();
+        rt.expect_emitted_event(
+            EventBuilder::new()
+                .typ("push")
+                .field_indexed("index", &0u64)
+                .field_indexed("root", &cid)
+                .build()
+                .unwrap(),
+        );
+        let result = rt
+            .call::<Actor>(
+                Method::PushCid as u64,
+                IpldBlock::serialize_cbor(&PushCidParams { cid }).unwrap(),
+            )
+            .unwrap()
+            .unwrap()
+            .deserialize::<PushReturn>()
+            .unwrap();
+        assert_eq!(result.index, 0);
+        rt.verify();
+
+        // The leaf is the pushed CID.
+        let state = rt.get_state::<State>();
+        assert_eq!(state.get_proof(rt.store(), 0).unwrap().leaf, cid);
+
+        // A CID the accumulator wouldn't hash leaves to is rejected.
+        rt.expect_validate_caller_any();
+        let err = rt
+            .call::<Actor>(
+                Method::PushCid as u64,
+                IpldBlock::serialize_cbor(&PushCidParams {
+                    cid: Cid::new_v1(fvm_ipld_encoding::IPLD_RAW, Code::Blake2b256.digest(b"raw")),
+                })
+                .unwrap(),
+            )
+            .unwrap_err();
+        assert_eq!(err.exit_code(), ExitCode::USR_ILLEGAL_ARGUMENT);
+        rt.verify();
+        assert_eq!(rt.get_state::<State>().leaf_count(), 1);
+    }
+
+    #[test]
+    fn test_push_with_proof() {
+        let rt = construct_and_verify(WriteAccess::Public);
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::Public).unwrap();
+
+        rt.set_caller(*ETHACCOUNT_ACTOR_CODE_ID, Address::new_id(110));
+        for i in 0..3u8 {
+            let expected = state.push(&store, vec![i; 3]).unwrap();
+            rt.expect_validate_caller_any();
+            rt.expect_emitted_event(
+                EventBuilder::new()
+                    .typ("push")
+                    .field_indexed("index", &expected.index)
+                    .field_indexed("root", &expected.root)
+                    .build()
+                    .unwrap(),
+            );
+            let result = rt
+                .call::<Actor>(
+                    Method::Push as u64,
+                    IpldBlock::serialize_cbor(&PushParams {
+                        data: vec![i; 3],
+                        metadata: HashMap::new(),
+                        with_proof: i > 0,
+                        namespace: vec![],
+                    })
+                    .unwrap(),
+                )
+                .unwrap()
+                .unwrap()
+                .deserialize::<PushReturn>()
+                .unwrap();
+            rt.verify();
+
+            if i == 0 {
+                assert!(result.proof.is_none());
+                continue;
+            }
+            let proof = result.proof.unwrap();
+            assert_eq!(proof.index, result.index);
+            assert_eq!(proof.peaks, result.peaks);
+            assert!(verify_proof(&proof, &result.root, HASH_CODE, state.root_scheme).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_push_empty_rejected() {
+        let rt = construct_and_verify(WriteAccess::Public);
+
+        rt.set_caller(*ETHACCOUNT_ACTOR_CODE_ID, Address::new_id(110));
+        rt.expect_validate_caller_any();
+        let err = rt
+            .call::<Actor>(
+                Method::Push as u64,
+                IpldBlock::serialize_cbor(&PushParams {
+                    data: vec![],
+                    metadata: HashMap::new(),
+                    with_proof: false,
+                    namespace: vec![],
+                })
+                .unwrap(),
+            )
+            .unwrap_err();
+        assert_eq!(err.exit_code(), ExitCode::USR_ILLEGAL_ARGUMENT);
+        rt.verify();
+
+        let state = rt.get_state::<State>();
+        assert_eq!(state.leaf_count(), 0);
+    }
+
+    #[test]
+    fn test_push_too_large_rejected() {
+        let rt = construct_and_verify(WriteAccess::Public);
+
+        rt.set_caller(*ETHACCOUNT_ACTOR_CODE_ID, Address::new_id(110));
+        rt.expect_validate_caller_any();
+        let err = rt
+            .call::<Actor>(
+                Method::Push as u64,
+                IpldBlock::serialize_cbor(&PushParams {
+                    data: vec![1; MAX_EVENT_LENGTH + 1],
+                    metadata: HashMap::new(),
+                    with_proof: false,
+                    namespace: vec![],
+                })
+                .unwrap(),
+            )
+            .unwrap_err();
+        assert_eq!(err.exit_code(), ExitCode::USR_ILLEGAL_ARGUMENT);
+        rt.verify();
+
+        let state = rt.get_state::<State>();
+        assert_eq!(state.leaf_count(), 0);
+    }
+
+    #[test]
+    fn test_push_with_metadata() {
+        let rt = construct_and_verify(WriteAccess::Public);
+        let metadata = HashMap::from([("source".to_string(), "sensor-1".to_string())]);
+
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::Public).unwrap();
+        let expected = state.push(&store, vec![1, 2, 3]).unwrap();
+
+        rt.set_caller(*ETHACCOUNT_ACTOR_CODE_ID, Address::new_id(110));
+        rt.expect_validate_caller_any();
+        rt.expect_emitted_event(
+            EventBuilder::new()
+                .typ("push")
+                .field_indexed("index", &expected.index)
+                .field_indexed("root", &expected.root)
+                .build()
+                .unwrap(),
+        );
+        rt.call::<Actor>(
+            Method::Push as u64,
+            IpldBlock::serialize_cbor(&PushParams {
+                data: vec![1, 2, 3],
+                metadata: metadata.clone(),
+                with_proof: false,
+                namespace: vec![],
+            })
+            .unwrap(),
+        )
+        .unwrap();
+        rt.verify();
+
+        rt.expect_validate_caller_any();
+        let result = rt
+            .call::<Actor>(
+                Method::GetMeta as u64,
+                IpldBlock::serialize_cbor(&0u64).unwrap(),
+            )
+            .unwrap()
+            .unwrap()
+            .deserialize::<Option<HashMap<String, String>>>()
+            .unwrap();
+        assert_eq!(result, Some(metadata));
+        rt.verify();
+    }
+
+    #[test]
+    fn test_max_pushes_per_epoch() {
+        let rt = construct_and_verify(WriteAccess::Public);
+        let push = |rt: &MockRuntime| {
+            rt.call::<Actor>(
+                Method::Push as u64,
+                IpldBlock::serialize_cbor(&PushParams {
+                    data: vec![1, 2, 3],
+                    metadata: HashMap::new(),
+                    with_proof: false,
+                    namespace: vec![],
+                })
+                .unwrap(),
+            )
+        };
+
+        // Compute the expected events against an identical, separate state.
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::Public).unwrap();
+        let mut expect_push = |rt: &MockRuntime| {
+            let expected = state.push(&store, vec![1, 2, 3]).unwrap();
+            rt.expect_emitted_event(
+                EventBuilder::new()
+                    .typ("push")
+                    .field_indexed("index", &expected.index)
+                    .field_indexed("root", &expected.root)
+                    .build()
+                    .unwrap(),
+            );
+        };
+
+        rt.set_caller(*ETHACCOUNT_ACTOR_CODE_ID, Address::new_id(100));
+        rt.expect_validate_caller_addr(vec![Address::new_id(100)]);
+        let result = rt
+            .call::<Actor>(
+                Method::SetMaxPushesPerEpoch as u64,
+                IpldBlock::serialize_cbor(&Some(1u64)).unwrap(),
+            )
+            .unwrap();
+        expect_empty(result);
+        rt.verify();
+
+        rt.set_caller(*ETHACCOUNT_ACTOR_CODE_ID, Address::new_id(110));
+        rt.set_epoch(1);
+        rt.expect_validate_caller_any();
+        expect_push(&rt);
+        push(&rt).unwrap();
+        rt.verify();
+
+        // The second push in the same epoch is rejected.
+        rt.expect_validate_caller_any();
+        let err = push(&rt).unwrap_err();
+        assert_eq!(err.exit_code(), ERR_RATE_LIMITED);
+        rt.verify();
+
+        // The next epoch starts over.
+        rt.set_epoch(2);
+        rt.expect_validate_caller_any();
+        expect_push(&rt);
+        push(&rt).unwrap();
+        rt.verify();
+
+        assert_eq!(rt.get_state::<State>().leaf_count(), 2);
+    }
+
+    #[test]
+    fn test_import_leaves() {
+        // Compute the exported root against a separate state.
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::Public).unwrap();
+        let leaves = (1..=5u8).map(|i| vec![i; 3]).collect::<Vec<_>>();
+        for leaf in &leaves {
+            state.push_bytes(&store, leaf.clone()).unwrap();
+        }
+        let root = state.get_root(&store).unwrap();
+        let params = |expected_root: Cid| ImportLeavesParams {
+            start: 0,
+            leaves: leaves.iter().cloned().map(ByteBuf).collect(),
+            leaf_codec: LeafCodec::default(),
+            root_scheme: RootScheme::default(),
+            expected_root,
+            force: false,
+        };
+
+        let rt = construct_and_verify(WriteAccess::Public);
+        rt.set_caller(*ETHACCOUNT_ACTOR_CODE_ID, Address::new_id(110));
+
+        // A corrupted export doesn't hash up to the expected root.
+        rt.expect_validate_caller_any();
+        let err = rt
+            .call::<Actor>(
+                Method::ImportLeaves as u64,
+                IpldBlock::serialize_cbor(&params(Cid::default())).unwrap(),
+            )
+            .unwrap_err();
+        assert_eq!(err.exit_code(), ExitCode::USR_ILLEGAL_ARGUMENT);
+        assert!(err.msg().contains("does not match expected root"));
+        rt.verify();
+        assert_eq!(rt.get_state::<State>().leaf_count(), 0);
+
+        rt.expect_validate_caller_any();
+        rt.expect_emitted_event(
+            EventBuilder::new()
+                .typ("import")
+                .field_indexed("start", &0u64)
+                .field_indexed("count", &5u64)
+                .field_indexed("root", &root)
+                .build()
+                .unwrap(),
+        );
+        let result = rt
+            .call::<Actor>(
+                Method::ImportLeaves as u64,
+                IpldBlock::serialize_cbor(&params(root)).unwrap(),
+            )
+            .unwrap()
+            .unwrap()
+            .deserialize::<Cid>()
+            .unwrap();
+        assert_eq!(result, root);
+        rt.verify();
+        assert_eq!(rt.get_state::<State>().leaf_count(), 5);
+
+        // Importing again into the now non-empty accumulator needs force.
+        rt.expect_validate_caller_any();
+        let err = rt
+            .call::<Actor>(
+                Method::ImportLeaves as u64,
+                IpldBlock::serialize_cbor(&params(root)).unwrap(),
+            )
+            .unwrap_err();
+        assert_eq!(err.exit_code(), ExitCode::USR_ILLEGAL_ARGUMENT);
+        rt.verify();
+    }
+
+    #[test]
+    fn test_dag_pb_rejected() {
+        let rt = construct_and_verify(WriteAccess::OnlyOwner);
+        let owner = Address::new_id(100);
+        rt.set_caller(*ETHACCOUNT_ACTOR_CODE_ID, owner);
+
+        rt.expect_validate_caller_addr(vec![owner]);
+        let err = rt
+            .call::<Actor>(
+                Method::SetLeafCodec as u64,
+                IpldBlock::serialize_cbor(&LeafCodec::DagPb).unwrap(),
+            )
+            .unwrap_err();
+        assert_eq!(err.exit_code(), ExitCode::USR_ILLEGAL_ARGUMENT);
+        rt.verify();
+
+        rt.expect_validate_caller_addr(vec![owner]);
+        let err = rt
+            .call::<Actor>(
+                Method::ImportLeaves as u64,
+                IpldBlock::serialize_cbor(&ImportLeavesParams {
+                    start: 0,
+                    leaves: vec![ByteBuf(vec![1])],
+                    leaf_codec: LeafCodec::DagPb,
+                    root_scheme: RootScheme::default(),
+                    expected_root: Cid::default(),
+                    force: true,
+                })
+                .unwrap(),
+            )
+            .unwrap_err();
+        assert_eq!(err.exit_code(), ExitCode::USR_ILLEGAL_ARGUMENT);
+        rt.verify();
+        assert_eq!(rt.get_state::<State>().leaf_codec, LeafCodec::DagCbor);
+
+        rt.expect_validate_caller_addr(vec![owner]);
+        rt.call::<Actor>(
+            Method::SetLeafCodec as u64,
+            IpldBlock::serialize_cbor(&LeafCodec::Raw).unwrap(),
+        )
+        .unwrap();
+        rt.verify();
+        assert_eq!(rt.get_state::<State>().leaf_codec, LeafCodec::Raw);
+    }
+
+    #[test]
+    fn test_can_write() {
+        let owner = Address::new_id(100);
+        let owner_robust = Address::new_actor(b"owner");
+        let stranger = Address::new_id(110);
+        let can_write = |rt: &MockRuntime, address: Address| {
+            rt.expect_validate_caller_any();
+            let ret = rt
+                .call::<Actor>(
+                    Method::CanWrite as u64,
+                    IpldBlock::serialize_cbor(&CanWriteParams { address }).unwrap(),
+                )
+                .unwrap()
+                .unwrap()
+                .deserialize::<bool>()
+                .unwrap();
+            rt.verify();
+            ret
+        };
+
+        for (write_access, stranger_can_write) in
+            [(WriteAccess::OnlyOwner, false), (WriteAccess::Public, true)]
+        {
+            let rt = construct_and_verify(write_access);
+            rt.add_id_address(owner_robust, owner);
+            rt.set_caller(*ETHACCOUNT_ACTOR_CODE_ID, stranger);
+
+            assert!(can_write(&rt, owner));
+            assert!(can_write(&rt, owner_robust));
+            assert_eq!(can_write(&rt, stranger), stranger_can_write);
+            // An address that doesn't resolve to an actor isn't the owner.
+            assert_eq!(
+                can_write(&rt, Address::new_actor(b"unknown")),
+                stranger_can_write
+            );
+
+            // Nobody can write to a frozen accumulator.
+            rt.set_caller(*ETHACCOUNT_ACTOR_CODE_ID, owner);
+            rt.expect_validate_caller_addr(vec![owner]);
+            let result = rt.call::<Actor>(Method::Freeze as u64, None).unwrap();
+            expect_empty(result);
+            rt.verify();
+            assert!(!can_write(&rt, owner));
+            assert!(!can_write(&rt, stranger));
+        }
+    }
+
+    #[test]
+    fn test_freeze() {
+        let rt = construct_and_verify(WriteAccess::Public);
+        let push = |rt: &MockRuntime| {
+            rt.call::<Actor>(
+                Method::Push as u64,
+                IpldBlock::serialize_cbor(&PushParams {
+                    data: vec![1, 2, 3],
+                    metadata: HashMap::new(),
+                    with_proof: false,
+                    namespace: vec![],
+                })
+                .unwrap(),
+            )
+        };
+
+        // Only the owner can freeze.
+        rt.set_caller(*ETHACCOUNT_ACTOR_CODE_ID, Address::new_id(110));
+        rt.expect_validate_caller_addr(vec![Address::new_id(100)]);
+        let err = rt.call::<Actor>(Method::Freeze as u64, None).unwrap_err();
+        assert_eq!(err.exit_code(), ExitCode::USR_FORBIDDEN);
+        rt.verify();
+
+        // Freezing twice is fine.
+        rt.set_caller(*ETHACCOUNT_ACTOR_CODE_ID, Address::new_id(100));
+        for _ in 0..2 {
+            rt.expect_validate_caller_addr(vec![Address::new_id(100)]);
+            let result = rt.call::<Actor>(Method::Freeze as u64, None).unwrap();
+            expect_empty(result);
+            rt.verify();
+        }
+        assert!(rt.get_state::<State>().is_frozen());
+
+        rt.expect_validate_caller_any();
+        let err = push(&rt).unwrap_err();
+        assert_eq!(err.exit_code(), ExitCode::USR_FORBIDDEN);
+        rt.verify();
+
+        // Reads still work.
+        rt.expect_validate_caller_any();
+        let count = rt
+            .call::<Actor>(Method::Count as u64, None)
+            .unwrap()
+            .unwrap()
+            .deserialize::<u64>()
+            .unwrap();
+        assert_eq!(count, 0);
+        rt.verify();
+    }
+
+    #[test]
+    fn test_set_metadata() {
+        let created = HashMap::from([("name".to_string(), "events".to_string())]);
+        let rt = construct_with_metadata(WriteAccess::Public, created.clone());
+        let get = |rt: &MockRuntime| {
+            rt.expect_validate_caller_any();
+            let metadata = rt
+                .call::<Actor>(Method::GetMetadata as u64, None)
+                .unwrap()
+                .unwrap()
+                .deserialize::<Metadata>()
+                .unwrap();
+            rt.verify();
+            metadata.metadata
+        };
+        let set = |rt: &MockRuntime, metadata: HashMap<String, String>| {
+            rt.call::<Actor>(
+                Method::SetMetadata as u64,
+                IpldBlock::serialize_cbor(&SetMetadataParams { metadata }).unwrap(),
+            )
+        };
+        assert_eq!(get(&rt), created);
+
+        // Only the owner can set the metadata, even with public write access.
+        let updated = HashMap::from([("name".to_string(), "logs".to_string())]);
+        rt.set_caller(*ETHACCOUNT_ACTOR_CODE_ID, Address::new_id(110));
+        rt.expect_validate_caller_addr(vec![Address::new_id(100)]);
+        let err = set(&rt, updated.clone()).unwrap_err();
+        assert_eq!(err.exit_code(), ExitCode::USR_FORBIDDEN);
+        rt.verify();
+        assert_eq!(get(&rt), created);
+
+        rt.set_caller(*ETHACCOUNT_ACTOR_CODE_ID, Address::new_id(100));
+        rt.expect_validate_caller_addr(vec![Address::new_id(100)]);
+        expect_empty(set(&rt, updated.clone()).unwrap());
+        rt.verify();
+        assert_eq!(get(&rt), updated);
+
+        // Too many entries are rejected.
+        let too_many = (0..=MAX_MACHINE_METADATA_ENTRIES)
+            .map(|i| (i.to_string(), String::new()))
+            .collect();
+        rt.expect_validate_caller_addr(vec![Address::new_id(100)]);
+        let err = set(&rt, too_many).unwrap_err();
+        assert_eq!(err.exit_code(), ExitCode::USR_ILLEGAL_ARGUMENT);
+        rt.verify();
+        assert_eq!(get(&rt), updated);
+    }
+
+    #[test]
+    fn test_verify_integrity() {
+        let rt = construct_and_verify(WriteAccess::OnlyOwner);
+        let verify = |rt: &MockRuntime| {
+            rt.expect_validate_caller_any();
+            let found = rt
+                .call::<Actor>(Method::VerifyIntegrity as u64, None)
+                .unwrap()
+                .unwrap()
+                .deserialize::<Option<String>>()
+                .unwrap();
+            rt.verify();
+            found
+        };
+        assert_eq!(verify(&rt), None);
+
+        let mut state = rt.get_state::<State>();
+        state.leaf_count = 1;
+        rt.replace_state(&state);
+        assert_eq!(
+            verify(&rt),
+            Some("expected 1 peaks for 1 leaves; found 0".to_string())
+        );
+    }
+}
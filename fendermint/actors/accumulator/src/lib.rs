@@ -2,8 +2,10 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
 
-#[cfg(feature = "fil-actor")]
+#[cfg(any(feature = "fil-actor", test))]
 mod actor;
+mod reader;
 mod shared;
 
+pub use reader::AccumulatorReader;
 pub use shared::*;
@@ -0,0 +1,251 @@
+// Copyright 2024 Textile
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use cid::Cid;
+use fendermint_actor_accumulator_verifier::{bag_peaks, path_for_eigen_root};
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::CborStore;
+use serde::de::DeserializeOwned;
+
+use crate::shared::{collect_peaks, load_peaks, NodeFormat};
+use crate::{AccumulatorError, LeafCodec, Proof, RootScheme, State, BIT_WIDTH, HASH_CODE};
+
+/// Read-only view of an accumulator at a fixed root, for serving reads off-chain.
+///
+/// The peaks are loaded once, and the eigentree nodes loaded by reads are cached, so repeated
+/// and overlapping reads don't go back to the store. Clones share the cache, and the reader can
+/// be shared across threads if the store can.
+pub struct AccumulatorReader<BS> {
+    inner: Arc<Inner<BS>>,
+}
+
+struct Inner<BS> {
+    store: BS,
+    leaf_count: u64,
+    peaks: Vec<Cid>,
+    leaf_codec: LeafCodec,
+    root_scheme: RootScheme,
+    format: NodeFormat,
+    /// Eigentree nodes loaded so far, by CID.
+    nodes: RwLock<HashMap<Cid, [Cid; 2]>>,
+}
+
+impl<BS> Clone for AccumulatorReader<BS> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<BS: Blockstore> AccumulatorReader<BS> {
+    /// Create a reader over the accumulator with `leaf_count` leaves whose peaks are stored in
+    /// the AMT rooted at `peaks`, with the default leaf settings and root scheme.
+    pub fn new(store: BS, peaks: Cid, leaf_count: u64) -> anyhow::Result<Self> {
+        Self::with_settings(
+            store,
+            peaks,
+            BIT_WIDTH,
+            leaf_count,
+            LeafCodec::default(),
+            RootScheme::default(),
+            NodeFormat::new(LeafCodec::default()),
+        )
+    }
+
+    /// Create a reader over the accumulator as of `state`.
+    pub fn from_state(store: BS, state: &State) -> anyhow::Result<Self> {
+        Self::with_settings(
+            store,
+            state.peaks,
+            state.peaks_bit_width,
+            state.leaf_count,
+            state.leaf_codec,
+            state.root_scheme,
+            state.node_format(),
+        )
+    }
+
+    fn with_settings(
+        store: BS,
+        peaks: Cid,
+        bit_width: u32,
+        leaf_count: u64,
+        leaf_codec: LeafCodec,
+        root_scheme: RootScheme,
+        format: NodeFormat,
+    ) -> anyhow::Result<Self> {
+        let peaks = collect_peaks(&load_peaks(&store, &peaks, bit_width)?)?;
+        if peaks.len() != leaf_count.count_ones() as usize {
+            return Err(anyhow::anyhow!(
+                "expected {} peaks for {} leaves; found {}",
+                leaf_count.count_ones(),
+                leaf_count,
+                peaks.len()
+            ));
+        }
+        Ok(Self {
+            inner: Arc::new(Inner {
+                store,
+                leaf_count,
+                peaks,
+                leaf_codec,
+                root_scheme,
+                format,
+                nodes: Default::default(),
+            }),
+        })
+    }
+
+    pub fn leaf_count(&self) -> u64 {
+        self.inner.leaf_count
+    }
+
+    /// Get the peaks of the MMR from left to right.
+    pub fn get_peaks(&self) -> &[Cid] {
+        &self.inner.peaks
+    }
+
+    /// Get the root commitment of the MMR, bagged from its peaks.
+    pub fn get_root(&self) -> Cid {
+        bag_peaks(&self.inner.peaks, HASH_CODE, self.inner.root_scheme)
+    }
+
+    /// Get the leaf at `index`, or `None` if the index is out of range.
+    pub fn get_leaf_at<S: DeserializeOwned>(&self, index: u64) -> anyhow::Result<Option<S>> {
+        if index >= self.inner.leaf_count {
+            return Ok(None);
+        }
+        let (cid, _) = self.walk(index)?;
+        match self.inner.store.get_cbor::<S>(&cid)? {
+            Some(leaf) => Ok(Some(leaf)),
+            None => Err(AccumulatorError::CorruptNode(cid).into()),
+        }
+    }
+
+    /// Get the bytes leaf at `index`, decoded with the leaf codec of the accumulator,
+    /// or `None` if the index is out of range.
+    pub fn get_leaf_bytes_at(&self, index: u64) -> anyhow::Result<Option<Vec<u8>>> {
+        if index >= self.inner.leaf_count {
+            return Ok(None);
+        }
+        let (cid, _) = self.walk(index)?;
+        let data = self
+            .inner
+            .store
+            .get(&cid)?
+            .ok_or(AccumulatorError::CorruptNode(cid))?;
+        Ok(Some(self.inner.leaf_codec.decode_leaf(&data)?))
+    }
+
+    /// Get the inclusion proof of the leaf at `index`.
+    pub fn get_proof(&self, index: u64) -> anyhow::Result<Proof> {
+        let (leaf, siblings) = self.walk(index)?;
+        Ok(Proof {
+            index,
+            leaf_count: self.inner.leaf_count,
+            leaf,
+            siblings,
+            peaks: self.inner.peaks.clone(),
+        })
+    }
+
+    /// Walk down from the peak of the leaf at `index`, returning the CID of the leaf and the
+    /// siblings on its path, bottom first.
+    fn walk(&self, index: u64) -> Result<(Cid, Vec<Cid>), AccumulatorError> {
+        let (path, eigen_index) = path_for_eigen_root(index, self.inner.leaf_count)?;
+        let mut cid = match self.inner.peaks.get(eigen_index as usize) {
+            Some(cid) => *cid,
+            None => return Err(AccumulatorError::MissingPeak(eigen_index)),
+        };
+
+        // Iterate over each bit of the path below the most significant one, which marks the peak
+        let significant_bits = u64::BITS - path.leading_zeros();
+        let mut siblings = Vec::with_capacity(significant_bits as usize - 1);
+        for i in (0..significant_bits - 1).rev() {
+            let pair = self.node(&cid)?;
+            let bit = ((path >> i) & 1) as usize;
+            siblings.push(pair[1 - bit]);
+            cid = pair[bit];
+        }
+        siblings.reverse();
+        Ok((cid, siblings))
+    }
+
+    /// Load the eigentree node `cid`, from the cache if it was loaded before.
+    fn node(&self, cid: &Cid) -> Result<[Cid; 2], AccumulatorError> {
+        if let Some(pair) = self.inner.nodes.read().unwrap().get(cid) {
+            return Ok(*pair);
+        }
+        let pair = self.inner.format.get(&self.inner.store, cid)?;
+        self.inner.nodes.write().unwrap().insert(*cid, pair);
+        Ok(pair)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fendermint_actor_machine::WriteAccess;
+    use fendermint_testing::blockstore::CountingBlockstore;
+    use fvm_shared::address::Address;
+    use std::sync::Mutex;
+
+    /// In-memory blockstore that can be shared across threads, unlike `MemoryBlockstore`.
+    #[derive(Default)]
+    struct SyncBlockstore(Mutex<HashMap<Cid, Vec<u8>>>);
+
+    impl Blockstore for SyncBlockstore {
+        fn get(&self, k: &Cid) -> anyhow::Result<Option<Vec<u8>>> {
+            Ok(self.0.lock().unwrap().get(k).cloned())
+        }
+
+        fn put_keyed(&self, k: &Cid, block: &[u8]) -> anyhow::Result<()> {
+            self.0.lock().unwrap().insert(*k, block.to_vec());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_concurrent_reads() {
+        let store = CountingBlockstore::new(SyncBlockstore::default());
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        for i in 0..37u64 {
+            state.push(&store, i).unwrap();
+        }
+        let reader = AccumulatorReader::from_state(&store, &state).unwrap();
+        assert_eq!(reader.get_root(), state.get_root(&store).unwrap());
+        assert_eq!(reader.get_peaks(), state.get_peaks(&store).unwrap());
+
+        // Every thread reads every leaf, starting at a different one.
+        std::thread::scope(|s| {
+            for t in 0..8u64 {
+                let reader = reader.clone();
+                let state = &state;
+                let store = &store;
+                s.spawn(move || {
+                    for i in 0..state.leaf_count {
+                        let index = (i + t * 5) % state.leaf_count;
+                        let leaf: u64 = reader.get_leaf_at(index).unwrap().unwrap();
+                        assert_eq!(leaf, index);
+                        let proof = reader.get_proof(index).unwrap();
+                        assert_eq!(proof, state.get_proof(store, index).unwrap());
+                    }
+                    assert_eq!(reader.get_leaf_at::<u64>(state.leaf_count).unwrap(), None);
+                });
+            }
+        });
+
+        // All the nodes are cached now, so reads only load the leaf blocks.
+        store.reset();
+        for index in 0..state.leaf_count {
+            reader.get_proof(index).unwrap();
+        }
+        assert_eq!(store.gets(), 0);
+        reader.get_leaf_at::<u64>(0).unwrap();
+        assert_eq!(store.gets(), 1);
+    }
+}
@@ -2,35 +2,139 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+use std::collections::HashMap;
+use std::fmt;
+
 use cid::multihash::{Code, MultihashDigest};
 use cid::Cid;
-use fendermint_actor_machine::{Kind, MachineState, WriteAccess, GET_METADATA_METHOD};
+use fendermint_actor_accumulator_verifier::{
+    bag_peaks as bag_peak_cids, hash_pair, path_for_eigen_root, ProofRef, VerifyError,
+};
+pub use fendermint_actor_accumulator_verifier::{peak_from_leaves, RootScheme};
+use fendermint_actor_machine::{
+    Kind, MachineState, WriteAccess, CAN_WRITE_METHOD, GET_METADATA_METHOD, SET_METADATA_METHOD,
+};
 use fvm_ipld_amt::Amt;
 use fvm_ipld_blockstore::Blockstore;
-use fvm_ipld_encoding::{strict_bytes, to_vec, tuple::*, CborStore, DAG_CBOR};
+use fvm_ipld_encoding::{
+    strict_bytes, strict_bytes::ByteBuf, to_vec, tuple::*, CborStore, DAG_CBOR, IPLD_RAW,
+};
+use fvm_ipld_hamt::{BytesKey, Hamt};
 use fvm_shared::address::Address;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::error::ExitCode;
 use fvm_shared::METHOD_CONSTRUCTOR;
 use num_derive::FromPrimitive;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 pub const ACCUMULATOR_ACTOR_NAME: &str = "accumulator";
-const BIT_WIDTH: u32 = 3;
+/// Maximum length in bytes of an object pushed into the accumulator.
+pub const MAX_EVENT_LENGTH: usize = 500 * 1024;
+/// Bit width of the AMTs holding the peaks and the leaf metadata.
+pub const BIT_WIDTH: u32 = 3;
+/// Maximum number of recent leaves pushes can be deduplicated against.
+pub const MAX_DEDUP_WINDOW: u64 = 100_000;
+/// Bit width of the AMT and HAMT remembering the recent leaves.
+const RECENT_LEAVES_BIT_WIDTH: u32 = 5;
+/// Maximum length in bytes of the name of a namespace.
+pub const MAX_NAMESPACE_LENGTH: usize = 64;
+/// Bit width of the HAMT holding the named namespaces.
+const NAMESPACES_BIT_WIDTH: u32 = 5;
+/// The multicodec of DAG-PB blocks.
+const DAG_PB: u64 = 0x70;
+/// The hash function of the leaves and nodes. The FVM only links blocks hashed with
+/// Blake2b-256, so accumulators hashed otherwise can only be checked off chain, with the
+/// verifier crate.
+pub const HASH_CODE: Code = Code::Blake2b256;
+/// The size in bytes of the digests of [`HASH_CODE`].
+const HASH_SIZE: u8 = 32;
 
 #[derive(FromPrimitive)]
 #[repr(u64)]
 pub enum Method {
     Constructor = METHOD_CONSTRUCTOR,
     GetMetadata = GET_METADATA_METHOD,
+    CanWrite = CAN_WRITE_METHOD,
+    SetMetadata = SET_METADATA_METHOD,
     Push = frc42_dispatch::method_hash!("Push"),
     Get = frc42_dispatch::method_hash!("Get"),
     Root = frc42_dispatch::method_hash!("Root"),
     Peaks = frc42_dispatch::method_hash!("Peaks"),
     Count = frc42_dispatch::method_hash!("Count"),
+    SetLeafCodec = frc42_dispatch::method_hash!("SetLeafCodec"),
+    GetMeta = frc42_dispatch::method_hash!("GetMeta"),
+    SetRootScheme = frc42_dispatch::method_hash!("SetRootScheme"),
+    Freeze = frc42_dispatch::method_hash!("Freeze"),
+    GetWithProof = frc42_dispatch::method_hash!("GetWithProof"),
+    VerifyPeak = frc42_dispatch::method_hash!("VerifyPeak"),
+    SetMaxPushesPerEpoch = frc42_dispatch::method_hash!("SetMaxPushesPerEpoch"),
+    ImportLeaves = frc42_dispatch::method_hash!("ImportLeaves"),
+    PushCid = frc42_dispatch::method_hash!("PushCid"),
+    GetCid = frc42_dispatch::method_hash!("GetCid"),
+    SetDedupWindow = frc42_dispatch::method_hash!("SetDedupWindow"),
+    VerifyIntegrity = frc42_dispatch::method_hash!("VerifyIntegrity"),
+}
+
+/// Params for pushing an object into the accumulator.
+///
+/// The object must not be empty. An empty leaf carries no information and can't be told
+/// apart from a client error, so it would only pollute the log.
+#[derive(Serialize_tuple, Deserialize_tuple)]
+pub struct PushParams {
+    /// The object to push.
+    #[serde(with = "strict_bytes")]
+    pub data: Vec<u8>,
+    /// Metadata to store alongside the leaf, e.g., its source or type.
+    ///
+    /// Metadata is not part of the leaf, so it is not committed to the root.
+    pub metadata: HashMap<String, String>,
+    /// Whether to return the inclusion proof of the pushed leaf.
+    pub with_proof: bool,
+    /// The namespace to push into, or empty for the default one.
+    #[serde(with = "strict_bytes")]
+    pub namespace: Vec<u8>,
+}
+
+/// Params for getting the leaf at an index of a namespace.
+#[derive(Clone, Debug, Default, PartialEq, Serialize_tuple, Deserialize_tuple)]
+pub struct GetParams {
+    /// The index of the leaf.
+    pub index: u64,
+    /// The namespace of the leaf, or empty for the default one.
+    #[serde(with = "strict_bytes")]
+    pub namespace: Vec<u8>,
+}
+
+/// Params for getting the root of a namespace.
+#[derive(Clone, Debug, Default, PartialEq, Serialize_tuple, Deserialize_tuple)]
+pub struct RootParams {
+    /// The namespace, or empty for the default one.
+    #[serde(with = "strict_bytes")]
+    pub namespace: Vec<u8>,
+}
+
+/// A named sub-accumulator: an MMR of its own, next to the default one held by the [`State`].
+///
+/// All namespaces share the leaf and root settings of the accumulator, its push limit and
+/// whether it's frozen. Only the default namespace keeps leaf metadata, deduplicates pushes,
+/// and can be exported and imported.
+#[derive(Clone, Debug, PartialEq, Serialize_tuple, Deserialize_tuple)]
+pub struct Namespace {
+    /// Root of the AMT storing the peaks of the MMR.
+    pub peaks: Cid,
+    /// Number of leaf nodes in the MMR.
+    pub leaf_count: u64,
 }
 
-#[derive(Serialize, Deserialize)]
-#[serde(transparent)]
-pub struct PushParams(#[serde(with = "strict_bytes")] pub Vec<u8>);
+/// Params for pushing a leaf by its CID, e.g. of content already in IPFS.
+///
+/// The CID becomes the leaf as it is, so the content it addresses is not stored by the
+/// accumulator. It must be a CIDv1 with the codec and hash function of the accumulator.
+#[derive(Serialize_tuple, Deserialize_tuple)]
+pub struct PushCidParams {
+    /// The CID of the leaf.
+    pub cid: Cid,
+}
 
 #[derive(Serialize_tuple, Deserialize_tuple)]
 pub struct PushReturn {
@@ -38,50 +142,568 @@ pub struct PushReturn {
     pub root: Cid,
     /// The index of the object that was just pushed into the accumulator.
     pub index: u64,
+    /// The peaks of the accumulator MMR after the object was pushed into it.
+    pub peaks: Vec<Cid>,
+    /// The inclusion proof of the pushed object against `root`, if it was asked for.
+    pub proof: Option<Proof>,
+    /// Whether the object was already one of the recent leaves, see [`State::dedup_window`],
+    /// in which case nothing was pushed and `index` is that of the existing leaf.
+    pub deduplicated: bool,
 }
 
-/// Compute the hash of a pair of CIDs.
-/// The hash is the CID of a new block containing the concatenation of the two CIDs.
-/// We do not include the index of the element(s) because incoming data should already be "nonced".
-fn hash_pair(left: Option<&Cid>, right: Option<&Cid>) -> anyhow::Result<Cid> {
-    if let (Some(left), Some(right)) = (left, right) {
-        // Encode the CIDs into a binary format
-        let data = to_vec(&[left, right])?;
-        // Compute the CID for the block
-        let mh_code = Code::Blake2b256;
-        let mh = mh_code.digest(&data);
-        let cid = Cid::new_v1(DAG_CBOR, mh);
-        Ok(cid)
-    } else {
-        Err(anyhow::anyhow!("hash_pair requires two CIDs"))
+/// Params for replaying a batch of exported leaves into the accumulator.
+///
+/// An import is a sequence of batches. The first one starts at leaf 0 and sets the codec of the
+/// leaves, and the following ones continue where the previous one ended.
+#[derive(Clone, Debug, PartialEq, Serialize_tuple, Deserialize_tuple)]
+pub struct ImportLeavesParams {
+    /// Index of the first leaf of the batch.
+    pub start: u64,
+    /// The leaves of the batch, as the bytes that were pushed.
+    pub leaves: Vec<ByteBuf>,
+    /// The codec the leaves were stored with.
+    pub leaf_codec: LeafCodec,
+    /// How the peaks are bagged into the root.
+    pub root_scheme: RootScheme,
+    /// The root the accumulator must have after the batch, or the batch is rejected.
+    pub expected_root: Cid,
+    /// Whether an import starting at leaf 0 may replace the leaves of a non-empty accumulator.
+    pub force: bool,
+}
+
+/// The codec used to store the byte leaves pushed into the accumulator.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LeafCodec {
+    /// Leaves are wrapped in DAG-CBOR.
+    #[default]
+    DagCbor,
+    /// Leaves are stored as they are, under the raw codec.
+    Raw,
+    /// Leaves are the data of a DAG-PB node without links, like the chunks of files added to
+    /// IPFS. The FVM can't store DAG-PB blocks, so the actor rejects it.
+    DagPb,
+}
+
+impl LeafCodec {
+    /// Encode pushed bytes as a leaf block, returning the CID it is stored under together with
+    /// the block data.
+    pub fn encode_leaf(self, bytes: &[u8]) -> Result<(Cid, Vec<u8>), AccumulatorError> {
+        let data = match self {
+            LeafCodec::DagCbor => to_vec(&bytes)?,
+            LeafCodec::Raw => bytes.to_vec(),
+            LeafCodec::DagPb => encode_pb_data(bytes),
+        };
+        let cid = Cid::new_v1(self.multicodec(), HASH_CODE.digest(&data));
+        Ok((cid, data))
+    }
+
+    /// The multicodec of the leaf blocks.
+    fn multicodec(self) -> u64 {
+        match self {
+            LeafCodec::DagCbor => DAG_CBOR,
+            LeafCodec::Raw => IPLD_RAW,
+            LeafCodec::DagPb => DAG_PB,
+        }
+    }
+
+    /// Decode the data of a leaf block back into the bytes that were pushed.
+    pub fn decode_leaf(self, data: &[u8]) -> Result<Vec<u8>, AccumulatorError> {
+        match self {
+            LeafCodec::DagCbor => Ok(fvm_ipld_encoding::from_slice(data)?),
+            LeafCodec::Raw => Ok(data.to_vec()),
+            LeafCodec::DagPb => decode_pb_data(data),
+        }
+    }
+}
+
+/// Encode bytes as a DAG-PB node holding them as its data, without links.
+fn encode_pb_data(bytes: &[u8]) -> Vec<u8> {
+    // The data is field 1 of the node, length delimited.
+    let mut data = vec![0x0a];
+    let mut len = bytes.len() as u64;
+    while len >= 0x80 {
+        data.push(len as u8 | 0x80);
+        len >>= 7;
+    }
+    data.push(len as u8);
+    data.extend_from_slice(bytes);
+    data
+}
+
+/// Decode the data of a DAG-PB node encoded with [`encode_pb_data`].
+fn decode_pb_data(data: &[u8]) -> Result<Vec<u8>, AccumulatorError> {
+    let invalid = || AccumulatorError::Store(anyhow::anyhow!("invalid DAG-PB leaf"));
+    let (&tag, mut rest) = data.split_first().ok_or_else(invalid)?;
+    if tag != 0x0a {
+        return Err(invalid());
+    }
+    let mut len = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, tail) = rest.split_first().ok_or_else(invalid)?;
+        rest = tail;
+        len |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    if len != rest.len() as u64 {
+        return Err(invalid());
+    }
+    Ok(rest.to_vec())
+}
+
+/// Exit code for operations that need at least one leaf on an empty accumulator.
+pub const ERR_EMPTY_ACCUMULATOR: ExitCode = ExitCode::new(32);
+/// Exit code for a leaf index that is not less than the leaf count.
+pub const ERR_INDEX_OUT_OF_RANGE: ExitCode = ExitCode::new(33);
+/// Exit code for an eigentree node or leaf missing from the store.
+pub const ERR_CORRUPT_NODE: ExitCode = ExitCode::new(34);
+/// Exit code for a peak missing from the peaks AMT.
+pub const ERR_MISSING_PEAK: ExitCode = ExitCode::new(35);
+/// Exit code for a push beyond the maximum number of pushes in an epoch.
+pub const ERR_RATE_LIMITED: ExitCode = ExitCode::new(36);
+
+/// Errors of the accumulator MMR operations.
+#[derive(Debug)]
+pub enum AccumulatorError {
+    /// The accumulator has no leaves.
+    EmptyAccumulator,
+    /// The leaf index is not less than the leaf count.
+    IndexOutOfRange { index: u64, leaf_count: u64 },
+    /// An eigentree node or leaf is missing from the store.
+    CorruptNode(Cid),
+    /// A peak is missing from the peaks AMT.
+    MissingPeak(u64),
+    /// The block of the peak at `index` could not be loaded from the store.
+    UnloadablePeak { index: u64, cid: Cid },
+    /// The number of peaks doesn't match the one implied by the leaf count.
+    PeakCountMismatch {
+        leaf_count: u64,
+        expected: u64,
+        found: u64,
+    },
+    /// The peak at `index` is not the hash of its children.
+    CorruptPeak { index: u64, cid: Cid },
+    /// The number of named namespaces doesn't match the recorded count.
+    NamespaceCountMismatch { expected: u64, found: u64 },
+    /// The state doesn't record the bit width of the peaks AMTs.
+    PeaksBitWidthUnknown,
+    /// A peaks AMT doesn't have the bit width recorded in the state.
+    PeaksBitWidthMismatch { expected: u32, found: u32 },
+    /// The accumulator is frozen.
+    Frozen,
+    /// The maximum number of pushes in the current epoch was reached.
+    RateLimited { epoch: ChainEpoch, limit: u64 },
+    /// A leaf CID isn't a CIDv1 with the codec and hash function of the accumulator.
+    MismatchedLeaf(Cid),
+    /// An import was started over a non-empty accumulator without force.
+    ImportWithoutForce,
+    /// An import would change the leaf settings or root scheme the named namespaces use.
+    ImportNamespaceSettingsMismatch,
+    /// An import batch doesn't start at the current leaf count.
+    ImportOutOfOrder { start: u64, expected: u64 },
+    /// An import batch doesn't have the leaf settings or root scheme of the accumulator.
+    ImportSettingsMismatch,
+    /// An import batch doesn't end at the root it was expected to.
+    ImportRootMismatch { root: Cid, expected: Cid },
+    /// The underlying store or encoding failed.
+    Store(anyhow::Error),
+}
+
+impl AccumulatorError {
+    /// The exit code the actor aborts with on this error.
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            AccumulatorError::EmptyAccumulator => ERR_EMPTY_ACCUMULATOR,
+            AccumulatorError::IndexOutOfRange { .. } => ERR_INDEX_OUT_OF_RANGE,
+            AccumulatorError::CorruptNode(_) => ERR_CORRUPT_NODE,
+            AccumulatorError::MissingPeak(_) => ERR_MISSING_PEAK,
+            AccumulatorError::UnloadablePeak { .. } => ERR_CORRUPT_NODE,
+            AccumulatorError::PeakCountMismatch { .. } => ExitCode::USR_ILLEGAL_STATE,
+            AccumulatorError::CorruptPeak { .. } => ERR_CORRUPT_NODE,
+            AccumulatorError::NamespaceCountMismatch { .. } => ExitCode::USR_ILLEGAL_STATE,
+            AccumulatorError::PeaksBitWidthUnknown => ExitCode::USR_ILLEGAL_STATE,
+            AccumulatorError::PeaksBitWidthMismatch { .. } => ExitCode::USR_ILLEGAL_STATE,
+            AccumulatorError::Frozen => ExitCode::USR_FORBIDDEN,
+            AccumulatorError::RateLimited { .. } => ERR_RATE_LIMITED,
+            AccumulatorError::MismatchedLeaf(_) => ExitCode::USR_ILLEGAL_ARGUMENT,
+            AccumulatorError::ImportWithoutForce => ExitCode::USR_ILLEGAL_ARGUMENT,
+            AccumulatorError::ImportNamespaceSettingsMismatch => ExitCode::USR_ILLEGAL_ARGUMENT,
+            AccumulatorError::ImportOutOfOrder { .. } => ExitCode::USR_ILLEGAL_ARGUMENT,
+            AccumulatorError::ImportSettingsMismatch => ExitCode::USR_ILLEGAL_ARGUMENT,
+            AccumulatorError::ImportRootMismatch { .. } => ExitCode::USR_ILLEGAL_ARGUMENT,
+            AccumulatorError::Store(_) => ExitCode::USR_ILLEGAL_STATE,
+        }
+    }
+}
+
+impl fmt::Display for AccumulatorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccumulatorError::EmptyAccumulator => write!(f, "accumulator is empty"),
+            AccumulatorError::IndexOutOfRange { index, leaf_count } => write!(
+                f,
+                "leaf index {} out of range for {} leaves",
+                index, leaf_count
+            ),
+            AccumulatorError::CorruptNode(cid) => write!(f, "failed to get node for cid {}", cid),
+            AccumulatorError::MissingPeak(index) => {
+                write!(f, "failed to get peak at index {}", index)
+            }
+            AccumulatorError::UnloadablePeak { index, cid } => {
+                write!(f, "failed to load peak {} with cid {}", index, cid)
+            }
+            AccumulatorError::PeakCountMismatch {
+                leaf_count,
+                expected,
+                found,
+            } => write!(
+                f,
+                "expected {} peaks for {} leaves; found {}",
+                expected, leaf_count, found
+            ),
+            AccumulatorError::CorruptPeak { index, cid } => {
+                write!(
+                    f,
+                    "peak {} with cid {} is not the hash of its children",
+                    index, cid
+                )
+            }
+            AccumulatorError::NamespaceCountMismatch { expected, found } => {
+                write!(f, "expected {} namespaces; found {}", expected, found)
+            }
+            AccumulatorError::PeaksBitWidthUnknown => write!(
+                f,
+                "peaks AMT bit width unknown; the state may be from an incompatible version"
+            ),
+            AccumulatorError::PeaksBitWidthMismatch { expected, found } => write!(
+                f,
+                "peaks AMT bit width mismatch: expected {}, found {}; \
+                 the state may be from an incompatible version",
+                expected, found
+            ),
+            AccumulatorError::Frozen => write!(f, "accumulator is frozen"),
+            AccumulatorError::RateLimited { epoch, limit } => write!(
+                f,
+                "reached the maximum of {} pushes in epoch {}",
+                limit, epoch
+            ),
+            AccumulatorError::MismatchedLeaf(cid) => write!(
+                f,
+                "leaf {} isn't a CIDv1 with the codec and hash function of the accumulator",
+                cid
+            ),
+            AccumulatorError::ImportWithoutForce => write!(
+                f,
+                "cannot import into a non-empty accumulator without force"
+            ),
+            AccumulatorError::ImportNamespaceSettingsMismatch => write!(
+                f,
+                "import leaf settings and root scheme must match those of the named namespaces"
+            ),
+            AccumulatorError::ImportOutOfOrder { start, expected } => write!(
+                f,
+                "import batch starts at leaf {}, expected {}",
+                start, expected
+            ),
+            AccumulatorError::ImportSettingsMismatch => write!(
+                f,
+                "import batch leaf settings and root scheme do not match the accumulator"
+            ),
+            AccumulatorError::ImportRootMismatch { root, expected } => write!(
+                f,
+                "imported root {} does not match expected root {}",
+                root, expected
+            ),
+            AccumulatorError::Store(e) => write!(f, "store error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for AccumulatorError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AccumulatorError::Store(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<anyhow::Error> for AccumulatorError {
+    fn from(e: anyhow::Error) -> Self {
+        AccumulatorError::Store(e)
+    }
+}
+
+impl From<VerifyError> for AccumulatorError {
+    fn from(e: VerifyError) -> Self {
+        match e {
+            VerifyError::EmptyAccumulator => AccumulatorError::EmptyAccumulator,
+            VerifyError::IndexOutOfRange { index, leaf_count } => {
+                AccumulatorError::IndexOutOfRange { index, leaf_count }
+            }
+        }
+    }
+}
+
+impl From<fvm_ipld_amt::Error> for AccumulatorError {
+    fn from(e: fvm_ipld_amt::Error) -> Self {
+        AccumulatorError::Store(e.into())
+    }
+}
+
+impl From<fvm_ipld_encoding::Error> for AccumulatorError {
+    fn from(e: fvm_ipld_encoding::Error) -> Self {
+        AccumulatorError::Store(e.into())
+    }
+}
+
+/// Inclusion proof of a leaf in the accumulator MMR.
+#[derive(Clone, Debug, PartialEq, Serialize_tuple, Deserialize_tuple)]
+pub struct Proof {
+    /// The index of the proven leaf.
+    pub index: u64,
+    /// The number of leaves in the accumulator the proof was built against.
+    pub leaf_count: u64,
+    /// The CID of the proven leaf.
+    pub leaf: Cid,
+    /// Sibling hashes on the path from the leaf up to its peak, bottom first.
+    pub siblings: Vec<Cid>,
+    /// The peaks of the accumulator, from which the root can be bagged.
+    pub peaks: Vec<Cid>,
+}
+
+impl Proof {
+    /// Borrow the proof, to check it with the verifier.
+    pub fn as_proof_ref(&self) -> ProofRef<'_> {
+        ProofRef {
+            index: self.index,
+            leaf_count: self.leaf_count,
+            leaf: &self.leaf,
+            siblings: &self.siblings,
+            peaks: &self.peaks,
+        }
     }
 }
 
+/// A leaf of the accumulator together with everything needed to verify it locally.
+#[derive(Clone, Debug, PartialEq, Serialize_tuple, Deserialize_tuple)]
+pub struct GetWithProofReturn {
+    /// The leaf bytes, decoded with the leaf codec of the accumulator.
+    #[serde(with = "strict_bytes")]
+    pub leaf: Vec<u8>,
+    /// The inclusion proof of the leaf.
+    pub proof: Proof,
+    /// The number of leaves in the accumulator.
+    pub leaf_count: u64,
+    /// The root of the accumulator, which the proof verifies against.
+    pub root: Cid,
+}
+
+/// Params for checking that a set of leaves hashes up to one of the peaks of the accumulator.
+#[derive(Clone, Debug, PartialEq, Serialize_tuple, Deserialize_tuple)]
+pub struct VerifyPeakParams {
+    /// The index of the peak, counting from the left.
+    pub peak_index: u64,
+    /// The CID the caller expects the peak to have.
+    pub expected_cid: Cid,
+    /// The CIDs of the leaves under the peak, from left to right.
+    pub leaves: Vec<Cid>,
+}
+
+/// Check that `proof` links its leaf to `root`, with pairs hashed using `mh_code` and the peaks
+/// bagged with `scheme`.
+///
+/// Returns `false` if the proof is well-formed but doesn't match the root.
+pub fn verify_proof(
+    proof: &Proof,
+    root: &Cid,
+    mh_code: Code,
+    scheme: RootScheme,
+) -> Result<bool, AccumulatorError> {
+    Ok(fendermint_actor_accumulator_verifier::verify_proof(
+        &proof.as_proof_ref(),
+        root,
+        mh_code,
+        scheme,
+    )?)
+}
+
 /// Compute and store the hash of a pair of CIDs.
 /// The hash is the CID of a new block containing the concatenation of the two CIDs.
 /// We do not include the index of the element(s) because incoming data should already be "nonced".
 fn hash_and_put_pair<BS: Blockstore>(
     store: &BS,
-    left: Option<&Cid>,
-    right: Option<&Cid>,
-) -> anyhow::Result<Cid> {
-    if let (Some(left), Some(right)) = (left, right) {
-        // Compute the CID for the block
-        store.put_cbor(&[left, right], Code::Blake2b256)
+    left: &Cid,
+    right: &Cid,
+    mh_code: Code,
+) -> Result<Cid, AccumulatorError> {
+    // Compute the CID for the block
+    Ok(store.put_cbor(&[left, right], mh_code)?)
+}
+
+/// What it takes to store and load the eigentree nodes of an accumulator.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct NodeFormat {
+    /// The multicodec of the leaves, which the nodes at height one link to.
+    leaf_codec: u64,
+}
+
+impl NodeFormat {
+    pub(crate) fn new(leaf_codec: LeafCodec) -> Self {
+        Self {
+            leaf_codec: leaf_codec.multicodec(),
+        }
+    }
+
+    /// Whether `leaf` is a CIDv1 with the codec and hash function of the accumulator.
+    fn matches_leaf(&self, leaf: &Cid) -> bool {
+        leaf.version() == cid::Version::V1
+            && leaf.codec() == self.leaf_codec
+            && leaf.hash().code() == u64::from(HASH_CODE)
+            && leaf.hash().size() == HASH_SIZE
+    }
+
+    /// Store the node linking `left` and `right`, returning its CID.
+    fn put<BS: Blockstore>(
+        &self,
+        store: &BS,
+        left: &Cid,
+        right: &Cid,
+    ) -> Result<Cid, AccumulatorError> {
+        hash_and_put_pair(store, left, right, HASH_CODE)
+    }
+
+    /// Load the links of the node `cid`.
+    pub(crate) fn get<BS: Blockstore>(
+        &self,
+        store: &BS,
+        cid: &Cid,
+    ) -> Result<[Cid; 2], AccumulatorError> {
+        store
+            .get_cbor::<[Cid; 2]>(cid)?
+            .ok_or(AccumulatorError::CorruptNode(*cid))
+    }
+}
+
+/// Get the peak at `index`, which must exist.
+fn peak_at<BS: Blockstore>(peaks: &Amt<Cid, &BS>, index: u64) -> Result<Cid, AccumulatorError> {
+    peaks
+        .get(index)
+        .map_err(|e| {
+            AccumulatorError::Store(
+                anyhow::Error::from(e).context(format!("failed to load peak {} from AMT", index)),
+            )
+        })?
+        .copied()
+        .ok_or(AccumulatorError::MissingPeak(index))
+}
+
+/// Load the peaks AMT rooted at `peaks`, which must have been created with `bit_width`.
+///
+/// A bit width of 0 is that of a state from before it was recorded, which can't be trusted.
+pub(crate) fn load_peaks<'a, BS: Blockstore>(
+    store: &'a BS,
+    peaks: &Cid,
+    bit_width: u32,
+) -> Result<Amt<Cid, &'a BS>, AccumulatorError> {
+    if bit_width == 0 {
+        return Err(AccumulatorError::PeaksBitWidthUnknown);
+    }
+    let amt = Amt::<Cid, &BS>::load(peaks, store)?;
+    if amt.bit_width() != bit_width {
+        return Err(AccumulatorError::PeaksBitWidthMismatch {
+            expected: bit_width,
+            found: amt.bit_width(),
+        });
+    }
+    Ok(amt)
+}
+
+/// Collect the peaks of the MMR from left to right.
+///
+/// Peaks are always stored at the contiguous indices `0..count`, so they are read one by one,
+/// which lets a failure name the offending index.
+pub(crate) fn collect_peaks<BS: Blockstore>(
+    peaks: &Amt<Cid, &BS>,
+) -> Result<Vec<Cid>, AccumulatorError> {
+    (0..peaks.count())
+        .map(|index| peak_at(peaks, index))
+        .collect()
+}
+
+/// Check that the block of every peak above the leaves of an MMR of `leaf_count` leaves can be
+/// loaded from the store. Leaf peaks aren't checked, as leaves pushed by CID aren't in the store.
+fn check_peak_blocks<BS: Blockstore>(
+    store: &BS,
+    peaks: &[Cid],
+    leaf_count: u64,
+) -> Result<(), AccumulatorError> {
+    // Peaks are ordered by decreasing height, so only the last one can be a leaf.
+    let nodes = if leaf_count & 1 == 1 {
+        peaks.len().saturating_sub(1)
     } else {
-        Err(anyhow::anyhow!("hash_pair requires two CIDs"))
+        peaks.len()
+    };
+    for (index, cid) in peaks[..nodes].iter().enumerate() {
+        if !matches!(store.has(cid), Ok(true)) {
+            return Err(AccumulatorError::UnloadablePeak {
+                index: index as u64,
+                cid: *cid,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Check the MMR of `leaf_count` leaves whose peaks are in the AMT `peaks` against the store.
+///
+/// There must be a peak per set bit of `leaf_count`, and every peak above the leaves must load
+/// and be the hash of its children. Leaf peaks aren't loaded, as leaves pushed by CID aren't
+/// in the store.
+fn verify_peaks<BS: Blockstore>(
+    store: &BS,
+    peaks: &Cid,
+    bit_width: u32,
+    leaf_count: u64,
+    format: &NodeFormat,
+) -> Result<(), AccumulatorError> {
+    let amt = load_peaks(store, peaks, bit_width)?;
+    let expected = leaf_count.count_ones() as u64;
+    if amt.count() != expected {
+        return Err(AccumulatorError::PeakCountMismatch {
+            leaf_count,
+            expected,
+            found: amt.count(),
+        });
+    }
+    let heights = (0..u64::BITS)
+        .rev()
+        .filter(|height| (leaf_count >> height) & 1 == 1);
+    for ((index, cid), height) in collect_peaks(&amt)?.into_iter().enumerate().zip(heights) {
+        if height == 0 {
+            continue;
+        }
+        let index = index as u64;
+        let [left, right] = format.get(store, &cid).map_err(|e| match e {
+            AccumulatorError::CorruptNode(_) => AccumulatorError::UnloadablePeak { index, cid },
+            e => e,
+        })?;
+        if hash_pair(&left, &right, HASH_CODE) != cid {
+            return Err(AccumulatorError::CorruptPeak { index, cid });
+        }
     }
+    Ok(())
 }
 
-/// Return the new peaks of the accumulator after adding `new_leaf`.
-fn push<BS: Blockstore, S: DeserializeOwned + Serialize>(
+/// Return the new peaks of the accumulator after adding `leaf`.
+fn push<BS: Blockstore>(
     store: &BS,
     leaf_count: u64,
     peaks: &mut Amt<Cid, &BS>,
-    obj: S,
-) -> anyhow::Result<Cid> {
-    // Create new leaf
-    let leaf = store.put_cbor(&obj, Code::Blake2b256)?;
+    leaf: Cid,
+    format: &NodeFormat,
+) -> Result<Cid, AccumulatorError> {
     // Push the new leaf onto the peaks
     peaks.set(peaks.count(), leaf)?;
     // Count trailing ones in binary representation of the previous leaf_count
@@ -91,131 +713,107 @@ fn push<BS: Blockstore, S: DeserializeOwned + Serialize>(
     let mut new_peaks = (!leaf_count).trailing_zeros();
     while new_peaks > 0 {
         // Pop the last two peaks and push their hash
-        let right = peaks.delete(peaks.count() - 1)?;
-        let left = peaks.delete(peaks.count() - 1)?;
+        let right_index = peaks.count() - 1;
+        let right = peaks
+            .delete(right_index)?
+            .ok_or(AccumulatorError::MissingPeak(right_index))?;
+        let left_index = peaks.count() - 1;
+        let left = peaks
+            .delete(left_index)?
+            .ok_or(AccumulatorError::MissingPeak(left_index))?;
         // Push the new peak onto the peaks array
-        peaks.set(
-            peaks.count(),
-            hash_and_put_pair(store, left.as_ref(), right.as_ref())?,
-        )?;
+        peaks.set(peaks.count(), format.put(store, &left, &right)?)?;
         new_peaks -= 1;
     }
     Ok(peaks.flush()?)
 }
 
 /// Collect the peaks and combine to compute the root commitment.
-fn bag_peaks<BS: Blockstore>(peaks: &Amt<Cid, &BS>) -> anyhow::Result<Cid> {
-    let peaks_count = peaks.count();
-    // Handle special cases where we have no peaks or only one peak
-    if peaks_count == 0 {
-        return Ok(Cid::default());
-    }
-    // If there is only one leaf element, we simply "promote" that to the root peak
-    if peaks_count == 1 {
-        return Ok(peaks.get(0)?.unwrap().to_owned());
-    }
-    // Walk backward through the peaks, combining them pairwise
-    let mut root = hash_pair(peaks.get(peaks_count - 2)?, peaks.get(peaks_count - 1)?)?;
-    for i in 2..peaks_count {
-        root = hash_pair(peaks.get(peaks_count - 1 - i)?, Some(&root))?;
-    }
-    Ok(root)
-}
-
-/// Given the size of the MMR and an index into the MMR, returns a tuple where the first element
-/// represents the path through the subtree that the leaf node lives in.
-/// The second element represents the index of the peak containing the subtree that the leaf node
-/// lives in.
-fn path_for_eigen_root(leaf_index: u64, leaf_count: u64) -> anyhow::Result<(u64, u64)> {
-    // Ensure `leaf_index` is within bounds.
-    if leaf_index >= leaf_count {
-        return Err(anyhow::anyhow!("`leaf_index` must less than `leaf_count`"));
-    }
-    // XOR turns matching bits into zeros and differing bits into ones, so to determine when
-    // the two "paths" converge, we simply look for the most significant 1 bit...
-    let diff = leaf_index ^ leaf_count;
-    // ...and then merge height of `leaf_index` and `leaf_count` occurs at ⌊log2(x ⊕ y)⌋
-    let eigentree_height = u64::BITS - diff.leading_zeros() - 1;
-    let merge_height = 1 << eigentree_height;
-    // Compute a bitmask (all the lower bits set to 1)
-    let bitmask = merge_height - 1;
-    // The Hamming weight of leaf_count is the number of eigentrees in the structure.
-    let eigentree_count = leaf_count.count_ones();
-    // Isolates the lower bits of leaf_count up to the merge_height, and count the one bits.
-    // This is essentially the offset to the eigentree containing leaf_index
-    let offset = (leaf_count & bitmask).count_ones();
-    // The index is simply the total eigentree count minus the offset (minus one)
-    let eigen_index = eigentree_count - offset - 1;
-    // Now that we have the offset, we need to determine the path within the local eigentree
-    let local_offset = leaf_index & bitmask;
-    // The local_index is the local_offset plus the merge_height for the local eigentree
-    let local_path = local_offset + merge_height;
-    Ok((local_path, eigen_index as u64))
-}
-
-fn get_at<BS: Blockstore, S: DeserializeOwned + Serialize>(
+fn bag_peaks<BS: Blockstore>(
+    peaks: &Amt<Cid, &BS>,
+    mh_code: Code,
+    scheme: RootScheme,
+) -> Result<Cid, AccumulatorError> {
+    Ok(bag_peak_cids(&collect_peaks(peaks)?, mh_code, scheme))
+}
+
+/// Get the CID of the leaf at `leaf_index` by walking down from its peak.
+fn get_at<BS: Blockstore>(
     store: &BS,
     leaf_index: u64,
     leaf_count: u64,
     peaks: &Amt<Cid, &BS>,
-) -> anyhow::Result<S> {
+    format: &NodeFormat,
+) -> Result<Cid, AccumulatorError> {
     let (path, eigen_index) = path_for_eigen_root(leaf_index, leaf_count)?;
-    let cid = match peaks.get(eigen_index)? {
-        Some(cid) => cid,
-        None => {
-            return Err(anyhow::anyhow!(
-                "failed to get peak at index {}",
-                eigen_index
-            ))
-        }
-    };
+    let cid = peak_at(peaks, eigen_index)?;
     // Special case where eigentree has height of one
     if path == 1 {
-        return match store.get_cbor::<S>(cid)? {
-            Some(value) => Ok(value),
-            None => Err(anyhow::anyhow!("failed to get leaf for cid {}", cid)),
-        };
+        return Ok(cid);
     }
 
-    let mut pair = match store.get_cbor::<[Cid; 2]>(cid)? {
-        Some(value) => value,
-        None => {
-            return Err(anyhow::anyhow!(
-                "failed to get eigentree root node for cid {}",
-                cid
-            ))
-        }
-    };
-
     let leading_zeros = path.leading_zeros();
     let significant_bits = 64 - leading_zeros;
 
+    // The peak is at the height of the eigentree, one less than the significant bits
+    let mut pair = format.get(store, &cid)?;
+
     // Iterate over each bit from the most significant bit to the least
     for i in 1..(significant_bits - 1) {
         let bit = ((path >> (significant_bits - i - 1)) & 1) as usize;
-        let cid = &pair[bit];
-        pair = match store.get_cbor(cid)? {
-            Some(root) => root,
+        pair = format.get(store, &pair[bit])?;
+    }
+
+    let bit = (path & 1) as usize;
+    Ok(pair[bit])
+}
+
+/// Build the inclusion proof of a leaf by walking down from its peak.
+/// Eigentree nodes are loaded through `nodes`, so that proofs sharing a subtree only
+/// read its nodes from the store once.
+fn proof_at<BS: Blockstore>(
+    store: &BS,
+    leaf_index: u64,
+    leaf_count: u64,
+    peaks: &[Cid],
+    format: &NodeFormat,
+    nodes: &mut HashMap<Cid, [Cid; 2]>,
+) -> Result<Proof, AccumulatorError> {
+    let (path, eigen_index) = path_for_eigen_root(leaf_index, leaf_count)?;
+    let mut cid = match peaks.get(eigen_index as usize) {
+        Some(cid) => *cid,
+        None => return Err(AccumulatorError::MissingPeak(eigen_index)),
+    };
+
+    // Iterate over each bit of the path below the most significant one, which marks the peak
+    let significant_bits = u64::BITS - path.leading_zeros();
+    let mut siblings = Vec::with_capacity(significant_bits as usize - 1);
+    for i in (0..significant_bits - 1).rev() {
+        let pair = match nodes.get(&cid) {
+            Some(pair) => *pair,
             None => {
-                return Err(anyhow::anyhow!(
-                    "failed to get eigentree intermediate node for cid {}",
-                    cid
-                ))
+                let pair = format.get(store, &cid)?;
+                nodes.insert(cid, pair);
+                pair
             }
         };
+        let bit = ((path >> i) & 1) as usize;
+        siblings.push(pair[1 - bit]);
+        cid = pair[bit];
     }
+    siblings.reverse();
 
-    let bit = (path & 1) as usize;
-    let cid = &pair[bit];
-    let leaf = match store.get_cbor::<S>(cid)? {
-        Some(root) => root,
-        None => return Err(anyhow::anyhow!("failed to get leaf for cid {}", cid)),
-    };
-    Ok(leaf)
+    Ok(Proof {
+        index: leaf_index,
+        leaf_count,
+        leaf: cid,
+        siblings,
+        peaks: peaks.to_vec(),
+    })
 }
 
 /// The state represents an MMR with peaks stored in an AMT
-#[derive(Serialize_tuple, Deserialize_tuple)]
+#[derive(Clone, Serialize_tuple, Deserialize_tuple)]
 pub struct State {
     /// The machine rubust owner address.
     pub owner: Address,
@@ -225,6 +823,39 @@ pub struct State {
     pub peaks: Cid,
     /// Number of leaf nodes in the accumulator MMR.
     pub leaf_count: u64,
+    /// The codec used to store byte leaves.
+    pub leaf_codec: LeafCodec,
+    /// Root of the AMT storing leaf metadata by leaf index.
+    /// It is kept apart from the MMR, so metadata is not committed to the root.
+    pub leaf_metadata: Cid,
+    /// Whether the accumulator is frozen. A frozen accumulator can be read but not pushed to.
+    pub frozen: bool,
+    /// Maximum number of pushes accepted in a single epoch, if limited.
+    pub max_pushes_per_epoch: Option<u64>,
+    /// The epoch of the last counted push.
+    pub push_epoch: ChainEpoch,
+    /// Number of pushes counted in `push_epoch`.
+    pub push_count: u64,
+    /// How the peaks are bagged into the root.
+    pub root_scheme: RootScheme,
+    /// Number of most recent leaves a push of an identical leaf is deduplicated against,
+    /// or 0 not to deduplicate pushes.
+    pub dedup_window: u64,
+    /// Root of the AMT holding the CIDs of the recent leaves, by index modulo the window.
+    pub recent_leaves: Cid,
+    /// Root of the HAMT holding the index of each of the recent leaves, by CID.
+    pub recent_index: Cid,
+    /// Root of the HAMT holding the named namespaces, by name.
+    pub namespaces: Cid,
+    /// Number of named namespaces, which have at least one leaf each.
+    pub namespace_count: u64,
+    /// Bit width of the peaks AMTs of all namespaces, or 0 in states from before it was
+    /// recorded.
+    #[serde(default)]
+    pub peaks_bit_width: u32,
+    /// Application-defined metadata, e.g. a name or a schema ID.
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
 }
 
 impl MachineState for State {
@@ -239,6 +870,18 @@ impl MachineState for State {
     fn write_access(&self) -> WriteAccess {
         self.write_access
     }
+
+    fn frozen(&self) -> bool {
+        self.frozen
+    }
+
+    fn metadata(&self) -> &HashMap<String, String> {
+        &self.metadata
+    }
+
+    fn set_metadata(&mut self, metadata: HashMap<String, String>) {
+        self.metadata = metadata;
+    }
 }
 
 impl State {
@@ -256,11 +899,33 @@ impl State {
                 ));
             }
         };
+        let leaf_metadata =
+            Amt::<HashMap<String, String>, _>::new_with_bit_width(store, BIT_WIDTH).flush()?;
+        let recent_leaves =
+            Amt::<Cid, _>::new_with_bit_width(store, RECENT_LEAVES_BIT_WIDTH).flush()?;
+        let recent_index =
+            Hamt::<_, u64>::new_with_bit_width(store, RECENT_LEAVES_BIT_WIDTH).flush()?;
+        let namespaces =
+            Hamt::<_, Namespace>::new_with_bit_width(store, NAMESPACES_BIT_WIDTH).flush()?;
         Ok(Self {
             owner: creator,
             write_access,
             peaks,
             leaf_count: 0,
+            leaf_codec: LeafCodec::default(),
+            leaf_metadata,
+            frozen: false,
+            max_pushes_per_epoch: None,
+            push_epoch: 0,
+            push_count: 0,
+            root_scheme: RootScheme::default(),
+            dedup_window: 0,
+            recent_leaves,
+            recent_index,
+            namespaces,
+            namespace_count: 0,
+            peaks_bit_width: BIT_WIDTH,
+            metadata: HashMap::new(),
         })
     }
 
@@ -272,57 +937,777 @@ impl State {
         self.leaf_count
     }
 
+    /// Whether no leaf was pushed into any namespace.
+    fn is_empty(&self) -> bool {
+        self.leaf_count == 0 && self.namespace_count == 0
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    /// Freezes the accumulator, so that no more leaves can be pushed.
+    /// Freezing a frozen accumulator has no effect.
+    pub fn freeze(&mut self) {
+        self.frozen = true;
+    }
+
+    /// Sets the codec used to store byte leaves.
+    /// The codec can only be changed while the accumulator is empty.
+    pub fn set_leaf_codec(&mut self, leaf_codec: LeafCodec) -> anyhow::Result<()> {
+        if !self.is_empty() {
+            return Err(anyhow::anyhow!(
+                "cannot change leaf codec of a non-empty accumulator"
+            ));
+        }
+        self.leaf_codec = leaf_codec;
+        Ok(())
+    }
+
+    /// Sets how the peaks are bagged into the root.
+    /// The scheme can only be changed while the accumulator is empty.
+    pub fn set_root_scheme(&mut self, scheme: RootScheme) -> anyhow::Result<()> {
+        if !self.is_empty() {
+            return Err(anyhow::anyhow!(
+                "cannot change root scheme of a non-empty accumulator"
+            ));
+        }
+        self.root_scheme = scheme;
+        Ok(())
+    }
+
+    /// The format of the eigentree nodes, which follows from the settings of the accumulator.
+    pub(crate) fn node_format(&self) -> NodeFormat {
+        NodeFormat::new(self.leaf_codec)
+    }
+
+    /// Sets the maximum number of pushes accepted in a single epoch, or lifts the limit with `None`.
+    /// The limit can only be changed while the accumulator is empty.
+    pub fn set_max_pushes_per_epoch(&mut self, max: Option<u64>) -> anyhow::Result<()> {
+        if !self.is_empty() {
+            return Err(anyhow::anyhow!(
+                "cannot change push limit of a non-empty accumulator"
+            ));
+        }
+        self.max_pushes_per_epoch = max;
+        Ok(())
+    }
+
+    /// Sets the number of most recent leaves a push of an identical leaf is deduplicated
+    /// against, or 0 not to deduplicate pushes. Deduplicated pushes return the index of the
+    /// existing leaf instead of appending it again, so clients can safely retry a push whose
+    /// outcome they don't know. The window can only be changed while the accumulator is empty.
+    pub fn set_dedup_window(&mut self, window: u64) -> anyhow::Result<()> {
+        if !self.is_empty() {
+            return Err(anyhow::anyhow!(
+                "cannot change dedup window of a non-empty accumulator"
+            ));
+        }
+        if window > MAX_DEDUP_WINDOW {
+            return Err(anyhow::anyhow!(
+                "dedup window {} exceeds maximum {}",
+                window,
+                MAX_DEDUP_WINDOW
+            ));
+        }
+        self.dedup_window = window;
+        Ok(())
+    }
+
+    /// Replays a batch of exported leaves, returning the new root.
+    ///
+    /// A batch starting at leaf 0 begins a new import, which is only allowed into an empty
+    /// accumulator unless `force` is set, in which case the existing leaves and their metadata
+    /// are dropped. The named namespaces and the metadata of the accumulator are kept. Any other
+    /// batch has to continue at the current leaf count, with the same codec, hash function and
+    /// root scheme. The batch is rejected, leaving the state untouched, if the
+    /// resulting root doesn't match the expected one.
+    pub fn import_leaves<BS: Blockstore>(
+        &mut self,
+        store: &BS,
+        params: ImportLeavesParams,
+    ) -> anyhow::Result<Cid> {
+        if self.frozen {
+            return Err(AccumulatorError::Frozen.into());
+        }
+        let mut next = if params.start == 0 {
+            if self.leaf_count > 0 && !params.force {
+                return Err(AccumulatorError::ImportWithoutForce.into());
+            }
+            // The named namespaces are kept, so their leaves have to stay readable.
+            if self.namespace_count > 0
+                && (params.leaf_codec != self.leaf_codec || params.root_scheme != self.root_scheme)
+            {
+                return Err(AccumulatorError::ImportNamespaceSettingsMismatch.into());
+            }
+            let mut fresh = State::new(store, self.owner, self.write_access)?;
+            fresh.leaf_codec = params.leaf_codec;
+            fresh.root_scheme = params.root_scheme;
+            fresh.dedup_window = self.dedup_window;
+            fresh.max_pushes_per_epoch = self.max_pushes_per_epoch;
+            fresh.push_epoch = self.push_epoch;
+            fresh.push_count = self.push_count;
+            fresh.namespaces = self.namespaces;
+            fresh.namespace_count = self.namespace_count;
+            fresh.metadata = self.metadata.clone();
+            // All namespaces share the bit width of their peaks AMTs, so the new default one
+            // has to be created with the bit width of the named namespaces it is kept with.
+            if self.namespace_count > 0 && self.peaks_bit_width != fresh.peaks_bit_width {
+                if self.peaks_bit_width == 0 {
+                    return Err(AccumulatorError::PeaksBitWidthUnknown.into());
+                }
+                fresh.peaks =
+                    Amt::<Cid, _>::new_with_bit_width(store, self.peaks_bit_width).flush()?;
+                fresh.peaks_bit_width = self.peaks_bit_width;
+            }
+            fresh
+        } else {
+            if params.start != self.leaf_count {
+                return Err(AccumulatorError::ImportOutOfOrder {
+                    start: params.start,
+                    expected: self.leaf_count,
+                }
+                .into());
+            }
+            if params.leaf_codec != self.leaf_codec || params.root_scheme != self.root_scheme {
+                return Err(AccumulatorError::ImportSettingsMismatch.into());
+            }
+            self.clone()
+        };
+        // Exports may hold identical leaves, which are replayed as they are.
+        for leaf in params.leaves {
+            let leaf = next.put_bytes(store, leaf.0)?;
+            next.append_leaf(store, leaf)?;
+        }
+        let root = next.get_root(store)?;
+        if root != params.expected_root {
+            return Err(AccumulatorError::ImportRootMismatch {
+                root,
+                expected: params.expected_root,
+            }
+            .into());
+        }
+        *self = next;
+        Ok(root)
+    }
+
+    /// Counts a push in `epoch`, failing if the limit for the epoch was already reached.
+    /// The count starts over with each new epoch.
+    pub fn count_push(&mut self, epoch: ChainEpoch) -> Result<(), AccumulatorError> {
+        if epoch != self.push_epoch {
+            self.push_epoch = epoch;
+            self.push_count = 0;
+        }
+        if let Some(limit) = self.max_pushes_per_epoch {
+            if self.push_count >= limit {
+                return Err(AccumulatorError::RateLimited { epoch, limit });
+            }
+        }
+        self.push_count += 1;
+        Ok(())
+    }
+
+    /// Pushes a typed object, stored as DAG-CBOR.
     pub fn push<BS: Blockstore, S: DeserializeOwned + Serialize>(
         &mut self,
         store: &BS,
         obj: S,
     ) -> anyhow::Result<PushReturn> {
-        let mut amt = Amt::<Cid, &BS>::load(&self.peaks, store)?;
-        self.peaks = push(store, self.leaf_count, &mut amt, obj)?;
-        self.leaf_count += 1;
+        let leaf = store.put_cbor(&obj, HASH_CODE)?;
+        self.push_leaf(store, leaf)
+    }
+
+    /// Pushes the CID of a leaf stored elsewhere, as it is.
+    ///
+    /// The CID must be a CIDv1 with the codec and hash function of the accumulator, so it is
+    /// indistinguishable from the leaves pushed as bytes.
+    pub fn push_cid<BS: Blockstore>(&mut self, store: &BS, cid: Cid) -> anyhow::Result<PushReturn> {
+        if !self.node_format().matches_leaf(&cid) {
+            return Err(AccumulatorError::MismatchedLeaf(cid).into());
+        }
+        self.push_leaf(store, cid)
+    }
+
+    /// Pushes bytes, stored with the leaf codec of the accumulator.
+    pub fn push_bytes<BS: Blockstore>(
+        &mut self,
+        store: &BS,
+        bytes: Vec<u8>,
+    ) -> anyhow::Result<PushReturn> {
+        let leaf = self.put_bytes(store, bytes)?;
+        self.push_leaf(store, leaf)
+    }
+
+    /// Pushes bytes into `namespace`, creating it with the first push.
+    ///
+    /// The empty namespace is the default one, see [`State::push_bytes`]. Pushes into named
+    /// namespaces are not deduplicated.
+    pub fn push_bytes_in<BS: Blockstore>(
+        &mut self,
+        store: &BS,
+        namespace: &[u8],
+        bytes: Vec<u8>,
+    ) -> anyhow::Result<PushReturn> {
+        if namespace.is_empty() {
+            return self.push_bytes(store, bytes);
+        }
+        if self.frozen {
+            return Err(AccumulatorError::Frozen.into());
+        }
+        let leaf = self.put_bytes(store, bytes)?;
+        let format = self.node_format();
+
+        let mut namespaces = Hamt::<&BS, Namespace>::load_with_bit_width(
+            &self.namespaces,
+            store,
+            NAMESPACES_BIT_WIDTH,
+        )?;
+        let key = BytesKey(namespace.to_vec());
+        let mut ns = match namespaces.get(&key)? {
+            Some(ns) => ns.clone(),
+            None => {
+                self.namespace_count += 1;
+                Namespace {
+                    peaks: Amt::<Cid, _>::new_with_bit_width(store, self.peaks_bit_width)
+                        .flush()?,
+                    leaf_count: 0,
+                }
+            }
+        };
+        let mut amt = load_peaks(store, &ns.peaks, self.peaks_bit_width)?;
+        ns.peaks = push(store, ns.leaf_count, &mut amt, leaf, &format)?;
+        ns.leaf_count += 1;
+        let index = ns.leaf_count - 1;
+        namespaces.set(key, ns)?;
+        self.namespaces = namespaces.flush()?;
 
-        let root = bag_peaks(&amt)?;
+        let root = bag_peaks(&amt, HASH_CODE, self.root_scheme)?;
         Ok(PushReturn {
             root,
-            index: self.leaf_count - 1,
+            index,
+            peaks: collect_peaks(&amt)?,
+            proof: None,
+            deduplicated: false,
         })
     }
 
-    pub fn get_root<BS: Blockstore>(&self, store: &BS) -> anyhow::Result<Cid> {
-        let amt = Amt::<Cid, &BS>::load(&self.peaks, store)?;
-        bag_peaks(&amt)
+    /// Get the MMR of `namespace`, or `None` if nothing was pushed into it.
+    ///
+    /// The empty namespace is the default one, made of [`State::peaks`] and
+    /// [`State::leaf_count`].
+    pub fn get_namespace<BS: Blockstore>(
+        &self,
+        store: &BS,
+        namespace: &[u8],
+    ) -> anyhow::Result<Option<Namespace>> {
+        if namespace.is_empty() {
+            return Ok(Some(Namespace {
+                peaks: self.peaks,
+                leaf_count: self.leaf_count,
+            }));
+        }
+        let namespaces = Hamt::<&BS, Namespace>::load_with_bit_width(
+            &self.namespaces,
+            store,
+            NAMESPACES_BIT_WIDTH,
+        )?;
+        Ok(namespaces.get(&BytesKey(namespace.to_vec()))?.cloned())
     }
 
-    pub fn get_peaks<BS: Blockstore>(&self, store: &BS) -> anyhow::Result<Vec<Cid>> {
-        let amt = Amt::<Cid, &BS>::load(&self.peaks, store)?;
-        let mut peaks = Vec::new();
-        amt.for_each(|_, cid| {
-            peaks.push(cid.to_owned());
-            Ok(())
-        })?;
-        Ok(peaks)
+    /// Number of leaves in `namespace`.
+    pub fn leaf_count_in<BS: Blockstore>(
+        &self,
+        store: &BS,
+        namespace: &[u8],
+    ) -> anyhow::Result<u64> {
+        Ok(self
+            .get_namespace(store, namespace)?
+            .map_or(0, |ns| ns.leaf_count))
     }
 
-    pub fn get_leaf_at<BS: Blockstore, S: DeserializeOwned + Serialize>(
+    /// Get the root commitment of the MMR of `namespace`.
+    /// A namespace nothing was pushed into has the root of an empty accumulator.
+    pub fn get_root_in<BS: Blockstore>(&self, store: &BS, namespace: &[u8]) -> anyhow::Result<Cid> {
+        let peaks = match self.get_namespace(store, namespace)? {
+            Some(ns) => {
+                let peaks = collect_peaks(&load_peaks(store, &ns.peaks, self.peaks_bit_width)?)?;
+                check_peak_blocks(store, &peaks, ns.leaf_count)?;
+                peaks
+            }
+            None => Vec::new(),
+        };
+        Ok(bag_peak_cids(&peaks, HASH_CODE, self.root_scheme))
+    }
+
+    /// Get the bytes leaf at `index` of `namespace`, or `None` if the index is out of range.
+    pub fn get_leaf_bytes_in<BS: Blockstore>(
         &self,
         store: &BS,
+        namespace: &[u8],
         index: u64,
-    ) -> anyhow::Result<Option<S>> {
-        let amt = Amt::<Cid, &BS>::load(&self.peaks, store)?;
-        let leaf = match get_at::<BS, S>(store, index, self.leaf_count, &amt) {
-            Ok(leaf) => Some(leaf),
-            Err(_) => None,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        let Some(ns) = self.get_namespace(store, namespace)? else {
+            return Ok(None);
         };
-        Ok(leaf)
+        if index >= ns.leaf_count {
+            return Ok(None);
+        }
+        let amt = load_peaks(store, &ns.peaks, self.peaks_bit_width)?;
+        let cid = get_at(store, index, ns.leaf_count, &amt, &self.node_format())?;
+        self.read_leaf_bytes(store, &cid).map(Some)
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::str::FromStr;
 
-    #[test]
+    /// Get the inclusion proof of the leaf at `index` of `namespace`, against its root.
+    pub fn get_proof_in<BS: Blockstore>(
+        &self,
+        store: &BS,
+        namespace: &[u8],
+        index: u64,
+    ) -> anyhow::Result<Proof> {
+        let Some(ns) = self.get_namespace(store, namespace)? else {
+            return Err(AccumulatorError::IndexOutOfRange {
+                index,
+                leaf_count: 0,
+            }
+            .into());
+        };
+        let peaks = collect_peaks(&load_peaks(store, &ns.peaks, self.peaks_bit_width)?)?;
+        check_peak_blocks(store, &peaks, ns.leaf_count)?;
+        Ok(proof_at(
+            store,
+            index,
+            ns.leaf_count,
+            &peaks,
+            &self.node_format(),
+            &mut HashMap::new(),
+        )?)
+    }
+
+    /// Stores bytes with the leaf codec of the accumulator, returning the CID
+    /// of the leaf.
+    fn put_bytes<BS: Blockstore>(&self, store: &BS, bytes: Vec<u8>) -> anyhow::Result<Cid> {
+        let (leaf, data) = self.leaf_codec.encode_leaf(&bytes)?;
+        store.put_keyed(&leaf, &data)?;
+        Ok(leaf)
+    }
+
+    /// Pushes a leaf, unless it's one of the recent leaves.
+    fn push_leaf<BS: Blockstore>(&mut self, store: &BS, leaf: Cid) -> anyhow::Result<PushReturn> {
+        if self.frozen {
+            return Err(AccumulatorError::Frozen.into());
+        }
+        if let Some(index) = self.recent_index_of(store, &leaf)? {
+            let amt = load_peaks(store, &self.peaks, self.peaks_bit_width)?;
+            return Ok(PushReturn {
+                root: bag_peaks(&amt, HASH_CODE, self.root_scheme)?,
+                index,
+                peaks: collect_peaks(&amt)?,
+                proof: None,
+                deduplicated: true,
+            });
+        }
+        self.append_leaf(store, leaf)
+    }
+
+    fn append_leaf<BS: Blockstore>(&mut self, store: &BS, leaf: Cid) -> anyhow::Result<PushReturn> {
+        let format = self.node_format();
+        let mut amt = load_peaks(store, &self.peaks, self.peaks_bit_width)?;
+        self.peaks = push(store, self.leaf_count, &mut amt, leaf, &format)?;
+        self.leaf_count += 1;
+        let index = self.leaf_count - 1;
+        self.remember_leaf(store, leaf, index)?;
+
+        let root = bag_peaks(&amt, HASH_CODE, self.root_scheme)?;
+        Ok(PushReturn {
+            root,
+            index,
+            peaks: collect_peaks(&amt)?,
+            proof: None,
+            deduplicated: false,
+        })
+    }
+
+    /// The index of `leaf` if it's one of the recent leaves.
+    fn recent_index_of<BS: Blockstore>(
+        &self,
+        store: &BS,
+        leaf: &Cid,
+    ) -> anyhow::Result<Option<u64>> {
+        if self.dedup_window == 0 {
+            return Ok(None);
+        }
+        let index = Hamt::<&BS, u64>::load_with_bit_width(
+            &self.recent_index,
+            store,
+            RECENT_LEAVES_BIT_WIDTH,
+        )?;
+        Ok(index.get(&BytesKey(leaf.to_bytes()))?.copied())
+    }
+
+    /// Remembers `leaf` as one of the recent leaves, forgetting the one that falls out of the
+    /// window, so at most `dedup_window` leaves are remembered.
+    fn remember_leaf<BS: Blockstore>(
+        &mut self,
+        store: &BS,
+        leaf: Cid,
+        index: u64,
+    ) -> anyhow::Result<()> {
+        if self.dedup_window == 0 {
+            return Ok(());
+        }
+        let mut leaves = Amt::<Cid, &BS>::load(&self.recent_leaves, store)?;
+        let mut by_cid = Hamt::<&BS, u64>::load_with_bit_width(
+            &self.recent_index,
+            store,
+            RECENT_LEAVES_BIT_WIDTH,
+        )?;
+        let slot = index % self.dedup_window;
+        if let Some(old) = leaves.get(slot)? {
+            let key = BytesKey(old.to_bytes());
+            // Imports append identical leaves, so the old leaf may have been appended again.
+            if by_cid.get(&key)? == Some(&(index - self.dedup_window)) {
+                by_cid.delete(&key)?;
+            }
+        }
+        leaves.set(slot, leaf)?;
+        by_cid.set(BytesKey(leaf.to_bytes()), index)?;
+        self.recent_leaves = leaves.flush()?;
+        self.recent_index = by_cid.flush()?;
+        Ok(())
+    }
+
+    /// Stores metadata for the leaf at `index`, replacing any existing metadata.
+    pub fn set_leaf_metadata<BS: Blockstore>(
+        &mut self,
+        store: &BS,
+        index: u64,
+        metadata: HashMap<String, String>,
+    ) -> anyhow::Result<()> {
+        if index >= self.leaf_count {
+            return Err(AccumulatorError::IndexOutOfRange {
+                index,
+                leaf_count: self.leaf_count,
+            }
+            .into());
+        }
+        let mut amt = Amt::<HashMap<String, String>, &BS>::load(&self.leaf_metadata, store)?;
+        amt.set(index, metadata)?;
+        self.leaf_metadata = amt.flush()?;
+        Ok(())
+    }
+
+    /// Returns the metadata of the leaf at `index`, if any was stored.
+    pub fn get_leaf_metadata<BS: Blockstore>(
+        &self,
+        store: &BS,
+        index: u64,
+    ) -> anyhow::Result<Option<HashMap<String, String>>> {
+        let amt = Amt::<HashMap<String, String>, &BS>::load(&self.leaf_metadata, store)?;
+        Ok(amt.get(index)?.cloned())
+    }
+
+    /// Get the root commitment of the MMR, bagged from its peaks.
+    ///
+    /// Fails with [`AccumulatorError::UnloadablePeak`] if the block of a peak above the leaves
+    /// is missing.
+    pub fn get_root<BS: Blockstore>(&self, store: &BS) -> anyhow::Result<Cid> {
+        let peaks = self.get_peaks(store)?;
+        Ok(bag_peak_cids(&peaks, HASH_CODE, self.root_scheme))
+    }
+
+    /// Get the peaks of the MMR from left to right.
+    ///
+    /// Fails with [`AccumulatorError::UnloadablePeak`] if the block of a peak above the leaves
+    /// is missing.
+    pub fn get_peaks<BS: Blockstore>(&self, store: &BS) -> anyhow::Result<Vec<Cid>> {
+        let amt = load_peaks(store, &self.peaks, self.peaks_bit_width)?;
+        let peaks = collect_peaks(&amt)?;
+        check_peak_blocks(store, &peaks, self.leaf_count)?;
+        Ok(peaks)
+    }
+
+    /// Check that the leaf counts agree with the peaks, which are kept apart, in every namespace.
+    ///
+    /// Each namespace must have a peak per set bit of its leaf count, and each peak above the
+    /// leaves must load and be the hash of its children, so that the root bagged from the peaks
+    /// is backed by the store. This is a self-check for operators, a mismatch means a bug or a
+    /// corrupt store. Errors are [`AccumulatorError`]s, with the name of the namespace as
+    /// context for the named ones.
+    pub fn verify_integrity<BS: Blockstore>(&self, store: &BS) -> anyhow::Result<()> {
+        let format = self.node_format();
+        verify_peaks(
+            store,
+            &self.peaks,
+            self.peaks_bit_width,
+            self.leaf_count,
+            &format,
+        )?;
+        let namespaces = Hamt::<&BS, Namespace>::load_with_bit_width(
+            &self.namespaces,
+            store,
+            NAMESPACES_BIT_WIDTH,
+        )?;
+        let mut named = Vec::new();
+        namespaces.for_each(|name, namespace| {
+            named.push((name.clone(), namespace.clone()));
+            Ok(())
+        })?;
+        for (name, namespace) in &named {
+            verify_peaks(
+                store,
+                &namespace.peaks,
+                self.peaks_bit_width,
+                namespace.leaf_count,
+                &format,
+            )
+            .map_err(|e| {
+                anyhow::Error::from(e)
+                    .context(format!("namespace {}", String::from_utf8_lossy(name)))
+            })?;
+        }
+        let found = named.len() as u64;
+        if found != self.namespace_count {
+            return Err(AccumulatorError::NamespaceCountMismatch {
+                expected: self.namespace_count,
+                found,
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Get the peaks of the MMR paired with their heights, from left to right.
+    ///
+    /// A peak of height `h` roots a perfect subtree of `2^h` leaves, so the heights follow
+    /// from the set bits of `leaf_count`, most significant first.
+    pub fn get_peaks_with_heights<BS: Blockstore>(
+        &self,
+        store: &BS,
+    ) -> anyhow::Result<Vec<(Cid, u32)>> {
+        let peaks = self.get_peaks(store)?;
+        if peaks.len() != self.peak_count() as usize {
+            return Err(AccumulatorError::PeakCountMismatch {
+                leaf_count: self.leaf_count,
+                expected: self.peak_count() as u64,
+                found: peaks.len() as u64,
+            }
+            .into());
+        }
+        let heights = (0..u64::BITS)
+            .rev()
+            .filter(|height| (self.leaf_count >> height) & 1 == 1);
+        Ok(peaks.into_iter().zip(heights).collect())
+    }
+
+    /// Get the inclusion proof of the leaf at `index`.
+    pub fn get_proof<BS: Blockstore>(&self, store: &BS, index: u64) -> anyhow::Result<Proof> {
+        let peaks = self.get_peaks(store)?;
+        Ok(proof_at(
+            store,
+            index,
+            self.leaf_count,
+            &peaks,
+            &self.node_format(),
+            &mut HashMap::new(),
+        )?)
+    }
+
+    /// Get the inclusion proofs of the leaves at `indices`, in the same order.
+    /// Nodes shared between the proofs are only loaded once.
+    pub fn get_proofs<BS: Blockstore>(
+        &self,
+        store: &BS,
+        indices: &[u64],
+    ) -> anyhow::Result<Vec<Proof>> {
+        let peaks = self.get_peaks(store)?;
+        let format = self.node_format();
+        let mut nodes = HashMap::new();
+        let proofs = indices
+            .iter()
+            .map(|index| proof_at(store, *index, self.leaf_count, &peaks, &format, &mut nodes))
+            .collect::<Result<_, _>>()?;
+        Ok(proofs)
+    }
+
+    /// Get the bytes leaf at `index` with its inclusion proof and the root it verifies against,
+    /// or `None` if the index is out of range.
+    ///
+    /// The leaf is located by the walk that builds the proof, so the peak is only traversed once.
+    /// Fails with [`AccumulatorError::UnloadablePeak`] if the block of a peak above the leaves
+    /// is missing, like [`State::get_root`].
+    pub fn get_with_proof<BS: Blockstore>(
+        &self,
+        store: &BS,
+        index: u64,
+    ) -> anyhow::Result<Option<GetWithProofReturn>> {
+        if index >= self.leaf_count {
+            return Ok(None);
+        }
+        let amt = load_peaks(store, &self.peaks, self.peaks_bit_width)?;
+        let peaks = collect_peaks(&amt)?;
+        check_peak_blocks(store, &peaks, self.leaf_count)?;
+        let root = bag_peak_cids(&peaks, HASH_CODE, self.root_scheme);
+        let proof = proof_at(
+            store,
+            index,
+            self.leaf_count,
+            &peaks,
+            &self.node_format(),
+            &mut HashMap::new(),
+        )?;
+        let leaf = self.read_leaf_bytes(store, &proof.leaf)?;
+        Ok(Some(GetWithProofReturn {
+            leaf,
+            proof,
+            leaf_count: self.leaf_count,
+            root,
+        }))
+    }
+
+    /// Check that `leaves` hash up to the peak at `peak_index`, and that the peak is
+    /// `expected_cid`.
+    ///
+    /// Returns `false` if the peak doesn't exist, or if the number of leaves doesn't match its height.
+    pub fn verify_peak<BS: Blockstore>(
+        &self,
+        store: &BS,
+        peak_index: u64,
+        expected_cid: &Cid,
+        leaves: &[Cid],
+    ) -> anyhow::Result<bool> {
+        let peaks = self.get_peaks_with_heights(store)?;
+        let Some((peak, height)) = peaks.get(peak_index as usize) else {
+            return Ok(false);
+        };
+        if peak != expected_cid || leaves.len() as u64 != 1 << height {
+            return Ok(false);
+        }
+        Ok(peak_from_leaves(leaves, HASH_CODE).as_ref() == Some(peak))
+    }
+
+    /// Read the bytes leaf stored under `cid`, decoded with the leaf codec of the accumulator.
+    fn read_leaf_bytes<BS: Blockstore>(&self, store: &BS, cid: &Cid) -> anyhow::Result<Vec<u8>> {
+        let data = store.get(cid)?.ok_or(AccumulatorError::CorruptNode(*cid))?;
+        Ok(self.leaf_codec.decode_leaf(&data)?)
+    }
+
+    /// Get the leaf at `index`, or `None` if the index is out of range.
+    ///
+    /// A missing node for an index in range means the store is corrupt, which is an error.
+    pub fn get_leaf_at<BS: Blockstore, S: DeserializeOwned + Serialize>(
+        &self,
+        store: &BS,
+        index: u64,
+    ) -> anyhow::Result<Option<S>> {
+        if index >= self.leaf_count {
+            return Ok(None);
+        }
+        let amt = load_peaks(store, &self.peaks, self.peaks_bit_width)?;
+        let cid = get_at(store, index, self.leaf_count, &amt, &self.node_format())?;
+        match store.get_cbor::<S>(&cid)? {
+            Some(leaf) => Ok(Some(leaf)),
+            None => Err(AccumulatorError::CorruptNode(cid).into()),
+        }
+    }
+
+    /// Get the bytes leaf at `index`, decoded with the leaf codec of the accumulator,
+    /// or `None` if the index is out of range.
+    pub fn get_leaf_bytes_at<BS: Blockstore>(
+        &self,
+        store: &BS,
+        index: u64,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        if index >= self.leaf_count {
+            return Ok(None);
+        }
+        let amt = load_peaks(store, &self.peaks, self.peaks_bit_width)?;
+        let cid = get_at(store, index, self.leaf_count, &amt, &self.node_format())?;
+        self.read_leaf_bytes(store, &cid).map(Some)
+    }
+
+    /// Get the CID of the leaf at `index`, without loading the leaf itself,
+    /// or `None` if the index is out of range.
+    ///
+    /// Unlike the other getters, this works for leaves pushed with [`State::push_cid`],
+    /// whose content isn't in the store.
+    pub fn get_leaf_cid_at<BS: Blockstore>(
+        &self,
+        store: &BS,
+        index: u64,
+    ) -> anyhow::Result<Option<Cid>> {
+        if index >= self.leaf_count {
+            return Ok(None);
+        }
+        let amt = load_peaks(store, &self.peaks, self.peaks_bit_width)?;
+        let cid = get_at(store, index, self.leaf_count, &amt, &self.node_format())?;
+        Ok(Some(cid))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fendermint_actor_accumulator_verifier::{hash_pair, hash_single};
+    use fendermint_testing::blockstore::CountingBlockstore;
+    use fvm_ipld_blockstore::MemoryBlockstore;
+    use quickcheck_macros::quickcheck;
+    use std::collections::HashSet;
+    use std::str::FromStr;
+
+    /// Blockstore that, like the FVM one, only stores a block under the CID of its bytes.
+    #[derive(Default)]
+    struct ContentAddressedBlockstore(MemoryBlockstore);
+
+    impl Blockstore for ContentAddressedBlockstore {
+        fn get(&self, k: &Cid) -> anyhow::Result<Option<Vec<u8>>> {
+            self.0.get(k)
+        }
+
+        fn put_keyed(&self, k: &Cid, block: &[u8]) -> anyhow::Result<()> {
+            let code = Code::try_from(k.hash().code())?;
+            if code.digest(block) != *k.hash() {
+                anyhow::bail!("block doesn't hash to its cid {}", k);
+            }
+            self.0.put_keyed(k, block)
+        }
+    }
+
+    /// Build an accumulator of `n` leaves, where the leaf at index `i` is `vec![i]`.
+    fn build_accumulator<BS: Blockstore>(store: &BS, n: u64) -> State {
+        let mut state = State::new(store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        for i in 0..n {
+            let res = state.push(store, vec![i]).unwrap();
+            assert_eq!(res.index, i);
+        }
+        state
+    }
+
+    /// Assert that every leaf of an accumulator built by [build_accumulator] is retrievable.
+    fn assert_all_leaves<BS: Blockstore>(state: &State, store: &BS, n: u64) {
+        assert_eq!(state.leaf_count(), n);
+        for i in 0..n {
+            let leaf = state.get_leaf_at::<_, Vec<u64>>(store, i).unwrap();
+            assert_eq!(leaf, Some(vec![i]));
+        }
+    }
+
+    #[quickcheck]
+    fn prop_leaves_and_roots(n: u8) {
+        let n = (n % 64) as u64;
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let mut roots = HashSet::new();
+        for i in 0..n {
+            // The root returned by each push is the root of the accumulator of that size,
+            // checked as it grows rather than by rebuilding every prefix.
+            let root = state.push(&store, vec![i]).unwrap().root;
+            assert_eq!(state.get_root(&store).unwrap(), root);
+            assert!(roots.insert(root), "root of {} leaves repeats", i + 1);
+        }
+        assert_all_leaves(&state, &store, n);
+    }
+
+    #[test]
     fn test_constructor() {
         let store = fvm_ipld_blockstore::MemoryBlockstore::default();
         let state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner);
@@ -346,8 +1731,8 @@ mod tests {
         let cid1 = state.push(&store, obj1).expect("push1 failed").root;
         let cid2 = state.push(&store, obj2).expect("push2 failed").root;
 
-        let pair_cid =
-            hash_and_put_pair(&store, Some(&cid1), Some(&cid2)).expect("hash_and_put_pair failed");
+        let pair_cid = hash_and_put_pair(&store, &cid1, &cid2, Code::Blake2b256)
+            .expect("hash_and_put_pair failed");
         let merkle_node = store
             .get_cbor::<[Cid; 2]>(&pair_cid)
             .expect("get_cbor failed")
@@ -367,9 +1752,9 @@ mod tests {
         let cid2 = state.push(&store, obj2).expect("push2 failed").root;
 
         // Compare hash_pair and hash_and_put_pair and make sure they result in the same CID.
-        let hash1 = hash_pair(Some(&cid1), Some(&cid2)).expect("hash_pair failed");
-        let hash2 =
-            hash_and_put_pair(&store, Some(&cid1), Some(&cid2)).expect("hash_and_put_pair failed");
+        let hash1 = hash_pair(&cid1, &cid2, Code::Blake2b256);
+        let hash2 = hash_and_put_pair(&store, &cid1, &cid2, Code::Blake2b256)
+            .expect("hash_and_put_pair failed");
         assert_eq!(hash1, hash2);
     }
 
@@ -402,20 +1787,598 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_push_returns_peaks() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        for i in 0..11u64 {
+            let ret = state.push(&store, vec![i]).unwrap();
+            assert_eq!(ret.peaks, state.get_peaks(&store).unwrap());
+            assert_eq!(ret.peaks.len(), state.peak_count() as usize);
+        }
+    }
+
     #[test]
     fn test_bag_peaks() {
+        let store = MemoryBlockstore::default();
+        let mut state = build_accumulator(&store, 10);
+        let root = state.push(&store, vec![10u64]).unwrap().root;
+        let peaks = state.get_peaks(&store).unwrap();
+        assert_eq!(peaks.len(), 3);
+        assert_eq!(state.leaf_count(), 11);
+        assert_eq!(root, state.get_root(&store).expect("get_root failed"));
+    }
+
+    #[test]
+    fn test_uniform_root() {
+        let store = MemoryBlockstore::default();
+        let mh_code = Code::Blake2b256;
+        let mut promoted =
+            State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let mut uniform = promoted.clone();
+        uniform.set_root_scheme(RootScheme::Uniform).unwrap();
+        assert_eq!(promoted.get_root(&store).unwrap(), Cid::default());
+        assert_eq!(uniform.get_root(&store).unwrap(), Cid::default());
+
+        // One leaf: the promoted root is the leaf, the uniform root wraps it.
+        let leaf0 = store.put_cbor(&vec![0u64], mh_code).unwrap();
+        assert_eq!(promoted.push(&store, vec![0u64]).unwrap().root, leaf0);
+        let root = uniform.push(&store, vec![0u64]).unwrap().root;
+        assert_eq!(root, hash_single(&leaf0, mh_code));
+        assert_eq!(root, store.put_cbor(&[leaf0], mh_code).unwrap());
+        assert_eq!(root, uniform.get_root(&store).unwrap());
+        assert!(uniform.set_root_scheme(RootScheme::Promoted).is_err());
+
+        // Two leaves make a single peak, which is wrapped the same way.
+        let leaf1 = store.put_cbor(&vec![1u64], mh_code).unwrap();
+        let peak = hash_pair(&leaf0, &leaf1, mh_code);
+        assert_eq!(promoted.push(&store, vec![1u64]).unwrap().root, peak);
+        let root = uniform.push(&store, vec![1u64]).unwrap().root;
+        assert_eq!(root, hash_single(&peak, mh_code));
+        assert_ne!(root, peak);
+
+        // Three leaves make two peaks, which are bagged alike under both schemes.
+        let root = uniform.push(&store, vec![2u64]).unwrap().root;
+        assert_eq!(promoted.push(&store, vec![2u64]).unwrap().root, root);
+
+        // Proofs only verify under the scheme of the accumulator.
+        let ret = uniform.get_with_proof(&store, 1).unwrap().unwrap();
+        assert!(verify_proof(&ret.proof, &ret.root, mh_code, RootScheme::Uniform).unwrap());
+        let mut single = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        single.set_root_scheme(RootScheme::Uniform).unwrap();
+        single.push(&store, vec![0u64]).unwrap();
+        let ret = single.get_with_proof(&store, 0).unwrap().unwrap();
+        assert!(verify_proof(&ret.proof, &ret.root, mh_code, RootScheme::Uniform).unwrap());
+        assert!(!verify_proof(&ret.proof, &ret.root, mh_code, RootScheme::Promoted).unwrap());
+    }
+
+    #[test]
+    fn test_get_peaks_with_heights() {
+        let store = MemoryBlockstore::default();
+        let state = build_accumulator(&store, 11);
+        let peaks = state.get_peaks(&store).unwrap();
+        let peaks_with_heights = state.get_peaks_with_heights(&store).unwrap();
+        assert_eq!(
+            peaks_with_heights.iter().map(|p| p.1).collect::<Vec<_>>(),
+            vec![3, 1, 0]
+        );
+        assert_eq!(
+            peaks_with_heights
+                .into_iter()
+                .map(|p| p.0)
+                .collect::<Vec<_>>(),
+            peaks
+        );
+    }
+
+    #[test]
+    fn test_get_proofs() {
+        let store = MemoryBlockstore::default();
+        let state = build_accumulator(&store, 11);
+        let indices: Vec<u64> = (0..11).collect();
+
+        let store = CountingBlockstore::new(&store);
+        let proofs: Vec<Proof> = indices
+            .iter()
+            .map(|i| state.get_proof(&store, *i).unwrap())
+            .collect();
+        let (single_reads, _) = store.reset();
+
+        let batch = state.get_proofs(&store, &indices).unwrap();
+        let batch_reads = store.gets();
+
+        assert_eq!(batch, proofs);
+        assert!(batch_reads < single_reads);
+
+        // The peaks of 8, 2 and 1 leaves have paths of 3, 1 and 0 siblings.
+        assert_eq!(batch[0].siblings.len(), 3);
+        assert_eq!(batch[8].siblings.len(), 1);
+        assert_eq!(batch[10].siblings.len(), 0);
+        assert_eq!(batch[10].leaf, batch[10].peaks[2]);
+        assert_eq!(
+            batch[10].leaf,
+            store.put_cbor(&vec![10], Code::Blake2b256).unwrap()
+        );
+        assert!(state.get_proof(&store, 11).is_err());
+    }
+
+    #[test]
+    fn test_get_with_proof() {
+        let store = MemoryBlockstore::default();
+        let state = build_accumulator(&store, 11);
+        let mh_code = HASH_CODE;
+        let root = state.get_root(&store).unwrap();
+
+        for i in 0..11u64 {
+            let ret = state.get_with_proof(&store, i).unwrap().unwrap();
+            assert_eq!(
+                ret.leaf,
+                state.get_leaf_bytes_at(&store, i).unwrap().unwrap()
+            );
+            assert_eq!(ret.proof, state.get_proof(&store, i).unwrap());
+            assert_eq!(ret.leaf_count, 11);
+            assert_eq!(ret.root, root);
+            assert!(verify_proof(&ret.proof, &ret.root, mh_code, state.root_scheme).unwrap());
+        }
+
+        // A proof for another leaf, or against another root, doesn't verify.
+        let mut ret = state.get_with_proof(&store, 3).unwrap().unwrap();
+        assert!(!verify_proof(&ret.proof, &Cid::default(), mh_code, state.root_scheme).unwrap());
+        ret.proof.leaf = state.get_proof(&store, 4).unwrap().leaf;
+        assert!(!verify_proof(&ret.proof, &ret.root, mh_code, state.root_scheme).unwrap());
+
+        assert!(state.get_with_proof(&store, 11).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_read_path_access_counts() {
+        let store = MemoryBlockstore::default();
+        let state = build_accumulator(&store, 11);
+        let heights: Vec<u32> = state
+            .get_peaks_with_heights(&store)
+            .unwrap()
+            .into_iter()
+            .flat_map(|(_, height)| std::iter::repeat(height).take(1 << height))
+            .collect();
+
+        let store = CountingBlockstore::new(&store);
+        let mut separate_reads = 0;
+        let mut combined_reads = 0;
+        for i in 0..11u64 {
+            // Reading a leaf takes the peaks, a node per level of its peak, and the leaf itself.
+            state.get_leaf_bytes_at(&store, i).unwrap().unwrap();
+            let (leaf_reads, writes) = store.reset();
+            assert!(leaf_reads <= heights[i as usize] as usize + 2);
+            assert_eq!(writes, 0);
+
+            state.get_proof(&store, i).unwrap();
+            separate_reads += leaf_reads + store.reset().0;
+
+            state.get_with_proof(&store, i).unwrap().unwrap();
+            combined_reads += store.reset().0;
+        }
+        assert!(combined_reads < separate_reads);
+    }
+
+    #[test]
+    fn test_verify_peak() {
+        let store = MemoryBlockstore::default();
+        let state = build_accumulator(&store, 11);
+        let leaves: Vec<Cid> = (0..11u64)
+            .map(|i| store.put_cbor(&vec![i], Code::Blake2b256).unwrap())
+            .collect();
+        let peaks = state.get_peaks(&store).unwrap();
+
+        // 11 leaves make peaks over 8, 2 and 1 leaves.
+        assert!(state
+            .verify_peak(&store, 0, &peaks[0], &leaves[0..8])
+            .unwrap());
+        assert!(state
+            .verify_peak(&store, 1, &peaks[1], &leaves[8..10])
+            .unwrap());
+        assert!(state
+            .verify_peak(&store, 2, &peaks[2], &leaves[10..])
+            .unwrap());
+
+        // A single swapped leaf fails.
+        let mut swapped = leaves[0..8].to_vec();
+        swapped[5] = leaves[10];
+        assert!(!state.verify_peak(&store, 0, &peaks[0], &swapped).unwrap());
+
+        // So do reordered leaves, the wrong number of leaves, another peak, or a missing peak.
+        let mut reordered = leaves[0..8].to_vec();
+        reordered.swap(2, 3);
+        assert!(!state.verify_peak(&store, 0, &peaks[0], &reordered).unwrap());
+        assert!(!state
+            .verify_peak(&store, 0, &peaks[0], &leaves[0..4])
+            .unwrap());
+        assert!(!state
+            .verify_peak(&store, 0, &peaks[1], &leaves[0..8])
+            .unwrap());
+        assert!(!state
+            .verify_peak(&store, 3, &peaks[2], &leaves[10..])
+            .unwrap());
+
+        assert_eq!(peak_from_leaves(&leaves[0..3], Code::Blake2b256), None);
+    }
+
+    #[test]
+    fn test_accumulator_errors() {
+        let store = MemoryBlockstore::default();
+
+        let empty = build_accumulator(&store, 0);
+        assert!(matches!(
+            path_for_eigen_root(0, 0),
+            Err(VerifyError::EmptyAccumulator)
+        ));
+        let err = empty.get_proof(&store, 0).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AccumulatorError>(),
+            Some(AccumulatorError::EmptyAccumulator)
+        ));
+
+        let state = build_accumulator(&store, 11);
+        let amt = Amt::<Cid, _>::load(&state.peaks, &store).unwrap();
+        assert!(matches!(
+            get_at(&store, 11, state.leaf_count, &amt, &state.node_format()),
+            Err(AccumulatorError::IndexOutOfRange {
+                index: 11,
+                leaf_count: 11
+            })
+        ));
+        let err = state.get_proof(&store, 12).unwrap_err();
+        let err = err.downcast_ref::<AccumulatorError>().unwrap();
+        assert!(matches!(
+            err,
+            AccumulatorError::IndexOutOfRange {
+                index: 12,
+                leaf_count: 11
+            }
+        ));
+        assert_eq!(err.exit_code(), ERR_INDEX_OUT_OF_RANGE);
+    }
+
+    #[test]
+    fn test_leaf_codecs() {
+        let store = MemoryBlockstore::default();
+        let mut cbor = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let mut raw = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        raw.set_leaf_codec(LeafCodec::Raw).unwrap();
+
+        let mut cbor_root = Cid::default();
+        let mut raw_root = Cid::default();
+        for i in 0..5u8 {
+            cbor_root = cbor.push_bytes(&store, vec![i; 3]).unwrap().root;
+            raw_root = raw.push_bytes(&store, vec![i; 3]).unwrap().root;
+        }
+        assert_ne!(cbor_root, raw_root);
+        for i in 0..5u8 {
+            let leaf = cbor.get_leaf_bytes_at(&store, i as u64).unwrap();
+            assert_eq!(leaf, Some(vec![i; 3]));
+            let leaf = raw.get_leaf_bytes_at(&store, i as u64).unwrap();
+            assert_eq!(leaf, Some(vec![i; 3]));
+        }
+        assert_eq!(raw.get_leaf_bytes_at(&store, 5).unwrap(), None);
+
+        // Raw leaves are stored under the raw codec, as they are.
+        let proof = raw.get_proof(&store, 4).unwrap();
+        assert_eq!(proof.leaf.codec(), IPLD_RAW);
+        assert_eq!(store.get(&proof.leaf).unwrap(), Some(vec![4; 3]));
+
+        // The codec can't change once there are leaves.
+        assert!(raw.set_leaf_codec(LeafCodec::DagCbor).is_err());
+    }
+
+    #[test]
+    fn test_freeze() {
+        let store = MemoryBlockstore::default();
+        let mut state = build_accumulator(&store, 3);
+        let root = state.get_root(&store).unwrap();
+
+        state.freeze();
+        assert!(state.is_frozen());
+        assert!(state.push(&store, vec![3]).is_err());
+        assert!(state.push_bytes(&store, vec![3]).is_err());
+
+        // Reads still work, and nothing changed.
+        assert_eq!(state.leaf_count(), 3);
+        assert_eq!(state.get_root(&store).unwrap(), root);
+        assert_eq!(state.get_peaks(&store).unwrap().len(), 2);
+        assert_eq!(state.get_leaf_at(&store, 2).unwrap(), Some(vec![2u64]));
+
+        state.freeze();
+        assert!(state.is_frozen());
+    }
+
+    #[test]
+    fn test_max_pushes_per_epoch() {
         let store = fvm_ipld_blockstore::MemoryBlockstore::default();
         let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
-        let mut root = Cid::default();
-        for i in 1..=11 {
-            let res = state.push(&store, vec![i]).unwrap();
-            root = res.root;
-            assert_eq!(res.index, i - 1);
+
+        // Unlimited by default.
+        for _ in 0..10 {
+            state.count_push(1).unwrap();
+        }
+
+        state.set_max_pushes_per_epoch(Some(3)).unwrap();
+        for _ in 0..3 {
+            state.count_push(2).unwrap();
+        }
+        let err = state.count_push(2).unwrap_err();
+        assert!(matches!(
+            err,
+            AccumulatorError::RateLimited { epoch: 2, limit: 3 }
+        ));
+        assert_eq!(err.exit_code(), ERR_RATE_LIMITED);
+
+        // A new epoch resets the count.
+        for _ in 0..3 {
+            state.count_push(3).unwrap();
         }
+        assert!(state.count_push(3).is_err());
+
+        // The limit is fixed once there are leaves.
+        state.push(&store, vec![1, 2, 3]).unwrap();
+        assert!(state.set_max_pushes_per_epoch(None).is_err());
+        assert_eq!(state.max_pushes_per_epoch, Some(3));
+    }
+
+    /// Blockstore that lost a block.
+    struct TamperedStore<'a> {
+        inner: &'a MemoryBlockstore,
+        missing: Cid,
+    }
+
+    impl Blockstore for TamperedStore<'_> {
+        fn get(&self, k: &Cid) -> anyhow::Result<Option<Vec<u8>>> {
+            if *k == self.missing {
+                return Ok(None);
+            }
+            self.inner.get(k)
+        }
+
+        fn put_keyed(&self, k: &Cid, block: &[u8]) -> anyhow::Result<()> {
+            self.inner.put_keyed(k, block)
+        }
+    }
+
+    #[test]
+    fn test_encode_leaf_matches_push() {
+        for codec in [LeafCodec::DagCbor, LeafCodec::Raw, LeafCodec::DagPb] {
+            let store = MemoryBlockstore::default();
+            let mut state =
+                State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+            state.set_leaf_codec(codec).unwrap();
+            let bytes = b"hello".to_vec();
+            state.push_bytes(&store, bytes.clone()).unwrap();
+
+            let (cid, data) = codec.encode_leaf(&bytes).unwrap();
+            assert_eq!(state.get_root(&store).unwrap(), cid);
+            assert_eq!(store.get(&cid).unwrap(), Some(data.clone()));
+            assert_eq!(codec.decode_leaf(&data).unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn test_get_peaks_missing_peak_block() {
+        let store = MemoryBlockstore::default();
+        // 11 leaves make peaks of 8, 2 and 1 leaves.
+        let state = build_accumulator(&store, 11);
         let peaks = state.get_peaks(&store).unwrap();
         assert_eq!(peaks.len(), 3);
+
+        let store = TamperedStore {
+            inner: &store,
+            missing: peaks[1],
+        };
+        // The proof of a leaf under another peak doesn't load the missing block, but the root
+        // returned with it would be bagged from it.
+        for err in [
+            state.get_peaks(&store).unwrap_err(),
+            state.get_root(&store).unwrap_err(),
+            state.get_with_proof(&store, 0).unwrap_err(),
+        ] {
+            assert!(err
+                .to_string()
+                .contains(&format!("peak 1 with cid {}", peaks[1])));
+            match err.downcast::<AccumulatorError>().unwrap() {
+                AccumulatorError::UnloadablePeak { index, cid } => {
+                    assert_eq!(index, 1);
+                    assert_eq!(cid, peaks[1]);
+                }
+                e => panic!("unexpected error: {}", e),
+            }
+        }
+
+        // The leaf peak isn't checked, as leaves pushed by CID aren't in the store.
+        let store = TamperedStore {
+            inner: store.inner,
+            missing: peaks[2],
+        };
+        assert_eq!(state.get_peaks(&store).unwrap(), peaks);
+        state.get_root(&store).unwrap();
+    }
+
+    /// Import batches of `batch_size` leaves of `source`, with the root after each batch.
+    fn import_batches(
+        source: &State,
+        store: &MemoryBlockstore,
+        batch_size: usize,
+        force: bool,
+    ) -> Vec<ImportLeavesParams> {
+        let fresh = MemoryBlockstore::default();
+        let mut replay = State::new(&fresh, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let leaves = (0..source.leaf_count())
+            .map(|i| source.get_leaf_bytes_at(store, i).unwrap().unwrap())
+            .collect::<Vec<_>>();
+        leaves
+            .chunks(batch_size)
+            .map(|batch| {
+                let start = replay.leaf_count();
+                for leaf in batch {
+                    replay.push_bytes(&fresh, leaf.clone()).unwrap();
+                }
+                ImportLeavesParams {
+                    start,
+                    leaves: batch.iter().cloned().map(ByteBuf).collect(),
+                    leaf_codec: source.leaf_codec,
+                    root_scheme: source.root_scheme,
+                    expected_root: replay.get_root(&fresh).unwrap(),
+                    force,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_import_leaves() {
+        let store = MemoryBlockstore::default();
+        let mut source = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        source.set_leaf_codec(LeafCodec::Raw).unwrap();
+        for i in 0..11u8 {
+            source.push_bytes(&store, vec![i; 4]).unwrap();
+        }
+        let batches = import_batches(&source, &store, 4, false);
+        assert_eq!(batches.len(), 3);
+
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        // Batches have to be imported in order.
+        let err = state.import_leaves(&store, batches[1].clone()).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AccumulatorError>(),
+            Some(AccumulatorError::ImportOutOfOrder {
+                start: 4,
+                expected: 0
+            })
+        ));
+        for batch in &batches {
+            state.import_leaves(&store, batch.clone()).unwrap();
+        }
         assert_eq!(state.leaf_count(), 11);
-        assert_eq!(root, state.get_root(&store).expect("get_root failed"));
+        assert_eq!(state.leaf_codec, LeafCodec::Raw);
+        assert_eq!(
+            state.get_root(&store).unwrap(),
+            source.get_root(&store).unwrap()
+        );
+        assert_eq!(
+            state.get_leaf_bytes_at(&store, 10).unwrap(),
+            Some(vec![10; 4])
+        );
+
+        // Starting over is only allowed with force.
+        let err = state.import_leaves(&store, batches[0].clone()).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AccumulatorError>(),
+            Some(AccumulatorError::ImportWithoutForce)
+        ));
+        assert!(err.to_string().contains("without force"));
+        let mut forced = batches[0].clone();
+        forced.force = true;
+        state.import_leaves(&store, forced).unwrap();
+        assert_eq!(state.leaf_count(), 4);
+    }
+
+    #[test]
+    fn test_import_leaves_keeps_state() {
+        let store = MemoryBlockstore::default();
+        let source = build_accumulator(&store, 5);
+        let mut batches = import_batches(&source, &store, 5, false);
+        batches[0].force = true;
+
+        // A state whose peaks AMTs don't have the current bit width, with a named namespace.
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        state.peaks_bit_width = BIT_WIDTH + 1;
+        state.peaks = Amt::<Cid, _>::new_with_bit_width(&store, BIT_WIDTH + 1)
+            .flush()
+            .unwrap();
+        state.push_bytes(&store, vec![1]).unwrap();
+        state.push_bytes_in(&store, b"a", vec![2]).unwrap();
+        let metadata = HashMap::from([("name".to_string(), "log".to_string())]);
+        state.metadata = metadata.clone();
+
+        state.import_leaves(&store, batches[0].clone()).unwrap();
+        assert_eq!(state.metadata, metadata);
+        assert_eq!(state.peaks_bit_width, BIT_WIDTH + 1);
+        assert_eq!(
+            state.get_root(&store).unwrap(),
+            source.get_root(&store).unwrap()
+        );
+        assert_eq!(state.leaf_count_in(&store, b"a").unwrap(), 1);
+        state.push_bytes_in(&store, b"a", vec![3]).unwrap();
+        state.verify_integrity(&store).unwrap();
+    }
+
+    #[test]
+    fn test_import_leaves_corrupted() {
+        let store = MemoryBlockstore::default();
+        let source = build_accumulator(&store, 5);
+        let mut batches = import_batches(&source, &store, 5, false);
+        batches[0].leaves[2] = ByteBuf(b"corrupted".to_vec());
+
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let root = state.get_root(&store).unwrap();
+        let err = state.import_leaves(&store, batches[0].clone()).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AccumulatorError>(),
+            Some(AccumulatorError::ImportRootMismatch { expected, .. })
+                if *expected == batches[0].expected_root
+        ));
+        assert_eq!(
+            err.downcast_ref::<AccumulatorError>().unwrap().exit_code(),
+            ExitCode::USR_ILLEGAL_ARGUMENT
+        );
+        assert!(err.to_string().contains("does not match expected root"));
+        // The state is left untouched.
+        assert_eq!(state.leaf_count(), 0);
+        assert_eq!(state.get_root(&store).unwrap(), root);
+    }
+
+    #[test]
+    fn test_leaf_metadata() {
+        let store = MemoryBlockstore::default();
+        let mut state = build_accumulator(&store, 5);
+        let plain = build_accumulator(&store, 5);
+
+        let metadata = HashMap::from([
+            ("source".to_string(), "sensor-1".to_string()),
+            ("type".to_string(), "reading".to_string()),
+        ]);
+        state
+            .set_leaf_metadata(&store, 3, metadata.clone())
+            .unwrap();
+
+        assert_eq!(state.get_leaf_metadata(&store, 3).unwrap(), Some(metadata));
+        assert_eq!(state.get_leaf_metadata(&store, 2).unwrap(), None);
+        assert!(state.set_leaf_metadata(&store, 5, HashMap::new()).is_err());
+
+        // Metadata is not committed to the root.
+        assert_eq!(
+            state.get_root(&store).unwrap(),
+            plain.get_root(&store).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_get_leaf_at_out_of_range() {
+        let store = MemoryBlockstore::default();
+        let state = build_accumulator(&store, 11);
+        let leaf = state.get_leaf_at::<_, Vec<u64>>(&store, 11).unwrap();
+        assert_eq!(leaf, None);
+    }
+
+    #[test]
+    fn test_get_leaf_at_tampered_store() {
+        let store = MemoryBlockstore::default();
+        let state = build_accumulator(&store, 11);
+        let missing = store.put_cbor(&vec![3u64], Code::Blake2b256).unwrap();
+        let store = TamperedStore {
+            inner: &store,
+            missing,
+        };
+        assert!(state.get_leaf_at::<_, Vec<u64>>(&store, 3).is_err());
+        // Other leaves are unaffected.
+        let leaf = state.get_leaf_at::<_, Vec<u64>>(&store, 4).unwrap();
+        assert_eq!(leaf, Some(vec![4]));
     }
 
     #[test]
@@ -472,18 +2435,327 @@ mod tests {
         let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
         for i in 0..31 {
             state.push(&store, vec![i]).unwrap();
-            assert_eq!(state.leaf_count(), i + 1);
 
             // As more items are added to the accumulator, ensure each item remains gettable at
             // each phase of the growth of the inner tree structures.
-            for j in 0..i {
-                let item = state
-                    .get_leaf_at::<_, Vec<u64>>(&store, j)
-                    .unwrap()
-                    .unwrap();
-                assert_eq!(item, vec![j]);
-            }
+            assert_all_leaves(&state, &store, i + 1);
         }
         assert_eq!(state.peak_count(), 5);
     }
+
+    #[test]
+    fn test_nodes_are_content_addressed() {
+        let store = ContentAddressedBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        for i in 0..37u64 {
+            state.push_bytes(&store, vec![i as u8; 3]).unwrap();
+        }
+        let root = state.get_root(&store).unwrap();
+        for i in 0..37u64 {
+            let proof = state.get_proof(&store, i).unwrap();
+            assert!(verify_proof(&proof, &root, HASH_CODE, state.root_scheme).unwrap());
+            assert_eq!(
+                state.get_leaf_bytes_at(&store, i).unwrap(),
+                Some(vec![i as u8; 3])
+            );
+        }
+    }
+
+    #[test]
+    fn test_dedup_window() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        assert!(state.set_dedup_window(MAX_DEDUP_WINDOW + 1).is_err());
+        state.set_dedup_window(2).unwrap();
+
+        // Retrying a push within the window returns the original leaf.
+        let first = state.push_bytes(&store, b"a".to_vec()).unwrap();
+        assert!(!first.deduplicated);
+        let retry = state.push_bytes(&store, b"a".to_vec()).unwrap();
+        assert!(retry.deduplicated);
+        assert_eq!(retry.index, first.index);
+        assert_eq!(retry.root, first.root);
+        assert_eq!(state.leaf_count(), 1);
+        assert!(state.set_dedup_window(0).is_err());
+
+        // Once two other leaves were pushed, the first one fell out of the window.
+        state.push_bytes(&store, b"b".to_vec()).unwrap();
+        assert!(
+            state
+                .push_bytes(&store, b"b".to_vec())
+                .unwrap()
+                .deduplicated
+        );
+        state.push_bytes(&store, b"c".to_vec()).unwrap();
+        let again = state.push_bytes(&store, b"a".to_vec()).unwrap();
+        assert!(!again.deduplicated);
+        assert_eq!(again.index, 3);
+        assert_eq!(state.leaf_count(), 4);
+
+        // Only the window is remembered.
+        let index = Hamt::<_, u64>::load_with_bit_width(
+            &state.recent_index,
+            &store,
+            RECENT_LEAVES_BIT_WIDTH,
+        )
+        .unwrap();
+        let mut remembered = Vec::new();
+        index
+            .for_each(|_, index| {
+                remembered.push(*index);
+                Ok(())
+            })
+            .unwrap();
+        remembered.sort();
+        assert_eq!(remembered, vec![2, 3]);
+
+        // Pushes aren't deduplicated by default.
+        let mut plain = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        plain.push_bytes(&store, b"a".to_vec()).unwrap();
+        assert!(
+            !plain
+                .push_bytes(&store, b"a".to_vec())
+                .unwrap()
+                .deduplicated
+        );
+        assert_eq!(plain.leaf_count(), 2);
+    }
+
+    #[test]
+    fn test_namespaces() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let empty_root = state.get_root(&store).unwrap();
+
+        for i in 0..3u8 {
+            state.push_bytes_in(&store, b"", vec![i]).unwrap();
+        }
+        for i in 0..5u8 {
+            let ret = state.push_bytes_in(&store, b"a", vec![10 + i]).unwrap();
+            assert_eq!(ret.index, i as u64);
+        }
+        let ret = state.push_bytes_in(&store, b"b", vec![20]).unwrap();
+        assert_eq!(ret.index, 0);
+        assert_eq!(state.namespace_count, 2);
+
+        // The empty namespace is the default one.
+        assert_eq!(state.leaf_count(), 3);
+        assert_eq!(state.leaf_count_in(&store, b"").unwrap(), 3);
+        assert_eq!(state.leaf_count_in(&store, b"a").unwrap(), 5);
+        assert_eq!(state.leaf_count_in(&store, b"b").unwrap(), 1);
+        assert_eq!(state.leaf_count_in(&store, b"c").unwrap(), 0);
+        assert_eq!(
+            state.get_root_in(&store, b"").unwrap(),
+            state.get_root(&store).unwrap()
+        );
+        assert_eq!(state.get_root_in(&store, b"c").unwrap(), empty_root);
+
+        // Each namespace has the root of an accumulator holding only its own leaves.
+        for (namespace, leaves) in [(b"a", 10..15u8), (b"b", 20..21u8)] {
+            let mut alone =
+                State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+            for leaf in leaves.clone() {
+                alone.push_bytes(&store, vec![leaf]).unwrap();
+            }
+            let root = state.get_root_in(&store, namespace).unwrap();
+            assert_eq!(root, alone.get_root(&store).unwrap());
+            for (index, leaf) in leaves.enumerate() {
+                let index = index as u64;
+                assert_eq!(
+                    state.get_leaf_bytes_in(&store, namespace, index).unwrap(),
+                    Some(vec![leaf])
+                );
+                let proof = state.get_proof_in(&store, namespace, index).unwrap();
+                assert!(verify_proof(&proof, &root, HASH_CODE, state.root_scheme).unwrap());
+            }
+        }
+        assert_eq!(state.get_leaf_bytes_in(&store, b"a", 5).unwrap(), None);
+        assert_eq!(state.get_leaf_bytes_in(&store, b"c", 0).unwrap(), None);
+
+        // The settings are shared, so they're fixed once any namespace has leaves.
+        let mut named = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        named.push_bytes_in(&store, b"a", vec![1]).unwrap();
+        assert_eq!(named.leaf_count(), 0);
+        assert!(named.set_leaf_codec(LeafCodec::Raw).is_err());
+        named.freeze();
+        assert!(named.push_bytes_in(&store, b"a", vec![2]).is_err());
+    }
+
+    #[test]
+    fn test_push_cid() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        state.set_leaf_codec(LeafCodec::Raw).unwrap();
+
+        // The CID is pushed as it is, without the content it addresses being stored.
+        let cid = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(b"elsewhere"));
+        let ret = state.push_cid(&store, cid).unwrap();
+        assert_eq!(ret.root, cid);
+        let proof = state.get_proof(&store, ret.index).unwrap();
+        assert_eq!(proof.leaf, cid);
+        assert!(store.get(&cid).unwrap().is_none());
+
+        // Pushed bytes hash to the same leaf.
+        let mut bytes = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        bytes.set_leaf_codec(LeafCodec::Raw).unwrap();
+        assert_eq!(
+            bytes
+                .push_bytes(&store, b"elsewhere".to_vec())
+                .unwrap()
+                .root,
+            cid
+        );
+
+        // CIDs of another codec, hash function or version are rejected.
+        for cid in [
+            Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(b"elsewhere")),
+            Cid::new_v1(IPLD_RAW, Code::Sha2_256.digest(b"elsewhere")),
+            Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(b"elsewhere").truncate(20)),
+        ] {
+            let err = state.push_cid(&store, cid).unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<AccumulatorError>(),
+                Some(AccumulatorError::MismatchedLeaf(_))
+            ));
+        }
+        assert_eq!(state.leaf_count(), 1);
+
+        // The CID of the leaf can be read back, although its content can't.
+        assert_eq!(state.get_leaf_cid_at(&store, 0).unwrap(), Some(cid));
+        assert_eq!(state.get_leaf_cid_at(&store, 1).unwrap(), None);
+        assert!(state.get_leaf_bytes_at(&store, 0).is_err());
+
+        // A pushed CID can be the last peak above others, and still be proven.
+        state.push_bytes(&store, b"stored".to_vec()).unwrap();
+        let other = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(b"also elsewhere"));
+        let ret = state.push_cid(&store, other).unwrap();
+        assert_eq!(ret.index, 2);
+        assert_eq!(state.get_peaks(&store).unwrap().last(), Some(&other));
+        assert_eq!(state.get_root(&store).unwrap(), ret.root);
+        assert_eq!(state.get_proof(&store, 2).unwrap().leaf, other);
+        assert_eq!(state.get_proof(&store, 0).unwrap().leaf, cid);
+        assert!(store.get(&other).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_verify_integrity() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        state.set_leaf_codec(LeafCodec::Raw).unwrap();
+        state.verify_integrity(&store).unwrap();
+        for i in 0..6u8 {
+            state.push_bytes(&store, vec![i]).unwrap();
+            state.push_bytes_in(&store, b"a", vec![10 + i]).unwrap();
+        }
+        // The last peak is a leaf whose content isn't in the store, which is fine.
+        state
+            .push_cid(
+                &store,
+                Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(&[20])),
+            )
+            .unwrap();
+        state.verify_integrity(&store).unwrap();
+
+        // A leaf count out of sync with the peaks.
+        let mut desynced = state.clone();
+        desynced.leaf_count += 1;
+        let err = desynced.verify_integrity(&store).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AccumulatorError>(),
+            Some(AccumulatorError::PeakCountMismatch {
+                leaf_count: 8,
+                expected: 1,
+                found: 3
+            })
+        ));
+
+        // A peak missing from the store.
+        let mut desynced = state.clone();
+        let mut amt = Amt::<Cid, _>::load(&desynced.peaks, &store).unwrap();
+        let missing = Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&[30]));
+        amt.set(0, missing).unwrap();
+        desynced.peaks = amt.flush().unwrap();
+        let err = desynced.verify_integrity(&store).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AccumulatorError>(),
+            Some(AccumulatorError::UnloadablePeak { index: 0, cid }) if *cid == missing
+        ));
+
+        // A named namespace out of sync, and a namespace count out of sync.
+        let mut desynced = state.clone();
+        let mut namespaces = Hamt::<_, Namespace>::load_with_bit_width(
+            &desynced.namespaces,
+            &store,
+            NAMESPACES_BIT_WIDTH,
+        )
+        .unwrap();
+        let mut namespace = namespaces
+            .get(&BytesKey(b"a".to_vec()))
+            .unwrap()
+            .cloned()
+            .unwrap();
+        namespace.leaf_count = 4;
+        namespaces.set(BytesKey(b"a".to_vec()), namespace).unwrap();
+        desynced.namespaces = namespaces.flush().unwrap();
+        let err = desynced.verify_integrity(&store).unwrap_err();
+        assert!(err.to_string().contains("namespace a"));
+        assert!(matches!(
+            err.downcast_ref::<AccumulatorError>(),
+            Some(AccumulatorError::PeakCountMismatch { .. })
+        ));
+
+        let mut desynced = state.clone();
+        desynced.namespace_count = 2;
+        let err = desynced.verify_integrity(&store).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AccumulatorError>(),
+            Some(AccumulatorError::NamespaceCountMismatch {
+                expected: 2,
+                found: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn test_peaks_bit_width_mismatch() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        state.push_bytes(&store, vec![1]).unwrap();
+        state.push_bytes_in(&store, b"a", vec![2]).unwrap();
+        assert_eq!(state.peaks_bit_width, BIT_WIDTH);
+
+        // A state from before the bit width was recorded decodes with an unknown bit width.
+        // The bit width and the metadata are the last two fields, a small integer and an empty
+        // map encoded in a single byte each, so they're dropped by shortening the array header
+        // and the encoding.
+        let mut encoded = fvm_ipld_encoding::to_vec(&state).unwrap();
+        assert_eq!(encoded.pop(), Some(0xa0));
+        assert_eq!(encoded.pop(), Some(BIT_WIDTH as u8));
+        encoded[0] -= 2;
+        let legacy: State = fvm_ipld_encoding::from_slice(&encoded).unwrap();
+        assert_eq!(legacy.peaks_bit_width, 0);
+        let err = legacy.get_root(&store).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AccumulatorError>(),
+            Some(AccumulatorError::PeaksBitWidthUnknown)
+        ));
+        assert!(err.to_string().contains("incompatible version"));
+
+        let mut mismatched = state.clone();
+        mismatched.peaks_bit_width = BIT_WIDTH + 1;
+        for err in [
+            mismatched.get_root(&store).unwrap_err(),
+            mismatched.push_bytes(&store, vec![3]).unwrap_err(),
+            mismatched.push_bytes_in(&store, b"a", vec![3]).unwrap_err(),
+            mismatched.verify_integrity(&store).unwrap_err(),
+        ] {
+            assert!(matches!(
+                err.downcast_ref::<AccumulatorError>(),
+                Some(AccumulatorError::PeaksBitWidthMismatch { expected, found })
+                    if *expected == BIT_WIDTH + 1 && *found == BIT_WIDTH
+            ));
+            assert!(err.to_string().contains("incompatible version"));
+        }
+        assert!(AccumulatorReader::from_state(&store, &mismatched).is_err());
+    }
 }
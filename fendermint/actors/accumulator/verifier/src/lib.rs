@@ -0,0 +1,228 @@
+// Copyright 2024 Textile
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Verification of accumulator inclusion proofs.
+//!
+//! This is the part of the accumulator MMR that needs no blockstore: hashing nodes, bagging
+//! peaks into the root and checking proofs against it. It is `no_std`, so clients can verify
+//! proofs in the browser through wasm, with the same code the actor uses.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use cid::multihash::{Code, MultihashDigest};
+use cid::Cid;
+
+/// The multicodec of DAG-CBOR blocks, which all accumulator nodes are.
+pub const DAG_CBOR: u64 = 0x71;
+
+/// How the peaks of the accumulator are bagged into its root.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RootScheme {
+    /// A single peak is promoted to the root as it is, so the root of a one-leaf accumulator
+    /// is the CID of its leaf.
+    #[default]
+    Promoted,
+    /// A single peak is wrapped in a node of its own, so the root is always a node hashed by
+    /// the accumulator, whatever the leaf count.
+    Uniform,
+}
+
+/// Errors of locating a leaf in the accumulator.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VerifyError {
+    /// The accumulator has no leaves.
+    EmptyAccumulator,
+    /// The leaf index is not less than the leaf count.
+    IndexOutOfRange { index: u64, leaf_count: u64 },
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::EmptyAccumulator => write!(f, "accumulator is empty"),
+            VerifyError::IndexOutOfRange { index, leaf_count } => write!(
+                f,
+                "leaf index {} out of range for {} leaves",
+                index, leaf_count
+            ),
+        }
+    }
+}
+
+/// An inclusion proof of a leaf, borrowed from wherever it was decoded.
+#[derive(Clone, Copy, Debug)]
+pub struct ProofRef<'a> {
+    /// The index of the proven leaf.
+    pub index: u64,
+    /// The number of leaves in the accumulator the proof was built against.
+    pub leaf_count: u64,
+    /// The CID of the proven leaf.
+    pub leaf: &'a Cid,
+    /// Sibling hashes on the path from the leaf up to its peak, bottom first.
+    pub siblings: &'a [Cid],
+    /// The peaks of the accumulator, from which the root can be bagged.
+    pub peaks: &'a [Cid],
+}
+
+/// Check that `proof` links its leaf to `root`, with pairs hashed using `mh_code` and the peaks
+/// bagged with `scheme`.
+///
+/// Returns `false` if the proof is well-formed but doesn't match the root.
+pub fn verify_proof(
+    proof: &ProofRef,
+    root: &Cid,
+    mh_code: Code,
+    scheme: RootScheme,
+) -> Result<bool, VerifyError> {
+    let (path, eigen_index) = path_for_eigen_root(proof.index, proof.leaf_count)?;
+    let height = u64::BITS - path.leading_zeros() - 1;
+    if proof.siblings.len() != height as usize
+        || proof.peaks.len() != proof.leaf_count.count_ones() as usize
+    {
+        return Ok(false);
+    }
+    // Hash up from the leaf, the bits of the path telling on which side each sibling is.
+    let mut cid = *proof.leaf;
+    for (i, sibling) in proof.siblings.iter().enumerate() {
+        cid = if (path >> i) & 1 == 1 {
+            hash_pair(sibling, &cid, mh_code)
+        } else {
+            hash_pair(&cid, sibling, mh_code)
+        };
+    }
+    if proof.peaks[eigen_index as usize] != cid {
+        return Ok(false);
+    }
+    Ok(bag_peaks(proof.peaks, mh_code, scheme) == *root)
+}
+
+/// Compute the root of the perfect eigentree over `leaves`, with pairs hashed using `mh_code`.
+///
+/// Returns `None` if the number of leaves is not a power of two, in which case they can't form
+/// a peak of the MMR.
+pub fn peak_from_leaves(leaves: &[Cid], mh_code: Code) -> Option<Cid> {
+    if !leaves.len().is_power_of_two() {
+        return None;
+    }
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = level
+            .chunks_exact(2)
+            .map(|pair| hash_pair(&pair[0], &pair[1], mh_code))
+            .collect();
+    }
+    level.pop()
+}
+
+/// Combine the peaks, from left to right, to compute the root commitment.
+pub fn bag_peaks(peaks: &[Cid], mh_code: Code, scheme: RootScheme) -> Cid {
+    match peaks {
+        // Handle special cases where we have no peaks or only one peak
+        [] => Cid::default(),
+        // If there is only one peak, we either "promote" it to the root, or wrap it
+        [peak] => match scheme {
+            RootScheme::Promoted => *peak,
+            RootScheme::Uniform => hash_single(peak, mh_code),
+        },
+        // Walk backward through the peaks, combining them pairwise
+        [rest @ .., left, right] => {
+            let mut root = hash_pair(left, right, mh_code);
+            for peak in rest.iter().rev() {
+                root = hash_pair(peak, &root, mh_code);
+            }
+            root
+        }
+    }
+}
+
+/// Compute the hash of a pair of CIDs.
+/// The hash is the CID of a new block containing the concatenation of the two CIDs.
+/// We do not include the index of the element(s) because incoming data should already be "nonced".
+pub fn hash_pair(left: &Cid, right: &Cid, mh_code: Code) -> Cid {
+    hash_links(&[left, right], mh_code)
+}
+
+/// Compute the hash of a single peak, wrapped as the root under [`RootScheme::Uniform`].
+/// The block holds a list of one CID, so it can't be mistaken for a pair.
+pub fn hash_single(peak: &Cid, mh_code: Code) -> Cid {
+    hash_links(&[peak], mh_code)
+}
+
+/// Compute the CID of the DAG-CBOR block holding a list of `links`.
+fn hash_links(links: &[&Cid], mh_code: Code) -> Cid {
+    let mut data = Vec::new();
+    write_header(&mut data, 4, links.len() as u64);
+    for link in links {
+        // A link is a byte string tagged 42, holding the CID after a zero byte.
+        let bytes = link.to_bytes();
+        data.extend_from_slice(&[0xd8, 42]);
+        write_header(&mut data, 2, bytes.len() as u64 + 1);
+        data.push(0);
+        data.extend_from_slice(&bytes);
+    }
+    Cid::new_v1(DAG_CBOR, mh_code.digest(&data))
+}
+
+/// Write the header of a CBOR item of `major` type, in its shortest form.
+fn write_header(data: &mut Vec<u8>, major: u8, value: u64) {
+    let major = major << 5;
+    match value {
+        0..=23 => data.push(major | value as u8),
+        24..=0xff => data.extend_from_slice(&[major | 24, value as u8]),
+        0x100..=0xffff => {
+            data.push(major | 25);
+            data.extend_from_slice(&(value as u16).to_be_bytes());
+        }
+        0x10000..=0xffff_ffff => {
+            data.push(major | 26);
+            data.extend_from_slice(&(value as u32).to_be_bytes());
+        }
+        _ => {
+            data.push(major | 27);
+            data.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+}
+
+/// Given the size of the MMR and an index into the MMR, returns a tuple where the first element
+/// represents the path through the subtree that the leaf node lives in.
+/// The second element represents the index of the peak containing the subtree that the leaf node
+/// lives in.
+pub fn path_for_eigen_root(leaf_index: u64, leaf_count: u64) -> Result<(u64, u64), VerifyError> {
+    // Ensure `leaf_index` is within bounds.
+    if leaf_count == 0 {
+        return Err(VerifyError::EmptyAccumulator);
+    }
+    if leaf_index >= leaf_count {
+        return Err(VerifyError::IndexOutOfRange {
+            index: leaf_index,
+            leaf_count,
+        });
+    }
+    // XOR turns matching bits into zeros and differing bits into ones, so to determine when
+    // the two "paths" converge, we simply look for the most significant 1 bit...
+    let diff = leaf_index ^ leaf_count;
+    // ...and then merge height of `leaf_index` and `leaf_count` occurs at ⌊log2(x ⊕ y)⌋
+    let eigentree_height = u64::BITS - diff.leading_zeros() - 1;
+    let merge_height = 1 << eigentree_height;
+    // Compute a bitmask (all the lower bits set to 1)
+    let bitmask = merge_height - 1;
+    // The Hamming weight of leaf_count is the number of eigentrees in the structure.
+    let eigentree_count = leaf_count.count_ones();
+    // Isolates the lower bits of leaf_count up to the merge_height, and count the one bits.
+    // This is essentially the offset to the eigentree containing leaf_index
+    let offset = (leaf_count & bitmask).count_ones();
+    // The index is simply the total eigentree count minus the offset (minus one)
+    let eigen_index = eigentree_count - offset - 1;
+    // Now that we have the offset, we need to determine the path within the local eigentree
+    let local_offset = leaf_index & bitmask;
+    // The local_index is the local_offset plus the merge_height for the local eigentree
+    let local_path = local_offset + merge_height;
+    Ok((local_path, eigen_index as u64))
+}
@@ -0,0 +1,136 @@
+// Copyright 2024 Textile
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Verifies proofs of a fixed accumulator, so that the verifier keeps agreeing with roots the
+//! actor has already committed to, whatever features it is built with.
+
+use cid::multihash::{Code, MultihashDigest};
+use cid::Cid;
+use fendermint_actor_accumulator_verifier::{
+    bag_peaks, hash_pair, peak_from_leaves, verify_proof, ProofRef, RootScheme, VerifyError,
+};
+
+/// Leaves `a`, `b` and `c`, stored raw and hashed with Blake2b-256.
+const LEAVES: [&str; 3] = [
+    "bafk2bzacecesrkxghscnq7vatble2hqdvwat6ed23vdu4vvo3uuggsoaya7ki",
+    "bafk2bzacebxfyh2fzoxrt6kcgc5dkaodpcstgwxxdizrww225vrhsizsfcg4g",
+    "bafk2bzacedwviarjtjraqakob5pslltmuo5n3xev3nt5zylezofkbbv5jclyu",
+];
+/// The peak over the first two leaves.
+const PEAK: &str = "bafy2bzacectkhaaagtxpskltgpecbzzesby2m5ebsqy4qzdufm5dtwicz4y6u";
+/// The root of the three leaves, the same under both schemes.
+const ROOT: &str = "bafy2bzaceahmoxnzgdmbquhfprzcxtdaj2junk7qxe3fmlv3wv55wx7zkidpa";
+/// The uniform root of an accumulator holding only the first leaf.
+const UNIFORM_SINGLE_ROOT: &str = "bafy2bzacecpxvccx5ptdwusqjqe75gqbs5g2dfwxyxkomdhbdueytv7pqsfte";
+
+fn cid(s: &str) -> Cid {
+    Cid::try_from(s).unwrap()
+}
+
+#[test]
+fn verify_fixture_proof() {
+    let leaves = LEAVES.map(cid);
+    for (leaf, data) in leaves.iter().zip([b"a", b"b", b"c"]) {
+        assert_eq!(*leaf, Cid::new_v1(0x55, Code::Blake2b256.digest(*data)));
+    }
+    let peaks = [cid(PEAK), leaves[2]];
+    let root = cid(ROOT);
+    assert_eq!(
+        hash_pair(&leaves[0], &leaves[1], Code::Blake2b256),
+        peaks[0]
+    );
+    assert_eq!(
+        peak_from_leaves(&leaves[0..2], Code::Blake2b256),
+        Some(peaks[0])
+    );
+    assert_eq!(
+        bag_peaks(&peaks, Code::Blake2b256, RootScheme::Promoted),
+        root
+    );
+    assert_eq!(
+        bag_peaks(&peaks, Code::Blake2b256, RootScheme::Uniform),
+        root
+    );
+
+    let siblings = [leaves[1]];
+    let proof = ProofRef {
+        index: 0,
+        leaf_count: 3,
+        leaf: &leaves[0],
+        siblings: &siblings,
+        peaks: &peaks,
+    };
+    assert!(verify_proof(&proof, &root, Code::Blake2b256, RootScheme::Promoted).unwrap());
+
+    // The wrong root, a tampered sibling or the wrong hash function don't verify.
+    assert!(!verify_proof(&proof, &peaks[0], Code::Blake2b256, RootScheme::Promoted).unwrap());
+    let tampered = [leaves[2]];
+    let bad = ProofRef {
+        siblings: &tampered,
+        ..proof
+    };
+    assert!(!verify_proof(&bad, &root, Code::Blake2b256, RootScheme::Promoted).unwrap());
+    assert!(!verify_proof(&proof, &root, Code::Blake2b512, RootScheme::Promoted).unwrap());
+
+    // The last leaf is a peak of its own, with no siblings.
+    let last = ProofRef {
+        index: 2,
+        leaf: &leaves[2],
+        siblings: &[],
+        ..proof
+    };
+    assert!(verify_proof(&last, &root, Code::Blake2b256, RootScheme::Promoted).unwrap());
+
+    let out_of_range = ProofRef { index: 3, ..last };
+    assert_eq!(
+        verify_proof(&out_of_range, &root, Code::Blake2b256, RootScheme::Promoted),
+        Err(VerifyError::IndexOutOfRange {
+            index: 3,
+            leaf_count: 3
+        })
+    );
+}
+
+#[test]
+fn verify_fixture_single_leaf() {
+    let leaf = cid(LEAVES[0]);
+    let peaks = [leaf];
+    let proof = ProofRef {
+        index: 0,
+        leaf_count: 1,
+        leaf: &leaf,
+        siblings: &[],
+        peaks: &peaks,
+    };
+    let uniform = cid(UNIFORM_SINGLE_ROOT);
+    assert!(verify_proof(&proof, &leaf, Code::Blake2b256, RootScheme::Promoted).unwrap());
+    assert!(verify_proof(&proof, &uniform, Code::Blake2b256, RootScheme::Uniform).unwrap());
+    assert!(!verify_proof(&proof, &leaf, Code::Blake2b256, RootScheme::Uniform).unwrap());
+}
+
+#[test]
+fn verify_other_hashes() {
+    // The actor only hashes with Blake2b-256, but accumulators kept off chain may use another
+    // hash function, which the verifier takes like any other.
+    let mut roots = Vec::new();
+    for (code, size) in [(Code::Blake2b512, 64), (Code::Sha2_256, 32)] {
+        let leaves = [b"a", b"b", b"c"].map(|data| Cid::new_v1(0x55, code.digest(data)));
+        let peaks = [peak_from_leaves(&leaves[0..2], code).unwrap(), leaves[2]];
+        let root = bag_peaks(&peaks, code, RootScheme::Promoted);
+        assert_eq!(root.hash().size(), size);
+
+        let siblings = [leaves[0]];
+        let proof = ProofRef {
+            index: 1,
+            leaf_count: 3,
+            leaf: &leaves[1],
+            siblings: &siblings,
+            peaks: &peaks,
+        };
+        assert!(verify_proof(&proof, &root, code, RootScheme::Promoted).unwrap());
+        assert!(!verify_proof(&proof, &root, Code::Blake2b256, RootScheme::Promoted).unwrap());
+        roots.push(root);
+    }
+    assert_ne!(roots[0], roots[1]);
+    assert!(!roots.contains(&cid(ROOT)));
+}
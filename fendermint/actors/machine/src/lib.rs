@@ -8,6 +8,7 @@ use fil_actors_runtime::{runtime::Runtime, ActorError};
 use fvm_ipld_encoding::tuple::*;
 use fvm_shared::{address::Address, MethodNum};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt::Display;
 use std::str::FromStr;
 
@@ -18,10 +19,13 @@ pub struct ConstructorParams {
     pub creator: Address,
     /// Write access dictates who can write to the machine.
     pub write_access: WriteAccess,
+    /// Application-defined metadata, e.g. a name or a schema ID.
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
 }
 
 /// The different types of machine write access.
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum WriteAccess {
     /// Only the owner can write to the machine.
     OnlyOwner,
@@ -54,6 +58,51 @@ impl Display for WriteAccess {
 /// Method number that machines must use for get metadata.
 pub const GET_METADATA_METHOD: MethodNum = frc42_dispatch::method_hash!("GetMetadata");
 
+/// Method number that machines must use for checking whether an address can write to them.
+pub const CAN_WRITE_METHOD: MethodNum = frc42_dispatch::method_hash!("CanWrite");
+
+/// Method number that machines must use for replacing their metadata.
+pub const SET_METADATA_METHOD: MethodNum = frc42_dispatch::method_hash!("SetMetadata");
+
+/// Maximum number of entries in the metadata of a machine.
+pub const MAX_MACHINE_METADATA_ENTRIES: usize = 32;
+
+/// Maximum total length in bytes of the keys and values of the metadata of a machine.
+pub const MAX_MACHINE_METADATA_SIZE: usize = 4 * 1024;
+
+/// Params for replacing the metadata of a machine.
+#[derive(Debug, Serialize_tuple, Deserialize_tuple)]
+pub struct SetMetadataParams {
+    /// The new metadata, replacing all of the current entries.
+    pub metadata: HashMap<String, String>,
+}
+
+/// Ensures that machine metadata is within the limits.
+pub fn ensure_metadata_allowed(metadata: &HashMap<String, String>) -> Result<(), ActorError> {
+    if metadata.len() > MAX_MACHINE_METADATA_ENTRIES {
+        return Err(ActorError::illegal_argument(format!(
+            "metadata has {} entries, exceeding maximum of {}",
+            metadata.len(),
+            MAX_MACHINE_METADATA_ENTRIES
+        )));
+    }
+    let size: usize = metadata.iter().map(|(k, v)| k.len() + v.len()).sum();
+    if size > MAX_MACHINE_METADATA_SIZE {
+        return Err(ActorError::illegal_argument(format!(
+            "metadata size {} exceeds maximum of {}",
+            size, MAX_MACHINE_METADATA_SIZE
+        )));
+    }
+    Ok(())
+}
+
+/// Params for checking whether an address can write to a machine.
+#[derive(Debug, Serialize_tuple, Deserialize_tuple)]
+pub struct CanWriteParams {
+    /// The address of the would-be writer.
+    pub address: Address,
+}
+
 // TODO: Add method for changing owner from ADM actor.
 pub trait MachineActor {
     type State: MachineState + DeserializeOwned;
@@ -62,29 +111,50 @@ pub trait MachineActor {
     fn ensure_write_allowed(rt: &impl Runtime) -> Result<(), ActorError> {
         let state = rt.state::<Self::State>()?;
         match state.write_access() {
-            WriteAccess::OnlyOwner => {
-                // Leaving this note here as something to revist in the future before mainnet.
-                //
-                // We want owner to be stored as a robust address that users can understand,
-                // but the caller is always an ID address. This means we have to resolve the
-                // actor ID from the init actor, which adds some extra ops and charges gas.
-                // We could instead store both actor ID and robust address in machine state,
-                // but I _think_ that could result in incorrect robust address to actor ID
-                // pairings in the case of a reorg.
-                if let Some(owner_id) = rt.resolve_address(&state.owner()) {
-                    rt.validate_immediate_caller_is(std::iter::once(&Address::new_id(owner_id)))?
-                } else {
-                    // This should not happen.
-                    return Err(ActorError::forbidden(String::from(
-                        "failed to resolve machine owner id",
-                    )));
-                }
-            }
+            WriteAccess::OnlyOwner => Self::ensure_owner(rt)?,
             WriteAccess::Public => rt.validate_immediate_caller_accept_any()?,
         }
         Ok(())
     }
 
+    /// Ensures that immediate caller is the machine owner, regardless of write access.
+    fn ensure_owner(rt: &impl Runtime) -> Result<(), ActorError> {
+        let state = rt.state::<Self::State>()?;
+        // Leaving this note here as something to revist in the future before mainnet.
+        //
+        // We want owner to be stored as a robust address that users can understand,
+        // but the caller is always an ID address. This means we have to resolve the
+        // actor ID from the init actor, which adds some extra ops and charges gas.
+        // We could instead store both actor ID and robust address in machine state,
+        // but I _think_ that could result in incorrect robust address to actor ID
+        // pairings in the case of a reorg.
+        if let Some(owner_id) = rt.resolve_address(&state.owner()) {
+            rt.validate_immediate_caller_is(std::iter::once(&Address::new_id(owner_id)))
+        } else {
+            // This should not happen.
+            Err(ActorError::forbidden(String::from(
+                "failed to resolve machine owner id",
+            )))
+        }
+    }
+
+    /// Check whether `address` is allowed to write to the machine, as a caller with that address
+    /// would be by [`MachineActor::ensure_write_allowed`]. A frozen machine can't be written to.
+    fn can_write(rt: &impl Runtime, params: CanWriteParams) -> Result<bool, ActorError> {
+        rt.validate_immediate_caller_accept_any()?;
+        let state = rt.state::<Self::State>()?;
+        if state.frozen() {
+            return Ok(false);
+        }
+        Ok(match state.write_access() {
+            WriteAccess::OnlyOwner => {
+                let owner_id = rt.resolve_address(&state.owner());
+                owner_id.is_some() && owner_id == rt.resolve_address(&params.address)
+            }
+            WriteAccess::Public => true,
+        })
+    }
+
     /// Get machine metadata.
     fn get_metadata(rt: &impl Runtime) -> Result<Metadata, ActorError> {
         rt.validate_immediate_caller_accept_any()?;
@@ -92,6 +162,23 @@ pub trait MachineActor {
         Ok(Metadata {
             owner: st.owner(),
             kind: st.kind(),
+            write_access: st.write_access(),
+            frozen: st.frozen(),
+            metadata: st.metadata().clone(),
+        })
+    }
+
+    /// Replace the machine metadata. Only the owner can, even if the machine is frozen, as
+    /// the metadata describes the machine rather than being part of what it stores.
+    fn set_metadata(rt: &impl Runtime, params: SetMetadataParams) -> Result<(), ActorError>
+    where
+        Self::State: Serialize,
+    {
+        Self::ensure_owner(rt)?;
+        ensure_metadata_allowed(&params.metadata)?;
+        rt.transaction(|st: &mut Self::State, _rt| {
+            st.set_metadata(params.metadata);
+            Ok(())
         })
     }
 }
@@ -103,6 +190,13 @@ pub struct Metadata {
     pub kind: Kind,
     /// Machine owner robust address.
     pub owner: Address,
+    /// Write access dictates who can write to the machine.
+    pub write_access: WriteAccess,
+    /// Whether the machine is frozen and no longer accepts writes.
+    pub frozen: bool,
+    /// Application-defined metadata, e.g. a name or a schema ID.
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
 }
 
 /// Trait that must be implemented by machine state.
@@ -110,4 +204,11 @@ pub trait MachineState {
     fn kind(&self) -> Kind;
     fn owner(&self) -> Address;
     fn write_access(&self) -> WriteAccess;
+    fn metadata(&self) -> &HashMap<String, String>;
+    fn set_metadata(&mut self, metadata: HashMap<String, String>);
+
+    /// Whether the machine is frozen and no longer accepts writes.
+    fn frozen(&self) -> bool {
+        false
+    }
 }
@@ -3,52 +3,207 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use cid::Cid;
-use fendermint_actor_machine::{ConstructorParams, MachineActor};
+use fendermint_actor_machine::{ensure_metadata_allowed, ConstructorParams, MachineActor};
 use fil_actors_runtime::{
-    actor_dispatch, actor_error,
+    actor_dispatch, actor_error, deserialize_block, extract_send_result,
     runtime::{ActorCode, Runtime},
-    ActorDowncast, ActorError, FIRST_EXPORTED_METHOD_NUMBER, INIT_ACTOR_ADDR, SYSTEM_ACTOR_ADDR,
+    ActorDowncast, ActorError, EventBuilder, FIRST_EXPORTED_METHOD_NUMBER, INIT_ACTOR_ADDR,
+    SYSTEM_ACTOR_ADDR,
 };
-use fvm_ipld_encoding::ipld_block::IpldBlock;
+use fvm_ipld_encoding::{ipld_block::IpldBlock, strict_bytes::ByteBuf};
 use fvm_ipld_hamt::BytesKey;
-use fvm_shared::{error::ExitCode, MethodNum};
+use fvm_shared::{address::Address, econ::TokenAmount, error::ExitCode, MethodNum};
+use num_traits::Zero;
+use std::collections::HashMap;
 
 use crate::{
-    AddParams, DeleteParams, GetParams, ListParams, Method, Object, ObjectList, ResolveParams,
-    State, OBJECTSTORE_ACTOR_NAME,
+    internal_cid, AddParams, ChangeLogEntry, ChangeOp, ClaimFirstParams, ClaimParams, Config,
+    CopyParams, DeleteParams, GetParams, GetVersionParams, HasManyParams, HasParams,
+    ListByTimeParams, ListParams, ListVersionsParams, Method, Object, ObjectList, ObjectStat,
+    ObjectStoreError, ObjectVersion, ResolveParams, RestoreParams, SetPrefixDefaultsParams,
+    StatParams, State, SwapParams, UpdateMetadataParams, MAX_HAS_MANY_KEYS,
+    MAX_LIST_PATTERN_LENGTH, OBJECTSTORE_ACTOR_NAME,
 };
 
 #[cfg(feature = "fil-actor")]
 fil_actors_runtime::wasm_trampoline!(Actor);
 
+/// Method number of the accumulator actor's `Root` method.
+const ACCUMULATOR_ROOT_METHOD: MethodNum = frc42_dispatch::method_hash!("Root");
+/// Method number of the accumulator actor's `Push` method.
+const ACCUMULATOR_PUSH_METHOD: MethodNum = frc42_dispatch::method_hash!("Push");
+
 pub struct Actor;
 
 impl Actor {
     fn constructor(rt: &impl Runtime, params: ConstructorParams) -> Result<(), ActorError> {
         rt.validate_immediate_caller_is(std::iter::once(&INIT_ACTOR_ADDR))?;
+        ensure_metadata_allowed(&params.metadata)?;
 
-        let state = State::new(rt.store(), params.creator, params.write_access).map_err(|e| {
-            e.downcast_default(
-                ExitCode::USR_ILLEGAL_STATE,
-                "failed to construct empty store",
-            )
-        })?;
+        let mut state =
+            State::new(rt.store(), params.creator, params.write_access).map_err(|e| {
+                e.downcast_default(
+                    ExitCode::USR_ILLEGAL_STATE,
+                    "failed to construct empty store",
+                )
+            })?;
+        state.metadata = params.metadata;
         rt.create(&state)
     }
 
     fn add_object(rt: &impl Runtime, params: AddParams) -> Result<Cid, ActorError> {
         Self::ensure_write_allowed(rt)?;
 
+        let key = params.key.clone();
+        let epoch = rt.curr_epoch();
+        if let Some(data) = &params.data {
+            if internal_cid(rt.hash_blake2b(&data.0)) != params.cid {
+                return Err(actor_error!(
+                    illegal_argument,
+                    "cid {} does not match the object content",
+                    params.cid
+                ));
+            }
+            if data.0.len() as u64 != params.size {
+                return Err(actor_error!(
+                    illegal_argument,
+                    "size {} does not match the object content of {} bytes",
+                    params.size,
+                    data.0.len()
+                ));
+            }
+        }
+        let (root, cid, inserted) = rt.transaction(|st: &mut State, rt| {
+            let (result, cid) = match params.data {
+                Some(data) => {
+                    let result = st.add_internal(
+                        rt.store(),
+                        BytesKey(params.key),
+                        params.cid,
+                        data.0,
+                        params.metadata,
+                        params.overwrite,
+                        epoch,
+                    );
+                    (result, params.cid)
+                }
+                None => {
+                    let cid = st
+                        .canonical_cid(params.cid)
+                        .map_err(|e| to_actor_error(e, "invalid object cid"))?;
+                    let result = st.add(
+                        rt.store(),
+                        BytesKey(params.key),
+                        cid,
+                        params.size,
+                        params.metadata,
+                        params.overwrite,
+                        epoch,
+                    );
+                    (result, cid)
+                }
+            };
+            let (root, inserted) = result.map_err(|e| to_actor_error(e, "failed to add object"))?;
+            Ok((root, cid, inserted))
+        })?;
+        // Adding over an existing key without overwrite succeeds without changing anything,
+        // so retried adds don't fail, but there's nothing to log.
+        if inserted {
+            Self::log_change(rt, ChangeOp::Add, &key, cid)?;
+            rt.emit_event(
+                &EventBuilder::new()
+                    .typ("object-added")
+                    .field_indexed("key", &ByteBuf(key))
+                    .field_indexed("cid", &cid)
+                    .build()?,
+            )?;
+        }
+        Ok(root)
+    }
+
+    fn swap_object(rt: &impl Runtime, params: SwapParams) -> Result<Cid, ActorError> {
+        Self::ensure_write_allowed(rt)?;
+
+        let key = params.key.clone();
+        let epoch = rt.curr_epoch();
+        let (root, cid) = rt.transaction(|st: &mut State, rt| {
+            let root = st
+                .swap(
+                    rt.store(),
+                    BytesKey(params.key),
+                    params.expected_cid,
+                    params.new_cid,
+                    params.size,
+                    params.metadata,
+                    epoch,
+                )
+                .map_err(|e| to_actor_error(e, "failed to swap object"))?;
+            let cid = st
+                .canonical_cid(params.new_cid)
+                .map_err(|e| to_actor_error(e, "invalid object cid"))?;
+            Ok((root, cid))
+        })?;
+        Self::log_change(rt, ChangeOp::Add, &key, cid)?;
+        Ok(root)
+    }
+
+    fn copy_object(rt: &impl Runtime, params: CopyParams) -> Result<Cid, ActorError> {
+        Self::ensure_write_allowed(rt)?;
+
+        let source = params.source.clone();
+        let destination = params.destination.clone();
+        let epoch = rt.curr_epoch();
+        let (object, root) = rt.transaction(|st: &mut State, rt| {
+            st.copy(
+                rt.store(),
+                &BytesKey(params.source),
+                BytesKey(params.destination),
+                params.overwrite,
+                params.delete_source,
+                epoch,
+            )
+            .map_err(|e| to_actor_error(e, "failed to copy object"))
+        })?;
+        if source == destination {
+            return Ok(root);
+        }
+        let cid = Cid::try_from(object.cid.0).map_err(
+            |e| actor_error!(illegal_state; "failed to decode copied object cid: {}", e),
+        )?;
+        Self::log_change(rt, ChangeOp::Add, &destination, cid)?;
+        if params.delete_source {
+            Self::log_change(rt, ChangeOp::Delete, &source, cid)?;
+        }
+        rt.emit_event(
+            &EventBuilder::new()
+                .typ("object-added")
+                .field_indexed("key", &ByteBuf(destination))
+                .field_indexed("cid", &cid)
+                .build()?,
+        )?;
+        if params.delete_source {
+            rt.emit_event(
+                &EventBuilder::new()
+                    .typ("object-deleted")
+                    .field_indexed("key", &ByteBuf(source))
+                    .field_indexed("cid", &cid)
+                    .build()?,
+            )?;
+        }
+        Ok(root)
+    }
+
+    fn update_metadata(rt: &impl Runtime, params: UpdateMetadataParams) -> Result<Cid, ActorError> {
+        Self::ensure_write_allowed(rt)?;
+
         let root = rt.transaction(|st: &mut State, rt| {
-            st.add(
+            st.update_metadata(
                 rt.store(),
                 BytesKey(params.key),
-                params.cid,
-                params.size,
                 params.metadata,
-                params.overwrite,
+                params.merge,
             )
-            .map_err(|e| e.downcast_default(ExitCode::USR_ILLEGAL_STATE, "failed to add object"))
+            .map_err(|e| to_actor_error(e, "failed to update metadata"))
         })?;
         Ok(root)
     }
@@ -56,15 +211,99 @@ impl Actor {
     fn resolve_object(rt: &impl Runtime, params: ResolveParams) -> Result<(), ActorError> {
         rt.validate_immediate_caller_is(std::iter::once(&SYSTEM_ACTOR_ADDR))?;
 
+        if params.failed {
+            return rt.transaction(|st: &mut State, rt| {
+                st.fail(rt.store(), BytesKey(params.key), params.value)
+                    .map_err(|e| {
+                        e.downcast_default(
+                            ExitCode::USR_ILLEGAL_STATE,
+                            "failed to mark object failed",
+                        )
+                    })
+            });
+        }
+
+        // The root is best-effort, an accumulator that can't be reached mustn't keep the
+        // object from being resolved.
+        let accumulator_root = match rt.state::<State>()?.config.accumulator {
+            Some(accumulator) => match Self::accumulator_root(rt, accumulator) {
+                Ok(root) => Some(root),
+                Err(e) => {
+                    log::warn!(
+                        "failed to get the root of accumulator {}: {}",
+                        accumulator,
+                        e
+                    );
+                    None
+                }
+            },
+            None => None,
+        };
+
         rt.transaction(|st: &mut State, rt| {
-            st.resolve(rt.store(), BytesKey(params.key), params.value)
-                .map_err(|e| {
-                    e.downcast_default(ExitCode::USR_ILLEGAL_STATE, "failed to resolve object")
-                })
+            st.resolve(
+                rt.store(),
+                BytesKey(params.key),
+                params.value,
+                accumulator_root,
+            )
+            .map_err(|e| {
+                e.downcast_default(ExitCode::USR_ILLEGAL_STATE, "failed to resolve object")
+            })
         })?;
         Ok(())
     }
 
+    /// Fetches the current root of the default namespace of the given accumulator.
+    fn accumulator_root(rt: &impl Runtime, accumulator: Address) -> Result<Cid, ActorError> {
+        // The params are the name of the namespace, empty for the default one.
+        let params = IpldBlock::serialize_cbor(&(ByteBuf(Vec::new()),))?;
+        let ret = extract_send_result(rt.send_simple(
+            &accumulator,
+            ACCUMULATOR_ROOT_METHOD,
+            params,
+            TokenAmount::zero(),
+        ))?;
+        deserialize_block(ret)
+    }
+
+    /// Pushes a change to the change log of the store, if it has one.
+    ///
+    /// The change is pushed to the default namespace, without metadata. A failed push fails
+    /// the change, so the log can't miss one.
+    fn log_change(rt: &impl Runtime, op: ChangeOp, key: &[u8], cid: Cid) -> Result<(), ActorError> {
+        let Some(change_log) = rt.state::<State>()?.config.change_log else {
+            return Ok(());
+        };
+        let seq = rt.transaction(|st: &mut State, _| Ok(st.next_change_seq()))?;
+        let entry = ChangeLogEntry {
+            op,
+            key: key.to_vec(),
+            cid,
+            seq,
+        };
+        extract_send_result(rt.send_simple(
+            &change_log,
+            ACCUMULATOR_PUSH_METHOD,
+            Self::push_params(&entry)?,
+            TokenAmount::zero(),
+        ))?;
+        Ok(())
+    }
+
+    /// Encodes the params of the accumulator's `Push` method for a change.
+    fn push_params(entry: &ChangeLogEntry) -> Result<Option<IpldBlock>, ActorError> {
+        let data = fvm_ipld_encoding::to_vec(entry)
+            .map_err(|e| actor_error!(illegal_state; "failed to encode change: {}", e))?;
+        // The params are the data, metadata, whether to return a proof and the namespace.
+        Ok(IpldBlock::serialize_cbor(&(
+            ByteBuf(data),
+            HashMap::<String, String>::new(),
+            false,
+            ByteBuf(Vec::new()),
+        ))?)
+    }
+
     // Deleting an object removes the key from the store, but not from the underlying storage.
     // So, we can't just delete it here via syscall.
     // Once implemented, the DA mechanism may cause the data to be entangled with other data.
@@ -72,14 +311,112 @@ impl Actor {
     fn delete_object(rt: &impl Runtime, params: DeleteParams) -> Result<Cid, ActorError> {
         Self::ensure_write_allowed(rt)?;
 
+        let key = params.key.clone();
+        let epoch = rt.curr_epoch();
         let res = rt.transaction(|st: &mut State, rt| {
-            st.delete(rt.store(), &BytesKey(params.key)).map_err(|e| {
-                e.downcast_default(ExitCode::USR_ILLEGAL_STATE, "failed to delete object")
-            })
+            st.delete(rt.store(), &BytesKey(params.key), epoch)
+                .map_err(|e| to_actor_error(e, "failed to delete object"))
         })?;
+        if let Some(object) = res.0 {
+            let cid = Cid::try_from(object.cid.0).map_err(
+                |e| actor_error!(illegal_state; "failed to decode deleted object cid: {}", e),
+            )?;
+            Self::log_change(rt, ChangeOp::Delete, &key, cid)?;
+            rt.emit_event(
+                &EventBuilder::new()
+                    .typ("object-deleted")
+                    .field_indexed("key", &ByteBuf(key))
+                    .field_indexed("cid", &cid)
+                    .build()?,
+            )?;
+        }
         Ok(res.1)
     }
 
+    fn claim_object(rt: &impl Runtime, params: ClaimParams) -> Result<Option<Object>, ActorError> {
+        Self::ensure_write_allowed(rt)?;
+
+        let key = params.key.clone();
+        let epoch = rt.curr_epoch();
+        let (object, _) = rt.transaction(|st: &mut State, rt| {
+            st.claim(rt.store(), &BytesKey(params.key), epoch)
+                .map_err(|e| to_actor_error(e, "failed to claim object"))
+        })?;
+        if let Some(object) = &object {
+            Self::emit_claimed(rt, key, object)?;
+        }
+        Ok(object)
+    }
+
+    fn claim_first(
+        rt: &impl Runtime,
+        params: ClaimFirstParams,
+    ) -> Result<Option<(Vec<u8>, Object)>, ActorError> {
+        Self::ensure_write_allowed(rt)?;
+
+        let epoch = rt.curr_epoch();
+        let (claimed, _) = rt.transaction(|st: &mut State, rt| {
+            st.claim_first(rt.store(), &params.prefix, epoch)
+                .map_err(|e| to_actor_error(e, "failed to claim object"))
+        })?;
+        if let Some((key, object)) = &claimed {
+            Self::emit_claimed(rt, key.clone(), object)?;
+        }
+        Ok(claimed)
+    }
+
+    /// Claimed objects are deleted, so they're logged and announced like deletions.
+    fn emit_claimed(rt: &impl Runtime, key: Vec<u8>, object: &Object) -> Result<(), ActorError> {
+        let cid = Cid::try_from(object.cid.0.as_slice()).map_err(
+            |e| actor_error!(illegal_state; "failed to decode claimed object cid: {}", e),
+        )?;
+        Self::log_change(rt, ChangeOp::Delete, &key, cid)?;
+        rt.emit_event(
+            &EventBuilder::new()
+                .typ("object-deleted")
+                .field_indexed("key", &ByteBuf(key))
+                .field_indexed("cid", &cid)
+                .build()?,
+        )
+    }
+
+    fn restore_object(rt: &impl Runtime, params: RestoreParams) -> Result<Cid, ActorError> {
+        Self::ensure_write_allowed(rt)?;
+
+        let key = params.key.clone();
+        let epoch = rt.curr_epoch();
+        let (object, root) = rt.transaction(|st: &mut State, rt| {
+            st.restore(rt.store(), BytesKey(params.key), epoch)
+                .map_err(|e| to_actor_error(e, "failed to restore object"))
+        })?;
+        let cid = Cid::try_from(object.cid.0).map_err(
+            |e| actor_error!(illegal_state; "failed to decode restored object cid: {}", e),
+        )?;
+        Self::log_change(rt, ChangeOp::Add, &key, cid)?;
+        rt.emit_event(
+            &EventBuilder::new()
+                .typ("object-restored")
+                .field_indexed("key", &ByteBuf(key))
+                .field_indexed("cid", &cid)
+                .build()?,
+        )?;
+        Ok(root)
+    }
+
+    fn purge_deleted(rt: &impl Runtime) -> Result<u64, ActorError> {
+        Self::ensure_write_allowed(rt)?;
+
+        let epoch = rt.curr_epoch();
+        rt.transaction(|st: &mut State, rt| {
+            st.purge_deleted(rt.store(), epoch).map_err(|e| {
+                e.downcast_default(
+                    ExitCode::USR_ILLEGAL_STATE,
+                    "failed to purge deleted objects",
+                )
+            })
+        })
+    }
+
     fn get_object(rt: &impl Runtime, params: GetParams) -> Result<Option<Object>, ActorError> {
         rt.validate_immediate_caller_accept_any()?;
 
@@ -88,8 +425,65 @@ impl Actor {
             .map_err(|e| e.downcast_default(ExitCode::USR_ILLEGAL_STATE, "failed to get object"))
     }
 
+    fn list_versions(
+        rt: &impl Runtime,
+        params: ListVersionsParams,
+    ) -> Result<Vec<ObjectVersion>, ActorError> {
+        rt.validate_immediate_caller_accept_any()?;
+
+        let st: State = rt.state()?;
+        st.list_versions(rt.store(), &BytesKey(params.key))
+            .map_err(|e| e.downcast_default(ExitCode::USR_ILLEGAL_STATE, "failed to list versions"))
+    }
+
+    fn get_version(
+        rt: &impl Runtime,
+        params: GetVersionParams,
+    ) -> Result<Option<ObjectVersion>, ActorError> {
+        rt.validate_immediate_caller_accept_any()?;
+
+        let st: State = rt.state()?;
+        st.get_version(rt.store(), &BytesKey(params.key), params.version)
+            .map_err(|e| e.downcast_default(ExitCode::USR_ILLEGAL_STATE, "failed to get version"))
+    }
+
+    fn has_object(rt: &impl Runtime, params: HasParams) -> Result<bool, ActorError> {
+        rt.validate_immediate_caller_accept_any()?;
+
+        let st: State = rt.state()?;
+        st.has(rt.store(), &BytesKey(params.key))
+            .map_err(|e| e.downcast_default(ExitCode::USR_ILLEGAL_STATE, "failed to check object"))
+    }
+
+    fn has_many(rt: &impl Runtime, params: HasManyParams) -> Result<Vec<bool>, ActorError> {
+        rt.validate_immediate_caller_accept_any()?;
+
+        if params.keys.len() > MAX_HAS_MANY_KEYS {
+            return Err(actor_error!(
+                illegal_argument;
+                "cannot check {} keys at once, the maximum is {}",
+                params.keys.len(),
+                MAX_HAS_MANY_KEYS
+            ));
+        }
+        let keys: Vec<BytesKey> = params.keys.into_iter().map(|k| BytesKey(k.0)).collect();
+        let st: State = rt.state()?;
+        st.has_many(rt.store(), &keys)
+            .map_err(|e| e.downcast_default(ExitCode::USR_ILLEGAL_STATE, "failed to check objects"))
+    }
+
     fn list_objects(rt: &impl Runtime, params: ListParams) -> Result<ObjectList, ActorError> {
         rt.validate_immediate_caller_accept_any()?;
+        if let Some(pattern) = &params.pattern {
+            if pattern.len() > MAX_LIST_PATTERN_LENGTH {
+                return Err(actor_error!(
+                    illegal_argument;
+                    "pattern length {} exceeds maximum {}",
+                    pattern.len(),
+                    MAX_LIST_PATTERN_LENGTH
+                ));
+            }
+        }
         let st: State = rt.state()?;
         let objects = st
             .list(
@@ -98,6 +492,8 @@ impl Actor {
                 params.delimiter,
                 params.offset,
                 params.limit,
+                params.reverse,
+                params.pattern.as_deref(),
             )
             .map_err(|e| {
                 e.downcast_default(ExitCode::USR_ILLEGAL_STATE, "failed to list objects")
@@ -105,6 +501,52 @@ impl Actor {
         Ok(objects)
     }
 
+    fn list_by_time(rt: &impl Runtime, params: ListByTimeParams) -> Result<ObjectList, ActorError> {
+        rt.validate_immediate_caller_accept_any()?;
+        let st: State = rt.state()?;
+        st.list_by_time(
+            rt.store(),
+            params.start_epoch,
+            params.end_epoch,
+            params.limit,
+        )
+        .map_err(|e| e.downcast_default(ExitCode::USR_ILLEGAL_STATE, "failed to list objects"))
+    }
+
+    fn stat_prefix(rt: &impl Runtime, params: StatParams) -> Result<ObjectStat, ActorError> {
+        rt.validate_immediate_caller_accept_any()?;
+        let st: State = rt.state()?;
+        st.stat(rt.store(), params.prefix)
+            .map_err(|e| e.downcast_default(ExitCode::USR_ILLEGAL_STATE, "failed to stat prefix"))
+    }
+
+    fn set_config(rt: &impl Runtime, params: Config) -> Result<(), ActorError> {
+        Self::ensure_owner(rt)?;
+
+        rt.transaction(|st: &mut State, rt| {
+            st.set_config(rt.store(), params).map_err(|e| {
+                e.downcast_default(ExitCode::USR_ILLEGAL_ARGUMENT, "failed to set config")
+            })
+        })
+    }
+
+    fn set_prefix_defaults(
+        rt: &impl Runtime,
+        params: SetPrefixDefaultsParams,
+    ) -> Result<(), ActorError> {
+        Self::ensure_owner(rt)?;
+
+        rt.transaction(|st: &mut State, _rt| {
+            st.set_prefix_defaults(params.prefix, params.metadata)
+                .map_err(|e| {
+                    e.downcast_default(
+                        ExitCode::USR_ILLEGAL_ARGUMENT,
+                        "failed to set prefix defaults",
+                    )
+                })
+        })
+    }
+
     /// Fallback method for unimplemented method numbers.
     pub fn fallback(
         rt: &impl Runtime,
@@ -120,6 +562,14 @@ impl Actor {
     }
 }
 
+/// Map an [`ObjectStoreError`] to its own exit code, and any other error to `USR_ILLEGAL_STATE`.
+fn to_actor_error(e: anyhow::Error, msg: &str) -> ActorError {
+    match e.downcast::<ObjectStoreError>() {
+        Ok(e) => ActorError::unchecked(e.exit_code(), format!("{}: {}", msg, e)),
+        Err(e) => e.downcast_default(ExitCode::USR_ILLEGAL_STATE, msg),
+    }
+}
+
 impl MachineActor for Actor {
     type State = State;
 }
@@ -134,11 +584,425 @@ impl ActorCode for Actor {
     actor_dispatch! {
         Constructor => constructor,
         GetMetadata => get_metadata,
+        CanWrite => can_write,
+        SetMetadata => set_metadata,
         AddObject => add_object,
         ResolveObject => resolve_object,
         DeleteObject => delete_object,
         GetObject => get_object,
+        HasObject => has_object,
+        HasMany => has_many,
         ListObjects => list_objects,
+        SwapObject => swap_object,
+        SetConfig => set_config,
+        UpdateMetadata => update_metadata,
+        StatPrefix => stat_prefix,
+        RestoreObject => restore_object,
+        PurgeDeleted => purge_deleted,
+        ListVersions => list_versions,
+        GetVersion => get_version,
+        ListByTime => list_by_time,
+        CopyObject => copy_object,
+        ClaimObject => claim_object,
+        ClaimFirst => claim_first,
+        SetPrefixDefaults => set_prefix_defaults,
         _ => fallback,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cid::multihash::{Code, MultihashDigest};
+    use fendermint_actor_accumulator::PushParams;
+    use fendermint_actor_machine::WriteAccess;
+    use fil_actors_runtime::test_utils::{
+        expect_empty, MockRuntime, ACCOUNT_ACTOR_CODE_ID, INIT_ACTOR_CODE_ID, SYSTEM_ACTOR_CODE_ID,
+    };
+    use fvm_ipld_encoding::DAG_CBOR;
+
+    const OWNER: u64 = 100;
+    const CHANGE_LOG: u64 = 101;
+    const ACCUMULATOR: u64 = 102;
+
+    /// Constructs a store with a change log, called by its owner.
+    fn construct_with_change_log() -> MockRuntime {
+        construct_with_config(Config {
+            change_log: Some(Address::new_id(CHANGE_LOG)),
+            ..Default::default()
+        })
+    }
+
+    /// Constructs a store with the config, called by its owner.
+    fn construct_with_config(config: Config) -> MockRuntime {
+        let rt = MockRuntime {
+            receiver: Address::new_id(10),
+            ..Default::default()
+        };
+
+        rt.set_caller(*INIT_ACTOR_CODE_ID, INIT_ACTOR_ADDR);
+        rt.expect_validate_caller_addr(vec![INIT_ACTOR_ADDR]);
+
+        let result = rt
+            .call::<Actor>(
+                Method::Constructor as u64,
+                IpldBlock::serialize_cbor(&ConstructorParams {
+                    creator: Address::new_id(OWNER),
+                    write_access: WriteAccess::OnlyOwner,
+                    metadata: HashMap::new(),
+                })
+                .unwrap(),
+            )
+            .unwrap();
+        expect_empty(result);
+        rt.verify();
+        rt.reset();
+
+        rt.set_caller(*ACCOUNT_ACTOR_CODE_ID, Address::new_id(OWNER));
+        rt.expect_validate_caller_addr(vec![Address::new_id(OWNER)]);
+        rt.call::<Actor>(
+            Method::SetConfig as u64,
+            IpldBlock::serialize_cbor(&config).unwrap(),
+        )
+        .unwrap();
+        rt.verify();
+        rt
+    }
+
+    fn expect_push(
+        rt: &MockRuntime,
+        op: ChangeOp,
+        key: &[u8],
+        cid: Cid,
+        seq: u64,
+        exit_code: ExitCode,
+    ) {
+        let entry = ChangeLogEntry {
+            op,
+            key: key.to_vec(),
+            cid,
+            seq,
+        };
+        let params = PushParams {
+            data: fvm_ipld_encoding::to_vec(&entry).unwrap(),
+            metadata: HashMap::new(),
+            with_proof: false,
+            namespace: vec![],
+        };
+        rt.expect_send_simple(
+            Address::new_id(CHANGE_LOG),
+            ACCUMULATOR_PUSH_METHOD,
+            IpldBlock::serialize_cbor(&params).unwrap(),
+            TokenAmount::zero(),
+            None,
+            exit_code,
+        );
+    }
+
+    fn add_params(key: &[u8], cid: Cid) -> AddParams {
+        AddParams {
+            key: key.to_vec(),
+            cid,
+            size: 3,
+            metadata: HashMap::new(),
+            overwrite: false,
+            data: None,
+        }
+    }
+
+    #[test]
+    fn test_changes_are_logged() {
+        let rt = construct_with_change_log();
+
+        let cid = Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&[1, 2, 3]));
+        for (seq, key) in [b"a".as_slice(), b"b".as_slice()].into_iter().enumerate() {
+            rt.expect_validate_caller_addr(vec![Address::new_id(OWNER)]);
+            expect_push(&rt, ChangeOp::Add, key, cid, seq as u64, ExitCode::OK);
+            rt.expect_emitted_event(
+                EventBuilder::new()
+                    .typ("object-added")
+                    .field_indexed("key", &ByteBuf(key.to_vec()))
+                    .field_indexed("cid", &cid)
+                    .build()
+                    .unwrap(),
+            );
+            rt.call::<Actor>(
+                Method::AddObject as u64,
+                IpldBlock::serialize_cbor(&add_params(key, cid)).unwrap(),
+            )
+            .unwrap();
+            rt.verify();
+        }
+
+        rt.expect_validate_caller_addr(vec![Address::new_id(OWNER)]);
+        expect_push(&rt, ChangeOp::Delete, b"a", cid, 2, ExitCode::OK);
+        rt.expect_emitted_event(
+            EventBuilder::new()
+                .typ("object-deleted")
+                .field_indexed("key", &ByteBuf(b"a".to_vec()))
+                .field_indexed("cid", &cid)
+                .build()
+                .unwrap(),
+        );
+        rt.call::<Actor>(
+            Method::DeleteObject as u64,
+            IpldBlock::serialize_cbor(&DeleteParams { key: b"a".to_vec() }).unwrap(),
+        )
+        .unwrap();
+        rt.verify();
+
+        // Adding the object again is a distinct change, so a deduplicating log keeps it.
+        rt.expect_validate_caller_addr(vec![Address::new_id(OWNER)]);
+        expect_push(&rt, ChangeOp::Add, b"a", cid, 3, ExitCode::OK);
+        rt.expect_emitted_event(
+            EventBuilder::new()
+                .typ("object-added")
+                .field_indexed("key", &ByteBuf(b"a".to_vec()))
+                .field_indexed("cid", &cid)
+                .build()
+                .unwrap(),
+        );
+        rt.call::<Actor>(
+            Method::AddObject as u64,
+            IpldBlock::serialize_cbor(&add_params(b"a", cid)).unwrap(),
+        )
+        .unwrap();
+        rt.verify();
+        assert_eq!(rt.get_state::<State>().change_log_seq, 4);
+
+        // The log can't be changed once there are objects.
+        rt.expect_validate_caller_addr(vec![Address::new_id(OWNER)]);
+        let err = rt
+            .call::<Actor>(
+                Method::SetConfig as u64,
+                IpldBlock::serialize_cbor(&Config::default()).unwrap(),
+            )
+            .unwrap_err();
+        assert_eq!(err.exit_code(), ExitCode::USR_ILLEGAL_ARGUMENT);
+    }
+
+    #[test]
+    fn test_add_existing_key_without_overwrite() {
+        let rt = construct_with_change_log();
+
+        let cid = Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&[1, 2, 3]));
+        rt.expect_validate_caller_addr(vec![Address::new_id(OWNER)]);
+        expect_push(&rt, ChangeOp::Add, b"a", cid, 0, ExitCode::OK);
+        rt.expect_emitted_event(
+            EventBuilder::new()
+                .typ("object-added")
+                .field_indexed("key", &ByteBuf(b"a".to_vec()))
+                .field_indexed("cid", &cid)
+                .build()
+                .unwrap(),
+        );
+        rt.call::<Actor>(
+            Method::AddObject as u64,
+            IpldBlock::serialize_cbor(&add_params(b"a", cid)).unwrap(),
+        )
+        .unwrap();
+        rt.verify();
+
+        // The key is left alone and the add still succeeds, so a retried add doesn't fail,
+        // but nothing is pushed to the change log and no event is emitted for the new CID.
+        let root = rt.get_state::<State>().root;
+        let other = Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&[4, 5, 6]));
+        rt.expect_validate_caller_addr(vec![Address::new_id(OWNER)]);
+        let result = rt
+            .call::<Actor>(
+                Method::AddObject as u64,
+                IpldBlock::serialize_cbor(&add_params(b"a", other)).unwrap(),
+            )
+            .unwrap()
+            .unwrap()
+            .deserialize::<Cid>()
+            .unwrap();
+        assert_eq!(result, root);
+        rt.verify();
+        let object = rt
+            .get_state::<State>()
+            .get(rt.store(), &BytesKey(b"a".to_vec()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(object.cid.0, cid.to_bytes());
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        let rt = construct_with_change_log();
+        let call = |method: Method, params: IpldBlock| {
+            rt.expect_validate_caller_addr(vec![Address::new_id(OWNER)]);
+            let err = rt.call::<Actor>(method as u64, Some(params)).unwrap_err();
+            rt.verify();
+            err.exit_code()
+        };
+
+        // Missing keys are not found.
+        let missing = b"missing".to_vec();
+        let delete = DeleteParams {
+            key: missing.clone(),
+        };
+        assert_eq!(
+            call(
+                Method::DeleteObject,
+                IpldBlock::serialize_cbor(&delete).unwrap().unwrap()
+            ),
+            ExitCode::USR_NOT_FOUND
+        );
+        let restore = RestoreParams {
+            key: missing.clone(),
+        };
+        assert_eq!(
+            call(
+                Method::RestoreObject,
+                IpldBlock::serialize_cbor(&restore).unwrap().unwrap()
+            ),
+            ExitCode::USR_NOT_FOUND
+        );
+        let copy = CopyParams {
+            source: missing.clone(),
+            destination: b"copy".to_vec(),
+            overwrite: false,
+            delete_source: false,
+        };
+        assert_eq!(
+            call(
+                Method::CopyObject,
+                IpldBlock::serialize_cbor(&copy).unwrap().unwrap()
+            ),
+            ExitCode::USR_NOT_FOUND
+        );
+
+        // A failed precondition is forbidden.
+        let swap = SwapParams {
+            key: missing,
+            expected_cid: Some(Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&[1]))),
+            new_cid: Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&[2])),
+            size: 3,
+            metadata: HashMap::new(),
+        };
+        assert_eq!(
+            call(
+                Method::SwapObject,
+                IpldBlock::serialize_cbor(&swap).unwrap().unwrap()
+            ),
+            ExitCode::USR_FORBIDDEN
+        );
+
+        // Arguments beyond the configured limits are illegal.
+        let mut add = add_params(b"a", Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&[3])));
+        add.metadata = (0..65).map(|i| (i.to_string(), String::new())).collect();
+        assert_eq!(
+            call(
+                Method::AddObject,
+                IpldBlock::serialize_cbor(&add).unwrap().unwrap()
+            ),
+            ExitCode::USR_ILLEGAL_ARGUMENT
+        );
+    }
+
+    #[test]
+    fn test_failed_push_fails_change() {
+        let rt = construct_with_change_log();
+
+        let cid = Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&[1, 2, 3]));
+        rt.expect_validate_caller_addr(vec![Address::new_id(OWNER)]);
+        expect_push(&rt, ChangeOp::Add, b"a", cid, 0, ExitCode::USR_FORBIDDEN);
+        let err = rt
+            .call::<Actor>(
+                Method::AddObject as u64,
+                IpldBlock::serialize_cbor(&add_params(b"a", cid)).unwrap(),
+            )
+            .unwrap_err();
+        assert_eq!(err.exit_code(), ExitCode::USR_FORBIDDEN);
+    }
+
+    #[test]
+    fn test_push_params_decode() {
+        let entry = ChangeLogEntry {
+            op: ChangeOp::Delete,
+            key: b"a".to_vec(),
+            cid: Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&[1, 2, 3])),
+            seq: 7,
+        };
+        // The accumulator decodes the hand-encoded params as its own.
+        let params = Actor::push_params(&entry)
+            .unwrap()
+            .unwrap()
+            .deserialize::<PushParams>()
+            .unwrap();
+        assert!(params.metadata.is_empty());
+        assert!(!params.with_proof);
+        assert!(params.namespace.is_empty());
+        let decoded: ChangeLogEntry = fvm_ipld_encoding::from_slice(&params.data).unwrap();
+        assert_eq!(decoded, entry);
+    }
+
+    #[test]
+    fn test_resolve_records_accumulator_root() {
+        let rt = construct_with_config(Config {
+            accumulator: Some(Address::new_id(ACCUMULATOR)),
+            ..Default::default()
+        });
+        let root = Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&[6, 7, 8]));
+
+        // The root is recorded if the accumulator returns it, and left out if it fails.
+        for (key, ret, exit_code, resolved_root) in [
+            (
+                b"a",
+                IpldBlock::serialize_cbor(&root).unwrap(),
+                ExitCode::OK,
+                Some(root),
+            ),
+            (b"b", None, ExitCode::USR_UNHANDLED_MESSAGE, None),
+        ] {
+            let cid = Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(key));
+            rt.set_caller(*ACCOUNT_ACTOR_CODE_ID, Address::new_id(OWNER));
+            rt.expect_validate_caller_addr(vec![Address::new_id(OWNER)]);
+            rt.expect_emitted_event(
+                EventBuilder::new()
+                    .typ("object-added")
+                    .field_indexed("key", &ByteBuf(key.to_vec()))
+                    .field_indexed("cid", &cid)
+                    .build()
+                    .unwrap(),
+            );
+            rt.call::<Actor>(
+                Method::AddObject as u64,
+                IpldBlock::serialize_cbor(&add_params(key, cid)).unwrap(),
+            )
+            .unwrap();
+            rt.verify();
+
+            rt.set_caller(*SYSTEM_ACTOR_CODE_ID, SYSTEM_ACTOR_ADDR);
+            rt.expect_validate_caller_addr(vec![SYSTEM_ACTOR_ADDR]);
+            rt.expect_send_simple(
+                Address::new_id(ACCUMULATOR),
+                ACCUMULATOR_ROOT_METHOD,
+                IpldBlock::serialize_cbor(&(ByteBuf(Vec::new()),)).unwrap(),
+                TokenAmount::zero(),
+                ret,
+                exit_code,
+            );
+            rt.call::<Actor>(
+                Method::ResolveObject as u64,
+                IpldBlock::serialize_cbor(&ResolveParams {
+                    key: key.to_vec(),
+                    value: cid,
+                    failed: false,
+                })
+                .unwrap(),
+            )
+            .unwrap();
+            rt.verify();
+
+            let object = rt
+                .get_state::<State>()
+                .get(rt.store(), &BytesKey(key.to_vec()))
+                .unwrap()
+                .unwrap();
+            assert!(object.resolved);
+            assert_eq!(object.resolved_root, resolved_root);
+        }
+    }
+}
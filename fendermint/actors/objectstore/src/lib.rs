@@ -2,7 +2,7 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
 
-#[cfg(feature = "fil-actor")]
+#[cfg(any(feature = "fil-actor", test))]
 mod actor;
 mod shared;
 mod state;
@@ -2,16 +2,62 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
 
-use cid::Cid;
-use fendermint_actor_machine::GET_METADATA_METHOD;
-use fvm_ipld_encoding::{strict_bytes, tuple::*};
-use fvm_shared::METHOD_CONSTRUCTOR;
+use cid::{multihash::Multihash, Cid};
+use fendermint_actor_machine::{CAN_WRITE_METHOD, GET_METADATA_METHOD, SET_METADATA_METHOD};
+use fvm_ipld_encoding::{strict_bytes, strict_bytes::ByteBuf, tuple::*, IPLD_RAW};
+use fvm_shared::{clock::ChainEpoch, crypto::hash::SupportedHashes, METHOD_CONSTRUCTOR};
 use num_derive::FromPrimitive;
 use std::collections::HashMap;
 
-pub use crate::state::{Object, ObjectList, State};
+pub use crate::state::{
+    Config, Object, ObjectKind, ObjectList, ObjectStat, ObjectStoreError, ObjectVersion,
+    PrefixDefaults, State, Tombstone, BIT_WIDTH, MAX_PREFIX_DEFAULTS,
+};
 
 pub const OBJECTSTORE_ACTOR_NAME: &str = "objectstore";
+/// Maximum number of keys checked by a single `HasMany` call.
+pub const MAX_HAS_MANY_KEYS: usize = 1000;
+/// Maximum length in bytes of a list pattern.
+pub const MAX_LIST_PATTERN_LENGTH: usize = 1024;
+
+/// The key of a content-addressed object, which is the string form of its CID.
+pub fn cid_key(cid: &Cid) -> Vec<u8> {
+    cid.to_string().into_bytes()
+}
+
+/// The CID of the content of an internal object, given its Blake2b-256 digest.
+///
+/// This is the CIDv1 of the content as a raw block, as clients compute it with
+/// `Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(data))`.
+pub fn internal_cid(digest: [u8; 32]) -> Cid {
+    let mh = Multihash::wrap(SupportedHashes::Blake2b256 as u64, &digest)
+        .expect("a 32 byte digest fits in a multihash");
+    Cid::new_v1(IPLD_RAW, mh)
+}
+
+/// A change to the objects of a store, see [`ChangeLogEntry`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChangeOp {
+    /// An object was added under the key, by a put, a swap, a copy or a restore.
+    Add,
+    /// The object under the key was deleted, by a delete, a move or a claim.
+    Delete,
+}
+
+/// A leaf of the change log of an object store, see [`Config::change_log`].
+#[derive(Clone, Debug, PartialEq, Serialize_tuple, Deserialize_tuple)]
+pub struct ChangeLogEntry {
+    pub op: ChangeOp,
+    /// Object key.
+    #[serde(with = "strict_bytes")]
+    pub key: Vec<u8>,
+    /// Object CID.
+    pub cid: Cid,
+    /// Number of the change in the log, counting from 0. It tells identical changes apart,
+    /// e.g. adding an object again after deleting it, which an accumulator with a dedup
+    /// window would otherwise drop.
+    pub seq: u64,
+}
 
 /// Params for putting an object.
 #[derive(Clone, Debug, Serialize_tuple, Deserialize_tuple)]
@@ -22,11 +68,71 @@ pub struct AddParams {
     /// Object value.
     pub cid: Cid,
     /// Object size.
-    pub size: usize,
+    pub size: u64,
     /// Object metadata.
     pub metadata: HashMap<String, String>,
     /// Whether to overwrite a key if it already exists.
     pub overwrite: bool,
+    /// Object content, to store the object internally rather than have it resolved from IPFS.
+    /// The CID has to be the [`internal_cid`] of the content, and the size its length.
+    /// Missing from the params of clients that predate internal objects.
+    #[serde(default)]
+    pub data: Option<ByteBuf>,
+}
+
+/// Params for swapping an object.
+#[derive(Clone, Debug, Serialize_tuple, Deserialize_tuple)]
+pub struct SwapParams {
+    /// Object key.
+    #[serde(with = "strict_bytes")]
+    pub key: Vec<u8>,
+    /// The value the object is expected to have.
+    /// `None` means the key is expected to be absent.
+    pub expected_cid: Option<Cid>,
+    /// New object value.
+    pub new_cid: Cid,
+    /// New object size.
+    pub size: u64,
+    /// New object metadata.
+    pub metadata: HashMap<String, String>,
+}
+
+/// Params for copying an object to another key.
+#[derive(Clone, Debug, Serialize_tuple, Deserialize_tuple)]
+pub struct CopyParams {
+    /// Key of the object to copy.
+    #[serde(with = "strict_bytes")]
+    pub source: Vec<u8>,
+    /// Key to copy the object to.
+    #[serde(with = "strict_bytes")]
+    pub destination: Vec<u8>,
+    /// Whether to overwrite the destination key if it already exists.
+    pub overwrite: bool,
+    /// Whether to delete the source key, moving the object rather than copying it.
+    pub delete_source: bool,
+}
+
+/// Params for updating the metadata of an object.
+#[derive(Clone, Debug, Serialize_tuple, Deserialize_tuple)]
+pub struct UpdateMetadataParams {
+    /// Object key.
+    #[serde(with = "strict_bytes")]
+    pub key: Vec<u8>,
+    /// Object metadata.
+    pub metadata: HashMap<String, String>,
+    /// Whether to merge the metadata into the existing metadata, or replace it.
+    pub merge: bool,
+}
+
+/// Params for setting the default metadata of the objects added under a key prefix.
+#[derive(Clone, Debug, Serialize_tuple, Deserialize_tuple)]
+pub struct SetPrefixDefaultsParams {
+    /// Key prefix.
+    #[serde(with = "strict_bytes")]
+    pub prefix: Vec<u8>,
+    /// Metadata of the objects added under the prefix, unless they set it themselves.
+    /// Empty metadata removes the defaults of the prefix.
+    pub metadata: HashMap<String, String>,
 }
 
 /// Params for resolving an object.
@@ -37,6 +143,10 @@ pub struct ResolveParams {
     pub key: Vec<u8>,
     /// Object value.
     pub value: Cid,
+    /// Whether validators found the content invalid, e.g. of another size than declared,
+    /// so the object is marked failed rather than resolved.
+    #[serde(default)]
+    pub failed: bool,
 }
 
 /// Params for deleting an object.
@@ -47,6 +157,30 @@ pub struct DeleteParams {
     pub key: Vec<u8>,
 }
 
+/// Params for claiming an object, i.e. getting and deleting it at once.
+#[derive(Clone, Debug, Serialize_tuple, Deserialize_tuple)]
+pub struct ClaimParams {
+    /// Object key.
+    #[serde(with = "strict_bytes")]
+    pub key: Vec<u8>,
+}
+
+/// Params for claiming the first object under a prefix, in lexicographic key order.
+#[derive(Clone, Debug, Default, Serialize_tuple, Deserialize_tuple)]
+pub struct ClaimFirstParams {
+    /// The prefix to claim an object under. Empty means any object.
+    #[serde(with = "strict_bytes")]
+    pub prefix: Vec<u8>,
+}
+
+/// Params for restoring a soft-deleted object.
+#[derive(Clone, Debug, Serialize_tuple, Deserialize_tuple)]
+pub struct RestoreParams {
+    /// Object key.
+    #[serde(with = "strict_bytes")]
+    pub key: Vec<u8>,
+}
+
 /// Params for getting an object.
 #[derive(Clone, Debug, Serialize_tuple, Deserialize_tuple)]
 pub struct GetParams {
@@ -55,6 +189,39 @@ pub struct GetParams {
     pub key: Vec<u8>,
 }
 
+/// Params for listing the prior versions of an object.
+#[derive(Clone, Debug, Serialize_tuple, Deserialize_tuple)]
+pub struct ListVersionsParams {
+    /// Object key.
+    #[serde(with = "strict_bytes")]
+    pub key: Vec<u8>,
+}
+
+/// Params for getting a prior version of an object.
+#[derive(Clone, Debug, Serialize_tuple, Deserialize_tuple)]
+pub struct GetVersionParams {
+    /// Object key.
+    #[serde(with = "strict_bytes")]
+    pub key: Vec<u8>,
+    /// Version number, counting from 0 for the oldest.
+    pub version: u64,
+}
+
+/// Params for checking whether an object exists.
+#[derive(Clone, Debug, Serialize_tuple, Deserialize_tuple)]
+pub struct HasParams {
+    /// Object key.
+    #[serde(with = "strict_bytes")]
+    pub key: Vec<u8>,
+}
+
+/// Params for checking which of a set of objects exist.
+#[derive(Clone, Debug, Serialize_tuple, Deserialize_tuple)]
+pub struct HasManyParams {
+    /// Object keys, at most [`MAX_HAS_MANY_KEYS`].
+    pub keys: Vec<ByteBuf>,
+}
+
 /// Params for listing objects.
 #[derive(Default, Debug, Serialize_tuple, Deserialize_tuple)]
 pub struct ListParams {
@@ -66,18 +233,109 @@ pub struct ListParams {
     pub delimiter: Vec<u8>,
     /// The offset to start listing objects from.
     pub offset: u64,
-    /// The maximum number of objects to list.
+    /// The maximum number of objects to list, clamped to the store's maximum.
+    /// Zero means the store's maximum.
+    pub limit: u64,
+    /// Whether to list objects in descending key order, rather than in the order of the key
+    /// hashes. The offset and limit then count from the end of the listing, and at most
+    /// [`crate::Config::max_pattern_scan`] keys under the prefix are collected for it.
+    #[serde(default)]
+    pub reverse: bool,
+    /// A glob pattern the keys under the prefix must match, e.g. `logs/2024-*/error.json`.
+    /// At most [`crate::Config::max_pattern_scan`] keys are matched against it, after which
+    /// the scan stops and [`crate::ObjectList::scan_truncated`] is set.
+    #[serde(default)]
+    pub pattern: Option<String>,
+}
+
+/// Params for listing objects by the epoch they were added at.
+#[derive(Default, Debug, Serialize_tuple, Deserialize_tuple)]
+pub struct ListByTimeParams {
+    /// The first epoch to list objects from.
+    pub start_epoch: ChainEpoch,
+    /// The last epoch to list objects from, inclusive.
+    pub end_epoch: ChainEpoch,
+    /// The maximum number of objects to list, clamped to the store's maximum.
+    /// Zero means the store's maximum.
     pub limit: u64,
 }
 
+/// Params for aggregating objects under a prefix.
+#[derive(Default, Debug, Serialize_tuple, Deserialize_tuple)]
+pub struct StatParams {
+    /// The prefix to filter objects by.
+    #[serde(with = "strict_bytes")]
+    pub prefix: Vec<u8>,
+}
+
 #[derive(FromPrimitive)]
 #[repr(u64)]
 pub enum Method {
     Constructor = METHOD_CONSTRUCTOR,
     GetMetadata = GET_METADATA_METHOD,
+    CanWrite = CAN_WRITE_METHOD,
+    SetMetadata = SET_METADATA_METHOD,
     AddObject = frc42_dispatch::method_hash!("AddObject"),
     ResolveObject = frc42_dispatch::method_hash!("ResolveObject"),
     DeleteObject = frc42_dispatch::method_hash!("DeleteObject"),
     GetObject = frc42_dispatch::method_hash!("GetObject"),
     ListObjects = frc42_dispatch::method_hash!("ListObjects"),
+    SwapObject = frc42_dispatch::method_hash!("SwapObject"),
+    SetConfig = frc42_dispatch::method_hash!("SetConfig"),
+    UpdateMetadata = frc42_dispatch::method_hash!("UpdateMetadata"),
+    StatPrefix = frc42_dispatch::method_hash!("StatPrefix"),
+    HasObject = frc42_dispatch::method_hash!("HasObject"),
+    HasMany = frc42_dispatch::method_hash!("HasMany"),
+    RestoreObject = frc42_dispatch::method_hash!("RestoreObject"),
+    PurgeDeleted = frc42_dispatch::method_hash!("PurgeDeleted"),
+    ListVersions = frc42_dispatch::method_hash!("ListVersions"),
+    GetVersion = frc42_dispatch::method_hash!("GetVersion"),
+    ListByTime = frc42_dispatch::method_hash!("ListByTime"),
+    CopyObject = frc42_dispatch::method_hash!("CopyObject"),
+    ClaimObject = frc42_dispatch::method_hash!("ClaimObject"),
+    ClaimFirst = frc42_dispatch::method_hash!("ClaimFirst"),
+    SetPrefixDefaults = frc42_dispatch::method_hash!("SetPrefixDefaults"),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_params_without_data() {
+        // Clients that predate internal objects send five fields.
+        let cid = Cid::default();
+        let encoded = fvm_ipld_encoding::to_vec(&(
+            ByteBuf(b"a".to_vec()),
+            cid,
+            3u64,
+            HashMap::<String, String>::new(),
+            true,
+        ))
+        .unwrap();
+        let params: AddParams = fvm_ipld_encoding::from_slice(&encoded).unwrap();
+        assert_eq!(params.key, b"a".to_vec());
+        assert_eq!(params.cid, cid);
+        assert!(params.overwrite);
+        assert!(params.data.is_none());
+    }
+
+    #[test]
+    fn test_list_params_without_reverse_and_pattern() {
+        // Clients that predate reverse and pattern listings send four fields.
+        let encoded = fvm_ipld_encoding::to_vec(&(
+            ByteBuf(b"logs/".to_vec()),
+            ByteBuf(b"/".to_vec()),
+            10u64,
+            5u64,
+        ))
+        .unwrap();
+        let params: ListParams = fvm_ipld_encoding::from_slice(&encoded).unwrap();
+        assert_eq!(params.prefix, b"logs/".to_vec());
+        assert_eq!(params.delimiter, b"/".to_vec());
+        assert_eq!(params.offset, 10);
+        assert_eq!(params.limit, 5);
+        assert!(!params.reverse);
+        assert!(params.pattern.is_none());
+    }
 }
@@ -2,18 +2,44 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
 
-use cid::Cid;
+use cid::{Cid, Version};
 use fendermint_actor_machine::{Kind, MachineState, WriteAccess};
+use fvm_ipld_amt::Amt;
 use fvm_ipld_blockstore::Blockstore;
-use fvm_ipld_encoding::{strict_bytes::ByteBuf, tuple::*};
+use fvm_ipld_encoding::{strict_bytes, strict_bytes::ByteBuf, tuple::*};
 use fvm_ipld_hamt::{BytesKey, Hamt};
-use fvm_shared::address::Address;
+use fvm_shared::{address::Address, clock::ChainEpoch, error::ExitCode};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
 
-const BIT_WIDTH: u32 = 8;
+/// Bit width of the HAMTs holding the objects, tombstones and version histories.
+pub const BIT_WIDTH: u32 = 8;
 
-const MAX_LIST_LIMIT: usize = 10000;
+/// Codec implied by a CIDv0.
+const DAG_PB: u64 = 0x70;
+
+/// Maximum number of objects returned by a list query, unless configured otherwise.
+const DEFAULT_MAX_LIST_LIMIT: u64 = 1000;
+
+/// Maximum number of keys a list query matches against its pattern, unless configured otherwise.
+const DEFAULT_MAX_PATTERN_SCAN: u64 = 10_000;
+
+/// Maximum number of metadata entries on an object, unless configured otherwise.
+const DEFAULT_MAX_METADATA_ENTRIES: u64 = 64;
+
+/// Maximum length in bytes of an object metadata key, unless configured otherwise.
+const DEFAULT_MAX_METADATA_KEY_LENGTH: u64 = 256;
+
+/// Maximum total length in bytes of the keys and values of object metadata,
+/// unless configured otherwise.
+const DEFAULT_MAX_METADATA_SIZE: u64 = 16 * 1024;
+
+/// Maximum size in bytes of an object stored internally, unless configured otherwise.
+const DEFAULT_MAX_INTERNAL_OBJECT_SIZE: u64 = 512;
+
+/// Maximum number of key prefixes with default metadata.
+pub const MAX_PREFIX_DEFAULTS: usize = 64;
 
 /// The state represents an object store backed by a Hamt.
 #[derive(Serialize_tuple, Deserialize_tuple)]
@@ -24,6 +50,128 @@ pub struct State {
     pub write_access: WriteAccess,
     /// The root cid of the Hamt.
     pub root: Cid,
+    /// Owner-defined store configuration.
+    pub config: Config,
+    /// The root cid of the Hamt of soft-deleted objects.
+    pub tombstones: Cid,
+    /// The root cid of the Hamt of version histories, mapping keys to Amts of prior versions.
+    pub versions: Cid,
+    /// The root cid of the Amt indexing object keys by creation epoch.
+    pub time_index: Cid,
+    /// Application-defined metadata of the store, e.g. a name or a schema ID.
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+    /// Default metadata of objects added under key prefixes, sorted by prefix.
+    #[serde(default)]
+    pub prefix_defaults: Vec<PrefixDefaults>,
+    /// Number of changes pushed to the change log, see [`crate::ChangeLogEntry::seq`].
+    #[serde(default)]
+    pub change_log_seq: u64,
+}
+
+/// An error of the object store that callers can tell apart from a failure of the store itself.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ObjectStoreError {
+    /// There is no object at the key.
+    KeyNotFound,
+    /// There is no soft-deleted object at the key.
+    NoDeletedObject,
+    /// There is already an object at the key, and it may not be replaced.
+    KeyExists,
+    /// The current value of the key doesn't match the expected CID.
+    CidMismatch(Cid),
+    /// The object hasn't been resolved yet.
+    NotResolved,
+    /// The grace period of the soft-deleted object is over.
+    RestoreExpired,
+    /// An argument is outside the configured limits or can't be stored as given.
+    InvalidArgument(String),
+}
+
+impl ObjectStoreError {
+    /// The exit code the actor aborts with on this error.
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            ObjectStoreError::KeyNotFound | ObjectStoreError::NoDeletedObject => {
+                ExitCode::USR_NOT_FOUND
+            }
+            ObjectStoreError::KeyExists
+            | ObjectStoreError::CidMismatch(_)
+            | ObjectStoreError::NotResolved
+            | ObjectStoreError::RestoreExpired => ExitCode::USR_FORBIDDEN,
+            ObjectStoreError::InvalidArgument(_) => ExitCode::USR_ILLEGAL_ARGUMENT,
+        }
+    }
+}
+
+impl fmt::Display for ObjectStoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjectStoreError::KeyNotFound => write!(f, "key not found"),
+            ObjectStoreError::NoDeletedObject => write!(f, "no deleted object to restore"),
+            ObjectStoreError::KeyExists => write!(f, "key already exists"),
+            ObjectStoreError::CidMismatch(expected) => {
+                write!(f, "current value does not match expected cid {}", expected)
+            }
+            ObjectStoreError::NotResolved => write!(f, "object is not resolved"),
+            ObjectStoreError::RestoreExpired => {
+                write!(f, "deleted object can no longer be restored")
+            }
+            ObjectStoreError::InvalidArgument(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ObjectStoreError {}
+
+/// Metadata merged into that of the objects added under a key prefix,
+/// see [`State::set_prefix_defaults`].
+#[derive(Clone, Debug, PartialEq, Serialize_tuple, Deserialize_tuple)]
+pub struct PrefixDefaults {
+    #[serde(with = "strict_bytes")]
+    pub prefix: Vec<u8>,
+    pub metadata: HashMap<String, String>,
+}
+
+/// Owner-defined object store configuration.
+#[derive(Clone, Debug, Default, PartialEq, Serialize_tuple, Deserialize_tuple)]
+pub struct Config {
+    /// Maximum object size in bytes. `None` means no limit.
+    ///
+    /// Note that object size is declared by the client. The resolver is expected to verify
+    /// the actual size of the content on resolution and fail objects that are oversized.
+    pub max_object_size: Option<u64>,
+    /// Accumulator whose root is recorded when an object is resolved. `None` means no root
+    /// is recorded.
+    pub accumulator: Option<Address>,
+    /// Maximum number of objects returned by a list query. `None` means the default of 1000.
+    pub max_list_limit: Option<u64>,
+    /// Number of epochs a deleted object can be restored for. `None` means deletes are
+    /// permanent.
+    pub soft_delete_grace_period: Option<ChainEpoch>,
+    /// Whether prior versions of overwritten and deleted objects are kept.
+    /// Can only be changed while the store is empty.
+    pub versioning: bool,
+    /// Whether object CIDs are stored as CIDv0 rather than the default CIDv1.
+    pub cid_v0: bool,
+    /// Maximum number of metadata entries on an object. `None` means the default of 64.
+    pub max_metadata_entries: Option<u64>,
+    /// Maximum length in bytes of a metadata key. `None` means the default of 256.
+    pub max_metadata_key_length: Option<u64>,
+    /// Maximum total length in bytes of the metadata keys and values of an object.
+    /// `None` means the default of 16 KiB.
+    pub max_metadata_size: Option<u64>,
+    /// Maximum size in bytes of an object whose content is stored in the state.
+    /// `None` means the default of 512 bytes.
+    pub max_internal_object_size: Option<u64>,
+    /// Maximum number of keys a list query with a pattern or in reverse, or a stat, scans.
+    /// `None` means the default of 10000.
+    pub max_pattern_scan: Option<u64>,
+    /// Accumulator each object added or deleted is pushed to as a [`crate::ChangeLogEntry`],
+    /// so the history of the store can be verified. A change fails if it can't be pushed, so
+    /// the accumulator has to accept pushes from the store. `None` means no change log.
+    /// Can only be changed while the store is empty, so the log covers every change.
+    pub change_log: Option<Address>,
 }
 
 impl MachineState for State {
@@ -38,6 +186,14 @@ impl MachineState for State {
     fn write_access(&self) -> WriteAccess {
         self.write_access
     }
+
+    fn metadata(&self) -> &HashMap<String, String> {
+        &self.metadata
+    }
+
+    fn set_metadata(&mut self, metadata: HashMap<String, String>) {
+        self.metadata = metadata;
+    }
 }
 
 /// The stored representation of an object in the object store.
@@ -46,11 +202,71 @@ pub struct Object {
     /// The object content identifier.
     pub cid: ByteBuf,
     /// The size of the content.
-    pub size: usize,
+    pub size: u64,
     /// Whether the object has been resolved.
     pub resolved: bool,
+    /// The root of the configured accumulator at the time the object was resolved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_root: Option<Cid>,
+    /// Whether validators found the content invalid, e.g. of another size than declared,
+    /// so the object will never be resolved.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub failed: bool,
+    /// Where the content is stored. Internal objects are resolved as soon as they're added.
+    #[serde(default, skip_serializing_if = "ObjectKind::is_external")]
+    pub kind: ObjectKind,
+    /// The content of an internal object.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<ByteBuf>,
     /// User-defined object metadata (e.g., size, last modified timestamp, etc.).
     pub metadata: HashMap<String, String>,
+    /// The epoch at which the object was added. Objects added before creation epochs were
+    /// recorded have 0.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub created_at: ChainEpoch,
+}
+
+fn is_zero(epoch: &ChainEpoch) -> bool {
+    *epoch == 0
+}
+
+fn is_false(b: &bool) -> bool {
+    !*b
+}
+
+/// Where the content of an object is stored.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ObjectKind {
+    /// The content is stored off-chain, e.g. in IPFS, and the object only becomes resolved
+    /// once validators have fetched it.
+    #[default]
+    External,
+    /// The content is stored in the object store state, so there is nothing to resolve.
+    Internal,
+}
+
+impl ObjectKind {
+    pub fn is_external(&self) -> bool {
+        *self == ObjectKind::External
+    }
+}
+
+/// A soft-deleted object, which can be restored until its grace period is over.
+#[derive(Clone, Debug, PartialEq, Serialize_tuple, Deserialize_tuple)]
+pub struct Tombstone {
+    /// The deleted object.
+    pub object: Object,
+    /// The epoch at which the object was deleted.
+    pub deleted_at: ChainEpoch,
+}
+
+/// A prior version of an object, kept when versioning is enabled.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ObjectVersion {
+    /// The object as it was before being overwritten or deleted.
+    Object(Object),
+    /// Marks the point at which the object was deleted.
+    DeleteMarker,
 }
 
 /// A list of objects and their common prefixes.
@@ -60,6 +276,25 @@ pub struct ObjectList {
     pub objects: Vec<(Vec<u8>, Object)>,
     /// When a delimiter is used in the list query, this contains common key prefixes.
     pub common_prefixes: Vec<Vec<u8>>,
+    /// Whether more objects match the list query beyond the returned page.
+    pub truncated: bool,
+    /// Whether a pattern was only matched against the store's maximum of keys, so matching
+    /// keys may have been left out. Paging further doesn't find them.
+    #[serde(default)]
+    pub scan_truncated: bool,
+}
+
+/// Aggregates over the objects matching a key prefix.
+#[derive(Clone, Debug, Default, PartialEq, Serialize_tuple, Deserialize_tuple)]
+pub struct ObjectStat {
+    /// Number of objects.
+    pub count: u64,
+    /// Sum of the object sizes in bytes.
+    pub total_bytes: u64,
+    /// Whether only the store's maximum of keys were scanned, see
+    /// [`Config::max_pattern_scan`], so the aggregates leave objects out.
+    #[serde(default)]
+    pub truncated: bool,
 }
 
 impl State {
@@ -77,50 +312,493 @@ impl State {
                 ));
             }
         };
+        let tombstones = Hamt::<_, Tombstone>::new_with_bit_width(store, BIT_WIDTH).flush()?;
+        let versions = Hamt::<_, Cid>::new_with_bit_width(store, BIT_WIDTH).flush()?;
+        let time_index = Amt::<Vec<ByteBuf>, _>::new(store).flush()?;
         Ok(Self {
             owner: creator,
             write_access,
             root,
+            config: Config::default(),
+            tombstones,
+            versions,
+            time_index,
+            metadata: HashMap::new(),
+            prefix_defaults: Vec::new(),
+            change_log_seq: 0,
         })
     }
 
+    /// Replaces the store configuration.
+    ///
+    /// Versioning can only be switched while the store is empty, so that every object
+    /// has either a complete history or none at all. The same goes for the change log,
+    /// so that it records every object in the store. A new change log is counted from 0.
+    pub fn set_config<BS: Blockstore>(&mut self, store: &BS, config: Config) -> anyhow::Result<()> {
+        for (changed, setting) in [
+            (config.versioning != self.config.versioning, "versioning"),
+            (config.change_log != self.config.change_log, "change log"),
+        ] {
+            if !changed {
+                continue;
+            }
+            let hamt = Hamt::<_, Object>::load_with_bit_width(&self.root, store, BIT_WIDTH)?;
+            if !hamt.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "{} can only be changed while the store is empty",
+                    setting
+                ));
+            }
+        }
+        if config.change_log != self.config.change_log {
+            self.change_log_seq = 0;
+        }
+        self.config = config;
+        Ok(())
+    }
+
+    /// Returns the number of the next change pushed to the change log, counting it.
+    pub fn next_change_seq(&mut self) -> u64 {
+        let seq = self.change_log_seq;
+        self.change_log_seq += 1;
+        seq
+    }
+
+    /// Sets the metadata merged into that of the objects added under `prefix`, replacing the
+    /// defaults already set for it. Empty `metadata` removes the defaults of the prefix.
+    pub fn set_prefix_defaults(
+        &mut self,
+        prefix: Vec<u8>,
+        metadata: HashMap<String, String>,
+    ) -> anyhow::Result<()> {
+        let pos = self
+            .prefix_defaults
+            .binary_search_by(|defaults| defaults.prefix.cmp(&prefix));
+        if metadata.is_empty() {
+            if let Ok(i) = pos {
+                self.prefix_defaults.remove(i);
+            }
+            return Ok(());
+        }
+        self.ensure_metadata_allowed(&metadata)?;
+        match pos {
+            Ok(i) => self.prefix_defaults[i].metadata = metadata,
+            Err(i) => {
+                if self.prefix_defaults.len() >= MAX_PREFIX_DEFAULTS {
+                    return Err(ObjectStoreError::InvalidArgument(format!(
+                        "at most {} prefixes can have default metadata",
+                        MAX_PREFIX_DEFAULTS
+                    ))
+                    .into());
+                }
+                self.prefix_defaults
+                    .insert(i, PrefixDefaults { prefix, metadata });
+            }
+        }
+        Ok(())
+    }
+
+    /// Merges the defaults of the prefixes of `key` into `metadata`, without overriding its
+    /// entries. The defaults of a longer prefix take precedence over those of a shorter one.
+    fn apply_prefix_defaults(&self, key: &[u8], metadata: &mut HashMap<String, String>) {
+        // Sorting puts the prefixes of a key before the longer ones they're a prefix of.
+        for defaults in self
+            .prefix_defaults
+            .iter()
+            .rev()
+            .filter(|defaults| key.starts_with(&defaults.prefix))
+        {
+            for (k, v) in &defaults.metadata {
+                metadata.entry(k.clone()).or_insert_with(|| v.clone());
+            }
+        }
+    }
+
+    /// Appends to the version history of `key`.
+    fn push_versions<BS: Blockstore>(
+        &mut self,
+        store: &BS,
+        key: &BytesKey,
+        versions: Vec<ObjectVersion>,
+    ) -> anyhow::Result<()> {
+        let mut histories = Hamt::<_, Cid>::load_with_bit_width(&self.versions, store, BIT_WIDTH)?;
+        let mut history = match histories.get(key)?.copied() {
+            Some(root) => Amt::<ObjectVersion, _>::load(&root, store)?,
+            None => Amt::new(store),
+        };
+        for version in versions {
+            history.set(history.count(), version)?;
+        }
+        histories.set(key.clone(), history.flush()?)?;
+        self.versions = histories.flush()?;
+        Ok(())
+    }
+
+    /// Moves `key` in the creation time index from the `old` epoch to the `new` one.
+    /// `None` means the key is not indexed at that side.
+    fn reindex_time<BS: Blockstore>(
+        &mut self,
+        store: &BS,
+        key: &BytesKey,
+        old: Option<ChainEpoch>,
+        new: Option<ChainEpoch>,
+    ) -> anyhow::Result<()> {
+        let mut index = Amt::<Vec<ByteBuf>, _>::load(&self.time_index, store)?;
+        if let Some(epoch) = old {
+            let epoch = epoch.max(0) as u64;
+            if let Some(keys) = index.get(epoch)? {
+                let keys: Vec<ByteBuf> = keys.iter().filter(|k| k.0 != key.0).cloned().collect();
+                if keys.is_empty() {
+                    index.delete(epoch)?;
+                } else {
+                    index.set(epoch, keys)?;
+                }
+            }
+        }
+        if let Some(epoch) = new {
+            let epoch = epoch.max(0) as u64;
+            let mut keys = index.get(epoch)?.cloned().unwrap_or_default();
+            keys.push(ByteBuf(key.0.clone()));
+            index.set(epoch, keys)?;
+        }
+        self.time_index = index.flush()?;
+        Ok(())
+    }
+
+    /// Converts `cid` to the configured CID version, keeping its multihash.
+    ///
+    /// Any CID can be expressed as CIDv1, but only DAG-PB objects hashed with SHA2-256
+    /// can be expressed as CIDv0.
+    pub fn canonical_cid(&self, cid: Cid) -> anyhow::Result<Cid> {
+        match (self.config.cid_v0, cid.version()) {
+            (false, Version::V0) => Ok(Cid::new_v1(DAG_PB, *cid.hash())),
+            (true, Version::V1) => cid.into_v0().map_err(|_| {
+                anyhow::Error::from(ObjectStoreError::InvalidArgument(format!(
+                    "cid {} cannot be converted to CIDv0",
+                    cid
+                )))
+            }),
+            _ => Ok(cid),
+        }
+    }
+
+    /// Ensures that the declared object size is within the configured limit.
+    fn ensure_size_allowed(&self, size: u64) -> anyhow::Result<()> {
+        if let Some(max) = self.config.max_object_size {
+            if size > max {
+                return Err(ObjectStoreError::InvalidArgument(format!(
+                    "object size {} exceeds maximum of {}",
+                    size, max
+                ))
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Ensures that object metadata is within the configured limits.
+    fn ensure_metadata_allowed(&self, metadata: &HashMap<String, String>) -> anyhow::Result<()> {
+        let max_entries = self
+            .config
+            .max_metadata_entries
+            .unwrap_or(DEFAULT_MAX_METADATA_ENTRIES);
+        if metadata.len() as u64 > max_entries {
+            return Err(ObjectStoreError::InvalidArgument(format!(
+                "metadata has {} entries, exceeding maximum of {}",
+                metadata.len(),
+                max_entries
+            ))
+            .into());
+        }
+        let max_key_length = self
+            .config
+            .max_metadata_key_length
+            .unwrap_or(DEFAULT_MAX_METADATA_KEY_LENGTH);
+        if let Some(key) = metadata.keys().find(|k| k.len() as u64 > max_key_length) {
+            return Err(ObjectStoreError::InvalidArgument(format!(
+                "metadata key of {} bytes exceeds maximum of {}",
+                key.len(),
+                max_key_length
+            ))
+            .into());
+        }
+        let max_size = self
+            .config
+            .max_metadata_size
+            .unwrap_or(DEFAULT_MAX_METADATA_SIZE);
+        let size: usize = metadata.iter().map(|(k, v)| k.len() + v.len()).sum();
+        if size as u64 > max_size {
+            return Err(ObjectStoreError::InvalidArgument(format!(
+                "metadata size {} exceeds maximum of {}",
+                size, max_size
+            ))
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Adds an object at `key`, returning the new root and whether the object was inserted.
+    ///
+    /// Without `overwrite`, an object already at the key is kept and nothing changes.
     pub fn add<BS: Blockstore>(
         &mut self,
         store: &BS,
         key: BytesKey,
         cid: Cid,
-        size: usize,
-        metadata: HashMap<String, String>,
+        size: u64,
+        mut metadata: HashMap<String, String>,
         overwrite: bool,
-    ) -> anyhow::Result<Cid> {
-        let mut hamt = Hamt::<_, Object>::load_with_bit_width(&self.root, store, BIT_WIDTH)?;
+        epoch: ChainEpoch,
+    ) -> anyhow::Result<(Cid, bool)> {
+        self.ensure_size_allowed(size)?;
+        self.apply_prefix_defaults(&key.0, &mut metadata);
+        self.ensure_metadata_allowed(&metadata)?;
+        let cid = self.canonical_cid(cid)?;
+        let hamt = Hamt::<_, Object>::load_with_bit_width(&self.root, store, BIT_WIDTH)?;
         let object = Object {
             cid: ByteBuf(cid.to_bytes()),
             size,
             resolved: false,
+            resolved_root: None,
+            failed: false,
+            kind: ObjectKind::External,
+            data: None,
+            metadata,
+            created_at: epoch,
+        };
+        self.put(store, hamt, key, object, overwrite)
+    }
+
+    /// Adds an object whose content is stored in the state, so it's resolved right away.
+    ///
+    /// The caller is expected to have checked that `cid` is the CID of `data`, see
+    /// [`crate::internal_cid`]. Returns the new root and whether the object was inserted,
+    /// like [`State::add`].
+    pub fn add_internal<BS: Blockstore>(
+        &mut self,
+        store: &BS,
+        key: BytesKey,
+        cid: Cid,
+        data: Vec<u8>,
+        mut metadata: HashMap<String, String>,
+        overwrite: bool,
+        epoch: ChainEpoch,
+    ) -> anyhow::Result<(Cid, bool)> {
+        let max = self
+            .config
+            .max_internal_object_size
+            .unwrap_or(DEFAULT_MAX_INTERNAL_OBJECT_SIZE);
+        if data.len() as u64 > max {
+            return Err(ObjectStoreError::InvalidArgument(format!(
+                "internal object size {} exceeds maximum of {}",
+                data.len(),
+                max
+            ))
+            .into());
+        }
+        self.ensure_size_allowed(data.len() as u64)?;
+        self.apply_prefix_defaults(&key.0, &mut metadata);
+        self.ensure_metadata_allowed(&metadata)?;
+        let hamt = Hamt::<_, Object>::load_with_bit_width(&self.root, store, BIT_WIDTH)?;
+        // Internal objects keep the CIDv1 of their raw content, which has no CIDv0 form.
+        let object = Object {
+            cid: ByteBuf(cid.to_bytes()),
+            size: data.len() as u64,
+            resolved: true,
+            resolved_root: None,
+            failed: false,
+            kind: ObjectKind::Internal,
+            data: Some(ByteBuf(data)),
             metadata,
+            created_at: epoch,
         };
+        self.put(store, hamt, key, object, overwrite)
+    }
+
+    /// Puts a new object at `key`, keeping the overwritten one as a version if enabled.
+    /// Returns the new root and whether the object was inserted.
+    fn put<BS: Blockstore>(
+        &mut self,
+        store: &BS,
+        mut hamt: Hamt<&BS, Object>,
+        key: BytesKey,
+        object: Object,
+        overwrite: bool,
+    ) -> anyhow::Result<(Cid, bool)> {
+        let epoch = object.created_at;
         if overwrite {
-            hamt.set(key, object)?;
+            let previous = hamt.set(key.clone(), object)?;
+            self.reindex_time(
+                store,
+                &key,
+                previous.as_ref().map(|p| p.created_at),
+                Some(epoch),
+            )?;
+            if let (Some(previous), true) = (previous, self.config.versioning) {
+                self.push_versions(store, &key, vec![ObjectVersion::Object(previous)])?;
+            }
+        } else if hamt.set_if_absent(key.clone(), object)? {
+            self.reindex_time(store, &key, None, Some(epoch))?;
+        } else {
+            // The object at the key is kept, so nothing changed.
+            return Ok((self.root, false));
+        }
+        self.root = hamt.flush()?;
+        Ok((self.root, true))
+    }
+
+    /// Sets the object at `key` only if its current value matches `expected_cid`.
+    ///
+    /// An `expected_cid` of `None` means the key must be absent.
+    pub fn swap<BS: Blockstore>(
+        &mut self,
+        store: &BS,
+        key: BytesKey,
+        expected_cid: Option<Cid>,
+        new_cid: Cid,
+        size: u64,
+        mut metadata: HashMap<String, String>,
+        epoch: ChainEpoch,
+    ) -> anyhow::Result<Cid> {
+        self.ensure_size_allowed(size)?;
+        self.apply_prefix_defaults(&key.0, &mut metadata);
+        self.ensure_metadata_allowed(&metadata)?;
+        let expected_cid = expected_cid.map(|c| self.canonical_cid(c)).transpose()?;
+        let new_cid = self.canonical_cid(new_cid)?;
+        let mut hamt = Hamt::<_, Object>::load_with_bit_width(&self.root, store, BIT_WIDTH)?;
+        let current = hamt.get(&key)?.map(|o| o.cid.0.clone());
+        match (expected_cid, current) {
+            (None, None) => {}
+            (None, Some(_)) => return Err(ObjectStoreError::KeyExists.into()),
+            (Some(expected), Some(current)) if expected.to_bytes() == current => {}
+            (Some(expected), _) => return Err(ObjectStoreError::CidMismatch(expected).into()),
+        }
+        let object = Object {
+            cid: ByteBuf(new_cid.to_bytes()),
+            size,
+            resolved: false,
+            resolved_root: None,
+            failed: false,
+            kind: ObjectKind::External,
+            data: None,
+            metadata,
+            created_at: epoch,
+        };
+        let previous = hamt.set(key.clone(), object)?;
+        self.reindex_time(
+            store,
+            &key,
+            previous.as_ref().map(|p| p.created_at),
+            Some(epoch),
+        )?;
+        if let (Some(previous), true) = (previous, self.config.versioning) {
+            self.push_versions(store, &key, vec![ObjectVersion::Object(previous)])?;
+        }
+        self.root = hamt.flush()?;
+        Ok(self.root)
+    }
+
+    /// Copies the object at `source` to `destination`, returning the copied object.
+    ///
+    /// The copy keeps the value, size, metadata, creation epoch, resolution status and recorded
+    /// accumulator root of the source, so it doesn't have to be resolved again. Only the
+    /// defaults of the prefixes of `destination` are merged into its metadata, like for an
+    /// added object. If `delete_source` is true, the source is then deleted as with
+    /// [`State::delete`], which moves the object.
+    ///
+    /// Pending objects can't be copied, because the resolver only resolves the key an object
+    /// was added under.
+    pub fn copy<BS: Blockstore>(
+        &mut self,
+        store: &BS,
+        source: &BytesKey,
+        destination: BytesKey,
+        overwrite: bool,
+        delete_source: bool,
+        epoch: ChainEpoch,
+    ) -> anyhow::Result<(Object, Cid)> {
+        let mut hamt = Hamt::<_, Object>::load_with_bit_width(&self.root, store, BIT_WIDTH)?;
+        let mut object = match hamt.get(source)? {
+            Some(object) => object.clone(),
+            None => return Err(ObjectStoreError::KeyNotFound.into()),
+        };
+        if !object.resolved {
+            return Err(ObjectStoreError::NotResolved.into());
+        }
+        if *source == destination {
+            return Ok((object, self.root));
+        }
+        self.apply_prefix_defaults(&destination.0, &mut object.metadata);
+        self.ensure_metadata_allowed(&object.metadata)?;
+        let previous = if overwrite {
+            hamt.set(destination.clone(), object.clone())?
+        } else if hamt.set_if_absent(destination.clone(), object.clone())? {
+            None
+        } else {
+            return Err(ObjectStoreError::KeyExists.into());
+        };
+        self.root = hamt.flush()?;
+        self.reindex_time(
+            store,
+            &destination,
+            previous.as_ref().map(|p| p.created_at),
+            Some(object.created_at),
+        )?;
+        if let (Some(previous), true) = (previous, self.config.versioning) {
+            self.push_versions(store, &destination, vec![ObjectVersion::Object(previous)])?;
+        }
+        if delete_source {
+            self.delete(store, source, epoch)?;
+        }
+        Ok((object, self.root))
+    }
+
+    /// Updates the metadata of the object at `key`, leaving its value untouched.
+    ///
+    /// If `merge` is true, `metadata` is overlaid onto the existing metadata.
+    /// Otherwise, it replaces the existing metadata.
+    pub fn update_metadata<BS: Blockstore>(
+        &mut self,
+        store: &BS,
+        key: BytesKey,
+        metadata: HashMap<String, String>,
+        merge: bool,
+    ) -> anyhow::Result<Cid> {
+        let mut hamt = Hamt::<_, Object>::load_with_bit_width(&self.root, store, BIT_WIDTH)?;
+        let mut object = match hamt.get(&key)? {
+            Some(object) => object.clone(),
+            None => return Err(ObjectStoreError::KeyNotFound.into()),
+        };
+        if merge {
+            object.metadata.extend(metadata);
         } else {
-            hamt.set_if_absent(key, object)?;
+            object.metadata = metadata;
         }
+        self.ensure_metadata_allowed(&object.metadata)?;
+        hamt.set(key, object)?;
         self.root = hamt.flush()?;
         Ok(self.root)
     }
 
+    /// Marks the object at `key` as resolved.
+    ///
+    /// If given, `accumulator_root` is recorded with the object, so that it can later be shown
+    /// that the object was resolved as of that root.
     pub fn resolve<BS: Blockstore>(
         &mut self,
         store: &BS,
         key: BytesKey,
         value: Cid,
+        accumulator_root: Option<Cid>,
     ) -> anyhow::Result<()> {
+        let value = self.canonical_cid(value)?;
         let mut hamt = Hamt::<_, Object>::load_with_bit_width(&self.root, store, BIT_WIDTH)?;
         match hamt.get(&key).map(|v| v.cloned())? {
             Some(mut object) => {
                 // Ignore if value changed before it was resolved.
                 if object.cid.0 == value.to_bytes() {
                     object.resolved = true;
+                    object.resolved_root = accumulator_root;
                     hamt.set(key, object)?;
                     self.root = hamt.flush()?;
                 }
@@ -131,18 +809,168 @@ impl State {
         }
     }
 
+    /// Marks the object at `key` as failed, validators having found its content invalid.
+    ///
+    /// Like in [`State::resolve`], a changed or deleted object is left alone.
+    pub fn fail<BS: Blockstore>(
+        &mut self,
+        store: &BS,
+        key: BytesKey,
+        value: Cid,
+    ) -> anyhow::Result<()> {
+        let value = self.canonical_cid(value)?;
+        let mut hamt = Hamt::<_, Object>::load_with_bit_width(&self.root, store, BIT_WIDTH)?;
+        if let Some(mut object) = hamt.get(&key)?.cloned() {
+            if object.cid.0 == value.to_bytes() && !object.resolved {
+                object.failed = true;
+                hamt.set(key, object)?;
+                self.root = hamt.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Deletes the object at `key`.
+    ///
+    /// If soft-delete is configured, the object is kept as a tombstone that can be restored
+    /// until the grace period is over.
     pub fn delete<BS: Blockstore>(
         &mut self,
         store: &BS,
         key: &BytesKey,
+        epoch: ChainEpoch,
     ) -> anyhow::Result<(Option<Object>, Cid)> {
         let mut hamt = Hamt::<_, Object>::load_with_bit_width(&self.root, store, BIT_WIDTH)?;
         if hamt.contains_key(key)? {
             let object = hamt.delete(key)?.map(|o| o.1);
+            if let Some(object) = &object {
+                self.reindex_time(store, key, Some(object.created_at), None)?;
+            }
+            if let (Some(object), Some(_)) = (&object, self.config.soft_delete_grace_period) {
+                let mut tombstones =
+                    Hamt::<_, Tombstone>::load_with_bit_width(&self.tombstones, store, BIT_WIDTH)?;
+                tombstones.set(
+                    key.clone(),
+                    Tombstone {
+                        object: object.clone(),
+                        deleted_at: epoch,
+                    },
+                )?;
+                self.tombstones = tombstones.flush()?;
+            }
+            if let (Some(object), true) = (&object, self.config.versioning) {
+                self.push_versions(
+                    store,
+                    key,
+                    vec![
+                        ObjectVersion::Object(object.clone()),
+                        ObjectVersion::DeleteMarker,
+                    ],
+                )?;
+            }
             self.root = hamt.flush()?;
             return Ok((object, self.root));
         }
-        Err(anyhow::anyhow!("key not found"))
+        Err(ObjectStoreError::KeyNotFound.into())
+    }
+
+    /// Deletes the object at `key` and returns it, or `None` if there is no object at `key`.
+    ///
+    /// Calls are applied one after the other, so only one of several claims of the same key
+    /// gets the object, which makes the store usable as a work queue.
+    pub fn claim<BS: Blockstore>(
+        &mut self,
+        store: &BS,
+        key: &BytesKey,
+        epoch: ChainEpoch,
+    ) -> anyhow::Result<(Option<Object>, Cid)> {
+        if !self.has(store, key)? {
+            return Ok((None, self.root));
+        }
+        self.delete(store, key, epoch)
+    }
+
+    /// Claims the object with the lexicographically first key under `prefix`, returning it
+    /// with its key, or `None` if there is no object under `prefix`.
+    pub fn claim_first<BS: Blockstore>(
+        &mut self,
+        store: &BS,
+        prefix: &[u8],
+        epoch: ChainEpoch,
+    ) -> anyhow::Result<(Option<(Vec<u8>, Object)>, Cid)> {
+        let hamt = Hamt::<_, Object>::load_with_bit_width(&self.root, store, BIT_WIDTH)?;
+        // The Hamt isn't ordered, so every key under the prefix is visited.
+        let mut first: Option<BytesKey> = None;
+        for pair in &hamt {
+            let (k, _) = pair?;
+            if k.0.starts_with(prefix) && first.as_ref().map_or(true, |first| k.0 < first.0) {
+                first = Some(k.clone());
+            }
+        }
+        let Some(key) = first else {
+            return Ok((None, self.root));
+        };
+        let (object, root) = self.delete(store, &key, epoch)?;
+        Ok((object.map(|object| (key.0, object)), root))
+    }
+
+    /// Restores the soft-deleted object at `key`, if its grace period is not over.
+    pub fn restore<BS: Blockstore>(
+        &mut self,
+        store: &BS,
+        key: BytesKey,
+        epoch: ChainEpoch,
+    ) -> anyhow::Result<(Object, Cid)> {
+        let mut tombstones =
+            Hamt::<_, Tombstone>::load_with_bit_width(&self.tombstones, store, BIT_WIDTH)?;
+        let tombstone = match tombstones.get(&key)? {
+            Some(tombstone) => tombstone.clone(),
+            None => return Err(ObjectStoreError::NoDeletedObject.into()),
+        };
+        if self.is_expired(&tombstone, epoch) {
+            return Err(ObjectStoreError::RestoreExpired.into());
+        }
+        let mut hamt = Hamt::<_, Object>::load_with_bit_width(&self.root, store, BIT_WIDTH)?;
+        if !hamt.set_if_absent(key.clone(), tombstone.object.clone())? {
+            return Err(ObjectStoreError::KeyExists.into());
+        }
+        tombstones.delete(&key)?;
+        self.tombstones = tombstones.flush()?;
+        self.root = hamt.flush()?;
+        self.reindex_time(store, &key, None, Some(tombstone.object.created_at))?;
+        Ok((tombstone.object, self.root))
+    }
+
+    /// Permanently removes the soft-deleted objects whose grace period is over,
+    /// returning how many were removed.
+    pub fn purge_deleted<BS: Blockstore>(
+        &mut self,
+        store: &BS,
+        epoch: ChainEpoch,
+    ) -> anyhow::Result<u64> {
+        let mut tombstones =
+            Hamt::<_, Tombstone>::load_with_bit_width(&self.tombstones, store, BIT_WIDTH)?;
+        let mut expired = Vec::new();
+        tombstones.for_each(|k, tombstone| {
+            if self.is_expired(tombstone, epoch) {
+                expired.push(k.clone());
+            }
+            Ok(())
+        })?;
+        for key in &expired {
+            tombstones.delete(key)?;
+        }
+        self.tombstones = tombstones.flush()?;
+        Ok(expired.len() as u64)
+    }
+
+    /// Whether the grace period of a tombstone is over.
+    /// Without a configured grace period, tombstones can no longer be restored.
+    fn is_expired(&self, tombstone: &Tombstone, epoch: ChainEpoch) -> bool {
+        match self.config.soft_delete_grace_period {
+            Some(grace_period) => epoch >= tombstone.deleted_at.saturating_add(grace_period),
+            None => true,
+        }
     }
 
     pub fn get<BS: Blockstore>(
@@ -155,6 +983,91 @@ impl State {
         Ok(object)
     }
 
+    /// Lists the prior versions of the object at `key`, oldest first.
+    pub fn list_versions<BS: Blockstore>(
+        &self,
+        store: &BS,
+        key: &BytesKey,
+    ) -> anyhow::Result<Vec<ObjectVersion>> {
+        let histories = Hamt::<_, Cid>::load_with_bit_width(&self.versions, store, BIT_WIDTH)?;
+        let mut versions = Vec::new();
+        if let Some(root) = histories.get(key)? {
+            let history = Amt::<ObjectVersion, _>::load(root, store)?;
+            history.for_each(|_, version| {
+                versions.push(version.clone());
+                Ok(())
+            })?;
+        }
+        Ok(versions)
+    }
+
+    /// Gets a prior version of the object at `key`, numbered from 0 for the oldest.
+    pub fn get_version<BS: Blockstore>(
+        &self,
+        store: &BS,
+        key: &BytesKey,
+        version: u64,
+    ) -> anyhow::Result<Option<ObjectVersion>> {
+        let histories = Hamt::<_, Cid>::load_with_bit_width(&self.versions, store, BIT_WIDTH)?;
+        match histories.get(key)? {
+            Some(root) => {
+                let history = Amt::<ObjectVersion, _>::load(root, store)?;
+                Ok(history.get(version)?.cloned())
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Checks whether there is an object at `key`, without decoding it.
+    pub fn has<BS: Blockstore>(&self, store: &BS, key: &BytesKey) -> anyhow::Result<bool> {
+        let hamt = Hamt::<_, Object>::load_with_bit_width(&self.root, store, BIT_WIDTH)?;
+        Ok(hamt.contains_key(key)?)
+    }
+
+    /// Checks which of `keys` have an object, in the same order, loading the HAMT once.
+    pub fn has_many<BS: Blockstore>(
+        &self,
+        store: &BS,
+        keys: &[BytesKey],
+    ) -> anyhow::Result<Vec<bool>> {
+        let hamt = Hamt::<_, Object>::load_with_bit_width(&self.root, store, BIT_WIDTH)?;
+        keys.iter().map(|key| Ok(hamt.contains_key(key)?)).collect()
+    }
+
+    /// Counts the objects under `prefix` and sums their sizes.
+    ///
+    /// The Hamt isn't ordered by key, so every key is scanned, up to the same maximum as
+    /// a pattern in [`State::list`].
+    pub fn stat<BS: Blockstore>(&self, store: &BS, prefix: Vec<u8>) -> anyhow::Result<ObjectStat> {
+        let hamt = Hamt::<_, Object>::load_with_bit_width(&self.root, store, BIT_WIDTH)?;
+        let max_scan = self
+            .config
+            .max_pattern_scan
+            .unwrap_or(DEFAULT_MAX_PATTERN_SCAN);
+        let mut scanned = 0;
+        let mut stat = ObjectStat::default();
+        for pair in &hamt {
+            if scanned >= max_scan {
+                stat.truncated = true;
+                break;
+            }
+            scanned += 1;
+            let (k, v) = pair?;
+            if k.0.starts_with(&prefix) {
+                stat.count += 1;
+                stat.total_bytes += v.size;
+            }
+        }
+        Ok(stat)
+    }
+
+    /// Lists objects matching the prefix, grouping keys by the delimiter if it's not empty.
+    ///
+    /// A forward listing is in the order of the key hashes. A reverse listing sorts the keys,
+    /// listing them in descending order, so it can only scan so many keys under the prefix,
+    /// like a pattern, see [`ObjectList::scan_truncated`].
+    ///
+    /// The limit is clamped to the configured maximum, and a limit of 0 means the maximum.
     pub fn list<BS: Blockstore>(
         &self,
         store: &BS,
@@ -162,22 +1075,57 @@ impl State {
         delimiter: Vec<u8>,
         offset: u64,
         limit: u64,
+        reverse: bool,
+        pattern: Option<&str>,
     ) -> anyhow::Result<ObjectList> {
         let hamt = Hamt::<_, Object>::load_with_bit_width(&self.root, store, BIT_WIDTH)?;
+        let pattern = pattern.map(Glob::new);
+        let max_scan = self
+            .config
+            .max_pattern_scan
+            .unwrap_or(DEFAULT_MAX_PATTERN_SCAN);
+        let mut scanned = 0;
+        let mut scan_truncated = false;
         let mut objects = Vec::new();
         let mut common_prefixes = std::collections::BTreeSet::<Vec<u8>>::new();
-        let limit = if limit == 0 {
-            MAX_LIST_LIMIT
-        } else {
-            (limit as usize).min(MAX_LIST_LIMIT)
-        };
+        let limit = self.list_limit(limit);
+        let mut truncated = false;
         let mut count = 0;
+        // Adds an object to the page, returning whether the page can take more.
+        let mut page = |key: Vec<u8>, object: Object| {
+            count += 1;
+            if count <= offset {
+                return true;
+            }
+            if objects.len() >= limit {
+                truncated = true;
+                return false;
+            }
+            objects.push((key, object));
+            true
+        };
+        // The Hamt is iterated in hash order, so a reverse listing collects and sorts the matches.
+        let mut matches = Vec::new();
         for pair in &hamt {
             let (k, v) = pair?;
             let key = k.0.clone();
             if !prefix.is_empty() && !key.starts_with(&prefix) {
                 continue;
             }
+            if pattern.is_some() || reverse {
+                // Bound the matching and collecting work, the keys that weren't scanned are
+                // left out.
+                if scanned >= max_scan {
+                    scan_truncated = true;
+                    break;
+                }
+                scanned += 1;
+            }
+            if let Some(pattern) = &pattern {
+                if !pattern.matches(&key) {
+                    continue;
+                }
+            }
             if !delimiter.is_empty() {
                 let utf8_prefix = String::from_utf8(prefix.clone())?;
                 let prefix_length = utf8_prefix.len();
@@ -189,12 +1137,15 @@ impl State {
                     continue;
                 }
             }
-            count += 1;
-            if count <= offset {
-                continue;
+            if reverse {
+                matches.push((key, v.to_owned()));
+            } else if !page(key, v.to_owned()) {
+                break;
             }
-            objects.push((key, v.to_owned()));
-            if limit > 0 && objects.len() >= limit {
+        }
+        matches.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (key, object) in matches.into_iter().rev() {
+            if !page(key, object) {
                 break;
             }
         }
@@ -202,9 +1153,116 @@ impl State {
         let result = ObjectList {
             objects,
             common_prefixes,
+            truncated,
+            scan_truncated,
         };
         Ok(result)
     }
+
+    /// Lists objects added between `start_epoch` and `end_epoch` inclusive, oldest first.
+    /// Objects added at the same epoch are listed in the order they were added.
+    ///
+    /// The limit is clamped like in [`State::list`].
+    pub fn list_by_time<BS: Blockstore>(
+        &self,
+        store: &BS,
+        start_epoch: ChainEpoch,
+        end_epoch: ChainEpoch,
+        limit: u64,
+    ) -> anyhow::Result<ObjectList> {
+        let mut result = ObjectList::default();
+        if end_epoch < start_epoch.max(0) {
+            return Ok(result);
+        }
+        let hamt = Hamt::<_, Object>::load_with_bit_width(&self.root, store, BIT_WIDTH)?;
+        let index = Amt::<Vec<ByteBuf>, _>::load(&self.time_index, store)?;
+        let limit = self.list_limit(limit);
+        let end_epoch = end_epoch as u64;
+        index.for_each_while_ranged(Some(start_epoch.max(0) as u64), None, |epoch, keys| {
+            if epoch > end_epoch {
+                return Ok(false);
+            }
+            for key in keys {
+                if result.objects.len() >= limit {
+                    result.truncated = true;
+                    return Ok(false);
+                }
+                let object = hamt
+                    .get(&BytesKey(key.0.clone()))?
+                    .cloned()
+                    .ok_or_else(|| anyhow::anyhow!("indexed key {:?} has no object", key.0))?;
+                result.objects.push((key.0.clone(), object));
+            }
+            Ok(true)
+        })?;
+        Ok(result)
+    }
+
+    /// Clamps a list limit to the configured maximum, a limit of 0 meaning the maximum.
+    fn list_limit(&self, limit: u64) -> usize {
+        let max_limit = self.config.max_list_limit.unwrap_or(DEFAULT_MAX_LIST_LIMIT);
+        let limit = if limit == 0 {
+            max_limit
+        } else {
+            limit.min(max_limit)
+        };
+        limit as usize
+    }
+}
+
+/// A glob pattern over object keys.
+///
+/// `*` matches any run of bytes within a path segment, i.e. without a `/`, `**` any run of
+/// bytes, and `?` a single byte other than `/`. A `\` matches the byte after it literally.
+struct Glob(Vec<GlobToken>);
+
+#[derive(Clone, Copy, PartialEq)]
+enum GlobToken {
+    Byte(u8),
+    AnyByte,
+    /// Any run of bytes, crossing `/` or not.
+    AnyRun(bool),
+}
+
+impl Glob {
+    fn new(pattern: &str) -> Self {
+        let mut tokens = Vec::new();
+        let mut bytes = pattern.bytes().peekable();
+        while let Some(b) = bytes.next() {
+            let token = match b {
+                b'\\' => GlobToken::Byte(bytes.next().unwrap_or(b'\\')),
+                b'?' => GlobToken::AnyByte,
+                b'*' if bytes.next_if_eq(&b'*').is_some() => GlobToken::AnyRun(true),
+                b'*' => GlobToken::AnyRun(false),
+                b => GlobToken::Byte(b),
+            };
+            tokens.push(token);
+        }
+        Self(tokens)
+    }
+
+    /// Whether `key` matches the pattern, in time proportional to the length of the key
+    /// times the length of the pattern.
+    fn matches(&self, key: &[u8]) -> bool {
+        // Which prefixes of the key the tokens so far match.
+        let mut matched = vec![false; key.len() + 1];
+        matched[0] = true;
+        for token in &self.0 {
+            let mut next = vec![false; key.len() + 1];
+            for i in 0..=key.len() {
+                next[i] = match *token {
+                    GlobToken::AnyRun(cross) => {
+                        matched[i] || (i > 0 && next[i - 1] && (cross || key[i - 1] != b'/'))
+                    }
+                    _ if i == 0 => false,
+                    GlobToken::Byte(b) => matched[i - 1] && key[i - 1] == b,
+                    GlobToken::AnyByte => matched[i - 1] && key[i - 1] != b'/',
+                };
+            }
+            matched = next;
+        }
+        matched[key.len()]
+    }
 }
 
 #[cfg(test)]
@@ -214,18 +1272,27 @@ mod tests {
     use fendermint_testing::arb::ArbCid;
     use fil_actors_runtime::MapKey;
     use fvm_ipld_blockstore::MemoryBlockstore;
-    use fvm_ipld_encoding::DAG_CBOR;
+    use fvm_ipld_encoding::{DAG_CBOR, IPLD_RAW};
     use quickcheck::Arbitrary;
     use quickcheck_macros::quickcheck;
     use std::str::FromStr;
 
     impl Arbitrary for Object {
         fn arbitrary(g: &mut quickcheck::Gen) -> Self {
+            // Objects are stored with CIDv1 by default.
+            let cid = ArbCid::<64>::arbitrary(g).0;
+            let cid = Cid::new_v1(cid.codec(), *cid.hash());
             Object {
-                cid: ByteBuf(ArbCid::<64>::arbitrary(g).0.to_bytes()),
-                size: usize::arbitrary(g),
-                metadata: HashMap::arbitrary(g),
+                cid: ByteBuf(cid.to_bytes()),
+                size: u64::arbitrary(g),
+                // Keep the metadata within the default limits.
+                metadata: HashMap::arbitrary(&mut quickcheck::Gen::new(16)),
                 resolved: false,
+                resolved_root: None,
+                failed: false,
+                kind: ObjectKind::External,
+                data: None,
+                created_at: 0,
             }
         }
     }
@@ -236,6 +1303,11 @@ mod tests {
             size: 0,
             metadata: HashMap::<String, String>::new(),
             resolved: false,
+            resolved_root: None,
+            failed: false,
+            kind: ObjectKind::External,
+            data: None,
+            created_at: 0,
         }
     }
 
@@ -252,141 +1324,1211 @@ mod tests {
             size: 5,
             metadata,
             resolved: false,
+            resolved_root: None,
+            failed: false,
+            kind: ObjectKind::External,
+            data: None,
+            created_at: 0,
+        }
+    }
+
+    const GOLDEN_CID: &str = "bafy2bzacebmog6w3ept45xctbw3lrt76i3rdbeaib6bikuhcddu5y5bqspozu";
+
+    #[test]
+    fn test_object_kind_encoding() {
+        // Objects stored before kinds were recorded are external.
+        let object = default_object();
+        let bytes = fvm_ipld_encoding::to_vec(&object).unwrap();
+        let decoded: Object = fvm_ipld_encoding::from_slice(&bytes).unwrap();
+        assert_eq!(decoded.kind, ObjectKind::External);
+        assert_eq!(decoded, object);
+
+        let object = Object {
+            resolved: true,
+            kind: ObjectKind::Internal,
+            ..default_object()
+        };
+        let bytes = fvm_ipld_encoding::to_vec(&object).unwrap();
+        let decoded: Object = fvm_ipld_encoding::from_slice(&bytes).unwrap();
+        assert_eq!(decoded.kind, ObjectKind::Internal);
+        assert!(decoded.resolved);
+    }
+
+    #[test]
+    fn test_constructor() {
+        let store = MemoryBlockstore::default();
+        let state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner);
+        assert!(state.is_ok());
+        assert_eq!(
+            state.unwrap().root,
+            Cid::from_str("bafy2bzaceamp42wmmgr2g2ymg46euououzfyck7szknvfacqscohrvaikwfay")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_add() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let object = golden_object();
+        assert!(state
+            .add(
+                &store,
+                BytesKey(vec![1, 2, 3]),
+                Cid::from_bytes(&object.cid.0).unwrap(),
+                object.size,
+                object.metadata,
+                true,
+                0
+            )
+            .is_ok());
+
+        assert_eq!(state.root, Cid::from_str(GOLDEN_CID).unwrap());
+    }
+
+    #[test]
+    fn test_add_internal() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let data = b"tiny".to_vec();
+        let cid = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(&data));
+        let digest: [u8; 32] = cid.hash().digest().try_into().unwrap();
+        assert_eq!(crate::internal_cid(digest), cid);
+        state
+            .add_internal(
+                &store,
+                BytesKey(b"small".to_vec()),
+                cid,
+                data.clone(),
+                HashMap::new(),
+                false,
+                0,
+            )
+            .unwrap();
+
+        // Internal objects are resolved as soon as they're added, and hold their content.
+        let object = state
+            .get(&store, &BytesKey(b"small".to_vec()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(object.kind, ObjectKind::Internal);
+        assert!(object.resolved);
+        assert_eq!(object.size, data.len() as u64);
+        assert_eq!(object.data, Some(ByteBuf(data)));
+
+        // Larger objects have to be stored externally.
+        let data = vec![0u8; DEFAULT_MAX_INTERNAL_OBJECT_SIZE as usize + 1];
+        let cid = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(&data));
+        assert!(state
+            .add_internal(
+                &store,
+                BytesKey(b"large".to_vec()),
+                cid,
+                data.clone(),
+                HashMap::new(),
+                false,
+                0,
+            )
+            .is_err());
+        state
+            .add(
+                &store,
+                BytesKey(b"large".to_vec()),
+                cid,
+                data.len(),
+                HashMap::new(),
+                false,
+                0,
+            )
+            .unwrap();
+        let object = state
+            .get(&store, &BytesKey(b"large".to_vec()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(object.kind, ObjectKind::External);
+        assert!(!object.resolved);
+        assert_eq!(object.data, None);
+    }
+
+    #[test]
+    fn test_add_within_max_size() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        state
+            .set_config(
+                &store,
+                Config {
+                    max_object_size: Some(5),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let object = golden_object();
+        assert!(state
+            .add(
+                &store,
+                BytesKey(vec![1, 2, 3]),
+                Cid::from_bytes(&object.cid.0).unwrap(),
+                object.size,
+                object.metadata,
+                true,
+                0
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn test_add_exceeds_max_size() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        state
+            .set_config(
+                &store,
+                Config {
+                    max_object_size: Some(4),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let root = state.root;
+        let object = golden_object();
+        let key = BytesKey(vec![1, 2, 3]);
+        assert!(state
+            .add(
+                &store,
+                key.clone(),
+                Cid::from_bytes(&object.cid.0).unwrap(),
+                object.size,
+                object.metadata,
+                true,
+                0
+            )
+            .is_err());
+        assert_eq!(state.root, root);
+        assert_eq!(state.get(&store, &key).unwrap(), None);
+    }
+
+    #[test]
+    fn test_add_without_overwrite() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let key = BytesKey(vec![1, 2, 3]);
+        let first = Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&[1]));
+        let second = Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&[2]));
+        state
+            .add(&store, key.clone(), first, 1, HashMap::new(), false, 0)
+            .unwrap();
+        let root = state.root;
+
+        // The object at the key is kept, and the add succeeds without inserting anything.
+        let result = state
+            .add(&store, key.clone(), second, 1, HashMap::new(), false, 1)
+            .unwrap();
+        assert_eq!(result, (root, false));
+        let result = state
+            .add_internal(
+                &store,
+                key.clone(),
+                second,
+                vec![2],
+                HashMap::new(),
+                false,
+                1,
+            )
+            .unwrap();
+        assert_eq!(result, (root, false));
+        assert_eq!(state.root, root);
+        let object = state.get(&store, &key).unwrap().unwrap();
+        assert_eq!(object.cid.0, first.to_bytes());
+    }
+
+    #[test]
+    fn test_add_size_above_u32() {
+        let size = u32::MAX as u64 + 1;
+        let params = crate::AddParams {
+            key: vec![1, 2, 3],
+            cid: Cid::from_bytes(&golden_object().cid.0).unwrap(),
+            size,
+            metadata: HashMap::new(),
+            overwrite: false,
+            data: None,
+        };
+        let params: crate::AddParams =
+            fvm_ipld_encoding::from_slice(&fvm_ipld_encoding::to_vec(&params).unwrap()).unwrap();
+        assert_eq!(params.size, size);
+
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let key = BytesKey(params.key);
+        state
+            .add(
+                &store,
+                key.clone(),
+                params.cid,
+                params.size,
+                params.metadata,
+                params.overwrite,
+                0,
+            )
+            .unwrap();
+        assert_eq!(state.get(&store, &key).unwrap().unwrap().size, size);
+        assert_eq!(state.stat(&store, vec![]).unwrap().total_bytes, size);
+    }
+
+    #[test]
+    fn test_prefix_defaults() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let md = |entries: &[(&str, &str)]| {
+            entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<HashMap<_, _>>()
+        };
+        state
+            .set_prefix_defaults(
+                b"images/".to_vec(),
+                md(&[("content-type", "image/*"), ("tier", "media")]),
+            )
+            .unwrap();
+        state
+            .set_prefix_defaults(
+                b"images/png/".to_vec(),
+                md(&[("content-type", "image/png")]),
+            )
+            .unwrap();
+        let mut add = |key: &[u8], metadata| {
+            let key = BytesKey(key.to_vec());
+            state
+                .add(&store, key.clone(), Cid::default(), 1, metadata, true, 0)
+                .unwrap();
+            state.get(&store, &key).unwrap().unwrap().metadata
+        };
+
+        // Objects under a prefix inherit its defaults, the longest prefix winning.
+        assert_eq!(
+            add(b"images/a.jpeg", HashMap::new()),
+            md(&[("content-type", "image/*"), ("tier", "media")])
+        );
+        assert_eq!(
+            add(b"images/png/a.png", HashMap::new()),
+            md(&[("content-type", "image/png"), ("tier", "media")])
+        );
+        // Explicit metadata overrides the defaults.
+        assert_eq!(
+            add(b"images/a.gif", md(&[("content-type", "image/gif")])),
+            md(&[("content-type", "image/gif"), ("tier", "media")])
+        );
+        // Other objects are left alone.
+        assert_eq!(add(b"docs/a.txt", HashMap::new()), HashMap::new());
+
+        // Swapped objects inherit the defaults too.
+        let key = BytesKey(b"images/png/b.png".to_vec());
+        state
+            .swap(
+                &store,
+                key.clone(),
+                None,
+                Cid::default(),
+                1,
+                HashMap::new(),
+                0,
+            )
+            .unwrap();
+        assert_eq!(
+            state.get(&store, &key).unwrap().unwrap().metadata,
+            md(&[("content-type", "image/png"), ("tier", "media")])
+        );
+
+        // A copy inherits the defaults of its destination, leaving the source alone.
+        let source = BytesKey(b"docs/a.txt".to_vec());
+        state
+            .resolve(&store, source.clone(), Cid::default(), None)
+            .unwrap();
+        let (copied, _) = state
+            .copy(
+                &store,
+                &source,
+                BytesKey(b"images/a.txt".to_vec()),
+                false,
+                false,
+                0,
+            )
+            .unwrap();
+        let expected = md(&[("content-type", "image/*"), ("tier", "media")]);
+        assert_eq!(copied.metadata, expected);
+        assert_eq!(
+            state
+                .get(&store, &BytesKey(b"images/a.txt".to_vec()))
+                .unwrap()
+                .unwrap()
+                .metadata,
+            expected
+        );
+        assert_eq!(
+            state.get(&store, &source).unwrap().unwrap().metadata,
+            HashMap::new()
+        );
+
+        // Empty defaults remove the prefix.
+        state
+            .set_prefix_defaults(b"images/".to_vec(), HashMap::new())
+            .unwrap();
+        assert_eq!(state.prefix_defaults.len(), 1);
+        assert_eq!(state.prefix_defaults[0].prefix, b"images/png/".to_vec());
+    }
+
+    #[quickcheck]
+    fn test_resolve(mut object: Object) {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let key = BytesKey(vec![1, 2, 3]);
+        let cid = Cid::from_bytes(&object.cid.0).unwrap();
+        let md = object.metadata.clone();
+        state
+            .add(&store, key.clone(), cid, object.size, md, true, 0)
+            .unwrap();
+        assert!(state.resolve(&store, key.clone(), cid, None).is_ok());
+
+        object.resolved = true;
+        let result = state.get(&store, &key);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap().unwrap(), object);
+    }
+
+    #[test]
+    fn test_resolve_records_accumulator_root() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let key = BytesKey(vec![1, 2, 3]);
+        let mut object = add_golden_object(&mut state, &store, key.clone());
+        let cid = Cid::from_bytes(&object.cid.0).unwrap();
+        let accumulator_root = Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&[6, 7, 8]));
+
+        state
+            .resolve(&store, key.clone(), cid, Some(accumulator_root))
+            .unwrap();
+
+        object.resolved = true;
+        object.resolved_root = Some(accumulator_root);
+        assert_eq!(state.get(&store, &key).unwrap().unwrap(), object);
+    }
+
+    #[test]
+    fn test_fail() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let key = BytesKey(vec![1, 2, 3]);
+        let mut object = add_golden_object(&mut state, &store, key.clone());
+        let cid = Cid::from_bytes(&object.cid.0).unwrap();
+
+        // An object that changed since is left alone.
+        let other = Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&[6, 7, 8]));
+        state.fail(&store, key.clone(), other).unwrap();
+        assert_eq!(state.get(&store, &key).unwrap().unwrap(), object);
+
+        state.fail(&store, key.clone(), cid).unwrap();
+        object.failed = true;
+        assert_eq!(state.get(&store, &key).unwrap().unwrap(), object);
+
+        // The flag survives encoding.
+        let decoded: Object =
+            fvm_ipld_encoding::from_slice(&fvm_ipld_encoding::to_vec(&object).unwrap()).unwrap();
+        assert!(decoded.failed);
+
+        // A deleted object is left alone.
+        state.fail(&store, BytesKey(vec![4]), cid).unwrap();
+    }
+
+    #[test]
+    fn test_swap_absent_match() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let key = BytesKey(vec![1, 2, 3]);
+        let object = golden_object();
+        let cid = Cid::from_bytes(&object.cid.0).unwrap();
+        state
+            .swap(
+                &store,
+                key.clone(),
+                None,
+                cid,
+                object.size,
+                object.metadata.clone(),
+                0,
+            )
+            .unwrap();
+
+        let result = state.get(&store, &key).unwrap();
+        assert_eq!(result, Some(object));
+    }
+
+    #[test]
+    fn test_swap_present_match() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let key = BytesKey(vec![1, 2, 3]);
+        state
+            .add(
+                &store,
+                key.clone(),
+                Cid::default(),
+                0,
+                HashMap::<String, String>::new(),
+                false,
+                0,
+            )
+            .unwrap();
+
+        let object = golden_object();
+        let cid = Cid::from_bytes(&object.cid.0).unwrap();
+        state
+            .swap(
+                &store,
+                key.clone(),
+                Some(Cid::default()),
+                cid,
+                object.size,
+                object.metadata.clone(),
+                0,
+            )
+            .unwrap();
+
+        let result = state.get(&store, &key).unwrap();
+        assert_eq!(result, Some(object));
+    }
+
+    #[test]
+    fn test_swap_mismatch() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let key = BytesKey(vec![1, 2, 3]);
+        let object = golden_object();
+        let cid = Cid::from_bytes(&object.cid.0).unwrap();
+
+        // Expecting a value for an absent key fails.
+        assert!(state
+            .swap(
+                &store,
+                key.clone(),
+                Some(cid),
+                Cid::default(),
+                0,
+                HashMap::new(),
+                0
+            )
+            .is_err());
+
+        state
+            .add(
+                &store,
+                key.clone(),
+                Cid::default(),
+                0,
+                HashMap::<String, String>::new(),
+                false,
+                0,
+            )
+            .unwrap();
+        let root = state.root;
+
+        // Expecting absence for a present key fails.
+        assert!(state
+            .swap(&store, key.clone(), None, cid, 0, HashMap::new(), 0)
+            .is_err());
+        // Expecting the wrong value fails.
+        assert!(state
+            .swap(&store, key.clone(), Some(cid), cid, 0, HashMap::new(), 0)
+            .is_err());
+
+        assert_eq!(state.root, root);
+        assert_eq!(state.get(&store, &key).unwrap(), Some(default_object()));
+    }
+
+    fn add_golden_object<BS: Blockstore>(state: &mut State, store: &BS, key: BytesKey) -> Object {
+        let object = golden_object();
+        state
+            .add(
+                store,
+                key,
+                Cid::from_bytes(&object.cid.0).unwrap(),
+                object.size,
+                object.metadata.clone(),
+                false,
+                0,
+            )
+            .unwrap();
+        object
+    }
+
+    #[test]
+    fn test_copy_resolved() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let source = BytesKey(vec![1, 2, 3]);
+        let destination = BytesKey(vec![4, 5, 6]);
+        let mut object = add_golden_object(&mut state, &store, source.clone());
+        let cid = Cid::from_bytes(&object.cid.0).unwrap();
+        let accumulator_root = Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&[6, 7, 8]));
+        state
+            .resolve(&store, source.clone(), cid, Some(accumulator_root))
+            .unwrap();
+        object.resolved = true;
+        object.resolved_root = Some(accumulator_root);
+
+        let (copied, _) = state
+            .copy(&store, &source, destination.clone(), false, false, 10)
+            .unwrap();
+        assert_eq!(copied, object);
+        assert_eq!(
+            state.get(&store, &destination).unwrap(),
+            Some(object.clone())
+        );
+        assert_eq!(state.get(&store, &source).unwrap(), Some(object.clone()));
+
+        // The destination is taken now.
+        assert!(state
+            .copy(&store, &source, destination.clone(), false, false, 10)
+            .is_err());
+    }
+
+    #[test]
+    fn test_move_resolved() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let source = BytesKey(vec![1, 2, 3]);
+        let destination = BytesKey(vec![4, 5, 6]);
+        let mut object = add_golden_object(&mut state, &store, source.clone());
+        let cid = Cid::from_bytes(&object.cid.0).unwrap();
+        state.resolve(&store, source.clone(), cid, None).unwrap();
+        object.resolved = true;
+
+        state
+            .copy(&store, &source, destination.clone(), false, true, 10)
+            .unwrap();
+        assert_eq!(state.get(&store, &destination).unwrap(), Some(object));
+        assert_eq!(state.get(&store, &source).unwrap(), None);
+
+        let list = state.list_by_time(&store, 0, 0, 0).unwrap();
+        let keys: Vec<Vec<u8>> = list.objects.into_iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, vec![destination.0]);
+    }
+
+    #[test]
+    fn test_copy_pending_rejected() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let source = BytesKey(vec![1, 2, 3]);
+        add_golden_object(&mut state, &store, source.clone());
+        let root = state.root;
+
+        assert!(state
+            .copy(&store, &source, BytesKey(vec![4, 5, 6]), false, false, 10)
+            .is_err());
+        assert_eq!(state.root, root);
+    }
+
+    #[test]
+    fn test_update_metadata_merge() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let key = BytesKey(vec![1, 2, 3]);
+        let mut object = add_golden_object(&mut state, &store, key.clone());
+
+        let metadata = HashMap::from([
+            ("_modified".to_string(), "1718464346".to_string()),
+            ("archived".to_string(), "true".to_string()),
+        ]);
+        state
+            .update_metadata(&store, key.clone(), metadata.clone(), true)
+            .unwrap();
+
+        object.metadata.extend(metadata);
+        assert_eq!(object.metadata.len(), 4);
+        assert_eq!(state.get(&store, &key).unwrap(), Some(object));
+    }
+
+    #[test]
+    fn test_update_metadata_replace() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let key = BytesKey(vec![1, 2, 3]);
+        let mut object = add_golden_object(&mut state, &store, key.clone());
+
+        let metadata = HashMap::from([("archived".to_string(), "true".to_string())]);
+        state
+            .update_metadata(&store, key.clone(), metadata.clone(), false)
+            .unwrap();
+
+        object.metadata = metadata;
+        assert_eq!(state.get(&store, &key).unwrap(), Some(object));
+    }
+
+    #[test]
+    fn test_metadata_limits() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        state
+            .set_config(
+                &store,
+                Config {
+                    max_metadata_entries: Some(2),
+                    max_metadata_key_length: Some(4),
+                    max_metadata_size: Some(16),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let key = BytesKey(vec![1, 2, 3]);
+        let cid = Cid::from_str(GOLDEN_CID).unwrap();
+        let add = |state: &mut State, metadata: &[(&str, &str)]| {
+            let metadata = metadata
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            state.add(&store, key.clone(), cid, 5, metadata, true, 0)
+        };
+
+        assert!(add(&mut state, &[("a", "1"), ("b", "2")]).is_ok());
+
+        let err = add(&mut state, &[("a", "1"), ("b", "2"), ("c", "3")]).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("3 entries, exceeding maximum of 2"));
+
+        let err = add(&mut state, &[("abcde", "1")]).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("key of 5 bytes exceeds maximum of 4"));
+
+        let err = add(&mut state, &[("a", "0123456789"), ("b", "01234")]).unwrap_err();
+        assert!(err.to_string().contains("size 17 exceeds maximum of 16"));
+
+        // Merged metadata is checked as a whole.
+        let metadata = HashMap::from([("c".to_string(), "3".to_string())]);
+        let err = state
+            .update_metadata(&store, key.clone(), metadata.clone(), true)
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("3 entries, exceeding maximum of 2"));
+        assert!(state
+            .update_metadata(&store, key.clone(), metadata, false)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_update_metadata_missing_key() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let root = state.root;
+        assert!(state
+            .update_metadata(&store, BytesKey(vec![1, 2, 3]), HashMap::new(), true)
+            .is_err());
+        assert_eq!(state.root, root);
+    }
+
+    #[quickcheck]
+    fn test_delete(object: Object) {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let key = BytesKey(vec![1, 2, 3]);
+        state
+            .add(
+                &store,
+                key.clone(),
+                Cid::from_bytes(&object.cid.0).unwrap(),
+                object.size,
+                object.metadata,
+                true,
+                0,
+            )
+            .unwrap();
+        assert!(state.delete(&store, &key, 0).is_ok());
+
+        let result = state.get(&store, &key);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), None);
+    }
+
+    #[quickcheck]
+    fn test_get(object: Object) {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let key = BytesKey(vec![1, 2, 3]);
+        let cid = Cid::from_bytes(&object.cid.0).unwrap();
+        let md = object.metadata.clone();
+        state
+            .add(&store, key.clone(), cid, object.size, md, true, 0)
+            .unwrap();
+        let result = state.get(&store, &key);
+
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap().unwrap(), object);
+    }
+
+    #[test]
+    fn test_has() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let key = BytesKey(vec![1, 2, 3]);
+        assert!(!state.has(&store, &key).unwrap());
+
+        add_golden_object(&mut state, &store, key.clone());
+        assert!(state.has(&store, &key).unwrap());
+        assert!(!state.has(&store, &BytesKey(vec![1, 2])).unwrap());
+    }
+
+    #[test]
+    fn test_has_many() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let keys = [vec![1], vec![1, 2], vec![1, 2, 3], vec![4]].map(BytesKey);
+        assert_eq!(state.has_many(&store, &keys).unwrap(), vec![false; 4]);
+        assert!(state.has_many(&store, &[]).unwrap().is_empty());
+
+        add_golden_object(&mut state, &store, keys[1].clone());
+        add_golden_object(&mut state, &store, keys[3].clone());
+        assert_eq!(
+            state.has_many(&store, &keys).unwrap(),
+            vec![false, true, false, true]
+        );
+        // Answers stay aligned with the keys, duplicates included.
+        let repeated = [keys[3].clone(), keys[0].clone(), keys[3].clone()];
+        assert_eq!(
+            state.has_many(&store, &repeated).unwrap(),
+            vec![true, false, true]
+        );
+    }
+
+    #[test]
+    fn test_claim() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let key = BytesKey(b"job".to_vec());
+        let object = add_golden_object(&mut state, &store, key.clone());
+
+        // Two workers claiming the same key: only the first one gets the object.
+        let (first, root) = state.claim(&store, &key, 1).unwrap();
+        assert_eq!(first, Some(object));
+        assert_eq!(root, state.root);
+        let (second, _) = state.claim(&store, &key, 1).unwrap();
+        assert_eq!(second, None);
+        assert!(!state.has(&store, &key).unwrap());
+
+        // Claiming a missing key doesn't change the store.
+        let root = state.root;
+        assert_eq!(state.claim(&store, &key, 2).unwrap(), (None, root));
+    }
+
+    #[test]
+    fn test_claim_first() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        for key in ["queue/2", "queue/10", "other/0", "queue/1"] {
+            add_golden_object(&mut state, &store, BytesKey(key.as_bytes().to_vec()));
+        }
+
+        // Objects come out in lexicographic key order, each of them once.
+        let mut claimed = Vec::new();
+        while let (Some((key, _)), _) = state.claim_first(&store, b"queue/", 1).unwrap() {
+            claimed.push(String::from_utf8(key).unwrap());
+        }
+        assert_eq!(claimed, ["queue/1", "queue/10", "queue/2"]);
+        assert!(state.has(&store, &BytesKey(b"other/0".to_vec())).unwrap());
+
+        let (last, _) = state.claim_first(&store, b"", 1).unwrap();
+        assert_eq!(last.map(|(key, _)| key), Some(b"other/0".to_vec()));
+        assert_eq!(state.claim_first(&store, b"", 1).unwrap().0, None);
+    }
+
+    fn create_and_put_objects(
+        state: &mut State,
+        store: &MemoryBlockstore,
+    ) -> anyhow::Result<(BytesKey, BytesKey, BytesKey)> {
+        let jpeg_key = BytesKey("foo.jpeg".as_bytes().to_vec());
+        state.add(
+            store,
+            jpeg_key.clone(),
+            Cid::default(),
+            0,
+            HashMap::<String, String>::new(),
+            false,
+            0,
+        )?;
+        let bar_key = BytesKey("foo/bar.png".as_bytes().to_vec());
+        state.add(
+            store,
+            bar_key.clone(),
+            Cid::default(),
+            0,
+            HashMap::<String, String>::new(),
+            false,
+            0,
+        )?;
+        let baz_key = BytesKey("foo/baz.png".as_bytes().to_vec());
+        state.add(
+            store,
+            baz_key.clone(),
+            Cid::default(),
+            0,
+            HashMap::<String, String>::new(),
+            false,
+            0,
+        )?;
+
+        // We'll mostly ignore this one
+        let other_key = BytesKey("zzzz/image.png".as_bytes().to_vec());
+        state.add(
+            &store,
+            other_key.clone(),
+            Cid::default(),
+            0,
+            HashMap::<String, String>::new(),
+            false,
+            0,
+        )?;
+        Ok((jpeg_key, bar_key, baz_key))
+    }
+
+    #[test]
+    fn test_stat() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        for (key, size) in [
+            ("foo.jpeg", 1),
+            ("foo/bar.png", 10),
+            ("foo/bar/baz.png", 100),
+            ("zzzz/image.png", 1000),
+        ] {
+            state
+                .add(
+                    &store,
+                    BytesKey(key.as_bytes().to_vec()),
+                    Cid::default(),
+                    size,
+                    HashMap::new(),
+                    false,
+                    0,
+                )
+                .unwrap();
+        }
+
+        let stat = |prefix: &str| state.stat(&store, prefix.as_bytes().to_vec()).unwrap();
+        assert_eq!(
+            stat(""),
+            ObjectStat {
+                count: 4,
+                total_bytes: 1111,
+                truncated: false
+            }
+        );
+        assert_eq!(
+            stat("foo"),
+            ObjectStat {
+                count: 3,
+                total_bytes: 111,
+                truncated: false
+            }
+        );
+        assert_eq!(
+            stat("foo/"),
+            ObjectStat {
+                count: 2,
+                total_bytes: 110,
+                truncated: false
+            }
+        );
+        assert_eq!(
+            stat("foo/bar/"),
+            ObjectStat {
+                count: 1,
+                total_bytes: 100,
+                truncated: false
+            }
+        );
+        assert_eq!(stat("nope"), ObjectStat::default());
+
+        // Only so many keys are scanned, the stat is truncated after that.
+        state
+            .set_config(
+                &store,
+                Config {
+                    max_pattern_scan: Some(2),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let stat = state.stat(&store, vec![]).unwrap();
+        assert_eq!(stat.count, 2);
+        assert!(stat.truncated);
+    }
+
+    fn soft_delete_state(store: &MemoryBlockstore) -> (State, BytesKey) {
+        let mut state = State::new(store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        state
+            .set_config(
+                store,
+                Config {
+                    soft_delete_grace_period: Some(10),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let key = BytesKey(vec![1, 2, 3]);
+        let object = golden_object();
+        state
+            .add(
+                store,
+                key.clone(),
+                Cid::from_bytes(&object.cid.0).unwrap(),
+                object.size,
+                object.metadata,
+                true,
+                0,
+            )
+            .unwrap();
+        (state, key)
+    }
+
+    #[test]
+    fn test_soft_delete_and_restore() {
+        let store = MemoryBlockstore::default();
+        let (mut state, key) = soft_delete_state(&store);
+        let root = state.root;
+        let object = state.get(&store, &key).unwrap().unwrap();
+
+        state.delete(&store, &key, 100).unwrap();
+        assert_eq!(state.get(&store, &key).unwrap(), None);
+        assert!(!state.has(&store, &key).unwrap());
+
+        let (restored, restored_root) = state.restore(&store, key.clone(), 109).unwrap();
+        assert_eq!(restored, object);
+        assert_eq!(restored_root, root);
+        assert_eq!(state.get(&store, &key).unwrap(), Some(object));
+
+        // The tombstone is gone once restored.
+        assert!(state.restore(&store, key, 109).is_err());
+    }
+
+    #[test]
+    fn test_soft_delete_expire_and_purge() {
+        let store = MemoryBlockstore::default();
+        let (mut state, key) = soft_delete_state(&store);
+
+        state.delete(&store, &key, 100).unwrap();
+        assert_eq!(state.purge_deleted(&store, 109).unwrap(), 0);
+        assert!(state.restore(&store, key.clone(), 110).is_err());
+        assert_eq!(state.purge_deleted(&store, 110).unwrap(), 1);
+        assert_eq!(state.purge_deleted(&store, 110).unwrap(), 0);
+        assert!(state.restore(&store, key.clone(), 100).is_err());
+        assert_eq!(state.get(&store, &key).unwrap(), None);
+    }
+
+    #[test]
+    fn test_hard_delete_by_default() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let tombstones = state.tombstones;
+        create_and_put_objects(&mut state, &store).unwrap();
+        state
+            .delete(&store, &BytesKey(b"foo.jpeg".to_vec()), 100)
+            .unwrap();
+        assert_eq!(state.tombstones, tombstones);
+        assert!(state
+            .restore(&store, BytesKey(b"foo.jpeg".to_vec()), 100)
+            .is_err());
+    }
+
+    fn versioned_state(store: &MemoryBlockstore) -> State {
+        let mut state = State::new(store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        state
+            .set_config(
+                store,
+                Config {
+                    versioning: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        state
+    }
+
+    fn object_with_cid(data: &[u8]) -> Object {
+        Object {
+            cid: ByteBuf(Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(data)).to_bytes()),
+            ..golden_object()
         }
     }
 
-    const GOLDEN_CID: &str = "bafy2bzacebmog6w3ept45xctbw3lrt76i3rdbeaib6bikuhcddu5y5bqspozu";
+    fn add_object(state: &mut State, store: &MemoryBlockstore, key: &BytesKey, object: &Object) {
+        state
+            .add(
+                store,
+                key.clone(),
+                Cid::from_bytes(&object.cid.0).unwrap(),
+                object.size,
+                object.metadata.clone(),
+                true,
+                0,
+            )
+            .unwrap();
+    }
 
     #[test]
-    fn test_constructor() {
+    fn test_versioning_overwrites() {
         let store = MemoryBlockstore::default();
-        let state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner);
-        assert!(state.is_ok());
+        let mut state = versioned_state(&store);
+        let key = BytesKey(vec![1, 2, 3]);
+        let objects: Vec<Object> = (0..3u8).map(|i| object_with_cid(&[i])).collect();
+        for object in &objects {
+            add_object(&mut state, &store, &key, object);
+        }
+        assert_eq!(state.get(&store, &key).unwrap().as_ref(), objects.last());
+
+        let versions = state.list_versions(&store, &key).unwrap();
         assert_eq!(
-            state.unwrap().root,
-            Cid::from_str("bafy2bzaceamp42wmmgr2g2ymg46euououzfyck7szknvfacqscohrvaikwfay")
-                .unwrap()
+            versions,
+            vec![
+                ObjectVersion::Object(objects[0].clone()),
+                ObjectVersion::Object(objects[1].clone())
+            ]
         );
+        assert_eq!(
+            state.get_version(&store, &key, 1).unwrap(),
+            Some(ObjectVersion::Object(objects[1].clone()))
+        );
+        assert_eq!(state.get_version(&store, &key, 2).unwrap(), None);
+
+        // Deleting keeps the current object and marks the deletion.
+        state.delete(&store, &key, 100).unwrap();
+        let versions = state.list_versions(&store, &key).unwrap();
+        assert_eq!(versions.len(), 4);
+        assert_eq!(versions[2], ObjectVersion::Object(objects[2].clone()));
+        assert_eq!(versions[3], ObjectVersion::DeleteMarker);
+
+        // Other keys have no history.
+        let other = BytesKey(vec![4, 5, 6]);
+        assert!(state.list_versions(&store, &other).unwrap().is_empty());
+        assert_eq!(state.get_version(&store, &other, 0).unwrap(), None);
     }
 
     #[test]
-    fn test_add() {
+    fn test_versioning_disabled_by_default() {
         let store = MemoryBlockstore::default();
         let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
-        let object = golden_object();
-        assert!(state
-            .add(
-                &store,
-                BytesKey(vec![1, 2, 3]),
-                Cid::from_bytes(&object.cid.0).unwrap(),
-                object.size,
-                object.metadata,
-                true
-            )
-            .is_ok());
+        let versions = state.versions;
+        let key = BytesKey(vec![1, 2, 3]);
+        add_object(&mut state, &store, &key, &object_with_cid(&[0]));
+        add_object(&mut state, &store, &key, &object_with_cid(&[1]));
+        state.delete(&store, &key, 100).unwrap();
+        assert_eq!(state.versions, versions);
+        assert!(state.list_versions(&store, &key).unwrap().is_empty());
+    }
 
-        assert_eq!(state.root, Cid::from_str(GOLDEN_CID).unwrap());
+    #[test]
+    fn test_versioning_requires_empty_store() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        add_object(&mut state, &store, &BytesKey(vec![1]), &golden_object());
+        let config = Config {
+            versioning: true,
+            ..Default::default()
+        };
+        assert!(state.set_config(&store, config).is_err());
+        assert!(!state.config.versioning);
+
+        // Other settings can still be changed.
+        let config = Config {
+            max_list_limit: Some(10),
+            ..Default::default()
+        };
+        assert!(state.set_config(&store, config).is_ok());
     }
 
-    #[quickcheck]
-    fn test_resolve(mut object: Object) {
+    #[test]
+    fn test_change_log_requires_empty_store() {
         let store = MemoryBlockstore::default();
         let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
-        let key = BytesKey(vec![1, 2, 3]);
-        let cid = Cid::from_bytes(&object.cid.0).unwrap();
-        let md = object.metadata.clone();
-        state
-            .add(&store, key.clone(), cid, object.size, md, true)
-            .unwrap();
-        assert!(state.resolve(&store, key.clone(), cid).is_ok());
+        add_object(&mut state, &store, &BytesKey(vec![1]), &golden_object());
+        let config = Config {
+            change_log: Some(Address::new_id(101)),
+            ..Default::default()
+        };
+        assert!(state.set_config(&store, config).is_err());
+        assert_eq!(state.config.change_log, None);
+    }
 
-        object.resolved = true;
-        let result = state.get(&store, &key);
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap().unwrap(), object);
+    fn v0_cid() -> Cid {
+        Cid::new_v0(Code::Sha2_256.digest(&[1, 2, 3])).unwrap()
     }
 
-    #[quickcheck]
-    fn test_delete(object: Object) {
+    #[test]
+    fn test_add_normalizes_v0_cid() {
         let store = MemoryBlockstore::default();
         let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
         let key = BytesKey(vec![1, 2, 3]);
+        let cid = v0_cid();
         state
-            .add(
-                &store,
-                key.clone(),
-                Cid::from_bytes(&object.cid.0).unwrap(),
-                object.size,
-                object.metadata,
-                true,
-            )
+            .add(&store, key.clone(), cid, 3, HashMap::new(), true, 0)
             .unwrap();
-        assert!(state.delete(&store, &key).is_ok());
 
-        let result = state.get(&store, &key);
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), None);
+        let object = state.get(&store, &key).unwrap().unwrap();
+        let stored = Cid::from_bytes(&object.cid.0).unwrap();
+        assert_eq!(stored.version(), Version::V1);
+        assert_eq!(stored.codec(), DAG_PB);
+        assert_eq!(stored.hash(), cid.hash());
+
+        // The original v0 form matches the stored value when swapping and resolving.
+        state
+            .swap(&store, key.clone(), Some(cid), cid, 3, HashMap::new(), 0)
+            .unwrap();
+        state.resolve(&store, key.clone(), cid, None).unwrap();
+        assert!(state.get(&store, &key).unwrap().unwrap().resolved);
     }
 
-    #[quickcheck]
-    fn test_get(object: Object) {
+    #[test]
+    fn test_add_cid_v0_config() {
         let store = MemoryBlockstore::default();
         let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        state
+            .set_config(
+                &store,
+                Config {
+                    cid_v0: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
         let key = BytesKey(vec![1, 2, 3]);
-        let cid = Cid::from_bytes(&object.cid.0).unwrap();
-        let md = object.metadata.clone();
+        let cid = v0_cid();
         state
-            .add(&store, key.clone(), cid, object.size, md, true)
+            .add(
+                &store,
+                key.clone(),
+                Cid::new_v1(DAG_PB, *cid.hash()),
+                3,
+                HashMap::new(),
+                true,
+                0,
+            )
             .unwrap();
-        let result = state.get(&store, &key);
-
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap().unwrap(), object);
-    }
-
-    fn create_and_put_objects(
-        state: &mut State,
-        store: &MemoryBlockstore,
-    ) -> anyhow::Result<(BytesKey, BytesKey, BytesKey)> {
-        let jpeg_key = BytesKey("foo.jpeg".as_bytes().to_vec());
-        state.add(
-            store,
-            jpeg_key.clone(),
-            Cid::default(),
-            0,
-            HashMap::<String, String>::new(),
-            false,
-        )?;
-        let bar_key = BytesKey("foo/bar.png".as_bytes().to_vec());
-        state.add(
-            store,
-            bar_key.clone(),
-            Cid::default(),
-            0,
-            HashMap::<String, String>::new(),
-            false,
-        )?;
-        let baz_key = BytesKey("foo/baz.png".as_bytes().to_vec());
-        state.add(
-            store,
-            baz_key.clone(),
-            Cid::default(),
-            0,
-            HashMap::<String, String>::new(),
-            false,
-        )?;
+        let object = state.get(&store, &key).unwrap().unwrap();
+        assert_eq!(Cid::from_bytes(&object.cid.0).unwrap(), cid);
 
-        // We'll mostly ignore this one
-        let other_key = BytesKey("zzzz/image.png".as_bytes().to_vec());
-        state.add(
-            &store,
-            other_key.clone(),
-            Cid::default(),
-            0,
-            HashMap::<String, String>::new(),
-            false,
-        )?;
-        Ok((jpeg_key, bar_key, baz_key))
+        // A DAG-CBOR object has no CIDv0 form.
+        let err = state
+            .add(
+                &store,
+                key,
+                Cid::new_v1(DAG_CBOR, *cid.hash()),
+                3,
+                HashMap::new(),
+                true,
+                0,
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("cannot be converted to CIDv0"));
     }
 
     #[test]
@@ -399,7 +2541,7 @@ mod tests {
         let default_obj = default_object();
 
         // List all keys with a limit
-        let result = state.list(&store, vec![], vec![], 0, 0);
+        let result = state.list(&store, vec![], vec![], 0, 0, false, None);
         assert!(result.is_ok());
         let result = result.unwrap();
         assert_eq!(result.objects.len(), 4);
@@ -416,7 +2558,7 @@ mod tests {
         let default_obj = default_object();
 
         let foo_key = BytesKey("foo".as_bytes().to_vec());
-        let result = state.list(&store, foo_key.0.clone(), vec![], 0, 0);
+        let result = state.list(&store, foo_key.0.clone(), vec![], 0, 0, false, None);
         assert!(result.is_ok());
         let result = result.unwrap();
         assert_eq!(result.objects.len(), 3);
@@ -424,6 +2566,186 @@ mod tests {
         assert_eq!(result.objects[1], (bar_key.0, default_obj.clone()));
     }
 
+    #[test]
+    fn test_glob() {
+        let glob = Glob::new("logs/2024-*/error.json");
+        assert!(glob.matches(b"logs/2024-01/error.json"));
+        assert!(glob.matches(b"logs/2024-/error.json"));
+        assert!(!glob.matches(b"logs/2024-01/02/error.json"));
+        assert!(!glob.matches(b"logs/2023-01/error.json"));
+        assert!(!glob.matches(b"logs/2024-01/error.json.bak"));
+
+        assert!(Glob::new("logs/**.json").matches(b"logs/2024-01/02/error.json"));
+        assert!(Glob::new("a?c").matches(b"abc"));
+        assert!(!Glob::new("a?c").matches(b"a/c"));
+        assert!(Glob::new("a\\*").matches(b"a*"));
+        assert!(!Glob::new("a\\*").matches(b"ab"));
+        assert!(Glob::new("").matches(b""));
+        assert!(!Glob::new("").matches(b"a"));
+    }
+
+    #[test]
+    fn test_list_pattern() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let keys = [
+            "logs/2024-01/error.json",
+            "logs/2024-01/info.json",
+            "logs/2024-02/error.json",
+            "logs/2023-12/error.json",
+            "other/2024-01/error.json",
+        ];
+        for key in keys {
+            add_golden_object(&mut state, &store, BytesKey(key.as_bytes().to_vec()));
+        }
+
+        let list = |state: &State, pattern| {
+            let list = state
+                .list(
+                    &store,
+                    b"logs/".to_vec(),
+                    vec![],
+                    0,
+                    0,
+                    false,
+                    Some(pattern),
+                )
+                .unwrap();
+            let mut keys = list
+                .objects
+                .into_iter()
+                .map(|(key, _)| String::from_utf8(key).unwrap())
+                .collect::<Vec<_>>();
+            keys.sort();
+            assert!(!list.truncated);
+            (keys, list.scan_truncated)
+        };
+        assert_eq!(
+            list(&state, "logs/2024-*/error.json"),
+            (
+                vec![
+                    "logs/2024-01/error.json".to_string(),
+                    "logs/2024-02/error.json".to_string()
+                ],
+                false
+            )
+        );
+        assert_eq!(list(&state, "logs/*"), (vec![], false));
+
+        // Only so many keys under the prefix are matched, the scan is truncated after that.
+        state
+            .set_config(
+                &store,
+                Config {
+                    max_pattern_scan: Some(2),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let (matched, scan_truncated) = list(&state, "logs/**");
+        assert_eq!(matched.len(), 2);
+        assert!(scan_truncated);
+
+        // Paging through a truncated scan ends with the keys that were matched.
+        let mut offset = 0;
+        let mut paged = Vec::new();
+        loop {
+            let page = state
+                .list(
+                    &store,
+                    b"logs/".to_vec(),
+                    vec![],
+                    offset,
+                    1,
+                    false,
+                    Some("logs/**"),
+                )
+                .unwrap();
+            assert!(page.scan_truncated);
+            offset += page.objects.len() as u64;
+            paged.extend(page.objects.into_iter().map(|(key, _)| key));
+            if !page.truncated {
+                break;
+            }
+            assert!(paged.len() <= matched.len(), "paging doesn't end");
+        }
+        assert_eq!(paged.len(), matched.len());
+        state
+            .set_config(
+                &store,
+                Config {
+                    max_pattern_scan: Some(4),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let mut expected = keys[..4].iter().map(|k| k.to_string()).collect::<Vec<_>>();
+        expected.sort();
+        assert_eq!(list(&state, "logs/**"), (expected, false));
+    }
+
+    #[test]
+    fn test_list_reverse() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        create_and_put_objects(&mut state, &store).unwrap();
+
+        let prefix = "foo".as_bytes().to_vec();
+        let forward = state
+            .list(&store, prefix.clone(), vec![], 0, 0, false, None)
+            .unwrap();
+        let reverse = state
+            .list(&store, prefix.clone(), vec![], 0, 0, true, None)
+            .unwrap();
+        assert_eq!(forward.objects.len(), 3);
+        // A reverse listing is in descending key order, whatever the order of the hashes.
+        let mut sorted = forward.objects.clone();
+        sorted.sort_by(|(a, _), (b, _)| b.cmp(a));
+        assert_eq!(reverse.objects, sorted);
+        assert_eq!(
+            sorted
+                .iter()
+                .map(|(key, _)| key.as_slice())
+                .collect::<Vec<_>>(),
+            vec![
+                "foo/baz.png".as_bytes(),
+                "foo/bar.png".as_bytes(),
+                "foo.jpeg".as_bytes()
+            ]
+        );
+        assert!(!reverse.scan_truncated);
+
+        // Pages of a reverse listing count from the end.
+        let page = state
+            .list(&store, prefix.clone(), vec![], 1, 1, true, None)
+            .unwrap();
+        assert_eq!(page.objects, vec![sorted[1].clone()]);
+        assert!(page.truncated);
+        let page = state
+            .list(&store, prefix.clone(), vec![], 2, 1, true, None)
+            .unwrap();
+        assert_eq!(page.objects, vec![sorted[2].clone()]);
+        assert!(!page.truncated);
+
+        // Only so many keys under the prefix are collected for a reverse listing.
+        state
+            .set_config(
+                &store,
+                Config {
+                    max_pattern_scan: Some(2),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let reverse = state
+            .list(&store, prefix, vec![], 0, 0, true, None)
+            .unwrap();
+        assert_eq!(reverse.objects.len(), 2);
+        assert!(reverse.scan_truncated);
+        assert!(!reverse.truncated);
+        assert!(reverse.objects.windows(2).all(|w| w[0].0 > w[1].0));
+    }
+
     #[test]
     fn test_list_keys_with_delimiter() {
         let store = MemoryBlockstore::default();
@@ -436,7 +2758,15 @@ mod tests {
         let foo_key = BytesKey("foo".as_bytes().to_vec());
         let delimiter_key = BytesKey("/".as_bytes().to_vec());
         let full_key = [foo_key.clone(), delimiter_key.clone()].concat();
-        let result = state.list(&store, foo_key.0.clone(), delimiter_key.0.clone(), 0, 3);
+        let result = state.list(
+            &store,
+            foo_key.0.clone(),
+            delimiter_key.0.clone(),
+            0,
+            3,
+            false,
+            None,
+        );
         assert!(result.is_ok());
         let result = result.unwrap();
         assert_eq!(result.objects.len(), 1);
@@ -458,6 +2788,7 @@ mod tests {
                 0,
                 HashMap::<String, String>::new(),
                 false,
+                0,
             )
             .unwrap();
         let bar_key = BytesKey("bin/foo/bar.png".as_bytes().to_vec());
@@ -469,6 +2800,7 @@ mod tests {
                 0,
                 HashMap::<String, String>::new(),
                 false,
+                0,
             )
             .unwrap();
         let baz_key = BytesKey("bin/foo/baz.png".as_bytes().to_vec());
@@ -480,13 +2812,22 @@ mod tests {
                 0,
                 HashMap::<String, String>::new(),
                 false,
+                0,
             )
             .unwrap();
 
         let bin_key = BytesKey("bin/".as_bytes().to_vec());
         let full_key = BytesKey("bin/foo/".as_bytes().to_vec());
         let delimiter_key = BytesKey("/".as_bytes().to_vec());
-        let result = state.list(&store, bin_key.0.clone(), delimiter_key.0.clone(), 0, 0);
+        let result = state.list(
+            &store,
+            bin_key.0.clone(),
+            delimiter_key.0.clone(),
+            0,
+            0,
+            false,
+            None,
+        );
         assert!(result.is_ok());
         let result = result.unwrap();
         assert_eq!(result.objects.len(), 0);
@@ -504,7 +2845,7 @@ mod tests {
         let default_obj = default_object();
 
         // List all keys with a limit and offset
-        let result = state.list(&store, vec![], vec![], 1, 1);
+        let result = state.list(&store, vec![], vec![], 1, 1, false, None);
         assert!(result.is_ok());
         let result = result.unwrap();
         assert_eq!(result.objects.len(), 1);
@@ -512,6 +2853,48 @@ mod tests {
         assert_eq!(result.objects.first(), Some(&(bar_key.0, default_obj)));
     }
 
+    #[test]
+    fn test_list_limit_clamped() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        create_and_put_objects(&mut state, &store).unwrap();
+
+        let result = state
+            .list(&store, vec![], vec![], 0, 4, false, None)
+            .unwrap();
+        assert_eq!(result.objects.len(), 4);
+        assert!(!result.truncated);
+
+        let result = state
+            .list(&store, vec![], vec![], 0, 3, false, None)
+            .unwrap();
+        assert_eq!(result.objects.len(), 3);
+        assert!(result.truncated);
+
+        let result = state
+            .list(&store, vec![], vec![], 3, 0, false, None)
+            .unwrap();
+        assert_eq!(result.objects.len(), 1);
+        assert!(!result.truncated);
+
+        state
+            .set_config(
+                &store,
+                Config {
+                    max_list_limit: Some(2),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        for limit in [0, 2, u64::MAX] {
+            let result = state
+                .list(&store, vec![], vec![], 0, limit, false, None)
+                .unwrap();
+            assert_eq!(result.objects.len(), 2);
+            assert!(result.truncated);
+        }
+    }
+
     #[test]
     fn test_list_with_prefix_delimiter_and_offset_and_limit() {
         let store = MemoryBlockstore::default();
@@ -526,6 +2909,7 @@ mod tests {
                 0,
                 HashMap::<String, String>::new(),
                 false,
+                0,
             )
             .unwrap();
         let two = BytesKey("hello/again".as_bytes().to_vec());
@@ -537,6 +2921,7 @@ mod tests {
                 0,
                 HashMap::<String, String>::new(),
                 false,
+                0,
             )
             .unwrap();
 
@@ -547,9 +2932,80 @@ mod tests {
             "/".as_bytes().to_vec(),
             2,
             0,
+            false,
+            None,
         );
         assert!(result.is_ok());
         let result = result.unwrap();
         assert_eq!(result.objects.len(), 0);
     }
+
+    fn keys(list: &ObjectList) -> Vec<&[u8]> {
+        list.objects.iter().map(|(k, _)| k.as_slice()).collect()
+    }
+
+    #[test]
+    fn test_list_by_time() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        for (key, epoch) in [("a", 10), ("b", 20), ("c", 20), ("d", 30), ("e", 40)] {
+            state
+                .add(
+                    &store,
+                    BytesKey(key.as_bytes().to_vec()),
+                    Cid::default(),
+                    0,
+                    HashMap::new(),
+                    false,
+                    epoch,
+                )
+                .unwrap();
+        }
+
+        let result = state.list_by_time(&store, 20, 30, 0).unwrap();
+        assert_eq!(keys(&result), vec![b"b", b"c", b"d"]);
+        assert!(!result.truncated);
+        assert_eq!(result.objects[0].1.created_at, 20);
+
+        let result = state.list_by_time(&store, 0, ChainEpoch::MAX, 2).unwrap();
+        assert_eq!(keys(&result), vec![b"a", b"b"]);
+        assert!(result.truncated);
+
+        let result = state.list_by_time(&store, 11, 19, 0).unwrap();
+        assert!(result.objects.is_empty());
+        let result = state.list_by_time(&store, 30, 20, 0).unwrap();
+        assert!(result.objects.is_empty());
+
+        // Overwriting an object moves it to the epoch of the overwrite, and deleting it
+        // removes it from the index.
+        state
+            .add(
+                &store,
+                BytesKey(b"b".to_vec()),
+                Cid::default(),
+                0,
+                HashMap::new(),
+                true,
+                50,
+            )
+            .unwrap();
+        state.delete(&store, &BytesKey(b"d".to_vec()), 50).unwrap();
+        let result = state.list_by_time(&store, 0, ChainEpoch::MAX, 0).unwrap();
+        assert_eq!(keys(&result), vec![b"a", b"c", b"e", b"b"]);
+
+        // Adding an existing key without overwriting leaves it in place.
+        state
+            .add(
+                &store,
+                BytesKey(b"a".to_vec()),
+                Cid::default(),
+                0,
+                HashMap::new(),
+                false,
+                60,
+            )
+            .unwrap();
+        let result = state.list_by_time(&store, 60, 60, 0).unwrap();
+        assert!(result.objects.is_empty());
+    }
 }
@@ -30,5 +30,68 @@ pub enum ObjectsCommands {
             env = "IPFS_RPC_ADDR"
         )]
         ipfs_addr: String,
+
+        /// Maximum size in bytes of an object upload request body.
+        #[arg(long, default_value_t = 1024 * 1024 * 1024, env = "MAX_OBJECT_LENGTH")]
+        max_object_length: u64,
+
+        /// Maximum size in bytes of each non-file field of an object upload request,
+        /// e.g. the signed message. These fields are buffered in memory.
+        #[arg(long, default_value_t = 1024 * 1024, env = "MAX_FIELD_LENGTH")]
+        max_field_length: u64,
+
+        /// Seconds after which listing objects or machines, or aggregating objects, is aborted.
+        #[arg(long, default_value_t = 30, env = "LIST_TIMEOUT")]
+        list_timeout: u64,
+
+        /// Seconds after which fetching an object or an accumulator leaf is aborted, if it hasn't
+        /// started streaming the content yet.
+        #[arg(long, default_value_t = 60, env = "FETCH_TIMEOUT")]
+        fetch_timeout: u64,
+
+        /// Consecutive failures to reach the node after which requests to it fail fast,
+        /// or 0 to always try the node.
+        #[arg(long, default_value_t = 5, env = "CIRCUIT_BREAKER_THRESHOLD")]
+        circuit_breaker_threshold: u32,
+
+        /// Seconds for which requests to the node fail fast once the threshold is reached,
+        /// before one of them is let through to check if it has recovered.
+        #[arg(long, default_value_t = 30, env = "CIRCUIT_BREAKER_COOLDOWN")]
+        circuit_breaker_cooldown: u64,
+
+        /// Attempts at fetching the content of an object missing from the IPFS node, before it's
+        /// reported as not found. Downloads answer `202 Accepted` while the content is fetched.
+        #[arg(long, default_value_t = 3, env = "FETCH_ATTEMPTS")]
+        fetch_attempts: u32,
+
+        /// Seconds clients are asked to wait before polling for content being fetched, which is
+        /// also the delay between attempts.
+        #[arg(long, default_value_t = 5, env = "FETCH_RETRY_AFTER")]
+        fetch_retry_after: u64,
+
+        /// Only serve HTTP/1.1. By default HTTP/2 is also served, to clients using it with
+        /// prior knowledge.
+        #[arg(long, env = "DISABLE_HTTP2")]
+        disable_http2: bool,
+
+        /// Seconds between keep-alive pings on HTTP/2 connections, which are closed if a ping
+        /// isn't answered in time. 0 disables keep-alive, also closing HTTP/1.1 connections
+        /// after each response.
+        #[arg(long, default_value_t = 20, env = "KEEP_ALIVE_INTERVAL")]
+        keep_alive_interval: u64,
+
+        /// Maximum number of concurrent requests on an HTTP/2 connection.
+        #[arg(long, default_value_t = 256, env = "MAX_CONCURRENT_STREAMS")]
+        max_concurrent_streams: u32,
+
+        /// Maximum number of open connections, or 0 for no limit. Connections over the limit
+        /// wait until another one is closed.
+        #[arg(long, default_value_t = 4096, env = "MAX_CONNECTIONS")]
+        max_connections: usize,
+
+        /// URL of the node's IPFS Resolver stats server, see the `resolver.stats_listen`
+        /// setting, relayed at `/v1/resolver/stats`. The route is disabled if not set.
+        #[arg(long, env = "RESOLVER_STATS_URL")]
+        resolver_stats_url: Option<String>,
     },
 }
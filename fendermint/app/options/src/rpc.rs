@@ -169,11 +169,21 @@ pub struct TransArgs {
     ///
     /// Any discrepancy between this and the base fee is paid for
     /// by the validator who puts the transaction into the block.
-    #[arg(long, value_parser = parse_token_amount, default_value = "0")]
-    pub gas_fee_cap: TokenAmount,
+    ///
+    /// When broadcasting, it defaults to a price recommended from the base fees of recent blocks.
+    #[arg(long, value_parser = parse_token_amount)]
+    pub gas_fee_cap: Option<TokenAmount>,
     /// Gas premium.
-    #[arg(long, value_parser = parse_token_amount, default_value = "0")]
-    pub gas_premium: TokenAmount,
+    ///
+    /// When broadcasting, it defaults to a premium recommended from the base fees of recent blocks.
+    #[arg(long, value_parser = parse_token_amount)]
+    pub gas_premium: Option<TokenAmount>,
+    /// Number of recent blocks whose base fees are sampled to recommend a gas price.
+    #[arg(long, default_value_t = 20)]
+    pub gas_sample_window: u64,
+    /// Percentile of the recent base fee increases paid as gas premium when recommending a gas price.
+    #[arg(long, default_value_t = 60, value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub gas_percentile: u8,
     /// Whether to wait for the results from Tendermint or not.
     #[arg(long, short, default_value = "commit")]
     pub broadcast_mode: BroadcastMode,
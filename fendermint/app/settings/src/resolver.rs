@@ -9,7 +9,7 @@ use serde_with::{serde_as, DurationSeconds};
 use ipc_api::subnet_id::SubnetID;
 use multiaddr::Multiaddr;
 
-use crate::{home_relative, IsHumanReadable};
+use crate::{home_relative, IsHumanReadable, SocketAddress};
 
 #[serde_as]
 #[derive(Debug, Clone, Deserialize)]
@@ -17,6 +17,17 @@ pub struct ResolverSettings {
     /// Time to wait between attempts to resolve a CID, in seconds.
     #[serde_as(as = "DurationSeconds<u64>")]
     pub retry_delay: Duration,
+    /// Check that resolved content is still pinned in the local IPFS node right before voting on it.
+    #[serde(default)]
+    pub verify_before_vote: bool,
+    /// Maximum number of CIDs from a single source subnet resolved at the same time.
+    /// Unlimited if not set.
+    #[serde(default)]
+    pub max_tasks_per_subnet: Option<usize>,
+    /// Address to serve the settings and live stats of the IPFS Resolver on, as JSON,
+    /// for the objects proxy to expose. Not served if not set.
+    #[serde(default)]
+    pub stats_listen: Option<SocketAddress>,
     pub network: NetworkSettings,
     pub discovery: DiscoverySettings,
     pub membership: MembershipSettings,
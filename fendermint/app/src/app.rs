@@ -18,7 +18,9 @@ use fendermint_vm_core::Timestamp;
 use fendermint_vm_interpreter::bytes::{
     BytesMessageApplyRes, BytesMessageCheckRes, BytesMessageQuery, BytesMessageQueryRes,
 };
-use fendermint_vm_interpreter::chain::{ChainEnv, ChainMessageApplyRet, IllegalMessage};
+use fendermint_vm_interpreter::chain::{
+    ChainEnv, ChainMessageApplyRet, IllegalMessage, PendingObjects,
+};
 use fendermint_vm_interpreter::fvm::state::{
     empty_state_tree, CheckStateRef, FvmExecState, FvmGenesisState, FvmQueryState, FvmStateParams,
     FvmUpdatableParams,
@@ -52,6 +54,8 @@ use crate::{tmconv::*, VERSION};
 #[repr(u8)]
 pub enum AppStoreKey {
     State,
+    /// Objects waiting for resolution or execution in the [`ChainEnv`] object pool.
+    PendingObjects,
 }
 
 // TODO: What range should we use for our own error codes? Should we shift FVM errors?
@@ -176,7 +180,8 @@ where
         + Codec<AppState>
         + Encode<AppStoreKey>
         + Encode<BlockHeight>
-        + Codec<FvmStateParams>,
+        + Codec<FvmStateParams>
+        + Codec<PendingObjects>,
     DB: KVWritable<S> + KVReadable<S> + Clone + 'static,
     SS: Blockstore + Clone + 'static,
 {
@@ -215,7 +220,8 @@ where
         + Codec<AppState>
         + Encode<AppStoreKey>
         + Encode<BlockHeight>
-        + Codec<FvmStateParams>,
+        + Codec<FvmStateParams>
+        + Codec<PendingObjects>,
     DB: KVWritable<S> + KVReadable<S> + 'static + Clone,
     SS: Blockstore + 'static + Clone,
 {
@@ -293,6 +299,34 @@ where
             .context("commit failed")
     }
 
+    /// Persist the objects still waiting in the pool, so they can be restored after a restart.
+    fn set_pending_objects(&self, pending: PendingObjects) -> Result<()> {
+        self.db
+            .with_write(|tx| {
+                tx.put(&self.namespace, &AppStoreKey::PendingObjects, &pending)?;
+                Ok(())
+            })
+            .context("failed to persist pending objects")
+    }
+
+    /// Re-add the objects which were pending at the last commit to the object pool.
+    ///
+    /// The pool only lives in memory; without this, objects delivered shortly before
+    /// a restart would never be resolved and voted on.
+    pub async fn restore_pending_objects(&self) -> Result<usize> {
+        let pending: Option<PendingObjects> = {
+            let tx = self.db.read();
+            tx.get(&self.namespace, &AppStoreKey::PendingObjects)
+                .context("get failed")?
+        };
+        let pending = pending.unwrap_or_default();
+        let count = pending.len();
+        if count > 0 {
+            atomically(|| self.chain_env.object_pool.add_pending(pending.clone())).await;
+        }
+        Ok(count)
+    }
+
     /// Put the execution state during block execution. Has to be empty.
     async fn put_exec_state(&self, state: FvmExecState<SS>) {
         let mut guard = self.exec_state.lock().await;
@@ -401,7 +435,8 @@ where
         + Codec<AppState>
         + Encode<AppStoreKey>
         + Encode<BlockHeight>
-        + Codec<FvmStateParams>,
+        + Codec<FvmStateParams>
+        + Codec<PendingObjects>,
     S::Namespace: Sync + Send,
     DB: KVWritable<S> + KVReadable<S> + Clone + Send + Sync + 'static,
     SS: Blockstore + Clone + Send + Sync + 'static,
@@ -885,6 +920,11 @@ where
             atomically(|| snapshots.notify(block_height, state.state_params.clone())).await;
         }
 
+        // Persist the object pool ahead of the app state, so that if we crash in between,
+        // the replayed block re-adds the same items rather than losing them.
+        let pending = atomically(|| self.chain_env.object_pool.pending()).await;
+        self.set_pending_objects(pending)?;
+
         // Commit app state to the datastore.
         self.set_committed_state(state)?;
 
@@ -0,0 +1,105 @@
+// Copyright 2024 Textile
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use warp::Rejection;
+
+use super::rejection::{Timeout, Unavailable, Upstream};
+
+/// When to stop sending requests to an unreachable node.
+#[derive(Clone, Copy, Debug)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failures to reach the node after which the circuit opens, or 0 to never open.
+    pub threshold: u32,
+    /// How long requests fail fast once the circuit is open, before one is let through to
+    /// check if the node has recovered.
+    pub cooldown: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 5,
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Circuit breaker around the requests to the node.
+///
+/// While the node is down, every request would otherwise wait on its own connection attempt
+/// or timeout, piling load on the proxy and on the node as it comes back. Once `threshold`
+/// requests in a row failed to reach the node, the circuit opens and requests fail fast for
+/// the cooldown. The first request after that is let through as a probe: if the node answers
+/// the circuit closes, if not it opens again for another cooldown.
+#[derive(Clone, Debug)]
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    state: Arc<Mutex<CircuitState>>,
+}
+
+#[derive(Debug, Default)]
+struct CircuitState {
+    /// Consecutive failures to reach the node.
+    failures: u32,
+    /// When the circuit opened, or the last probe was let through.
+    opened_at: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            state: Default::default(),
+        }
+    }
+
+    /// Check if a request may go to the node, rejecting it as unavailable if the circuit is open.
+    pub fn acquire(&self) -> Result<(), Rejection> {
+        let mut state = self.state.lock().unwrap();
+        match state.opened_at {
+            Some(opened_at) if opened_at.elapsed() < self.config.cooldown => {
+                Err(Rejection::from(Unavailable))
+            }
+            Some(_) => {
+                // Half-open: let this request probe the node, and keep failing the others
+                // fast until it's done, or for another cooldown if it never reports back.
+                state.opened_at = Some(Instant::now());
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Record the outcome of a request let through by [`CircuitBreaker::acquire`].
+    pub fn record<T>(&self, result: &Result<T, Rejection>) {
+        let mut state = self.state.lock().unwrap();
+        match result {
+            // No route matched, so the node was never asked.
+            Err(e) if e.is_not_found() => {}
+            Err(e) if e.find::<Upstream>().is_some() || e.find::<Timeout>().is_some() => {
+                state.failures = state.failures.saturating_add(1);
+                if self.config.threshold > 0 && state.failures >= self.config.threshold {
+                    if state.opened_at.is_none() {
+                        tracing::warn!(
+                            failures = state.failures,
+                            "node unreachable, opening circuit"
+                        );
+                    }
+                    state.opened_at = Some(Instant::now());
+                }
+            }
+            _ => {
+                if state.opened_at.is_some() {
+                    tracing::info!("node reachable again, closing circuit");
+                }
+                *state = CircuitState::default();
+            }
+        }
+    }
+}
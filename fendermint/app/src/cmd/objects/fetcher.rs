@@ -0,0 +1,277 @@
+// Copyright 2024 Textile
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use cid::Cid;
+use warp::{
+    http::{HeaderValue, StatusCode},
+    hyper::body::Body,
+};
+
+use super::store::ContentStore;
+
+/// How content missing from the content store is fetched for the downloads asking for it.
+#[derive(Clone, Copy, Debug)]
+pub struct FetchConfig {
+    /// Attempts at fetching the content before it's reported as not found, or 0 to report
+    /// missing content as not found right away.
+    pub attempts: u32,
+    /// How long clients are asked to wait before polling for the content again, which is
+    /// also the delay between attempts.
+    pub retry_after: Duration,
+    /// Timeout of each attempt.
+    pub attempt_timeout: Duration,
+}
+
+impl Default for FetchConfig {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            retry_after: Duration::from_secs(5),
+            attempt_timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+/// How long content that couldn't be fetched is reported as not found, before a download
+/// tries fetching it again.
+const FETCH_FAILURE_TTL: Duration = Duration::from_secs(600);
+
+/// Background fetches of object content missing from the content store.
+///
+/// The objects a node has resolved can still be missing from the IPFS node of the proxy, e.g.
+/// if it's not the one the validators pin content to. Rather than failing the download, or
+/// holding it until the content arrives, the content is fetched in the background and the
+/// client is told to poll for it with `202 Accepted`. Once every attempt has failed, the
+/// content is reported as not found for a while, rather than fetched over and over.
+#[derive(Clone, Debug)]
+pub struct ContentFetcher {
+    pub config: FetchConfig,
+    fetches: Arc<Mutex<HashMap<Cid, FetchState>>>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum FetchState {
+    /// The content is being fetched.
+    Pending,
+    /// Every attempt failed, the last one at this time.
+    Failed(Instant),
+}
+
+impl FetchState {
+    fn is_expired(&self) -> bool {
+        matches!(self, FetchState::Failed(at) if at.elapsed() >= FETCH_FAILURE_TTL)
+    }
+}
+
+impl ContentFetcher {
+    pub fn new(config: FetchConfig) -> Self {
+        Self {
+            config,
+            fetches: Default::default(),
+        }
+    }
+
+    /// Start fetching `cid` into `store`, unless it's already being fetched.
+    ///
+    /// Returns `false` if every attempt at fetching it has failed recently.
+    pub fn fetch<S: ContentStore + Send + Sync + 'static>(&self, store: S, cid: Cid) -> bool {
+        if self.config.attempts == 0 {
+            return false;
+        }
+        let mut fetches = self.fetches.lock().unwrap();
+        match fetches.get(&cid) {
+            Some(FetchState::Pending) => return true,
+            Some(state) if !state.is_expired() => return false,
+            _ => {}
+        }
+        fetches.retain(|_, state| !state.is_expired());
+        fetches.insert(cid, FetchState::Pending);
+        drop(fetches);
+
+        let fetcher = self.clone();
+        tokio::spawn(async move {
+            let fetched = fetcher.attempt_all(&store, cid).await;
+            let mut fetches = fetcher.fetches.lock().unwrap();
+            if fetched {
+                fetches.remove(&cid);
+            } else {
+                fetches.insert(cid, FetchState::Failed(Instant::now()));
+            }
+        });
+        true
+    }
+
+    /// Try fetching `cid` until an attempt succeeds, returning `false` if none did.
+    async fn attempt_all<S: ContentStore>(&self, store: &S, cid: Cid) -> bool {
+        for attempt in 1..=self.config.attempts {
+            match tokio::time::timeout(self.config.attempt_timeout, store.fetch(cid)).await {
+                Ok(Ok(())) => return true,
+                Ok(Err(e)) => tracing::debug!(%cid, attempt, error = %e, "failed to fetch content"),
+                Err(_) => tracing::debug!(%cid, attempt, "timed out fetching content"),
+            }
+            if attempt < self.config.attempts {
+                tokio::time::sleep(self.config.retry_after).await;
+            }
+        }
+        tracing::warn!(%cid, attempts = self.config.attempts, "giving up fetching content");
+        false
+    }
+}
+
+/// Tell the client the content is being fetched, and when to poll for it again.
+pub fn fetch_pending_response(retry_after: Duration) -> warp::reply::Response {
+    let mut response = warp::reply::Response::new(Body::empty());
+    *response.status_mut() = StatusCode::ACCEPTED;
+    // Retry-After is in whole seconds, so round up not to have clients poll right away.
+    let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    response
+        .headers_mut()
+        .insert("Retry-After", HeaderValue::from(seconds));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use fendermint_actor_objectstore::{Object, ObjectKind};
+    use fendermint_rpc::client::FendermintClient;
+    use fvm_ipld_encoding::strict_bytes::ByteBuf;
+    use fvm_shared::address::Address;
+    use tendermint_rpc::{Method, MockClient, MockRequestMethodMatcher};
+    use warp::Reply;
+
+    use crate::cmd::objects::handlers::objectstore::handle_object_download;
+    use crate::cmd::objects::rejection::handle_rejection;
+    use crate::cmd::objects::testing::{abci_query_response, raw_cid, MemoryContentStore};
+    use crate::cmd::objects::HeightQuery;
+
+    async fn download_fetched(
+        content: &[u8],
+        store: MemoryContentStore,
+        fetcher: ContentFetcher,
+    ) -> (StatusCode, Option<String>, bytes::Bytes) {
+        let object = Object {
+            cid: ByteBuf(raw_cid(content).to_bytes()),
+            size: content.len() as u64,
+            resolved: true,
+            resolved_root: None,
+            failed: false,
+            kind: ObjectKind::External,
+            data: None,
+            metadata: HashMap::new(),
+            created_at: 0,
+        };
+        let matcher = MockRequestMethodMatcher::default()
+            .map(Method::AbciQuery, Ok(abci_query_response(&Some(&object))));
+        let client = FendermintClient::new(MockClient::new(matcher).0);
+        let response = match handle_object_download(
+            Address::new_id(90),
+            warp::test::request()
+                .path("/foo")
+                .filter(&warp::path::tail())
+                .await
+                .unwrap(),
+            "GET".to_string(),
+            None,
+            HeightQuery { height: None },
+            client,
+            store,
+            fetcher,
+        )
+        .await
+        {
+            Ok(reply) => reply.into_response(),
+            Err(e) => handle_rejection(e, "").into_response(),
+        };
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get("Retry-After")
+            .map(|v| v.to_str().unwrap().to_string());
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        (status, retry_after, body)
+    }
+
+    fn test_fetcher() -> ContentFetcher {
+        ContentFetcher::new(FetchConfig {
+            attempts: 3,
+            retry_after: Duration::from_millis(10),
+            attempt_timeout: Duration::from_secs(1),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_handle_object_download_fetches_missing_content() {
+        let content = b"hello world";
+        let store = MemoryContentStore::default();
+        store
+            .network
+            .lock()
+            .unwrap()
+            .insert(raw_cid(content), content.to_vec());
+        let fetcher = test_fetcher();
+
+        // The content isn't stored yet, so the client is told to come back for it.
+        let (status, retry_after, _) =
+            download_fetched(content, store.clone(), fetcher.clone()).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        assert_eq!(retry_after.as_deref(), Some("1"));
+
+        let mut polls = 0;
+        let body = loop {
+            let (status, _, body) = download_fetched(content, store.clone(), fetcher.clone()).await;
+            if status != StatusCode::ACCEPTED {
+                assert_eq!(status, StatusCode::OK);
+                break body;
+            }
+            polls += 1;
+            assert!(polls < 100, "content never fetched");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        };
+        assert_eq!(body, content.as_ref());
+        assert!(fetcher.fetches.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_handle_object_download_unavailable_content() {
+        let content = b"hello world";
+        let store = MemoryContentStore::default();
+        let fetcher = test_fetcher();
+
+        let (status, _, _) = download_fetched(content, store.clone(), fetcher.clone()).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+
+        // Once every attempt failed, the content is reported as not found.
+        let mut polls = 0;
+        loop {
+            let (status, _, _) = download_fetched(content, store.clone(), fetcher.clone()).await;
+            if status != StatusCode::ACCEPTED {
+                assert_eq!(status, StatusCode::NOT_FOUND);
+                break;
+            }
+            polls += 1;
+            assert!(polls < 100, "content fetch never gave up");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // Without fetching it again, even once it's on the network.
+        store
+            .network
+            .lock()
+            .unwrap()
+            .insert(raw_cid(content), content.to_vec());
+        let (status, _, _) = download_fetched(content, store.clone(), fetcher).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(!store.has(raw_cid(content)).await.unwrap());
+    }
+}
@@ -0,0 +1,1152 @@
+// Copyright 2024 Textile
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    num::ParseIntError,
+};
+
+use anyhow::anyhow;
+use base64::{engine::general_purpose, Engine};
+use bytes::Buf;
+use cid::Cid;
+use fendermint_actor_accumulator::{State as AccumulatorState, BIT_WIDTH as ACCUMULATOR_BIT_WIDTH};
+use fendermint_actor_machine::Kind;
+use fendermint_rpc::{
+    export::{import_batches, ExportHeader, ExportWriter, DEFAULT_IMPORT_BATCH_SIZE},
+    proxy::{
+        AccumulatorDiffResponse, AccumulatorMetadataResponse, AccumulatorSummaryResponse,
+        ImportQuery, ImportResponse, IntegrityResponse, LeafProofResponse, LeafStatusResponse,
+        ListedLeaf,
+    },
+    QueryClient,
+};
+use fendermint_vm_message::query::FvmQueryHeight;
+use futures_util::{StreamExt, TryStreamExt};
+use fvm_shared::address::Address;
+use warp::{http::HeaderValue, hyper::body::Body, Rejection, Reply};
+
+use super::{machine_metadata, object_response};
+use crate::cmd::objects::query::{
+    acc_get, acc_get_cid, acc_get_with_proof, acc_peaks, acc_root, acc_verify_integrity,
+    machine_get_metadata,
+};
+use crate::cmd::objects::rejection::{call_rejection, BadRequest, NotAcceptable, NotFound};
+use crate::cmd::objects::store::ContentStore;
+use crate::cmd::objects::{AccGetQuery, DiffQuery, HeightQuery, RootsQuery};
+
+/// Maximum number of heights in a single accumulator roots query.
+const MAX_ROOT_HEIGHTS: usize = 100;
+
+/// Maximum number of leaves in a single accumulator diff.
+const MAX_DIFF_LEAVES: u64 = 1000;
+
+/// Maximum number of accumulator leaves read from the node at once.
+const MAX_CONCURRENT_LEAF_READS: usize = 32;
+
+pub async fn handle_acc_metadata<F: QueryClient + Clone + Send + Sync>(
+    address: Address,
+    height_query: HeightQuery,
+    client: F,
+) -> Result<impl Reply, Rejection> {
+    let height = height_query
+        .height
+        .unwrap_or(FvmQueryHeight::Committed.into());
+    let (machine, state) =
+        machine_metadata::<_, AccumulatorState>(client, address, height, Kind::Accumulator).await?;
+    let state = state.value;
+
+    Ok(warp::reply::json(&AccumulatorMetadataResponse {
+        machine,
+        bit_width: ACCUMULATOR_BIT_WIDTH,
+        leaf_count: state.leaf_count,
+        leaf_codec: state.leaf_codec,
+        root_scheme: state.root_scheme,
+        max_pushes_per_epoch: state.max_pushes_per_epoch,
+        dedup_window: state.dedup_window,
+    }))
+}
+
+/// Get the root, leaf count and peaks of an accumulator in one request.
+///
+/// The state is read first to pin the height, so that the root and peaks queried after it
+/// agree with the count even if a block is committed meanwhile.
+pub async fn handle_acc_summary<F: QueryClient + Clone + Send + Sync>(
+    address: Address,
+    height_query: HeightQuery,
+    client: F,
+) -> Result<impl Reply, Rejection> {
+    let height = height_query
+        .height
+        .unwrap_or(FvmQueryHeight::Committed.into());
+    let (_, state) =
+        machine_metadata::<_, AccumulatorState>(client.clone(), address, height, Kind::Accumulator)
+            .await?;
+    let height = state.height.value();
+    let (root, peaks) = futures_util::future::try_join(
+        acc_root(client.clone(), address, height),
+        acc_peaks(client, address, height),
+    )
+    .await
+    .map_err(|e| call_rejection(e, "accumulator summary error"))?;
+
+    Ok(warp::reply::json(&AccumulatorSummaryResponse {
+        height,
+        root: root.to_string(),
+        count: state.value.leaf_count,
+        peaks: peaks.iter().map(Cid::to_string).collect(),
+    }))
+}
+
+/// Check the leaf counts of an accumulator against its peaks, for operators to detect a
+/// corrupt state. A failed check is reported in the response, not as an error.
+pub async fn handle_acc_verify<F: QueryClient + Clone + Send + Sync>(
+    address: Address,
+    height_query: HeightQuery,
+    client: F,
+) -> Result<impl Reply, Rejection> {
+    let height = height_query
+        .height
+        .unwrap_or(FvmQueryHeight::Committed.into());
+    let metadata = machine_get_metadata(client.clone(), address, height)
+        .await
+        .map_err(|e| call_rejection(e, "machine metadata error"))?;
+    if metadata.kind != Kind::Accumulator {
+        return Err(Rejection::from(BadRequest {
+            message: format!("machine {} is not an {}", address, Kind::Accumulator),
+        }));
+    }
+    let error = acc_verify_integrity(client, address, height)
+        .await
+        .map_err(|e| call_rejection(e, "accumulator integrity error"))?;
+
+    Ok(warp::reply::json(&IntegrityResponse {
+        ok: error.is_none(),
+        error,
+    }))
+}
+
+/// How an accumulator leaf is returned, as negotiated with the `Accept` header.
+#[derive(Debug, PartialEq)]
+enum LeafFormat {
+    /// The leaf bytes as they were pushed.
+    Raw,
+    /// The leaf decoded from DAG-CBOR and serialized as JSON.
+    Json,
+}
+
+/// Pick the leaf format from the first acceptable media type, defaulting to raw bytes.
+fn leaf_format(accept: Option<&str>) -> Option<LeafFormat> {
+    let Some(accept) = accept else {
+        return Some(LeafFormat::Raw);
+    };
+    accept.split(',').find_map(|media_type| {
+        match media_type.split(';').next().unwrap_or_default().trim() {
+            "application/json" => Some(LeafFormat::Json),
+            "application/octet-stream" | "application/*" | "*/*" => Some(LeafFormat::Raw),
+            _ => None,
+        }
+    })
+}
+
+/// Decode a leaf that is a CID, either in its binary or its string form.
+fn leaf_cid(leaf: &[u8]) -> Option<Cid> {
+    if let Ok(cid) = Cid::try_from(leaf) {
+        // Opaque bytes can start like a CID, so they have to be one in full.
+        if cid.to_bytes() == leaf {
+            return Some(cid);
+        }
+    }
+    std::str::from_utf8(leaf).ok()?.trim().parse().ok()
+}
+
+pub async fn handle_acc_get<F: QueryClient + Send + Sync, S: ContentStore>(
+    address: Address,
+    index: u64,
+    accept: Option<String>,
+    range: Option<String>,
+    query: AccGetQuery,
+    client: F,
+    store: S,
+) -> Result<warp::reply::Response, Rejection> {
+    let height = query.height.unwrap_or(FvmQueryHeight::Committed.into());
+    if query.proof {
+        let ret = acc_get_with_proof(client, address, index, height)
+            .await
+            .map_err(|e| call_rejection(e, "accumulator get error"))?
+            .ok_or_else(|| Rejection::from(NotFound))?;
+        return Ok(warp::reply::json(&LeafProofResponse::from(ret)).into_response());
+    }
+
+    let format = leaf_format(accept.as_deref()).ok_or_else(|| Rejection::from(NotAcceptable))?;
+    let leaf = acc_get(client, address, index, height)
+        .await
+        .map_err(|e| call_rejection(e, "accumulator get error"))?
+        .ok_or_else(|| Rejection::from(NotFound))?;
+
+    if query.resolve {
+        if let Some(cid) = leaf_cid(&leaf) {
+            let object_range = store.get(cid, range).await.map_err(|e| {
+                Rejection::from(BadRequest {
+                    message: format!("failed to fetch object {}", e),
+                })
+            })?;
+            return Ok(object_response(object_range));
+        }
+    }
+
+    match format {
+        LeafFormat::Raw => Ok(leaf.into_response()),
+        LeafFormat::Json => {
+            let value = fvm_ipld_encoding::from_slice::<serde_json::Value>(&leaf)
+                .map_err(|_| Rejection::from(NotAcceptable))?;
+            Ok(warp::reply::json(&value).into_response())
+        }
+    }
+}
+
+/// Report the CID of an accumulator leaf and whether its content has been resolved.
+///
+/// The resolver's vote tally lives in the node and isn't queryable, but resolved content is
+/// pinned in IPFS, so the leaf counts as resolved once its CID is pinned by our IPFS node.
+pub async fn handle_acc_leaf_status<F: QueryClient + Send + Sync, S: ContentStore>(
+    address: Address,
+    index: u64,
+    height_query: HeightQuery,
+    client: F,
+    store: S,
+) -> Result<impl Reply, Rejection> {
+    let height = height_query
+        .height
+        .unwrap_or(FvmQueryHeight::Committed.into());
+    let leaf = acc_get_cid(client, address, index, height)
+        .await
+        .map_err(|e| call_rejection(e, "accumulator get error"))?
+        .ok_or_else(|| Rejection::from(NotFound))?;
+    let resolved = store.has(leaf).await.map_err(|e| {
+        Rejection::from(BadRequest {
+            message: format!("failed to check object {}: {}", leaf, e),
+        })
+    })?;
+
+    Ok(warp::reply::json(&LeafStatusResponse {
+        index,
+        leaf,
+        resolved,
+    }))
+}
+
+/// Stream all the leaves of an accumulator as an export file, see [`fendermint_rpc::export`].
+///
+/// Every leaf is read at the height of the exported root, so the export can be replayed into
+/// an accumulator with the same root.
+pub async fn handle_acc_export<F: QueryClient + Clone + Send + Sync + 'static>(
+    address: Address,
+    height_query: HeightQuery,
+    client: F,
+) -> Result<warp::reply::Response, Rejection> {
+    let height = height_query
+        .height
+        .unwrap_or(FvmQueryHeight::Committed.into());
+    let (_, state) =
+        machine_metadata::<_, AccumulatorState>(client.clone(), address, height, Kind::Accumulator)
+            .await?;
+    let height = state.height.value();
+    let state = state.value;
+    let root = acc_root(client.clone(), address, height)
+        .await
+        .map_err(|e| call_rejection(e, "accumulator root error"))?;
+
+    let leaf_count = state.leaf_count;
+    let header = ExportHeader {
+        leaf_count,
+        root,
+        leaf_codec: state.leaf_codec,
+        root_scheme: state.root_scheme,
+    };
+    let writer = ExportWriter::new(header, Vec::new()).map_err(|e| {
+        Rejection::from(BadRequest {
+            message: format!("failed to start export: {}", e),
+        })
+    })?;
+
+    // Each chunk is whatever was written since the last one, starting with the header.
+    let chunks = futures_util::stream::try_unfold((Some(writer), 0u64), move |(writer, index)| {
+        let client = client.clone();
+        async move {
+            let Some(mut writer) = writer else {
+                return Ok::<_, anyhow::Error>(None);
+            };
+            if index == leaf_count {
+                return Ok(Some((writer.finish()?, (None, index))));
+            }
+            let leaf = acc_get(client, address, index, height)
+                .await?
+                .ok_or_else(|| anyhow!("leaf {} not found", index))?;
+            writer.write_leaf(&leaf)?;
+            let chunk = std::mem::take(writer.get_mut());
+            Ok(Some((chunk, (Some(writer), index + 1))))
+        }
+    });
+
+    let mut response = warp::reply::Response::new(Body::wrap_stream(chunks));
+    response.headers_mut().insert(
+        "Content-Type",
+        HeaderValue::from_static("application/vnd.ipld.car"),
+    );
+    Ok(response)
+}
+
+/// Verify an export, see [`fendermint_rpc::export`], and split it into `ImportLeaves` batches.
+///
+/// The proxy holds no keys, so the batches are returned for the client to sign and send in
+/// order. Each batch carries the root the accumulator must have after it, so the actor rejects
+/// a batch that doesn't extend the accumulator as exported.
+pub async fn handle_acc_import<F: QueryClient + Clone + Send + Sync>(
+    address: Address,
+    query: ImportQuery,
+    body: bytes::Bytes,
+    client: F,
+) -> Result<impl Reply, Rejection> {
+    let force = query.force.unwrap_or_default();
+    let batch_size = query
+        .batch_size
+        .map_or(DEFAULT_IMPORT_BATCH_SIZE, |size| size as usize);
+
+    let (_, state) = machine_metadata::<_, AccumulatorState>(
+        client,
+        address,
+        FvmQueryHeight::Committed.into(),
+        Kind::Accumulator,
+    )
+    .await?;
+    if state.value.leaf_count > 0 && !force {
+        return Err(Rejection::from(BadRequest {
+            message: format!(
+                "accumulator {} has {} leaves; set force to replace them",
+                address, state.value.leaf_count
+            ),
+        }));
+    }
+
+    let (header, batches) = import_batches(body.reader(), batch_size, force).map_err(|e| {
+        Rejection::from(BadRequest {
+            message: format!("invalid export: {}", e),
+        })
+    })?;
+    let batches = batches
+        .iter()
+        .map(|params| {
+            fvm_ipld_encoding::to_vec(params).map(|b| general_purpose::STANDARD.encode(b))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| {
+            Rejection::from(BadRequest {
+                message: format!("failed to encode import batches: {}", e),
+            })
+        })?;
+
+    Ok(warp::reply::json(&ImportResponse {
+        leaf_count: header.leaf_count,
+        root: header.root,
+        batches,
+    }))
+}
+
+/// Parse a comma-separated list of heights, dropping duplicates.
+fn parse_heights(heights: &str) -> Result<BTreeSet<u64>, ParseIntError> {
+    heights.split(',').map(|h| h.trim().parse()).collect()
+}
+
+/// Get the roots of an accumulator at several heights, querying them concurrently.
+pub async fn handle_acc_roots<F: QueryClient + Clone + Send + Sync>(
+    address: Address,
+    roots_query: RootsQuery,
+    client: F,
+) -> Result<impl Reply, Rejection> {
+    let heights = parse_heights(&roots_query.heights).map_err(|e| {
+        Rejection::from(BadRequest {
+            message: format!("invalid heights: {}", e),
+        })
+    })?;
+    if heights.len() > MAX_ROOT_HEIGHTS {
+        return Err(Rejection::from(BadRequest {
+            message: format!("at most {} heights can be queried", MAX_ROOT_HEIGHTS),
+        }));
+    }
+
+    let roots = futures_util::future::try_join_all(
+        heights
+            .iter()
+            .map(|height| acc_root(client.clone(), address, *height)),
+    )
+    .await
+    .map_err(|e| call_rejection(e, "accumulator root error"))?;
+
+    let roots: BTreeMap<u64, String> = heights
+        .into_iter()
+        .zip(roots.iter().map(|root| root.to_string()))
+        .collect();
+    Ok(warp::reply::json(&roots))
+}
+
+/// Get the leaves appended to an accumulator after `from_height`, up to `to_height`.
+///
+/// The leaf counts at both heights delimit the diff, whose leaves are read at `to_height`.
+/// Indexers can catch up with it from the last height they processed.
+pub async fn handle_acc_diff<F: QueryClient + Clone + Send + Sync>(
+    address: Address,
+    diff_query: DiffQuery,
+    client: F,
+) -> Result<impl Reply, Rejection> {
+    let DiffQuery {
+        from_height,
+        to_height,
+    } = diff_query;
+    // Height 0 would query the last committed state instead.
+    if from_height == 0 {
+        return Err(Rejection::from(BadRequest {
+            message: "from_height must be positive".to_string(),
+        }));
+    }
+    if to_height < from_height {
+        return Err(Rejection::from(BadRequest {
+            message: format!(
+                "to_height {} is before from_height {}",
+                to_height, from_height
+            ),
+        }));
+    }
+
+    let (_, from) = machine_metadata::<_, AccumulatorState>(
+        client.clone(),
+        address,
+        from_height,
+        Kind::Accumulator,
+    )
+    .await?;
+    let (_, to) = machine_metadata::<_, AccumulatorState>(
+        client.clone(),
+        address,
+        to_height,
+        Kind::Accumulator,
+    )
+    .await?;
+    let from_leaf_count = from.value.leaf_count;
+    let to_leaf_count = to.value.leaf_count.max(from_leaf_count);
+    if to_leaf_count - from_leaf_count > MAX_DIFF_LEAVES {
+        return Err(Rejection::from(BadRequest {
+            message: format!(
+                "{} leaves were added between the heights, at most {} can be diffed at once",
+                to_leaf_count - from_leaf_count,
+                MAX_DIFF_LEAVES
+            ),
+        }));
+    }
+
+    let height = to.height.value();
+    let leaves = futures_util::stream::iter(from_leaf_count..to_leaf_count)
+        .map(|index| {
+            let client = client.clone();
+            async move {
+                let leaf = acc_get(client, address, index, height)
+                    .await?
+                    .ok_or_else(|| anyhow!("leaf {} not found", index))?;
+                Ok::<_, anyhow::Error>(ListedLeaf {
+                    index,
+                    leaf: general_purpose::STANDARD.encode(leaf),
+                })
+            }
+        })
+        .buffered(MAX_CONCURRENT_LEAF_READS)
+        .try_collect::<Vec<_>>()
+        .await
+        .map_err(|e| call_rejection(e, "accumulator get error"))?;
+
+    Ok(warp::reply::json(&AccumulatorDiffResponse {
+        from_leaf_count,
+        to_leaf_count,
+        leaves,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    use cid::multihash::{Code, MultihashDigest};
+    use fendermint_actor_accumulator::{LeafCodec, RootScheme};
+    use fendermint_actor_machine::{Metadata as MachineMetadata, WriteAccess};
+    use fendermint_rpc::{client::FendermintClient, proxy::MachineMetadataResponse};
+    use fvm_ipld_blockstore::MemoryBlockstore;
+    use serde::Serialize;
+    use tendermint_rpc::{Method, MockClient, MockRequestMethodMatcher};
+    use warp::{http::StatusCode, Filter};
+
+    use crate::cmd::objects::handlers::objectstore::handle_os_metadata;
+    use crate::cmd::objects::testing::{
+        abci_query_response, acc_client, raw_cid, MachineClient, MemoryContentStore,
+    };
+
+    /// The root an accumulator is mocked to have at `height`.
+    fn root_at(height: u64) -> Cid {
+        Cid::new_v1(0x55, Code::Blake2b256.digest(&height.to_be_bytes()))
+    }
+
+    /// Serve a CometBFT endpoint which answers every query with the root at the queried height.
+    fn spawn_roots_comet() -> FendermintClient {
+        let comet = warp::post()
+            .and(warp::body::json())
+            .map(|req: serde_json::Value| {
+                let height = &req["params"]["height"];
+                let height = height
+                    .as_u64()
+                    .or_else(|| height.as_str().and_then(|h| h.parse().ok()))
+                    .unwrap();
+                abci_query_response(&root_at(height))
+            });
+        let (comet_addr, comet_server) = warp::serve(comet).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(comet_server);
+
+        let comet_url = format!("http://{comet_addr}").parse().unwrap();
+        FendermintClient::new_http(comet_url, None).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_handle_acc_roots() {
+        let client = spawn_roots_comet();
+        let response = handle_acc_roots(
+            Address::new_actor("t2mnd5jkuvmsaf457ympnf3monalh3vothdd5njoy".as_bytes()),
+            RootsQuery {
+                heights: "3, 1,2,3".to_string(),
+            },
+            client,
+        )
+        .await
+        .unwrap()
+        .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let roots: BTreeMap<u64, String> = serde_json::from_slice(&body).unwrap();
+        let expected: BTreeMap<u64, String> = [1, 2, 3]
+            .into_iter()
+            .map(|h| (h, root_at(h).to_string()))
+            .collect();
+        assert_eq!(roots, expected);
+    }
+
+    #[tokio::test]
+    async fn test_handle_acc_roots_invalid_heights() {
+        let address = Address::new_actor("t2mnd5jkuvmsaf457ympnf3monalh3vothdd5njoy".as_bytes());
+        let too_many = (1..=MAX_ROOT_HEIGHTS as u64 + 1)
+            .map(|h| h.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        for heights in ["", "1,x", too_many.as_str()] {
+            let err = handle_acc_roots(
+                address,
+                RootsQuery {
+                    heights: heights.to_string(),
+                },
+                spawn_roots_comet(),
+            )
+            .await
+            .map(Reply::into_response)
+            .unwrap_err();
+            assert!(err.find::<BadRequest>().is_some());
+        }
+    }
+
+    async fn acc_get(
+        leaf: Vec<u8>,
+        accept: Option<&str>,
+    ) -> Result<warp::reply::Response, Rejection> {
+        acc_get_resolved(leaf, accept, false, MemoryContentStore::default()).await
+    }
+
+    async fn acc_get_resolved(
+        leaf: Vec<u8>,
+        accept: Option<&str>,
+        resolve: bool,
+        store: MemoryContentStore,
+    ) -> Result<warp::reply::Response, Rejection> {
+        let matcher = MockRequestMethodMatcher::default()
+            .map(Method::AbciQuery, Ok(abci_query_response(&Some(leaf))));
+        let client = FendermintClient::new(MockClient::new(matcher).0);
+        handle_acc_get(
+            Address::new_actor("t2mnd5jkuvmsaf457ympnf3monalh3vothdd5njoy".as_bytes()),
+            0,
+            accept.map(String::from),
+            None,
+            AccGetQuery {
+                height: Some(1),
+                proof: false,
+                resolve,
+            },
+            client,
+            store,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_handle_acc_get_raw_leaf() {
+        let leaf = vec![0xff, 0xfe, 0xfd];
+
+        let response = acc_get(leaf.clone(), Some("application/octet-stream"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body.to_vec(), leaf);
+
+        // Not DAG-CBOR, so it can't be returned as JSON.
+        let err = acc_get(leaf, Some("application/json")).await.unwrap_err();
+        assert!(err.find::<NotAcceptable>().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_handle_acc_get_cbor_leaf() {
+        #[derive(Serialize)]
+        struct Reading {
+            sensor: String,
+            value: u64,
+        }
+        let leaf = fvm_ipld_encoding::to_vec(&Reading {
+            sensor: "sensor-1".to_string(),
+            value: 42,
+        })
+        .unwrap();
+
+        let response = acc_get(leaf.clone(), Some("application/octet-stream"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body.to_vec(), leaf);
+
+        let response = acc_get(leaf, Some("application/json")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({"sensor": "sensor-1", "value": 42})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handle_acc_get_with_proof() {
+        let store = MemoryBlockstore::default();
+        let mut state = fendermint_actor_accumulator::State::new(
+            &store,
+            Address::new_id(100),
+            WriteAccess::OnlyOwner,
+        )
+        .unwrap();
+        for i in 0..5u8 {
+            state.push_bytes(&store, vec![i; 3]).unwrap();
+        }
+        let ret = state.get_with_proof(&store, 2).unwrap();
+
+        let matcher = MockRequestMethodMatcher::default()
+            .map(Method::AbciQuery, Ok(abci_query_response(&ret)));
+        let client = FendermintClient::new(MockClient::new(matcher).0);
+        let response = handle_acc_get(
+            Address::new_actor("t2mnd5jkuvmsaf457ympnf3monalh3vothdd5njoy".as_bytes()),
+            2,
+            None,
+            None,
+            AccGetQuery {
+                height: Some(1),
+                proof: true,
+                resolve: false,
+            },
+            client,
+            MemoryContentStore::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let res: LeafProofResponse = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(res.leaf_bytes().unwrap(), vec![2; 3]);
+        assert_eq!(res.leaf_count, 5);
+        assert_eq!(res.root, state.get_root(&store).unwrap());
+        assert!(fendermint_actor_accumulator::verify_proof(
+            &res.proof(),
+            &res.root,
+            fendermint_actor_accumulator::HASH_CODE,
+            state.root_scheme
+        )
+        .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_handle_acc_get_resolve_cid_leaf() {
+        let content = b"content behind the leaf".to_vec();
+        let cid = raw_cid(&content);
+        let store = MemoryContentStore::default();
+        store.objects.lock().unwrap().insert(cid, content.clone());
+
+        for leaf in [cid.to_bytes(), cid.to_string().into_bytes()] {
+            let response = acc_get_resolved(leaf.clone(), None, true, store.clone())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = warp::hyper::body::to_bytes(response.into_body())
+                .await
+                .unwrap();
+            assert_eq!(body.to_vec(), content);
+
+            // Without resolving, the leaf itself is returned.
+            let response = acc_get_resolved(leaf.clone(), None, false, store.clone())
+                .await
+                .unwrap();
+            let body = warp::hyper::body::to_bytes(response.into_body())
+                .await
+                .unwrap();
+            assert_eq!(body.to_vec(), leaf);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handle_acc_get_resolve_opaque_leaf() {
+        let leaf = vec![0xff, 0xfe, 0xfd];
+
+        let response = acc_get_resolved(leaf.clone(), None, true, MemoryContentStore::default())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body.to_vec(), leaf);
+    }
+
+    #[tokio::test]
+    async fn test_handle_acc_get_unsupported_accept() {
+        let err = acc_get(vec![1, 2, 3], Some("text/html")).await.unwrap_err();
+        assert!(err.find::<NotAcceptable>().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_handle_acc_metadata() {
+        let owner = Address::new_id(100);
+        let store = MemoryBlockstore::default();
+        let mut state = AccumulatorState::new(&store, owner, WriteAccess::OnlyOwner).unwrap();
+        state.set_leaf_codec(LeafCodec::Raw).unwrap();
+        state.set_max_pushes_per_epoch(Some(10)).unwrap();
+        let client = MachineClient::new(
+            store,
+            &state,
+            MachineMetadata {
+                kind: Kind::Accumulator,
+                owner,
+                write_access: WriteAccess::OnlyOwner,
+                frozen: true,
+                metadata: HashMap::from([("name".to_string(), "events".to_string())]),
+            },
+        );
+
+        let address = Address::new_id(1000);
+        let response = handle_acc_metadata(address, HeightQuery { height: None }, client.clone())
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let metadata: AccumulatorMetadataResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            metadata,
+            AccumulatorMetadataResponse {
+                machine: MachineMetadataResponse {
+                    kind: "accumulator".to_string(),
+                    owner: owner.to_string(),
+                    write_access: "onlyowner".to_string(),
+                    frozen: true,
+                    metadata: HashMap::from([("name".to_string(), "events".to_string())]),
+                },
+                bit_width: ACCUMULATOR_BIT_WIDTH,
+                leaf_count: 0,
+                leaf_codec: LeafCodec::Raw,
+                root_scheme: RootScheme::Promoted,
+                max_pushes_per_epoch: Some(10),
+                dedup_window: 0,
+            }
+        );
+
+        // The accumulator is not an object store.
+        let err = handle_os_metadata(address, HeightQuery { height: None }, client)
+            .await
+            .map(Reply::into_response)
+            .unwrap_err();
+        assert!(err.find::<BadRequest>().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_handle_acc_verify() {
+        let owner = Address::new_id(100);
+        let store = MemoryBlockstore::default();
+        let mut state = AccumulatorState::new(&store, owner, WriteAccess::OnlyOwner).unwrap();
+        for i in 0..3u8 {
+            state.push_bytes(&store, vec![i]).unwrap();
+        }
+        let mut desynced = state.clone();
+        desynced.leaf_count = 4;
+        let client = MachineClient::new(
+            store,
+            &state,
+            MachineMetadata {
+                kind: Kind::Accumulator,
+                owner,
+                write_access: WriteAccess::OnlyOwner,
+                frozen: false,
+                metadata: HashMap::new(),
+            },
+        )
+        .with_state_at(2, &desynced);
+
+        let address = Address::new_id(1000);
+        let verify = |height| {
+            let client = client.clone();
+            async move {
+                let response = handle_acc_verify(address, HeightQuery { height }, client)
+                    .await
+                    .unwrap()
+                    .into_response();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body = warp::hyper::body::to_bytes(response.into_body())
+                    .await
+                    .unwrap();
+                serde_json::from_slice::<IntegrityResponse>(&body).unwrap()
+            }
+        };
+        assert_eq!(
+            verify(Some(1)).await,
+            IntegrityResponse {
+                ok: true,
+                error: None,
+            }
+        );
+        assert_eq!(
+            verify(Some(2)).await,
+            IntegrityResponse {
+                ok: false,
+                error: Some("expected 1 peaks for 4 leaves; found 2".to_string()),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handle_acc_export() {
+        let owner = Address::new_id(100);
+        let store = MemoryBlockstore::default();
+        let mut state = AccumulatorState::new(&store, owner, WriteAccess::OnlyOwner).unwrap();
+        state.set_leaf_codec(LeafCodec::Raw).unwrap();
+        for i in 0..11u8 {
+            state.push_bytes(&store, vec![i; 3]).unwrap();
+        }
+        let root = state.get_root(&store).unwrap();
+        let client = MachineClient::new(
+            store,
+            &state,
+            MachineMetadata {
+                kind: Kind::Accumulator,
+                owner,
+                write_access: WriteAccess::OnlyOwner,
+                frozen: false,
+                metadata: HashMap::new(),
+            },
+        );
+
+        let response =
+            handle_acc_export(Address::new_id(1000), HeightQuery { height: None }, client)
+                .await
+                .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get("Content-Type").unwrap(),
+            "application/vnd.ipld.car"
+        );
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+
+        // Replaying the export into a fresh accumulator rebuilds the same root.
+        let fresh = MemoryBlockstore::default();
+        let replayed = fendermint_rpc::export::replay_export(&fresh, owner, body.as_ref()).unwrap();
+        assert_eq!(replayed.leaf_count(), 11);
+        assert_eq!(replayed.get_root(&fresh).unwrap(), root);
+    }
+
+    /// Export `n` leaves pushed into a fresh accumulator, returning the export with its root.
+    fn acc_export(n: u8) -> (Vec<u8>, Cid) {
+        let store = MemoryBlockstore::default();
+        let mut state =
+            AccumulatorState::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let leaves = (0..n).map(|i| vec![i; 3]).collect::<Vec<_>>();
+        for leaf in &leaves {
+            state.push_bytes(&store, leaf.clone()).unwrap();
+        }
+        let header = ExportHeader {
+            leaf_count: state.leaf_count(),
+            root: state.get_root(&store).unwrap(),
+            leaf_codec: state.leaf_codec,
+            root_scheme: state.root_scheme,
+        };
+        let root = header.root;
+        let mut writer = ExportWriter::new(header, Vec::new()).unwrap();
+        for leaf in &leaves {
+            writer.write_leaf(leaf).unwrap();
+        }
+        (writer.finish().unwrap(), root)
+    }
+
+    #[tokio::test]
+    async fn test_handle_acc_diff() {
+        let owner = Address::new_id(100);
+        let address = Address::new_id(1000);
+        let store = MemoryBlockstore::default();
+        let mut state = AccumulatorState::new(&store, owner, WriteAccess::OnlyOwner).unwrap();
+        for leaf in ["a", "b"] {
+            state.push(&store, leaf.to_string()).unwrap();
+        }
+        // Two leaves up to height 10, and three more at height 20.
+        let client = acc_client(&state, store);
+        for leaf in ["c", "d", "e"] {
+            state.push(client.store.as_ref(), leaf.to_string()).unwrap();
+        }
+        let client = client.with_state_at(20, &state);
+        let diff = |from_height, to_height| {
+            handle_acc_diff(
+                address,
+                DiffQuery {
+                    from_height,
+                    to_height,
+                },
+                client.clone(),
+            )
+        };
+
+        let reply = diff(10, 20).await.unwrap();
+        let body = warp::hyper::body::to_bytes(reply.into_response().into_body())
+            .await
+            .unwrap();
+        let response: AccumulatorDiffResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response.from_leaf_count, 2);
+        assert_eq!(response.to_leaf_count, 5);
+        let leaves = response
+            .leaves
+            .iter()
+            .map(|leaf| (leaf.index, leaf.leaf_bytes().unwrap()))
+            .collect::<Vec<_>>();
+        let expected = ["c", "d", "e"]
+            .into_iter()
+            .enumerate()
+            .map(|(i, leaf)| (i as u64 + 2, fvm_ipld_encoding::to_vec(&leaf).unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(leaves, expected);
+
+        // Nothing was added between the same height.
+        let reply = diff(20, 20).await.unwrap();
+        let body = warp::hyper::body::to_bytes(reply.into_response().into_body())
+            .await
+            .unwrap();
+        let response: AccumulatorDiffResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response.from_leaf_count, 5);
+        assert!(response.leaves.is_empty());
+
+        for (from_height, to_height) in [(20, 10), (0, 10)] {
+            let err = diff(from_height, to_height)
+                .await
+                .map(Reply::into_response)
+                .unwrap_err();
+            assert!(err.find::<BadRequest>().is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handle_acc_summary() {
+        let owner = Address::new_id(100);
+        let address = Address::new_id(1000);
+        let store = MemoryBlockstore::default();
+        let mut state = AccumulatorState::new(&store, owner, WriteAccess::OnlyOwner).unwrap();
+        for leaf in ["a", "b"] {
+            state.push(&store, leaf.to_string()).unwrap();
+        }
+        // Two leaves by default, and five at height 20.
+        let client = acc_client(&state, store);
+        for leaf in ["c", "d", "e"] {
+            state.push(client.store.as_ref(), leaf.to_string()).unwrap();
+        }
+        let client = client.with_state_at(20, &state);
+
+        for (height, count) in [(Some(20), 5), (None, 2)] {
+            let reply = handle_acc_summary(address, HeightQuery { height }, client.clone())
+                .await
+                .unwrap();
+            let body = warp::hyper::body::to_bytes(reply.into_response().into_body())
+                .await
+                .unwrap();
+            let summary: AccumulatorSummaryResponse = serde_json::from_slice(&body).unwrap();
+            assert_eq!(summary.count, count);
+            let root = acc_root(client.clone(), address, summary.height)
+                .await
+                .unwrap();
+            assert_eq!(summary.root, root.to_string());
+            let peaks = acc_peaks(client.clone(), address, summary.height)
+                .await
+                .unwrap();
+            assert_eq!(summary.peaks.len(), count.count_ones() as usize);
+            assert_eq!(
+                summary.peaks,
+                peaks.iter().map(Cid::to_string).collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handle_acc_import() {
+        let owner = Address::new_id(100);
+        let (export, root) = acc_export(11);
+
+        let store = MemoryBlockstore::default();
+        let mut state = AccumulatorState::new(&store, owner, WriteAccess::OnlyOwner).unwrap();
+        let client = acc_client(&state, store);
+        let query = ImportQuery {
+            force: None,
+            batch_size: Some(4),
+        };
+        let reply = handle_acc_import(
+            Address::new_id(1000),
+            query.clone(),
+            export.clone().into(),
+            client,
+        )
+        .await
+        .unwrap();
+        let body = warp::hyper::body::to_bytes(reply.into_response().into_body())
+            .await
+            .unwrap();
+        let response: ImportResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response.leaf_count, 11);
+        assert_eq!(response.root, root);
+        assert_eq!(response.batches.len(), 3);
+
+        // Importing the batches in order rebuilds the exported accumulator.
+        let store = MemoryBlockstore::default();
+        for params in response.params().unwrap() {
+            state.import_leaves(&store, params).unwrap();
+        }
+        assert_eq!(state.get_root(&store).unwrap(), root);
+
+        // Replacing the leaves of a non-empty accumulator has to be forced.
+        let client = acc_client(&state, store);
+        let err = handle_acc_import(
+            Address::new_id(1000),
+            query,
+            export.clone().into(),
+            client.clone(),
+        )
+        .await
+        .unwrap_err();
+        assert!(err.find::<BadRequest>().is_some());
+        let query = ImportQuery {
+            force: Some(true),
+            batch_size: None,
+        };
+        handle_acc_import(Address::new_id(1000), query, export.into(), client)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_handle_acc_leaf_status() {
+        let address = Address::new_id(1000);
+        let store = MemoryBlockstore::default();
+        let mut state =
+            AccumulatorState::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        state.set_leaf_codec(LeafCodec::Raw).unwrap();
+        let content = b"hello world";
+        let cid = raw_cid(content);
+        state.push_cid(&store, cid).unwrap();
+        let client = acc_client(&state, store);
+        let content_store = MemoryContentStore::default();
+
+        let status = |index| {
+            let client = client.clone();
+            let content_store = content_store.clone();
+            async move {
+                let reply = handle_acc_leaf_status(
+                    address,
+                    index,
+                    HeightQuery { height: None },
+                    client,
+                    content_store,
+                )
+                .await?;
+                let body = warp::hyper::body::to_bytes(reply.into_response().into_body())
+                    .await
+                    .unwrap();
+                Ok::<_, Rejection>(serde_json::from_slice::<LeafStatusResponse>(&body).unwrap())
+            }
+        };
+
+        // The CID is on chain, but its content hasn't been resolved yet.
+        let response = status(0).await.unwrap();
+        assert_eq!(response.index, 0);
+        assert_eq!(response.leaf, cid);
+        assert!(!response.resolved);
+
+        // Once the content is resolved into IPFS, the leaf is reported as resolved.
+        content_store
+            .objects
+            .lock()
+            .unwrap()
+            .insert(cid, content.to_vec());
+        let response = status(0).await.unwrap();
+        assert_eq!(response.leaf, cid);
+        assert!(response.resolved);
+
+        let err = status(1).await.unwrap_err();
+        assert!(err.find::<NotFound>().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_handle_acc_import_corrupted() {
+        let (mut export, _) = acc_export(5);
+        // Flip a byte of the last leaf.
+        *export.last_mut().unwrap() ^= 1;
+
+        let store = MemoryBlockstore::default();
+        let state =
+            AccumulatorState::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        let client = acc_client(&state, store);
+        let err = handle_acc_import(
+            Address::new_id(1000),
+            ImportQuery::default(),
+            export.into(),
+            client,
+        )
+        .await
+        .unwrap_err();
+        assert!(err.find::<BadRequest>().is_some());
+    }
+}
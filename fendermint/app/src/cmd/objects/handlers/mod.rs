@@ -0,0 +1,655 @@
+// Copyright 2024 Textile
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+pub mod accumulator;
+pub mod objectstore;
+
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use base64::{engine::general_purpose, Engine};
+use fendermint_actor_machine::Kind;
+use fendermint_rpc::{
+    client::FendermintClient,
+    proxy::{
+        CanWriteResponse, ListedMachine, MachineMetadataResponse, MachinesQuery,
+        ResolverStatsResponse, SimulateRequest, SimulateResponse, TxStatus, Txn,
+    },
+    query::{CallError, QueryResponse},
+    QueryClient,
+};
+use fendermint_vm_message::{query::FvmQueryHeight, signed::SignedMessage};
+use fvm_shared::{address::Address, chainid::ChainID};
+use serde::de::DeserializeOwned;
+use tendermint_rpc::error::ErrorDetail;
+use warp::{
+    http::{HeaderMap, HeaderValue, StatusCode},
+    Rejection, Reply,
+};
+
+use super::query::{adm_list_metadata, machine_can_write, machine_get_metadata};
+use super::rejection::{call_rejection, is_upstream_error, BadRequest, NotFound, Upstream};
+use super::store::ObjectRange;
+use super::CanWriteQuery;
+
+pub async fn health() -> Result<impl Reply, Rejection> {
+    Ok(warp::reply::reply())
+}
+
+/// Stream the content of an object, as a partial response if only a range of it was fetched.
+fn object_response(object_range: ObjectRange) -> warp::reply::Response {
+    let mut response = warp::reply::Response::new(object_range.body);
+    let mut header_map = HeaderMap::new();
+    if object_range.len < object_range.size {
+        *response.status_mut() = StatusCode::PARTIAL_CONTENT;
+        header_map.insert(
+            "Content-Range",
+            HeaderValue::from_str(&format!(
+                "bytes {}-{}/{}",
+                object_range.start, object_range.end, object_range.len
+            ))
+            .unwrap(),
+        );
+    } else {
+        header_map.insert("Accept-Ranges", HeaderValue::from_str("bytes").unwrap());
+    }
+    header_map.insert("Content-Length", HeaderValue::from(object_range.len));
+    let headers = response.headers_mut();
+    headers.extend(header_map);
+    response
+}
+
+/// Decode a URL-safe base64 encoded signed message and verify its signature.
+fn decode_signed_message(chain_id: u64, msg: &str) -> Result<SignedMessage, Rejection> {
+    let signed_msg = general_purpose::URL_SAFE
+        .decode(msg)
+        .map_err(|e| anyhow!("failed to decode b64 encoded message: {}", e))
+        .and_then(|bytes| {
+            fvm_ipld_encoding::from_slice::<SignedMessage>(&bytes)
+                .map_err(|e| anyhow!("failed to deserialize signed message: {}", e))
+        })
+        .map_err(|e| {
+            Rejection::from(BadRequest {
+                message: e.to_string(),
+            })
+        })?;
+    signed_msg.verify(&ChainID::from(chain_id)).map_err(|e| {
+        Rejection::from(BadRequest {
+            message: e.to_string(),
+        })
+    })?;
+    Ok(signed_msg)
+}
+
+/// Run a signed write message as a read-only call, reporting whether it would succeed.
+///
+/// The message is neither broadcast nor does it need the next sequence of the sender: it runs
+/// at whatever sequence the sender has in the committed state, and nothing it does is kept.
+pub async fn handle_simulate<F: QueryClient + Send + Sync>(
+    request: SimulateRequest,
+    client: F,
+) -> Result<impl Reply, Rejection> {
+    let signed_msg = decode_signed_message(request.chain_id, &request.msg)?;
+    let mut message = signed_msg.message;
+    if !request.target.methods().contains(&message.method_num) {
+        return Err(Rejection::from(BadRequest {
+            message: format!(
+                "method {} doesn't match the {:?} target",
+                message.method_num, request.target
+            ),
+        }));
+    }
+    // A zero sequence runs the call at the sequence of the sender in the state.
+    message.sequence = 0;
+
+    let res = client
+        .call(message, FvmQueryHeight::Committed)
+        .await
+        .map_err(|e| call_rejection(e, "failed to simulate message"))?;
+    let deliver_tx = res.value;
+    let error = deliver_tx.code.is_err().then(|| {
+        let e = CallError::from(&deliver_tx);
+        if e.info.is_empty() {
+            format!("message aborted with exit code {}", e.exit_code)
+        } else {
+            e.info
+        }
+    });
+    Ok(warp::reply::json(&SimulateResponse {
+        would_succeed: error.is_none(),
+        gas_used: deliver_tx.gas_used,
+        error,
+    }))
+}
+
+/// Get the metadata of a machine of the expected kind, together with its decoded state
+/// and the height the state was read at.
+async fn machine_metadata<F: QueryClient + Clone + Send + Sync, T: DeserializeOwned + Send>(
+    client: F,
+    address: Address,
+    height: u64,
+    kind: Kind,
+) -> Result<(MachineMetadataResponse, QueryResponse<T>), Rejection> {
+    let metadata = machine_get_metadata(client.clone(), address, height)
+        .await
+        .map_err(|e| call_rejection(e, "machine metadata error"))?;
+    if metadata.kind != kind {
+        return Err(Rejection::from(BadRequest {
+            message: format!("machine {} is not an {}", address, kind),
+        }));
+    }
+    let res = client
+        .actor_state_as::<T>(&address, FvmQueryHeight::from(height))
+        .await
+        .map_err(|e| call_rejection(e, "machine state error"))?;
+    let state = QueryResponse {
+        height: res.height,
+        value: res.value.ok_or_else(|| Rejection::from(NotFound))?,
+    };
+
+    let metadata = MachineMetadataResponse {
+        kind: kind.to_string(),
+        owner: metadata.owner.to_string(),
+        write_access: metadata.write_access.to_string(),
+        frozen: metadata.frozen,
+        metadata: metadata.metadata,
+    };
+    Ok((metadata, state))
+}
+
+/// Check whether an address may currently write to a machine of the expected kind,
+/// taking its write access and whether it is frozen into account.
+pub async fn handle_can_write<F: QueryClient + Clone + Send + Sync>(
+    kind: Kind,
+    address: Address,
+    query: CanWriteQuery,
+    client: F,
+) -> Result<impl Reply, Rejection> {
+    let who = Address::from_str(&query.addr).map_err(|e| {
+        Rejection::from(BadRequest {
+            message: format!("invalid address {}: {}", query.addr, e),
+        })
+    })?;
+    let height = query.height.unwrap_or(FvmQueryHeight::Committed.into());
+    let metadata = machine_get_metadata(client.clone(), address, height)
+        .await
+        .map_err(|e| call_rejection(e, "machine metadata error"))?;
+    if metadata.kind != kind {
+        return Err(Rejection::from(BadRequest {
+            message: format!("machine {} is not an {}", address, kind),
+        }));
+    }
+    let can_write = machine_can_write(client, address, who, height)
+        .await
+        .map_err(|e| call_rejection(e, "machine can write error"))?;
+
+    Ok(warp::reply::json(&CanWriteResponse { can_write }))
+}
+
+/// List the machines of an owner, optionally only those of one kind.
+///
+/// The owner is required, since the proxy doesn't have an account of its own.
+pub async fn handle_machines_list<F: QueryClient + Send + Sync>(
+    machines_query: MachinesQuery,
+    client: F,
+) -> Result<impl Reply, Rejection> {
+    let owner = machines_query.owner.ok_or_else(|| {
+        Rejection::from(BadRequest {
+            message: "owner is required".to_string(),
+        })
+    })?;
+    let owner = Address::from_str(&owner).map_err(|e| {
+        Rejection::from(BadRequest {
+            message: format!("invalid owner address: {}", e),
+        })
+    })?;
+    let height = machines_query
+        .height
+        .unwrap_or(FvmQueryHeight::Committed.into());
+    let machines = adm_list_metadata(client, owner, height)
+        .await
+        .map_err(|e| call_rejection(e, "adm list error"))?;
+
+    let machines = machines
+        .into_iter()
+        .map(|m| ListedMachine {
+            kind: m.kind.to_string(),
+            address: m.address.to_string(),
+        })
+        .filter(|m| match &machines_query.kind {
+            Some(kind) => m.kind == *kind,
+            None => true,
+        })
+        .collect::<Vec<_>>();
+    Ok(warp::reply::json(&machines))
+}
+
+pub async fn handle_tx_status<C: tendermint_rpc::Client + Send + Sync>(
+    hash: String,
+    client: FendermintClient<C>,
+) -> Result<impl Reply, Rejection> {
+    let tx_hash = tendermint::Hash::from_str(&hash.to_uppercase()).map_err(|e| {
+        Rejection::from(BadRequest {
+            message: format!("invalid transaction hash: {}", e),
+        })
+    })?;
+
+    let txn = match client.underlying().tx(tx_hash, false).await {
+        Ok(res) => {
+            let status = if res.tx_result.code.is_ok() {
+                TxStatus::Committed
+            } else {
+                TxStatus::Failed
+            };
+            let info = if res.tx_result.info.is_empty() {
+                res.tx_result.log
+            } else {
+                res.tx_result.info
+            };
+            Txn {
+                hash: res.hash.to_string(),
+                status,
+                height: Some(res.height.value()),
+                gas_used: Some(res.tx_result.gas_used),
+                info: (!info.is_empty()).then_some(info),
+            }
+        }
+        // The node only knows about transactions once they are in a block.
+        Err(e) if is_tx_not_found(&e) => Txn {
+            hash: tx_hash.to_string(),
+            status: TxStatus::Pending,
+            height: None,
+            gas_used: None,
+            info: None,
+        },
+        Err(e) if is_upstream_error(&e) => {
+            return Err(Rejection::from(Upstream {
+                message: format!("transaction query error: {}", e),
+            }))
+        }
+        Err(e) => {
+            return Err(Rejection::from(BadRequest {
+                message: format!("transaction query error: {}", e),
+            }))
+        }
+    };
+
+    Ok(warp::reply::json(&txn))
+}
+
+fn is_tx_not_found(err: &tendermint_rpc::Error) -> bool {
+    match err.detail() {
+        ErrorDetail::Response(e) => e
+            .source
+            .data()
+            .is_some_and(|data| data.contains("not found")),
+        _ => false,
+    }
+}
+
+/// Get the settings and live stats of the IPFS Resolver from the node serving them.
+///
+/// The resolver runs in the node, so the stats are relayed from its stats server, see the
+/// `resolver.stats_listen` setting. Without its URL, the route is not found.
+pub async fn handle_resolver_stats(
+    http: reqwest::Client,
+    url: Option<reqwest::Url>,
+) -> Result<impl Reply, Rejection> {
+    let url = url.ok_or_else(|| Rejection::from(NotFound))?;
+    let stats = resolver_stats(&http, url).await.map_err(|e| {
+        Rejection::from(Upstream {
+            message: format!("failed to get resolver stats: {}", e),
+        })
+    })?;
+    Ok(warp::reply::json(&stats))
+}
+
+async fn resolver_stats(
+    http: &reqwest::Client,
+    url: reqwest::Url,
+) -> anyhow::Result<ResolverStatsResponse> {
+    let url = url.join("v1/resolver/stats")?;
+    let stats = http
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    use ethers::core::rand::rngs::StdRng;
+    use fendermint_actor_accumulator::State as AccumulatorState;
+    use fendermint_actor_machine::{Metadata as MachineMetadata, WriteAccess};
+    use fendermint_rpc::{
+        message::{GasParams, SignedMessageFactory},
+        proxy::SimulateTarget,
+    };
+    use fendermint_vm_actor_interface::adm::Metadata;
+    use fendermint_vm_message::chain::ChainMessage;
+    use fvm_ipld_blockstore::MemoryBlockstore;
+    use fvm_shared::econ::TokenAmount;
+    use tendermint_rpc::{Method, MockClient, MockRequestMethodMatcher};
+    use warp::Filter;
+
+    use crate::cmd::objects::testing::{
+        abci_query_response, raw_cid, tx_response, MachineClient, CALL_GAS_USED, TX_HASH,
+    };
+
+    async fn tx_status(matcher: MockRequestMethodMatcher) -> serde_json::Value {
+        let client = FendermintClient::new(MockClient::new(matcher).0);
+        let reply = handle_tx_status(TX_HASH.to_lowercase(), client)
+            .await
+            .unwrap();
+        let response = reply.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_handle_tx_status_pending() {
+        let matcher = MockRequestMethodMatcher::default().map(
+            Method::Tx,
+            Err(tendermint_rpc::Error::response(
+                tendermint_rpc::response_error::ResponseError::new(
+                    tendermint_rpc::response_error::Code::InternalError,
+                    Some(format!("tx ({TX_HASH}) not found")),
+                ),
+            )),
+        );
+        let txn = tx_status(matcher).await;
+        assert_eq!(txn["status"], "pending");
+        assert_eq!(txn["hash"], TX_HASH);
+        assert!(txn.get("height").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_handle_tx_status_committed() {
+        let matcher = MockRequestMethodMatcher::default().map(Method::Tx, Ok(tx_response(0, "")));
+        let txn = tx_status(matcher).await;
+        assert_eq!(txn["status"], "committed");
+        assert_eq!(txn["height"], 42);
+        assert_eq!(txn["gas_used"], 80);
+        assert!(txn.get("info").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_handle_tx_status_failed() {
+        let matcher = MockRequestMethodMatcher::default()
+            .map(Method::Tx, Ok(tx_response(1, "message failed")));
+        let txn = tx_status(matcher).await;
+        assert_eq!(txn["status"], "failed");
+        assert_eq!(txn["height"], 42);
+        assert_eq!(txn["info"], "message failed");
+    }
+
+    #[tokio::test]
+    async fn test_handle_machines_list() {
+        use fendermint_vm_actor_interface::adm::Kind;
+
+        let os = Address::new_actor(b"objectstore");
+        let acc = Address::new_actor(b"accumulator");
+        let machines = vec![
+            Metadata {
+                kind: Kind::ObjectStore,
+                address: os,
+            },
+            Metadata {
+                kind: Kind::Accumulator,
+                address: acc,
+            },
+        ];
+        let list = |kind: Option<&str>| {
+            let matcher = MockRequestMethodMatcher::default()
+                .map(Method::AbciQuery, Ok(abci_query_response(&machines)));
+            let client = FendermintClient::new(MockClient::new(matcher).0);
+            handle_machines_list(
+                MachinesQuery {
+                    owner: Some(Address::new_id(100).to_string()),
+                    kind: kind.map(String::from),
+                    height: Some(1),
+                },
+                client,
+            )
+        };
+
+        let response = list(None).await.unwrap().into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let listed: Vec<ListedMachine> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            listed,
+            vec![
+                ListedMachine {
+                    kind: "objectstore".to_string(),
+                    address: os.to_string(),
+                },
+                ListedMachine {
+                    kind: "accumulator".to_string(),
+                    address: acc.to_string(),
+                },
+            ]
+        );
+
+        let response = list(Some("accumulator")).await.unwrap().into_response();
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let listed: Vec<ListedMachine> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].address, acc.to_string());
+    }
+
+    #[tokio::test]
+    async fn test_handle_machines_list_requires_owner() {
+        let client = FendermintClient::new(MockClient::new(MockRequestMethodMatcher::default()).0);
+        let err = handle_machines_list(MachinesQuery::default(), client)
+            .await
+            .map(Reply::into_response)
+            .unwrap_err();
+        assert!(err.find::<BadRequest>().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_handle_simulate() {
+        let chain_id = ChainID::from(314159);
+        let sk = fendermint_crypto::SecretKey::random(&mut StdRng::from_entropy());
+        let mut owner = SignedMessageFactory::new_secp256k1(sk, 0, chain_id);
+        let sk = fendermint_crypto::SecretKey::random(&mut StdRng::from_entropy());
+        let mut stranger = SignedMessageFactory::new_secp256k1(sk, 0, chain_id);
+
+        let store = MemoryBlockstore::default();
+        let state =
+            AccumulatorState::new(&store, *owner.address(), WriteAccess::OnlyOwner).unwrap();
+        let client = MachineClient::new(
+            store,
+            &state,
+            MachineMetadata {
+                kind: Kind::Accumulator,
+                owner: *owner.address(),
+                write_access: WriteAccess::OnlyOwner,
+                frozen: false,
+                metadata: HashMap::new(),
+            },
+        );
+
+        let address = Address::new_id(1000);
+        let simulate = |factory: &mut SignedMessageFactory, target| {
+            let params = fendermint_actor_accumulator::PushCidParams {
+                cid: raw_cid(b"leaf"),
+            };
+            let gas_params = GasParams {
+                gas_limit: 10_000_000,
+                gas_fee_cap: TokenAmount::from_atto(0),
+                gas_premium: TokenAmount::from_atto(0),
+            };
+            let message = factory
+                .acc_push_cid(address, params, TokenAmount::from_atto(0), gas_params)
+                .unwrap();
+            let ChainMessage::Signed(signed) = message else {
+                panic!("expected a signed message");
+            };
+            let request = SimulateRequest::new(chain_id, &signed, target).unwrap();
+            handle_simulate(request, client.clone())
+        };
+        let response = |reply: warp::reply::Response| async move {
+            assert_eq!(reply.status(), StatusCode::OK);
+            let body = warp::hyper::body::to_bytes(reply.into_body())
+                .await
+                .unwrap();
+            serde_json::from_slice::<SimulateResponse>(&body).unwrap()
+        };
+
+        let reply = simulate(&mut owner, SimulateTarget::AccPush).await.unwrap();
+        assert_eq!(
+            response(reply.into_response()).await,
+            SimulateResponse {
+                would_succeed: true,
+                gas_used: CALL_GAS_USED,
+                error: None,
+            }
+        );
+
+        // Only the owner can push, which the simulation reports rather than rejects.
+        let reply = simulate(&mut stranger, SimulateTarget::AccPush)
+            .await
+            .unwrap();
+        let simulated = response(reply.into_response()).await;
+        assert!(!simulated.would_succeed);
+        assert_eq!(simulated.gas_used, CALL_GAS_USED);
+        assert_eq!(
+            simulated.error,
+            Some(format!("{} is not allowed to write", stranger.address()))
+        );
+
+        // The message has to be for the target.
+        let err = simulate(&mut owner, SimulateTarget::OsPut)
+            .await
+            .map(|_| ())
+            .unwrap_err();
+        assert!(err.find::<BadRequest>().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_handle_resolver_stats() {
+        let snapshot = fendermint_vm_ipfs_resolver::ipfs::ResolverStatsSnapshot {
+            retry_delay_secs: 10,
+            verify_before_vote: true,
+            in_flight: 2,
+            queued: 1,
+            resolved: 5,
+            failed: 1,
+            retried: 3,
+        };
+        let node = warp::path!("v1" / "resolver" / "stats")
+            .and(warp::get())
+            .map(move || warp::reply::json(&snapshot));
+        let (node_addr, node_server) = warp::serve(node).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(node_server);
+        let url = reqwest::Url::parse(&format!("http://{node_addr}")).unwrap();
+
+        let response = handle_resolver_stats(reqwest::Client::new(), Some(url))
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(
+            serde_json::from_slice::<ResolverStatsResponse>(&body).unwrap(),
+            ResolverStatsResponse {
+                retry_delay_secs: 10,
+                verify_before_vote: true,
+                in_flight: 2,
+                queued: 1,
+                resolved: 5,
+                failed: 1,
+                retried: 3,
+            }
+        );
+
+        // Without the node's stats server, there are no stats to relay.
+        let err = handle_resolver_stats(reqwest::Client::new(), None)
+            .await
+            .map(Reply::into_response)
+            .unwrap_err();
+        assert!(err.find::<NotFound>().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_handle_can_write() {
+        let owner = Address::new_id(100);
+        let stranger = Address::new_id(110);
+        let address = Address::new_id(1000);
+        let can_write = |client: MachineClient, kind: Kind, who: Address| async move {
+            let query = CanWriteQuery {
+                addr: who.to_string(),
+                height: None,
+            };
+            let response = handle_can_write(kind, address, query, client)
+                .await
+                .unwrap()
+                .into_response();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = warp::hyper::body::to_bytes(response.into_body())
+                .await
+                .unwrap();
+            serde_json::from_slice::<CanWriteResponse>(&body)
+                .unwrap()
+                .can_write
+        };
+
+        for (write_access, frozen, owner_can_write, stranger_can_write) in [
+            (WriteAccess::OnlyOwner, false, true, false),
+            (WriteAccess::Public, false, true, true),
+            (WriteAccess::OnlyOwner, true, false, false),
+            (WriteAccess::Public, true, false, false),
+        ] {
+            let store = MemoryBlockstore::default();
+            let state = AccumulatorState::new(&store, owner, write_access).unwrap();
+            let client = MachineClient::new(
+                store,
+                &state,
+                MachineMetadata {
+                    kind: Kind::Accumulator,
+                    owner,
+                    write_access,
+                    frozen,
+                    metadata: HashMap::new(),
+                },
+            );
+            assert_eq!(
+                can_write(client.clone(), Kind::Accumulator, owner).await,
+                owner_can_write
+            );
+            assert_eq!(
+                can_write(client.clone(), Kind::Accumulator, stranger).await,
+                stranger_can_write
+            );
+
+            // The accumulator is not an object store.
+            let query = CanWriteQuery {
+                addr: owner.to_string(),
+                height: None,
+            };
+            let err = handle_can_write(Kind::ObjectStore, address, query, client)
+                .await
+                .map(Reply::into_response)
+                .unwrap_err();
+            assert!(err.find::<BadRequest>().is_some());
+        }
+    }
+}
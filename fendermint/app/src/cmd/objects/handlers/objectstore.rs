@@ -0,0 +1,1893 @@
+// Copyright 2024 Textile
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use anyhow::anyhow;
+use async_tempfile::TempFile;
+use base64::{engine::general_purpose, Engine};
+use bytes::Buf;
+use cid::{
+    multihash::{Code, MultihashDigest},
+    Cid,
+};
+use ethers::core::types as et;
+use fendermint_actor_machine::Kind;
+use fendermint_actor_objectstore::{
+    cid_key, AddParams, ClaimParams, GetParams, HasManyParams, ListByTimeParams, ListParams,
+    Method::{AddObject, ClaimFirst, ClaimObject, SwapObject},
+    ObjectList, StatParams, State as ObjectStoreState, SwapParams,
+    BIT_WIDTH as OBJECTSTORE_BIT_WIDTH, MAX_HAS_MANY_KEYS,
+};
+use fendermint_rpc::{
+    client::FendermintClient,
+    message::SignedMessageFactory,
+    proxy::{
+        ClaimRequest, ContentUploadResponse, HasManyRequest, HasManyResponse, KeyEncoding,
+        ListByTimeQuery, ListDetail, ListQuery, ListResponse, ListedObject,
+        ObjectStoreMetadataResponse, StatResponse,
+    },
+    query::CallError,
+    response::{decode_os_claim_first, decode_os_get},
+    QueryClient,
+};
+use fendermint_vm_message::{
+    chain::ChainMessage, conv::from_fvm::to_eth_tokens, query::FvmQueryHeight,
+    signed::SignedMessage,
+};
+use futures_util::StreamExt;
+use fvm_ipld_encoding::{strict_bytes::ByteBuf, IPLD_RAW};
+use fvm_shared::{address::Address, chainid::ChainID};
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+use warp::{
+    filters::multipart::Part,
+    http::{HeaderMap, HeaderValue},
+    hyper::body::Body,
+    path::Tail,
+    Rejection, Reply,
+};
+
+use super::{decode_signed_message, machine_metadata, object_response};
+use crate::cmd::objects::fetcher::{fetch_pending_response, ContentFetcher};
+use crate::cmd::objects::query::{os_get, os_has_many, os_list, os_list_by_time, os_stat};
+use crate::cmd::objects::rejection::{
+    call_rejection, BadRequest, FieldTooLarge, NotFound, PreconditionFailed,
+};
+use crate::cmd::objects::store::{get_range_params, ContentStore, ObjectRange, ObjectsError};
+use crate::cmd::objects::{HeightQuery, Precondition, StatQuery};
+
+struct ObjectParser {
+    signed_msg: Option<SignedMessage>,
+    chain_id: ChainID,
+    temp_file: Option<TempFile>,
+    /// Maximum length of the fields that are buffered in memory.
+    max_field_length: u64,
+}
+
+impl ObjectParser {
+    fn new(max_field_length: u64) -> Self {
+        ObjectParser {
+            signed_msg: None,
+            chain_id: ChainID::from(0),
+            temp_file: None,
+            max_field_length,
+        }
+    }
+
+    async fn read_part(&mut self, part: Part) -> anyhow::Result<Vec<u8>> {
+        let field = part.name().to_string();
+        let mut value = Vec::new();
+        let mut part_stream = part.stream();
+        while let Some(data) = part_stream.next().await {
+            let data = data?;
+            if (value.len() + data.remaining()) as u64 > self.max_field_length {
+                return Err(FieldTooLarge {
+                    field,
+                    max: self.max_field_length,
+                }
+                .into());
+            }
+            value.extend_from_slice(data.chunk());
+        }
+        Ok(value)
+    }
+
+    async fn read_chain_id(&mut self, form_part: Part) -> anyhow::Result<()> {
+        let value = self.read_part(form_part).await?;
+        let text = String::from_utf8(value).map_err(|_| anyhow!("cannot parse chain id"))?;
+        let int: u64 = text.parse().map_err(|_| anyhow!("cannot parse chain_id"))?;
+        self.chain_id = ChainID::from(int);
+        Ok(())
+    }
+
+    async fn read_msg(&mut self, form_part: Part) -> anyhow::Result<()> {
+        let value = self.read_part(form_part).await?;
+        let signed_msg = general_purpose::URL_SAFE
+            .decode(value)
+            .map_err(|e| anyhow!("Failed to decode b64 encoded message: {}", e))
+            .and_then(|b64_decoded| {
+                fvm_ipld_encoding::from_slice::<SignedMessage>(&b64_decoded)
+                    .map_err(|e| anyhow!("Failed to deserialize signed message: {}", e))
+            })?;
+        self.signed_msg = Some(signed_msg);
+        Ok(())
+    }
+
+    async fn read_object(&mut self, form_part: Part) -> anyhow::Result<()> {
+        let mut temp_file = TempFile::new()
+            .await
+            .map_err(|e| anyhow!("failed to create temporary file: {}", e))?;
+        let mut part_stream = form_part.stream();
+
+        while let Some(data) = part_stream.next().await {
+            let mut data = data?;
+            while data.remaining() > 0 {
+                let chunk = data.chunk().to_owned();
+                let chunk_len = chunk.len();
+                temp_file.write_all(&chunk).await?;
+                temp_file.flush().await?;
+                data.advance(chunk_len);
+            }
+        }
+        temp_file
+            .rewind()
+            .await
+            .map_err(|e| anyhow!("failed to rewind temporary file: {}", e))?;
+
+        self.temp_file = Some(temp_file);
+        Ok(())
+    }
+
+    async fn read_form(
+        mut form_parts: warp::multipart::FormData,
+        max_field_length: u64,
+    ) -> anyhow::Result<Self> {
+        let mut object_parser = ObjectParser::new(max_field_length);
+        while let Some(part) = form_parts.next().await {
+            let part = part.map_err(|_| anyhow!("cannot read form data"))?;
+            match part.name() {
+                "chain_id" => {
+                    object_parser.read_chain_id(part).await?;
+                }
+                "msg" => {
+                    object_parser.read_msg(part).await?;
+                }
+                "object" => {
+                    object_parser.read_object(part).await?;
+                }
+                _ => {
+                    return Err(anyhow!("unknown form field"));
+                }
+            }
+        }
+        Ok(object_parser)
+    }
+}
+
+async fn ensure_balance<F: QueryClient>(client: &F, from: Address) -> anyhow::Result<()> {
+    let actor_state = client.actor_state(&from, FvmQueryHeight::Committed).await?;
+    let balance = match actor_state.value {
+        Some((_, state)) => to_eth_tokens(&state.balance)?,
+        None => et::U256::zero(),
+    };
+
+    // TODO: make cost_per_byte a configurable constant
+    // TODO: uncomment it when we decide the pricing logic
+    // let cost_per_byte = et::U256::from(1_000_000_000u128);
+    // let required_balance = cost_per_byte * self.size;
+    if balance <= et::U256::zero() {
+        return Err(anyhow!("insufficient balance"));
+    }
+
+    Ok(())
+}
+
+/// Upload an object, under the precondition given by the `If-Match` or `If-None-Match` header,
+/// if any.
+///
+/// A conditional upload has to be signed as a `SwapObject` expecting the same value, which
+/// is checked against the key before the content is stored, failing with
+/// `412 Precondition Failed` if it doesn't hold.
+pub async fn handle_object_upload<F: QueryClient + Clone + Send + Sync, S: ContentStore>(
+    client: F,
+    store: S,
+    form_parts: warp::multipart::FormData,
+    max_field_length: u64,
+    precondition: Option<Precondition>,
+) -> Result<impl Reply, Rejection> {
+    let (_, cid) = upload_object(client, store, form_parts, max_field_length, precondition).await?;
+    Ok(cid.to_string())
+}
+
+/// Upload an object that is added under its CID, returning the derived key.
+///
+/// The signed message has to add the object under [`cid_key`], so that clients don't have to
+/// come up with keys for content-addressed objects.
+pub async fn handle_object_upload_content<F: QueryClient + Clone + Send + Sync, S: ContentStore>(
+    client: F,
+    store: S,
+    form_parts: warp::multipart::FormData,
+    max_field_length: u64,
+) -> Result<impl Reply, Rejection> {
+    let (signed_msg, cid) =
+        upload_object(client, store, form_parts, max_field_length, None).await?;
+    let key = ensure_cid_key(&signed_msg, cid)?;
+    Ok(warp::reply::json(&ContentUploadResponse {
+        cid,
+        key: String::from_utf8(key).expect("cid strings are UTF-8"),
+    }))
+}
+
+/// Ensure that a signed message adds its object under the key derived from its CID.
+fn ensure_cid_key(signed_msg: &SignedMessage, cid: Cid) -> Result<Vec<u8>, Rejection> {
+    if signed_msg.message.method_num != AddObject as u64 {
+        return Err(Rejection::from(BadRequest {
+            message: "content-addressed uploads must add an object".to_string(),
+        }));
+    }
+    let params: AddParams = signed_msg.message.params.deserialize().map_err(|e| {
+        Rejection::from(BadRequest {
+            message: format!("failed to decode add params: {}", e),
+        })
+    })?;
+    let key = cid_key(&cid);
+    let object_key = signed_msg.object.as_ref().map(|object| &object.key);
+    if params.key != key || object_key != Some(&key) {
+        return Err(Rejection::from(BadRequest {
+            message: format!("object key must be its cid {}", cid),
+        }));
+    }
+    Ok(key)
+}
+
+/// Verify a signed message carrying the CID of an object, and add the object to the content
+/// store, returning the message with the CID.
+async fn upload_object<F: QueryClient + Clone + Send + Sync, S: ContentStore>(
+    client: F,
+    store: S,
+    form_parts: warp::multipart::FormData,
+    max_field_length: u64,
+    precondition: Option<Precondition>,
+) -> Result<(SignedMessage, Cid), Rejection> {
+    let parser = ObjectParser::read_form(form_parts, max_field_length)
+        .await
+        .map_err(|e| match e.downcast::<FieldTooLarge>() {
+            Ok(e) => Rejection::from(e),
+            Err(e) => Rejection::from(BadRequest {
+                message: format!("failed to read form: {}", e),
+            }),
+        })?;
+
+    // Verify the signature
+    let signed_msg = match parser.signed_msg {
+        Some(signed_msg) => signed_msg,
+        None => {
+            return Err(Rejection::from(BadRequest {
+                message: "missing signed message".to_string(),
+            }))
+        }
+    };
+    signed_msg.verify(&parser.chain_id).map_err(|e| {
+        Rejection::from(BadRequest {
+            message: e.to_string(),
+        })
+    })?;
+    if let Some(precondition) = precondition {
+        check_precondition(client.clone(), &signed_msg, precondition).await?;
+    }
+
+    // Objects carrying their content are stored internally, so there's nothing to upload.
+    if let Some(cid) = internal_object_cid(&signed_msg)? {
+        return Ok((signed_msg, cid));
+    }
+
+    // Ensure the sender has enough balance, and add the data to IPFS
+    let message = &signed_msg.message;
+    ensure_balance(&client, message.from)
+        .await
+        .map_err(|e| call_rejection(e, "failed to ensure balance"))?;
+    ensure_objectstore_exists(client, message.to)
+        .await
+        .map_err(|e| call_rejection(e, "failed to connect with objectstore"))?;
+    let client_cid = match &signed_msg.object {
+        Some(object) => object.value,
+        None => {
+            return Err(Rejection::from(BadRequest {
+                message: "missing CID in signed message".to_string(),
+            }))
+        }
+    };
+    let file = match parser.temp_file {
+        Some(file) => file,
+        None => {
+            return Err(Rejection::from(BadRequest {
+                message: "missing file in form".to_string(),
+            }))
+        }
+    };
+    let cid = store.put(file).await.map_err(|e| {
+        Rejection::from(BadRequest {
+            message: format!("failed to add file: {}", e),
+        })
+    })?;
+    // Check if the computed CID matches the one in the signed message.
+    // It is important to verify that CID represents the data correctly
+    // separately from signature because the signature is over the CID,
+    // it is unaware of the actual data.
+    if cid != client_cid {
+        return Err(Rejection::from(BadRequest {
+            message: format!(
+                "failed to add file: computed cid {:?} does not match {:?}",
+                cid, client_cid
+            ),
+        }));
+    }
+    tracing::info!(cid = %cid, store = %message.to, "object added to content store");
+
+    Ok((signed_msg, cid))
+}
+
+/// The CID of the object added by a signed message, if the message carries its content.
+fn internal_object_cid(signed_msg: &SignedMessage) -> Result<Option<Cid>, Rejection> {
+    if signed_msg.message.method_num != AddObject as u64 {
+        return Ok(None);
+    }
+    let params: AddParams = signed_msg.message.params.deserialize().map_err(|e| {
+        Rejection::from(BadRequest {
+            message: format!("failed to decode add params: {}", e),
+        })
+    })?;
+    let Some(data) = params.data else {
+        return Ok(None);
+    };
+    let cid = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(&data.0));
+    if cid != params.cid {
+        return Err(Rejection::from(BadRequest {
+            message: format!(
+                "computed cid {} of the inline content does not match {}",
+                cid, params.cid
+            ),
+        }));
+    }
+    Ok(Some(cid))
+}
+
+/// Check that the key swapped by a signed message has the value expected by `precondition`.
+///
+/// The message has to expect the same value, so that the condition also holds when it's
+/// executed, as the key may change in the meantime.
+async fn check_precondition<F: QueryClient + Send + Sync>(
+    client: F,
+    signed_msg: &SignedMessage,
+    precondition: Precondition,
+) -> Result<(), Rejection> {
+    let message = &signed_msg.message;
+    if message.method_num != SwapObject as u64 {
+        return Err(Rejection::from(BadRequest {
+            message: "conditional uploads must swap an object".to_string(),
+        }));
+    }
+    let params: SwapParams = message.params.deserialize().map_err(|e| {
+        Rejection::from(BadRequest {
+            message: format!("failed to decode swap params: {}", e),
+        })
+    })?;
+    if params.expected_cid != precondition.expected_cid() {
+        return Err(Rejection::from(BadRequest {
+            message: "the swap must expect the value given by the precondition".to_string(),
+        }));
+    }
+    let object = os_get(
+        client,
+        message.to,
+        GetParams { key: params.key },
+        FvmQueryHeight::Committed.into(),
+    )
+    .await
+    .map_err(|e| call_rejection(e, "objectstore get error"))?;
+    let current = object
+        .map(|object| Cid::try_from(object.cid.0))
+        .transpose()
+        .map_err(|e| {
+            Rejection::from(BadRequest {
+                message: format!("failed to decode object cid: {}", e),
+            })
+        })?;
+    if current != params.expected_cid {
+        let message = match current {
+            Some(cid) => format!("key holds object {}", cid),
+            None => "key is absent".to_string(),
+        };
+        return Err(Rejection::from(PreconditionFailed { message }));
+    }
+    Ok(())
+}
+
+async fn ensure_objectstore_exists<F: QueryClient>(client: F, to: Address) -> anyhow::Result<()> {
+    let actor_state = client.actor_state(&to, FvmQueryHeight::Committed).await?;
+    actor_state.value.ok_or(anyhow!("cannot find actor {to}"))?;
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_object_download<
+    F: QueryClient + Send + Sync,
+    S: ContentStore + Clone + Send + Sync + 'static,
+>(
+    address: Address,
+    tail: Tail,
+    method: String,
+    range: Option<String>,
+    height_query: HeightQuery,
+    client: F,
+    store: S,
+    fetcher: ContentFetcher,
+) -> Result<impl Reply, Rejection> {
+    let height = height_query
+        .height
+        .unwrap_or(FvmQueryHeight::Committed.into());
+    let path = tail.as_str();
+    let key: Vec<u8> = path.into();
+    let maybe_object = os_get(client, address, GetParams { key }, height)
+        .await
+        .map_err(|e| call_rejection(e, "objectstore get error"))?;
+
+    match maybe_object {
+        Some(object) => {
+            let cid = Cid::try_from(object.cid.0).map_err(|e| {
+                Rejection::from(BadRequest {
+                    message: format!("failed to decode cid: {}", e),
+                })
+            })?;
+            if !object.resolved {
+                return Err(Rejection::from(BadRequest {
+                    message: "object is not resolved".to_string(),
+                }));
+            }
+            let object_range = match object.data {
+                Some(data) => internal_object_range(data.0, range).map_err(|e| {
+                    Rejection::from(BadRequest {
+                        message: format!("failed to read object {}", e),
+                    })
+                })?,
+                None => {
+                    let stored = store.has(cid).await.map_err(|e| {
+                        Rejection::from(BadRequest {
+                            message: format!("failed to fetch object {}", e),
+                        })
+                    })?;
+                    if !stored {
+                        return if fetcher.fetch(store, cid) {
+                            Ok(fetch_pending_response(fetcher.config.retry_after))
+                        } else {
+                            Err(Rejection::from(NotFound))
+                        };
+                    }
+                    store.get(cid, range).await.map_err(|e| {
+                        Rejection::from(BadRequest {
+                            message: format!("failed to fetch object {}", e),
+                        })
+                    })?
+                }
+            };
+
+            // If it is a HEAD request, we don't need to send the body
+            // but we still need to send the Content-Length header
+            if method == "HEAD" {
+                let mut response = warp::reply::Response::new(warp::hyper::Body::empty());
+                let mut header_map = HeaderMap::new();
+                header_map.insert("Content-Length", HeaderValue::from(object_range.size));
+                let headers = response.headers_mut();
+                headers.extend(header_map);
+                return Ok(response);
+            }
+
+            Ok(object_response(object_range))
+        }
+        None => Err(Rejection::from(NotFound)),
+    }
+}
+
+/// Read a range of the content of an internal object, which comes with the object itself.
+fn internal_object_range(
+    data: Vec<u8>,
+    range: Option<String>,
+) -> Result<ObjectRange, ObjectsError> {
+    let size = data.len() as u64;
+    if size == 0 {
+        return Ok(ObjectRange {
+            start: 0,
+            end: 0,
+            len: 0,
+            size,
+            body: Body::empty(),
+        });
+    }
+    let (start, end) = match range {
+        Some(range) => get_range_params(range, size)?,
+        None => (0, size - 1),
+    };
+    if start > end || end >= size {
+        return Err(ObjectsError::RangeHeaderInvalid);
+    }
+    let body = Body::from(data[start as usize..=end as usize].to_vec());
+    Ok(ObjectRange {
+        start,
+        end,
+        len: end - start + 1,
+        size,
+        body,
+    })
+}
+
+/// Report whether an object exists, along with its declared size and content type.
+///
+/// Unlike a `HEAD` on the object content, this doesn't need the object to be resolved.
+pub async fn handle_object_head<F: QueryClient + Send + Sync>(
+    address: Address,
+    tail: Tail,
+    height_query: HeightQuery,
+    client: F,
+) -> Result<impl Reply, Rejection> {
+    let height = height_query
+        .height
+        .unwrap_or(FvmQueryHeight::Committed.into());
+    let key: Vec<u8> = tail.as_str().into();
+    let object = os_get(client, address, GetParams { key }, height)
+        .await
+        .map_err(|e| call_rejection(e, "objectstore get error"))?
+        .ok_or_else(|| Rejection::from(NotFound))?;
+
+    let content_type = object
+        .metadata
+        .get("content-type")
+        .and_then(|v| HeaderValue::from_str(v).ok())
+        .unwrap_or(HeaderValue::from_static("application/octet-stream"));
+
+    let mut response = warp::reply::Response::new(warp::hyper::Body::empty());
+    let headers = response.headers_mut();
+    headers.insert("Content-Length", HeaderValue::from(object.size));
+    headers.insert("Content-Type", content_type);
+    Ok(response)
+}
+
+pub async fn handle_object_has_many<F: QueryClient + Send + Sync>(
+    address: Address,
+    height_query: HeightQuery,
+    request: HasManyRequest,
+    client: F,
+) -> Result<impl Reply, Rejection> {
+    let height = height_query
+        .height
+        .unwrap_or(FvmQueryHeight::Committed.into());
+    if request.keys.len() > MAX_HAS_MANY_KEYS {
+        return Err(Rejection::from(BadRequest {
+            message: format!(
+                "cannot check {} keys at once, the maximum is {}",
+                request.keys.len(),
+                MAX_HAS_MANY_KEYS
+            ),
+        }));
+    }
+    let key_encoding = request.key_encoding.unwrap_or_default();
+    let keys = request
+        .keys
+        .iter()
+        .map(|key| key_encoding.decode(key).map(ByteBuf))
+        .collect::<anyhow::Result<Vec<_>>>()
+        .map_err(|e| {
+            Rejection::from(BadRequest {
+                message: format!("invalid key: {}", e),
+            })
+        })?;
+    let has = os_has_many(client, address, HasManyParams { keys }, height)
+        .await
+        .map_err(|e| call_rejection(e, "objectstore has many error"))?;
+    Ok(warp::reply::json(&HasManyResponse { has }))
+}
+
+/// Broadcast a signed `ClaimObject` or `ClaimFirst` message and return the object it claimed.
+///
+/// A claim is only useful with its result, so unlike uploads, the proxy broadcasts the message
+/// and waits for it to be committed. The message is still signed by the client.
+pub async fn handle_object_claim<C: tendermint_rpc::Client + Send + Sync>(
+    address: Address,
+    request: ClaimRequest,
+    client: FendermintClient<C>,
+) -> Result<impl Reply, Rejection> {
+    let signed_msg = decode_signed_message(request.chain_id, &request.msg)?;
+    let key = claim_key(address, &signed_msg.message)?;
+
+    let data = SignedMessageFactory::serialize(&ChainMessage::Signed(signed_msg)).map_err(|e| {
+        Rejection::from(BadRequest {
+            message: format!("failed to serialize message: {}", e),
+        })
+    })?;
+    let response = client
+        .underlying()
+        .broadcast_tx_commit(data)
+        .await
+        .map_err(|e| call_rejection(e.into(), "failed to broadcast claim"))?;
+    if response.check_tx.code.is_err() {
+        return Err(Rejection::from(BadRequest {
+            message: format!("claim rejected: {}", response.check_tx.log),
+        }));
+    }
+    if response.deliver_tx.code.is_err() {
+        return Err(call_rejection(
+            CallError::from(&response.deliver_tx).into(),
+            "objectstore claim error",
+        ));
+    }
+
+    let claimed = match key {
+        Some(key) => decode_os_get(&response.deliver_tx).map(|o| o.map(|object| (key, object))),
+        None => decode_os_claim_first(&response.deliver_tx),
+    }
+    .map_err(|e| {
+        Rejection::from(BadRequest {
+            message: format!("failed to decode claimed object: {}", e),
+        })
+    })?;
+    let Some((key, object)) = claimed else {
+        return Err(Rejection::from(NotFound));
+    };
+    tracing::info!(store = %address, key = ?String::from_utf8_lossy(&key), "object claimed");
+
+    // The object is gone from the store by now, so a key that can't be encoded is reported
+    // in base64 rather than lost.
+    let key_encoding = request.key_encoding.unwrap_or_default();
+    let key = key_encoding.encode(&key).ok_or_else(|| {
+        Rejection::from(BadRequest {
+            message: format!(
+                "claimed key {} is not valid UTF-8; claim with key_encoding=base64",
+                KeyEncoding::Base64.encode(&key).unwrap_or_default()
+            ),
+        })
+    })?;
+    let cid = Cid::try_from(object.cid.0).map_err(|e| {
+        Rejection::from(BadRequest {
+            message: format!("failed to decode cid: {}", e),
+        })
+    })?;
+    Ok(warp::reply::json(&ListedObject {
+        key,
+        cid,
+        size: object.size,
+        resolved: object.resolved,
+        kind: object.kind,
+        metadata: object.metadata,
+        created_at: object.created_at,
+    }))
+}
+
+/// Check that a message claims an object from the object store at `address`, returning the
+/// key it claims, or `None` if it claims the first object under a prefix.
+fn claim_key(
+    address: Address,
+    message: &fvm_shared::message::Message,
+) -> Result<Option<Vec<u8>>, Rejection> {
+    if message.to != address {
+        return Err(Rejection::from(BadRequest {
+            message: format!("message is sent to {} rather than {}", message.to, address),
+        }));
+    }
+    if message.method_num == ClaimFirst as u64 {
+        return Ok(None);
+    }
+    if message.method_num != ClaimObject as u64 {
+        return Err(Rejection::from(BadRequest {
+            message: "message must claim an object".to_string(),
+        }));
+    }
+    let params: ClaimParams = message.params.deserialize().map_err(|e| {
+        Rejection::from(BadRequest {
+            message: format!("failed to decode claim params: {}", e),
+        })
+    })?;
+    Ok(Some(params.key))
+}
+
+pub async fn handle_object_list<F: QueryClient + Send + Sync>(
+    address: Address,
+    list_query: ListQuery,
+    client: F,
+) -> Result<impl Reply, Rejection> {
+    let height = list_query
+        .height
+        .unwrap_or(FvmQueryHeight::Committed.into());
+    let params = ListParams {
+        prefix: list_query.prefix.unwrap_or_default().into_bytes(),
+        delimiter: list_query.delimiter.unwrap_or_default().into_bytes(),
+        offset: list_query.offset.unwrap_or_default(),
+        limit: list_query.limit.unwrap_or_default(),
+        reverse: list_query.reverse.unwrap_or_default(),
+        pattern: list_query.glob,
+    };
+    let list = os_list(client, address, params, height)
+        .await
+        .map_err(|e| call_rejection(e, "objectstore list error"))?;
+    let response = list_response(list, list_query.key_encoding.unwrap_or_default())?;
+    Ok(match list_query.detail.unwrap_or_default() {
+        ListDetail::Full => warp::reply::json(&response),
+        ListDetail::Minimal => warp::reply::json(&response.into_minimal()),
+    })
+}
+
+pub async fn handle_object_list_by_time<F: QueryClient + Send + Sync>(
+    address: Address,
+    list_query: ListByTimeQuery,
+    client: F,
+) -> Result<impl Reply, Rejection> {
+    let height = list_query
+        .height
+        .unwrap_or(FvmQueryHeight::Committed.into());
+    let params = ListByTimeParams {
+        start_epoch: list_query.start_epoch.unwrap_or_default(),
+        end_epoch: list_query.end_epoch.unwrap_or(i64::MAX),
+        limit: list_query.limit.unwrap_or_default(),
+    };
+    let list = os_list_by_time(client, address, params, height)
+        .await
+        .map_err(|e| call_rejection(e, "objectstore list error"))?;
+    let response = list_response(list, list_query.key_encoding.unwrap_or_default())?;
+    Ok(warp::reply::json(&response))
+}
+
+/// Convert an object list to its JSON representation, rejecting keys that can't be encoded.
+fn list_response(list: ObjectList, key_encoding: KeyEncoding) -> Result<ListResponse, Rejection> {
+    let encode = |key: &[u8]| {
+        key_encoding.encode(key).ok_or_else(|| {
+            Rejection::from(BadRequest {
+                message: format!(
+                    "key {:?} is not valid UTF-8; list with key_encoding=base64",
+                    String::from_utf8_lossy(key)
+                ),
+            })
+        })
+    };
+
+    let objects = list
+        .objects
+        .into_iter()
+        .map(|(key, object)| {
+            let cid = Cid::try_from(object.cid.0).map_err(|e| {
+                Rejection::from(BadRequest {
+                    message: format!("failed to decode cid: {}", e),
+                })
+            })?;
+            Ok(ListedObject {
+                key: encode(&key)?,
+                cid,
+                size: object.size,
+                resolved: object.resolved,
+                kind: object.kind,
+                metadata: object.metadata,
+                created_at: object.created_at,
+            })
+        })
+        .collect::<Result<Vec<_>, Rejection>>()?;
+    let common_prefixes = list
+        .common_prefixes
+        .iter()
+        .map(|p| encode(p))
+        .collect::<Result<Vec<_>, Rejection>>()?;
+
+    Ok(ListResponse {
+        objects,
+        common_prefixes,
+        truncated: list.truncated,
+        scan_truncated: list.scan_truncated,
+    })
+}
+
+pub async fn handle_object_stat<F: QueryClient + Send + Sync>(
+    address: Address,
+    stat_query: StatQuery,
+    client: F,
+) -> Result<impl Reply, Rejection> {
+    let height = stat_query
+        .height
+        .unwrap_or(FvmQueryHeight::Committed.into());
+    let prefix = stat_query.prefix.unwrap_or_default().into_bytes();
+    let stat = os_stat(client, address, StatParams { prefix }, height)
+        .await
+        .map_err(|e| call_rejection(e, "objectstore stat error"))?;
+
+    Ok(warp::reply::json(&StatResponse {
+        count: stat.count,
+        total_bytes: stat.total_bytes,
+        truncated: stat.truncated,
+    }))
+}
+
+pub async fn handle_os_metadata<F: QueryClient + Clone + Send + Sync>(
+    address: Address,
+    height_query: HeightQuery,
+    client: F,
+) -> Result<impl Reply, Rejection> {
+    let height = height_query
+        .height
+        .unwrap_or(FvmQueryHeight::Committed.into());
+    let (machine, state) =
+        machine_metadata::<_, ObjectStoreState>(client, address, height, Kind::ObjectStore).await?;
+
+    let config = state.value.config;
+    Ok(warp::reply::json(&ObjectStoreMetadataResponse {
+        machine,
+        bit_width: OBJECTSTORE_BIT_WIDTH,
+        max_object_size: config.max_object_size,
+        accumulator: config.accumulator.map(|a| a.to_string()),
+        max_list_limit: config.max_list_limit,
+        soft_delete_grace_period: config.soft_delete_grace_period,
+        versioning: config.versioning,
+        cid_v0: config.cid_v0,
+        max_metadata_entries: config.max_metadata_entries,
+        max_metadata_key_length: config.max_metadata_key_length,
+        max_metadata_size: config.max_metadata_size,
+        max_internal_object_size: config.max_internal_object_size,
+        max_pattern_scan: config.max_pattern_scan,
+        change_log: config.change_log.map(|a| a.to_string()),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    use fendermint_actor_machine::{Metadata as MachineMetadata, WriteAccess};
+    use fendermint_actor_objectstore::{Object, ObjectKind};
+    use fendermint_rpc::proxy::MachineMetadataResponse;
+    use fendermint_vm_message::query::FvmQuery;
+    use fvm_ipld_blockstore::MemoryBlockstore;
+    use fvm_ipld_encoding::RawBytes;
+    use fvm_ipld_hamt::BytesKey;
+    use fvm_shared::{econ::TokenAmount, error::ExitCode};
+    use ipfs_api_backend_hyper::IpfsClient;
+    use tendermint_rpc::{Method, MockClient, MockRequestMethodMatcher};
+    use warp::{http::StatusCode, Filter};
+
+    use crate::cmd::objects::breaker::CircuitBreakerConfig;
+    use crate::cmd::objects::fetcher::FetchConfig;
+    use crate::cmd::objects::rejection::handle_rejection;
+    use crate::cmd::objects::testing::{
+        abci_query_response, form_body, multipart_form, raw_cid, sign_add_message,
+        signed_add_internal_message, signed_add_message, signed_swap_message, spawn_object_comet,
+        IpfsMocked, MachineClient, MemoryContentStore, ABCI_QUERY_RESPONSE_ABSENT,
+        ABCI_QUERY_RESPONSE_DOWNLOAD, ABCI_QUERY_RESPONSE_UPLOAD,
+    };
+    use crate::cmd::objects::{router, ReadTimeouts};
+
+    #[tokio::test]
+    async fn test_handle_object_stat_forbidden() {
+        use prost::Message;
+        let deliver_tx = tendermint_proto::abci::ResponseDeliverTx {
+            code: ExitCode::USR_FORBIDDEN.value(),
+            info: "caller is not allowed".to_string(),
+            ..Default::default()
+        };
+        let value = fvm_ipld_encoding::to_vec(&deliver_tx.encode_to_vec()).unwrap();
+        let response = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": "",
+            "result": {
+                "response": {
+                    "code": 0,
+                    "log": "",
+                    "info": "",
+                    "index": "0",
+                    "key": "",
+                    "value": general_purpose::STANDARD.encode(value),
+                    "proof": null,
+                    "height": "1",
+                    "codespace": ""
+                }
+            }
+        })
+        .to_string();
+        let matcher = MockRequestMethodMatcher::default().map(Method::AbciQuery, Ok(response));
+        let client = FendermintClient::new(MockClient::new(matcher).0);
+
+        let rejection = handle_object_stat(
+            Address::new_actor("t2mnd5jkuvmsaf457ympnf3monalh3vothdd5njoy".as_bytes()),
+            StatQuery {
+                prefix: None,
+                height: None,
+            },
+            client,
+        )
+        .await
+        .map(Reply::into_response)
+        .unwrap_err();
+        let response = handle_rejection(rejection, "id").into_response();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_handle_object_upload() {
+        let matcher = MockRequestMethodMatcher::default().map(
+            Method::AbciQuery,
+            Ok(ABCI_QUERY_RESPONSE_UPLOAD.to_string()),
+        );
+        let client = FendermintClient::new(MockClient::new(matcher).0);
+        let ipfs = IpfsMocked {
+            _inner: IpfsClient::default(),
+        };
+
+        let external_object = b"hello world".as_ref();
+        let serialized_signed_message_b64 = signed_add_message(b"key", raw_cid(external_object));
+
+        let multipart_form = multipart_form(&serialized_signed_message_b64, external_object).await;
+        let reply = handle_object_upload(client, ipfs, multipart_form, 1024 * 1024, None)
+            .await
+            .unwrap();
+        let response = reply.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_claim_key() {
+        let address = Address::new_id(90);
+        let message = |to, method_num, params| fvm_shared::message::Message {
+            version: Default::default(),
+            from: Address::new_id(100),
+            to,
+            sequence: 0,
+            value: TokenAmount::from_atto(0),
+            method_num,
+            params,
+            gas_limit: 3000000,
+            gas_fee_cap: TokenAmount::from_atto(0),
+            gas_premium: TokenAmount::from_atto(0),
+        };
+        let claim = RawBytes::serialize(ClaimParams {
+            key: b"job".to_vec(),
+        })
+        .unwrap();
+
+        let key = claim_key(
+            address,
+            &message(address, ClaimObject as u64, claim.clone()),
+        );
+        assert_eq!(key.unwrap(), Some(b"job".to_vec()));
+        let first = fendermint_actor_objectstore::ClaimFirstParams::default();
+        let first = RawBytes::serialize(first).unwrap();
+        let key = claim_key(address, &message(address, ClaimFirst as u64, first));
+        assert_eq!(key.unwrap(), None);
+
+        // Only claims of the store in the path are broadcast.
+        for msg in [
+            message(Address::new_id(91), ClaimObject as u64, claim.clone()),
+            message(address, AddObject as u64, claim),
+        ] {
+            let err = claim_key(address, &msg).unwrap_err();
+            assert!(err.find::<BadRequest>().is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handle_object_upload_internal() {
+        let matcher = MockRequestMethodMatcher::default().map(
+            Method::AbciQuery,
+            Ok(ABCI_QUERY_RESPONSE_UPLOAD.to_string()),
+        );
+        let client = FendermintClient::new(MockClient::new(matcher).0);
+        let store = MemoryContentStore::default();
+
+        // The content comes with the message, so it isn't added to the content store.
+        let content = b"tiny".as_ref();
+        let signed_msg = signed_add_internal_message(b"key", content);
+        let form = multipart_form(&signed_msg, content).await;
+        let reply = handle_object_upload(client.clone(), store.clone(), form, 1024 * 1024, None)
+            .await
+            .unwrap();
+        let body = warp::hyper::body::to_bytes(reply.into_response().into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, raw_cid(content).to_string().as_bytes());
+        assert!(store.objects.lock().unwrap().is_empty());
+
+        // The content has to match the signed CID.
+        let params = AddParams {
+            key: b"key".to_vec(),
+            cid: raw_cid(b"other"),
+            size: content.len() as u64,
+            metadata: HashMap::new(),
+            overwrite: true,
+            data: Some(ByteBuf(content.to_vec())),
+        };
+        let form = multipart_form(&sign_add_message(params, None), content).await;
+        let err = handle_object_upload(client, store, form, 1024 * 1024, None)
+            .await
+            .map(Reply::into_response)
+            .unwrap_err();
+        assert!(err.find::<BadRequest>().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_content_store_upload_and_download() {
+        let content = b"hello world".as_ref();
+        let cid = raw_cid(content);
+
+        // Answer actor state queries for the upload, and object store calls for the download.
+        let object = Object {
+            cid: fvm_ipld_encoding::strict_bytes::ByteBuf(cid.to_bytes()),
+            size: content.len() as u64,
+            resolved: true,
+            resolved_root: None,
+            failed: false,
+            kind: ObjectKind::External,
+            data: None,
+            created_at: 0,
+            metadata: HashMap::new(),
+        };
+        let object_response = abci_query_response(&Some(object));
+        let comet = warp::post()
+            .and(warp::body::json())
+            .map(move |req: serde_json::Value| {
+                let data = hex::decode(req["params"]["data"].as_str().unwrap()).unwrap();
+                match fvm_ipld_encoding::from_slice::<FvmQuery>(&data).unwrap() {
+                    FvmQuery::ActorState(_) => ABCI_QUERY_RESPONSE_UPLOAD.to_string(),
+                    _ => object_response.clone(),
+                }
+            });
+        let (comet_addr, comet_server) = warp::serve(comet).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(comet_server);
+        let comet_url = format!("http://{comet_addr}").parse().unwrap();
+        let client = FendermintClient::new_http(comet_url, None).unwrap();
+
+        let store = MemoryContentStore::default();
+        let router = router(
+            client,
+            store.clone(),
+            1024 * 1024,
+            1024 * 1024,
+            ReadTimeouts::default(),
+            CircuitBreakerConfig::default(),
+            FetchConfig::default(),
+            None,
+        );
+
+        let boundary = "--abcdef1234--";
+        let response = warp::test::request()
+            .method("POST")
+            .path("/v1/objects")
+            .header(
+                "Content-Type",
+                format!("multipart/form-data; boundary={}", boundary),
+            )
+            .body(form_body(
+                boundary,
+                &signed_add_message(b"foo", cid),
+                content,
+            ))
+            .reply(&router)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), cid.to_string().as_bytes());
+        assert!(store.has(cid).await.unwrap());
+
+        let response = warp::test::request()
+            .method("GET")
+            .path(&format!("/v1/objects/{}/foo", Address::new_id(90)))
+            .reply(&router)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), content);
+
+        let response = warp::test::request()
+            .method("GET")
+            .path(&format!("/v1/objects/{}/foo", Address::new_id(90)))
+            .header("Range", "bytes=6-10")
+            .reply(&router)
+            .await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.body(), "world".as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_conditional_upload() {
+        let content = b"hello world".as_ref();
+        let cid = raw_cid(content);
+        let current = raw_cid(b"current");
+        let other = raw_cid(b"other");
+        let object = Object {
+            cid: fvm_ipld_encoding::strict_bytes::ByteBuf(current.to_bytes()),
+            size: 7,
+            resolved: true,
+            resolved_root: None,
+            failed: false,
+            kind: ObjectKind::External,
+            data: None,
+            created_at: 0,
+            metadata: HashMap::new(),
+        };
+        let boundary = "--abcdef1234--";
+        let upload = |msg: String, header: &str, value: String| {
+            warp::test::request()
+                .method("POST")
+                .path("/v1/objects")
+                .header(
+                    "Content-Type",
+                    format!("multipart/form-data; boundary={}", boundary),
+                )
+                .header(header, value)
+                .body(form_body(boundary, &msg, content))
+        };
+        let router_with = |object: Option<Object>, store: MemoryContentStore| {
+            router(
+                spawn_object_comet(object),
+                store,
+                1024 * 1024,
+                1024 * 1024,
+                ReadTimeouts::default(),
+                CircuitBreakerConfig::default(),
+                FetchConfig::default(),
+                None,
+            )
+        };
+
+        // The key holds the expected object.
+        let store = MemoryContentStore::default();
+        let router = router_with(Some(object.clone()), store.clone());
+        let response = upload(
+            signed_swap_message(b"key", Some(current), cid),
+            "If-Match",
+            format!("\"{}\"", current),
+        )
+        .reply(&router)
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), cid.to_string().as_bytes());
+        assert!(store.objects.lock().unwrap().contains_key(&cid));
+
+        // The key holds another object, so the content isn't stored.
+        let store = MemoryContentStore::default();
+        let router = router_with(Some(object), store.clone());
+        let response = upload(
+            signed_swap_message(b"key", Some(other), cid),
+            "If-Match",
+            other.to_string(),
+        )
+        .reply(&router)
+        .await;
+        assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+        let err: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert!(err["message"]
+            .as_str()
+            .unwrap()
+            .contains(&current.to_string()));
+        assert!(store.objects.lock().unwrap().is_empty());
+
+        // The key has to be absent.
+        let response = upload(
+            signed_swap_message(b"key", None, cid),
+            "If-None-Match",
+            "*".into(),
+        )
+        .reply(&router)
+        .await;
+        assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+        assert!(store.objects.lock().unwrap().is_empty());
+
+        let store = MemoryContentStore::default();
+        let absent = router_with(None, store.clone());
+        let response = upload(
+            signed_swap_message(b"key", None, cid),
+            "If-None-Match",
+            "*".into(),
+        )
+        .reply(&absent)
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(store.objects.lock().unwrap().contains_key(&cid));
+
+        // The signed swap has to expect what the header does.
+        let response = upload(
+            signed_swap_message(b"key", Some(other), cid),
+            "If-Match",
+            current.to_string(),
+        )
+        .reply(&router)
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = upload(signed_add_message(b"key", cid), "If-None-Match", "*".into())
+            .reply(&absent)
+            .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_content_addressed_upload() {
+        let content = b"hello world".as_ref();
+        let cid = raw_cid(content);
+        let object = Object {
+            cid: fvm_ipld_encoding::strict_bytes::ByteBuf(cid.to_bytes()),
+            size: content.len() as u64,
+            resolved: true,
+            resolved_root: None,
+            failed: false,
+            kind: ObjectKind::External,
+            data: None,
+            created_at: 0,
+            metadata: HashMap::new(),
+        };
+        let object_response = abci_query_response(&Some(object));
+        let comet = warp::post()
+            .and(warp::body::json())
+            .map(move |req: serde_json::Value| {
+                let data = hex::decode(req["params"]["data"].as_str().unwrap()).unwrap();
+                match fvm_ipld_encoding::from_slice::<FvmQuery>(&data).unwrap() {
+                    FvmQuery::ActorState(_) => ABCI_QUERY_RESPONSE_UPLOAD.to_string(),
+                    _ => object_response.clone(),
+                }
+            });
+        let (comet_addr, comet_server) = warp::serve(comet).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(comet_server);
+        let comet_url = format!("http://{comet_addr}").parse().unwrap();
+        let client = FendermintClient::new_http(comet_url, None).unwrap();
+
+        let store = MemoryContentStore::default();
+        let router = router(
+            client,
+            store.clone(),
+            1024 * 1024,
+            1024 * 1024,
+            ReadTimeouts::default(),
+            CircuitBreakerConfig::default(),
+            FetchConfig::default(),
+            None,
+        );
+        let boundary = "--abcdef1234--";
+        let upload = |msg: String| {
+            warp::test::request()
+                .method("PUT")
+                .path("/v1/os")
+                .header(
+                    "Content-Type",
+                    format!("multipart/form-data; boundary={}", boundary),
+                )
+                .body(form_body(boundary, &msg, content))
+        };
+
+        // The object has to be added under its CID.
+        let response = upload(signed_add_message(b"foo", cid)).reply(&router).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = upload(signed_add_message(&cid_key(&cid), cid))
+            .reply(&router)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let uploaded: ContentUploadResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(uploaded.cid, cid);
+        assert_eq!(uploaded.key, cid.to_string());
+
+        let response = warp::test::request()
+            .method("GET")
+            .path(&format!(
+                "/v1/objects/{}/{}",
+                Address::new_id(90),
+                uploaded.key
+            ))
+            .reply(&router)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), content);
+    }
+
+    #[tokio::test]
+    async fn test_handle_object_download_internal() {
+        let content = b"hello world".as_ref();
+        let object = Object {
+            cid: ByteBuf(raw_cid(content).to_bytes()),
+            size: content.len() as u64,
+            resolved: true,
+            resolved_root: None,
+            failed: false,
+            kind: ObjectKind::Internal,
+            data: Some(ByteBuf(content.to_vec())),
+            metadata: HashMap::new(),
+            created_at: 0,
+        };
+        let download = |range: Option<&str>| {
+            let matcher = MockRequestMethodMatcher::default()
+                .map(Method::AbciQuery, Ok(abci_query_response(&Some(&object))));
+            let client = FendermintClient::new(MockClient::new(matcher).0);
+            let range = range.map(String::from);
+            async move {
+                // The content store is empty, the content comes with the object.
+                let response = handle_object_download(
+                    Address::new_id(90),
+                    warp::test::request()
+                        .path("/foo")
+                        .filter(&warp::path::tail())
+                        .await
+                        .unwrap(),
+                    "GET".to_string(),
+                    range,
+                    HeightQuery { height: None },
+                    client,
+                    MemoryContentStore::default(),
+                    ContentFetcher::new(FetchConfig::default()),
+                )
+                .await
+                .unwrap()
+                .into_response();
+                let status = response.status();
+                let body = warp::hyper::body::to_bytes(response.into_body())
+                    .await
+                    .unwrap();
+                (status, body)
+            }
+        };
+
+        let (status, body) = download(None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, content);
+
+        let (status, body) = download(Some("bytes=6-10")).await;
+        assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(body, b"world".as_ref());
+    }
+
+    /// Download an external object whose content may be missing from `store`, returning the
+    /// status, the Retry-After header and the body of the response.
+    #[tokio::test]
+    async fn test_handle_object_download_get() {
+        let matcher = MockRequestMethodMatcher::default().map(
+            Method::AbciQuery,
+            Ok(ABCI_QUERY_RESPONSE_DOWNLOAD.to_string()),
+        );
+        let client = FendermintClient::new(MockClient::new(matcher).0);
+        let ipfs = IpfsMocked {
+            _inner: IpfsClient::default(),
+        };
+        let result = handle_object_download(
+            Address::new_actor("t2mnd5jkuvmsaf457ympnf3monalh3vothdd5njoy".as_bytes()),
+            warp::test::request()
+                .path("/foo/bar")
+                .filter(&warp::path::tail())
+                .await
+                .unwrap(),
+            "GET".to_string(),
+            None,
+            HeightQuery { height: Some(1) },
+            client,
+            ipfs,
+            ContentFetcher::new(FetchConfig::default()),
+        )
+        .await;
+        assert!(result.is_ok());
+        let response = result.unwrap().into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, "hello world".as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_handle_object_download_with_range() {
+        let matcher = MockRequestMethodMatcher::default().map(
+            Method::AbciQuery,
+            Ok(ABCI_QUERY_RESPONSE_DOWNLOAD.to_string()),
+        );
+        let client = FendermintClient::new(MockClient::new(matcher).0);
+        let ipfs = IpfsMocked {
+            _inner: IpfsClient::default(),
+        };
+        let result = handle_object_download(
+            Address::new_actor("t2mnd5jkuvmsaf457ympnf3monalh3vothdd5njoy".as_bytes()),
+            warp::test::request()
+                .path("/foo/bar")
+                .filter(&warp::path::tail())
+                .await
+                .unwrap(),
+            "GET".to_string(),
+            Some("bytes=0-4".to_string()),
+            HeightQuery { height: Some(1) },
+            client,
+            ipfs,
+            ContentFetcher::new(FetchConfig::default()),
+        )
+        .await;
+        assert!(result.is_ok());
+        let response = result.unwrap().into_response();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, "hello".as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_handle_object_download_head() {
+        let matcher = MockRequestMethodMatcher::default().map(
+            Method::AbciQuery,
+            Ok(ABCI_QUERY_RESPONSE_DOWNLOAD.to_string()),
+        );
+        let client = FendermintClient::new(MockClient::new(matcher).0);
+        let ipfs = IpfsMocked {
+            _inner: IpfsClient::default(),
+        };
+        let result = handle_object_download(
+            Address::new_actor("t2mnd5jkuvmsaf457ympnf3monalh3vothdd5njoy".as_bytes()),
+            warp::test::request()
+                .path("/foo/bar")
+                .filter(&warp::path::tail())
+                .await
+                .unwrap(),
+            "HEAD".to_string(),
+            None,
+            HeightQuery { height: Some(1) },
+            client,
+            ipfs,
+            ContentFetcher::new(FetchConfig::default()),
+        )
+        .await;
+
+        assert!(result.is_ok());
+        let response = result.unwrap().into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get("Content-Length").unwrap(), "11");
+    }
+
+    async fn object_head(abci_query_response: &str) -> Result<warp::reply::Response, Rejection> {
+        let matcher = MockRequestMethodMatcher::default()
+            .map(Method::AbciQuery, Ok(abci_query_response.to_string()));
+        let client = FendermintClient::new(MockClient::new(matcher).0);
+        handle_object_head(
+            Address::new_actor("t2mnd5jkuvmsaf457ympnf3monalh3vothdd5njoy".as_bytes()),
+            warp::test::request()
+                .path("/foo/bar")
+                .filter(&warp::path::tail())
+                .await
+                .unwrap(),
+            HeightQuery { height: Some(1) },
+            client,
+        )
+        .await
+        .map(Reply::into_response)
+    }
+
+    #[tokio::test]
+    async fn test_handle_object_head_present() {
+        let response = object_head(ABCI_QUERY_RESPONSE_DOWNLOAD).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get("Content-Length").unwrap(), "6");
+        assert_eq!(
+            response.headers().get("Content-Type").unwrap(),
+            "application/octet-stream"
+        );
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert!(body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_handle_object_head_absent() {
+        let err = object_head(ABCI_QUERY_RESPONSE_ABSENT).await.unwrap_err();
+        assert!(err.find::<NotFound>().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_handle_object_list_kinds() {
+        let object = |data: &[u8], resolved, kind| Object {
+            cid: ByteBuf(raw_cid(data).to_bytes()),
+            size: data.len() as u64,
+            resolved,
+            resolved_root: None,
+            failed: false,
+            kind,
+            data: None,
+            metadata: HashMap::new(),
+            created_at: 0,
+        };
+        let list = ObjectList {
+            objects: vec![
+                (
+                    b"external".to_vec(),
+                    object(b"external", false, ObjectKind::External),
+                ),
+                (
+                    b"internal".to_vec(),
+                    object(b"internal", true, ObjectKind::Internal),
+                ),
+            ],
+            common_prefixes: vec![],
+            truncated: false,
+            scan_truncated: false,
+        };
+        let matcher = MockRequestMethodMatcher::default()
+            .map(Method::AbciQuery, Ok(abci_query_response(&list)));
+        let client = FendermintClient::new(MockClient::new(matcher).0);
+        let response = handle_object_list(
+            Address::new_actor("t2mnd5jkuvmsaf457ympnf3monalh3vothdd5njoy".as_bytes()),
+            ListQuery::default(),
+            client,
+        )
+        .await
+        .unwrap()
+        .into_response();
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let res: ListResponse = serde_json::from_slice(&body).unwrap();
+        let kinds = res
+            .objects
+            .iter()
+            .map(|o| (o.key.as_str(), o.kind, o.resolved))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                ("external", ObjectKind::External, false),
+                ("internal", ObjectKind::Internal, true),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handle_object_list_detail() {
+        let mut metadata = HashMap::new();
+        metadata.insert("foo".to_string(), "bar".to_string());
+        let cid = raw_cid(b"hello world");
+        let list_json = |detail| {
+            let list = ObjectList {
+                objects: vec![(
+                    b"hello".to_vec(),
+                    Object {
+                        cid: ByteBuf(cid.to_bytes()),
+                        size: 11,
+                        resolved: true,
+                        resolved_root: None,
+                        failed: false,
+                        kind: ObjectKind::External,
+                        data: None,
+                        metadata: metadata.clone(),
+                        created_at: 5,
+                    },
+                )],
+                common_prefixes: vec![],
+                truncated: false,
+                scan_truncated: false,
+            };
+            async move {
+                let matcher = MockRequestMethodMatcher::default()
+                    .map(Method::AbciQuery, Ok(abci_query_response(&list)));
+                let client = FendermintClient::new(MockClient::new(matcher).0);
+                let query = ListQuery {
+                    detail,
+                    ..Default::default()
+                };
+                let response = handle_object_list(Address::new_id(1000), query, client)
+                    .await
+                    .unwrap()
+                    .into_response();
+                let body = warp::hyper::body::to_bytes(response.into_body())
+                    .await
+                    .unwrap();
+                serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+            }
+        };
+
+        let full = serde_json::json!({
+            "objects": [{
+                "key": "hello",
+                "cid": cid.to_string(),
+                "size": 11,
+                "resolved": true,
+                "kind": "External",
+                "metadata": {"foo": "bar"},
+                "created_at": 5,
+            }],
+            "common_prefixes": [],
+            "truncated": false,
+            "scan_truncated": false,
+        });
+        assert_eq!(list_json(None).await, full);
+        assert_eq!(list_json(Some(ListDetail::Full)).await, full);
+        assert_eq!(
+            list_json(Some(ListDetail::Minimal)).await,
+            serde_json::json!({
+                "objects": [{"key": "hello", "cid": cid.to_string()}],
+                "common_prefixes": [],
+                "truncated": false,
+                "scan_truncated": false,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handle_object_list_binary_key() {
+        let cid = Cid::new_v1(0x55, Code::Blake2b256.digest(b"hello world"));
+        let key = vec![0xff, 0x00, 0xfe];
+        let list = ObjectList {
+            objects: vec![(
+                key.clone(),
+                Object {
+                    cid: fvm_ipld_encoding::strict_bytes::ByteBuf(cid.to_bytes()),
+                    size: 11,
+                    resolved: true,
+                    resolved_root: None,
+                    failed: false,
+                    kind: ObjectKind::External,
+                    data: None,
+                    metadata: HashMap::new(),
+                    created_at: 0,
+                },
+            )],
+            common_prefixes: vec![],
+            truncated: false,
+            scan_truncated: false,
+        };
+        let list_objects = |key_encoding| {
+            let matcher = MockRequestMethodMatcher::default()
+                .map(Method::AbciQuery, Ok(abci_query_response(&list)));
+            let client = FendermintClient::new(MockClient::new(matcher).0);
+            handle_object_list(
+                Address::new_actor("t2mnd5jkuvmsaf457ympnf3monalh3vothdd5njoy".as_bytes()),
+                ListQuery {
+                    key_encoding,
+                    ..Default::default()
+                },
+                client,
+            )
+        };
+
+        // The key can't be listed as UTF-8 without altering it.
+        let err = list_objects(None)
+            .await
+            .map(Reply::into_response)
+            .unwrap_err();
+        assert!(err.find::<BadRequest>().is_some());
+
+        let response = list_objects(Some(KeyEncoding::Base64))
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let res: ListResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(res.objects.len(), 1);
+        assert_eq!(
+            KeyEncoding::Base64.decode(&res.objects[0].key).unwrap(),
+            key
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handle_object_list() {
+        let cid = Cid::new_v1(0x55, Code::Blake2b256.digest(b"hello world"));
+        let list = ObjectList {
+            objects: vec![(
+                b"photos/cat.png".to_vec(),
+                Object {
+                    cid: fvm_ipld_encoding::strict_bytes::ByteBuf(cid.to_bytes()),
+                    size: 11,
+                    resolved: true,
+                    resolved_root: None,
+                    failed: false,
+                    kind: ObjectKind::External,
+                    data: None,
+                    metadata: HashMap::new(),
+                    created_at: 0,
+                },
+            )],
+            common_prefixes: vec![b"photos/2023/".to_vec(), b"photos/2024/".to_vec()],
+            truncated: true,
+            scan_truncated: false,
+        };
+        let matcher = MockRequestMethodMatcher::default()
+            .map(Method::AbciQuery, Ok(abci_query_response(&list)));
+        let client = FendermintClient::new(MockClient::new(matcher).0);
+
+        let reply = handle_object_list(
+            Address::new_actor("t2mnd5jkuvmsaf457ympnf3monalh3vothdd5njoy".as_bytes()),
+            ListQuery {
+                prefix: Some("photos/".to_string()),
+                delimiter: Some("/".to_string()),
+                ..Default::default()
+            },
+            client,
+        )
+        .await
+        .unwrap();
+        let response = reply.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let list: ListResponse = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(list.objects.len(), 1);
+        assert_eq!(list.objects[0].key, "photos/cat.png");
+        assert_eq!(list.objects[0].cid, cid);
+        assert_eq!(list.common_prefixes, vec!["photos/2023/", "photos/2024/"]);
+        assert!(list.truncated);
+    }
+
+    #[tokio::test]
+    async fn test_handle_object_list_by_time() {
+        let cid = Cid::new_v1(0x55, Code::Blake2b256.digest(b"hello world"));
+        let list = ObjectList {
+            objects: vec![(
+                b"photos/cat.png".to_vec(),
+                Object {
+                    cid: fvm_ipld_encoding::strict_bytes::ByteBuf(cid.to_bytes()),
+                    size: 11,
+                    resolved: true,
+                    resolved_root: None,
+                    failed: false,
+                    kind: ObjectKind::External,
+                    data: None,
+                    metadata: HashMap::new(),
+                    created_at: 20,
+                },
+            )],
+            common_prefixes: vec![],
+            truncated: false,
+            scan_truncated: false,
+        };
+        let matcher = MockRequestMethodMatcher::default()
+            .map(Method::AbciQuery, Ok(abci_query_response(&list)));
+        let client = FendermintClient::new(MockClient::new(matcher).0);
+
+        let reply = handle_object_list_by_time(
+            Address::new_actor("t2mnd5jkuvmsaf457ympnf3monalh3vothdd5njoy".as_bytes()),
+            ListByTimeQuery {
+                start_epoch: Some(10),
+                end_epoch: Some(30),
+                ..Default::default()
+            },
+            client,
+        )
+        .await
+        .unwrap();
+        let response = reply.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let list: ListResponse = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(list.objects.len(), 1);
+        assert_eq!(list.objects[0].key, "photos/cat.png");
+        assert_eq!(list.objects[0].created_at, 20);
+        assert!(!list.truncated);
+    }
+
+    #[tokio::test]
+    async fn test_handle_os_metadata() {
+        let owner = Address::new_id(100);
+        let acc = Address::new_id(101);
+        let store = MemoryBlockstore::default();
+        let mut state = ObjectStoreState::new(&store, owner, WriteAccess::Public).unwrap();
+        state.config = fendermint_actor_objectstore::Config {
+            max_object_size: Some(1024),
+            accumulator: Some(acc),
+            versioning: true,
+            change_log: Some(acc),
+            ..Default::default()
+        };
+        let client = MachineClient::new(
+            store,
+            &state,
+            MachineMetadata {
+                kind: Kind::ObjectStore,
+                owner,
+                write_access: WriteAccess::Public,
+                frozen: false,
+                metadata: HashMap::new(),
+            },
+        );
+
+        let address = Address::new_id(1000);
+        let response = handle_os_metadata(address, HeightQuery { height: Some(1) }, client)
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let metadata: ObjectStoreMetadataResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            metadata,
+            ObjectStoreMetadataResponse {
+                machine: MachineMetadataResponse {
+                    kind: "objectstore".to_string(),
+                    owner: owner.to_string(),
+                    write_access: "public".to_string(),
+                    frozen: false,
+                    metadata: HashMap::new(),
+                },
+                bit_width: OBJECTSTORE_BIT_WIDTH,
+                max_object_size: Some(1024),
+                accumulator: Some(acc.to_string()),
+                max_list_limit: None,
+                soft_delete_grace_period: None,
+                versioning: true,
+                cid_v0: false,
+                max_metadata_entries: None,
+                max_metadata_key_length: None,
+                max_metadata_size: None,
+                max_internal_object_size: None,
+                max_pattern_scan: None,
+                change_log: Some(acc.to_string()),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handle_object_has_many() {
+        let owner = Address::new_id(100);
+        let store = MemoryBlockstore::default();
+        let mut state = ObjectStoreState::new(&store, owner, WriteAccess::Public).unwrap();
+        let cid = Cid::new_v1(0x55, Code::Blake2b256.digest(b"hello world"));
+        for key in [b"a".to_vec(), b"c".to_vec(), vec![0xff, 0x00]] {
+            state
+                .add(&store, BytesKey(key), cid, 11, HashMap::new(), false, 0)
+                .unwrap();
+        }
+        let client = MachineClient::new(
+            store,
+            &state,
+            MachineMetadata {
+                kind: Kind::ObjectStore,
+                owner,
+                write_access: WriteAccess::Public,
+                frozen: false,
+                metadata: HashMap::new(),
+            },
+        );
+        let address = Address::new_id(1000);
+        let has_many = |keys: Vec<String>, key_encoding: Option<KeyEncoding>| {
+            handle_object_has_many(
+                address,
+                HeightQuery { height: None },
+                HasManyRequest { keys, key_encoding },
+                client.clone(),
+            )
+        };
+
+        let keys = ["a", "b", "c", "a", ""].map(String::from).to_vec();
+        let response = has_many(keys, None).await.unwrap().into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let response: HasManyResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response.has, vec![true, false, true, true, false]);
+
+        // Binary keys can be checked in base64.
+        let keys = vec![
+            general_purpose::STANDARD.encode([0xff, 0x00]),
+            general_purpose::STANDARD.encode([0xff]),
+        ];
+        let response = has_many(keys, Some(KeyEncoding::Base64))
+            .await
+            .unwrap()
+            .into_response();
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let response: HasManyResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response.has, vec![true, false]);
+
+        // Keys that don't decode, and too many keys, are rejected before querying.
+        let err = has_many(vec!["not base64!".to_string()], Some(KeyEncoding::Base64))
+            .await
+            .map(Reply::into_response)
+            .unwrap_err();
+        assert!(err.find::<BadRequest>().is_some());
+        let err = has_many(vec!["a".to_string(); MAX_HAS_MANY_KEYS + 1], None)
+            .await
+            .map(Reply::into_response)
+            .unwrap_err();
+        assert!(err.find::<BadRequest>().is_some());
+    }
+}
@@ -0,0 +1,1019 @@
+// Copyright 2024 Textile
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+mod breaker;
+mod fetcher;
+mod handlers;
+mod query;
+mod rejection;
+mod server;
+mod store;
+#[cfg(test)]
+mod testing;
+
+use std::{
+    convert::Infallible,
+    future::Future,
+    net::{SocketAddr, ToSocketAddrs},
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use cid::Cid;
+use fendermint_actor_machine::Kind;
+use fendermint_app_settings::{objects::ObjectsSettings, SocketAddress};
+use fendermint_rpc::{
+    client::FendermintClient,
+    proxy::{ImportQuery, ListByTimeQuery, ListQuery, MachinesQuery},
+};
+use fvm_shared::address::Address;
+use ipfs_api_backend_hyper::{IpfsClient, TryFromUri};
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpListener;
+use warp::{Filter, Rejection, Reply};
+
+use crate::cmd;
+use crate::options::objects::{ObjectsArgs, ObjectsCommands};
+use breaker::{CircuitBreaker, CircuitBreakerConfig};
+use fetcher::{ContentFetcher, FetchConfig};
+use handlers::accumulator::{
+    handle_acc_diff, handle_acc_export, handle_acc_get, handle_acc_import, handle_acc_leaf_status,
+    handle_acc_metadata, handle_acc_roots, handle_acc_summary, handle_acc_verify,
+};
+use handlers::objectstore::{
+    handle_object_claim, handle_object_download, handle_object_has_many, handle_object_head,
+    handle_object_list, handle_object_list_by_time, handle_object_stat, handle_object_upload,
+    handle_object_upload_content, handle_os_metadata,
+};
+use handlers::{
+    handle_can_write, handle_machines_list, handle_resolver_stats, handle_simulate,
+    handle_tx_status, health,
+};
+use rejection::{reply_with_request_id, BadRequest, Timeout};
+use server::{serve, ServerConfig};
+use store::{ContentStore, IpfsContentStore};
+
+const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+cmd! {
+    ObjectsArgs(self, settings: ObjectsSettings) {
+        match self.command.clone() {
+            ObjectsCommands::Run {
+                tendermint_url,
+                ipfs_addr,
+                max_object_length,
+                max_field_length,
+                list_timeout,
+                fetch_timeout,
+                circuit_breaker_threshold,
+                circuit_breaker_cooldown,
+                fetch_attempts,
+                fetch_retry_after,
+                disable_http2,
+                keep_alive_interval,
+                max_concurrent_streams,
+                max_connections,
+                resolver_stats_url,
+            } => {
+                let client = FendermintClient::new_http(tendermint_url.clone(), None)
+                    .map_err(|e| anyhow!("invalid Tendermint URL '{}': {}", tendermint_url, e))?;
+                let ipfs = IpfsClient::from_multiaddr_str(&ipfs_addr)
+                    .map_err(|e| anyhow!("invalid IPFS address '{}': {}", ipfs_addr, e))?;
+                let resolver_stats_url = resolver_stats_url
+                    .map(|url| {
+                        reqwest::Url::parse(&url)
+                            .map_err(|e| anyhow!("invalid resolver stats URL '{}': {}", url, e))
+                    })
+                    .transpose()?;
+                let content_store = IpfsContentStore { inner: ipfs.clone() };
+                let timeouts = ReadTimeouts {
+                    list: Duration::from_secs(list_timeout),
+                    fetch: Duration::from_secs(fetch_timeout),
+                };
+                let breaker = CircuitBreakerConfig {
+                    threshold: circuit_breaker_threshold,
+                    cooldown: Duration::from_secs(circuit_breaker_cooldown),
+                };
+                let fetch = FetchConfig {
+                    attempts: fetch_attempts,
+                    retry_after: Duration::from_secs(fetch_retry_after),
+                    attempt_timeout: timeouts.fetch,
+                };
+                let server = ServerConfig {
+                    http2: !disable_http2,
+                    keep_alive: (keep_alive_interval > 0)
+                        .then(|| Duration::from_secs(keep_alive_interval)),
+                    max_concurrent_streams,
+                    max_connections,
+                };
+
+                let router = router(
+                    client,
+                    content_store,
+                    max_object_length,
+                    max_field_length,
+                    timeouts,
+                    breaker,
+                    fetch,
+                    resolver_stats_url,
+                );
+
+                let listen_addr = listen_addr(&settings.listen)?;
+                let listener = TcpListener::bind(listen_addr)
+                    .await
+                    .map_err(|e| anyhow!("failed to listen on {}: {}", listen_addr, e))?;
+                tracing::info!(addr = %listener.local_addr()?, "objects proxy listening");
+                serve(router, listener, server).await
+            },
+        }
+    }
+}
+
+/// Resolve the address the proxy listens on, with an error naming it if it's invalid.
+fn listen_addr(listen: &SocketAddress) -> anyhow::Result<SocketAddr> {
+    listen
+        .to_socket_addrs()
+        .map_err(|e| anyhow!("invalid listen address '{}': {}", listen, e))?
+        .next()
+        .ok_or_else(|| anyhow!("invalid listen address '{}': no socket address", listen))
+}
+
+/// Timeouts of the read routes that can take long, e.g. on a large object store.
+#[derive(Clone, Copy, Debug)]
+struct ReadTimeouts {
+    /// Timeout of listing objects or machines, and aggregating objects.
+    list: Duration,
+    /// Timeout of fetching an object or an accumulator leaf, up to when the content starts
+    /// streaming. Once it has, it streams for as long as the client keeps reading.
+    fetch: Duration,
+}
+
+impl Default for ReadTimeouts {
+    fn default() -> Self {
+        Self {
+            list: Duration::from_secs(30),
+            fetch: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Build the proxy routes, with request IDs, tracing and error handling.
+///
+/// Handlers, and the streams of content they return, are dropped when the client disconnects,
+/// which cancels the queries and IPFS fetches they are waiting on.
+fn router<S: ContentStore + Clone + Send + Sync + 'static>(
+    client: FendermintClient,
+    content_store: S,
+    max_object_length: u64,
+    max_field_length: u64,
+    timeouts: ReadTimeouts,
+    breaker: CircuitBreakerConfig,
+    fetch: FetchConfig,
+    resolver_stats_url: Option<reqwest::Url>,
+) -> impl Filter<Extract = (warp::reply::Response,), Error = Rejection> + Clone {
+    // Admin routes
+    let health_route = warp::path!("health").and(warp::get()).and_then(health);
+
+    let http = reqwest::Client::new();
+    let resolver_stats = warp::path!("v1" / "resolver" / "stats")
+        .and(warp::get())
+        .and(warp::any().map(move || http.clone()))
+        .and(warp::any().map(move || resolver_stats_url.clone()))
+        .and_then(move |http, url| with_timeout(timeouts.list, handle_resolver_stats(http, url)));
+
+    // Objects routes
+    let fetcher = ContentFetcher::new(fetch);
+    let objects_upload = warp::path!("v1" / "objects")
+        .and(warp::post())
+        .and(with_client(client.clone()))
+        .and(with_content_store(content_store.clone()))
+        .and(warp::multipart::form().max_length(max_object_length))
+        .and(warp::any().map(move || max_field_length))
+        .and(with_precondition())
+        .and_then(handle_object_upload);
+
+    let objects_upload_content = warp::path!("v1" / "os")
+        .and(warp::put())
+        .and(with_client(client.clone()))
+        .and(with_content_store(content_store.clone()))
+        .and(warp::multipart::form().max_length(max_object_length))
+        .and(warp::any().map(move || max_field_length))
+        .and_then(handle_object_upload_content);
+
+    let objects_download = warp::path!("v1" / "objects" / Address / ..)
+        .and(warp::path::tail())
+        .and(
+            warp::get()
+                .map(|| "GET".to_string())
+                .or(warp::head().map(|| "HEAD".to_string()))
+                .unify(),
+        )
+        .and(warp::header::optional::<String>("Range"))
+        .and(warp::query::<HeightQuery>())
+        .and(with_client(client.clone()))
+        .and(with_content_store(content_store.clone()))
+        .and(warp::any().map(move || fetcher.clone()))
+        .and_then(
+            move |address, tail, method, range, query, client, store, fetcher| {
+                with_timeout(
+                    timeouts.fetch,
+                    handle_object_download(
+                        address, tail, method, range, query, client, store, fetcher,
+                    ),
+                )
+            },
+        );
+
+    let objects_head = warp::path!("v1" / "os" / Address / ..)
+        .and(warp::path::tail())
+        .and(warp::head())
+        .and(warp::query::<HeightQuery>())
+        .and(with_client(client.clone()))
+        .and_then(move |address, tail, query, client| {
+            with_timeout(
+                timeouts.fetch,
+                handle_object_head(address, tail, query, client),
+            )
+        });
+
+    let objects_list = warp::path!("v1" / "list" / Address)
+        .and(warp::get())
+        .and(warp::query::<ListQuery>())
+        .and(with_client(client.clone()))
+        .and_then(move |address, query, client| {
+            with_timeout(timeouts.list, handle_object_list(address, query, client))
+        });
+
+    let objects_list_by_time = warp::path!("v1" / "list" / Address / "by_time")
+        .and(warp::get())
+        .and(warp::query::<ListByTimeQuery>())
+        .and(with_client(client.clone()))
+        .and_then(move |address, query, client| {
+            with_timeout(
+                timeouts.list,
+                handle_object_list_by_time(address, query, client),
+            )
+        });
+
+    let objects_stat = warp::path!("v1" / "stat" / Address)
+        .and(warp::get())
+        .and(warp::query::<StatQuery>())
+        .and(with_client(client.clone()))
+        .and_then(move |address, query, client| {
+            with_timeout(timeouts.list, handle_object_stat(address, query, client))
+        });
+
+    let objects_claim = warp::path!("v1" / "os" / Address / "claim")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(max_field_length))
+        .and(warp::body::json())
+        .and(with_client(client.clone()))
+        .and_then(handle_object_claim);
+
+    let objects_has_many = warp::path!("v1" / "os" / Address / "has-many")
+        .and(warp::post())
+        .and(warp::query::<HeightQuery>())
+        .and(warp::body::content_length_limit(max_object_length))
+        .and(warp::body::json())
+        .and(with_client(client.clone()))
+        .and_then(handle_object_has_many);
+
+    let objects_metadata = warp::path!("v1" / "os" / Address / "metadata")
+        .and(warp::get())
+        .and(warp::query::<HeightQuery>())
+        .and(with_client(client.clone()))
+        .and_then(handle_os_metadata);
+
+    let objects_can_write = warp::path!("v1" / "os" / Address / "can-write")
+        .and(warp::get())
+        .and(warp::query::<CanWriteQuery>())
+        .and(with_client(client.clone()))
+        .and_then(|address, query, client| {
+            handle_can_write(Kind::ObjectStore, address, query, client)
+        });
+
+    // Accumulator routes
+    let acc_get = warp::path!("v1" / "acc" / Address / u64)
+        .and(warp::get())
+        .and(warp::header::optional::<String>("Accept"))
+        .and(warp::header::optional::<String>("Range"))
+        .and(warp::query::<AccGetQuery>())
+        .and(with_client(client.clone()))
+        .and(with_content_store(content_store.clone()))
+        .and_then(move |address, index, accept, range, query, client, store| {
+            with_timeout(
+                timeouts.fetch,
+                handle_acc_get(address, index, accept, range, query, client, store),
+            )
+        });
+
+    let acc_leaf_status = warp::path!("v1" / "acc" / Address / u64 / "status")
+        .and(warp::get())
+        .and(warp::query::<HeightQuery>())
+        .and(with_client(client.clone()))
+        .and(with_content_store(content_store.clone()))
+        .and_then(move |address, index, query, client, store| {
+            with_timeout(
+                timeouts.fetch,
+                handle_acc_leaf_status(address, index, query, client, store),
+            )
+        });
+
+    let acc_roots = warp::path!("v1" / "acc" / Address / "roots")
+        .and(warp::get())
+        .and(warp::query::<RootsQuery>())
+        .and(with_client(client.clone()))
+        .and_then(handle_acc_roots);
+
+    let acc_diff = warp::path!("v1" / "acc" / Address / "diff")
+        .and(warp::get())
+        .and(warp::query::<DiffQuery>())
+        .and(with_client(client.clone()))
+        .and_then(move |address, query, client| {
+            with_timeout(timeouts.list, handle_acc_diff(address, query, client))
+        });
+
+    let acc_metadata = warp::path!("v1" / "acc" / Address / "metadata")
+        .and(warp::get())
+        .and(warp::query::<HeightQuery>())
+        .and(with_client(client.clone()))
+        .and_then(handle_acc_metadata);
+
+    let acc_summary = warp::path!("v1" / "acc" / Address / "summary")
+        .and(warp::get())
+        .and(warp::query::<HeightQuery>())
+        .and(with_client(client.clone()))
+        .and_then(handle_acc_summary);
+
+    let acc_verify = warp::path!("v1" / "acc" / Address / "verify")
+        .and(warp::get())
+        .and(warp::query::<HeightQuery>())
+        .and(with_client(client.clone()))
+        .and_then(handle_acc_verify);
+
+    let acc_can_write = warp::path!("v1" / "acc" / Address / "can-write")
+        .and(warp::get())
+        .and(warp::query::<CanWriteQuery>())
+        .and(with_client(client.clone()))
+        .and_then(|address, query, client| {
+            handle_can_write(Kind::Accumulator, address, query, client)
+        });
+
+    let acc_export = warp::path!("v1" / "acc" / Address / "export")
+        .and(warp::get())
+        .and(warp::query::<HeightQuery>())
+        .and(with_client(client.clone()))
+        .and_then(move |address, query, client| {
+            with_timeout(timeouts.fetch, handle_acc_export(address, query, client))
+        });
+
+    let acc_import = warp::path!("v1" / "acc" / Address / "import")
+        .and(warp::post())
+        .and(warp::query::<ImportQuery>())
+        .and(warp::body::content_length_limit(max_object_length))
+        .and(warp::body::bytes())
+        .and(with_client(client.clone()))
+        .and_then(handle_acc_import);
+
+    // Machine routes
+    let machines_list = warp::path!("v1" / "machines")
+        .and(warp::get())
+        .and(warp::query::<MachinesQuery>())
+        .and(with_client(client.clone()))
+        .and_then(move |query, client| {
+            with_timeout(timeouts.list, handle_machines_list(query, client))
+        });
+
+    // Transaction routes
+    let tx_status = warp::path!("v1" / "tx" / String)
+        .and(warp::get())
+        .and(with_client(client.clone()))
+        .and_then(handle_tx_status);
+
+    let simulate = warp::path!("v1" / "simulate")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(max_field_length))
+        .and(warp::body::json())
+        .and(with_client(client.clone()))
+        .and_then(handle_simulate);
+
+    let node_routes = objects_upload
+        .or(objects_upload_content)
+        .or(objects_download)
+        .or(objects_head)
+        .or(objects_list)
+        .or(objects_list_by_time)
+        .or(objects_stat)
+        .or(objects_claim)
+        .or(objects_has_many)
+        .or(objects_metadata)
+        .or(objects_can_write)
+        .or(acc_get)
+        .or(acc_leaf_status)
+        .or(acc_roots)
+        .or(acc_diff)
+        .or(acc_metadata)
+        .or(acc_summary)
+        .or(acc_verify)
+        .or(acc_can_write)
+        .or(acc_export)
+        .or(acc_import)
+        .or(machines_list)
+        .or(tx_status)
+        .or(simulate);
+
+    let routes = health_route
+        .or(resolver_stats)
+        .or(with_circuit_breaker(
+            CircuitBreaker::new(breaker),
+            node_routes,
+        ))
+        .with(
+            warp::cors()
+                .allow_any_origin()
+                .allow_headers(vec![
+                    "Content-Type",
+                    "If-Match",
+                    "If-None-Match",
+                    REQUEST_ID_HEADER,
+                ])
+                .allow_methods(vec!["PUT", "DEL", "GET", "HEAD"]),
+        )
+        .map(|reply| Ok::<_, Rejection>(Reply::into_response(reply)))
+        .or_else(|err| async { Ok::<_, Rejection>((Err(err),)) });
+
+    with_request_id()
+        .and(routes)
+        .then(reply_with_request_id)
+        .with(warp::trace(|info| {
+            tracing::info_span!(
+                "request",
+                method = %info.method(),
+                path = info.path(),
+                request_id = tracing::field::Empty,
+            )
+        }))
+}
+
+/// Take the request ID from the request headers, or generate a new one,
+/// and record it in the request span.
+fn with_request_id() -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+    warp::header::optional::<String>(REQUEST_ID_HEADER).map(|request_id: Option<String>| {
+        let request_id = request_id.unwrap_or_else(|| format!("{:032x}", rand::random::<u128>()));
+        tracing::Span::current().record("request_id", request_id.as_str());
+        request_id
+    })
+}
+
+/// Guard routes that call the node with `breaker`, failing them fast while the circuit is open.
+fn with_circuit_breaker<F, R>(
+    breaker: CircuitBreaker,
+    routes: F,
+) -> impl Filter<Extract = (warp::reply::Response,), Error = Rejection> + Clone
+where
+    F: Filter<Extract = (R,), Error = Rejection> + Clone + Send + Sync + 'static,
+    R: Reply + Send,
+{
+    let routes = routes
+        .map(|reply| Ok::<_, Rejection>(Reply::into_response(reply)))
+        .or_else(|err| async { Ok::<_, Rejection>((Err(err),)) });
+    warp::any()
+        .map(move || breaker.clone())
+        .and_then(|breaker: CircuitBreaker| async move { breaker.acquire().map(|_| breaker) })
+        .and(routes)
+        .and_then(
+            |breaker: CircuitBreaker, result: Result<warp::reply::Response, Rejection>| async move {
+                breaker.record(&result);
+                result
+            },
+        )
+}
+
+/// Run a handler, rejecting with a gateway timeout if it doesn't reply within `timeout`.
+async fn with_timeout<T>(
+    timeout: Duration,
+    handler: impl Future<Output = Result<T, Rejection>>,
+) -> Result<T, Rejection> {
+    tokio::time::timeout(timeout, handler)
+        .await
+        .map_err(|_| Rejection::from(Timeout))?
+}
+
+fn with_client(
+    client: FendermintClient,
+) -> impl Filter<Extract = (FendermintClient,), Error = Infallible> + Clone {
+    warp::any().map(move || client.clone())
+}
+
+fn with_content_store<S: ContentStore + Clone + Send>(
+    store: S,
+) -> impl Filter<Extract = (S,), Error = Infallible> + Clone {
+    warp::any().map(move || store.clone())
+}
+
+/// A condition on the current value of the key written by an upload.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Precondition {
+    /// The key must hold an object with this CID, from `If-Match: <cid>`.
+    Match(Cid),
+    /// The key must be absent, from `If-None-Match: *`.
+    Absent,
+}
+
+impl Precondition {
+    /// The value the key is expected to have, as in
+    /// [`fendermint_actor_objectstore::SwapParams::expected_cid`].
+    fn expected_cid(&self) -> Option<Cid> {
+        match self {
+            Precondition::Match(cid) => Some(*cid),
+            Precondition::Absent => None,
+        }
+    }
+}
+
+/// Take the precondition of an upload from the `If-Match` and `If-None-Match` headers.
+fn with_precondition() -> impl Filter<Extract = (Option<Precondition>,), Error = Rejection> + Clone
+{
+    warp::header::optional::<String>("If-Match")
+        .and(warp::header::optional::<String>("If-None-Match"))
+        .and_then(
+            |if_match: Option<String>, if_none_match: Option<String>| async move {
+                parse_precondition(if_match.as_deref(), if_none_match.as_deref())
+            },
+        )
+}
+
+fn parse_precondition(
+    if_match: Option<&str>,
+    if_none_match: Option<&str>,
+) -> Result<Option<Precondition>, Rejection> {
+    match (if_match, if_none_match) {
+        (None, None) => Ok(None),
+        (Some(_), Some(_)) => Err(Rejection::from(BadRequest {
+            message: "If-Match and If-None-Match cannot be combined".to_string(),
+        })),
+        (Some(tag), None) => {
+            // Entity tags are quoted, but a bare CID is accepted as well.
+            let tag = tag.trim();
+            let tag = tag
+                .strip_prefix('"')
+                .and_then(|tag| tag.strip_suffix('"'))
+                .unwrap_or(tag);
+            let cid = Cid::try_from(tag).map_err(|e| {
+                Rejection::from(BadRequest {
+                    message: format!("invalid If-Match cid: {}", e),
+                })
+            })?;
+            Ok(Some(Precondition::Match(cid)))
+        }
+        (None, Some(tag)) if tag.trim() == "*" => Ok(Some(Precondition::Absent)),
+        (None, Some(_)) => Err(Rejection::from(BadRequest {
+            message: "only `If-None-Match: *` is supported".to_string(),
+        })),
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct HeightQuery {
+    pub height: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CanWriteQuery {
+    /// The address to check, in any format the chain can resolve.
+    pub addr: String,
+    pub height: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct AccGetQuery {
+    pub height: Option<u64>,
+    /// Return the leaf as JSON, with its inclusion proof and the root it verifies against.
+    #[serde(default)]
+    pub proof: bool,
+    /// If the leaf is a CID, return the content it points to instead of the leaf.
+    #[serde(default)]
+    pub resolve: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StatQuery {
+    pub prefix: Option<String>,
+    pub height: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RootsQuery {
+    /// Comma-separated list of heights.
+    pub heights: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DiffQuery {
+    pub from_height: u64,
+    pub to_height: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{collections::HashMap, sync::Arc};
+
+    use async_tempfile::TempFile;
+    use fendermint_actor_objectstore::{Object, ObjectKind};
+    use fendermint_rpc::proxy::{ProxyClient, TxStatus};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use warp::{http::StatusCode, hyper::body::Body};
+
+    use crate::cmd::objects::rejection::NotFound;
+    use crate::cmd::objects::store::ObjectRange;
+    use crate::cmd::objects::testing::{
+        abci_query_response, form_body, raw_cid, tx_response, MemoryContentStore, TX_HASH,
+    };
+
+    /// Serve the proxy in-process, backed by a CometBFT endpoint which answers every request
+    /// with `response`, and return a client for it.
+    async fn spawn_proxy(response: String) -> ProxyClient {
+        let comet = warp::post().map(move || response.clone());
+        let (comet_addr, comet_server) = warp::serve(comet).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(comet_server);
+
+        let comet_url = format!("http://{comet_addr}").parse().unwrap();
+        let client = FendermintClient::new_http(comet_url, None).unwrap();
+        let content_store = IpfsContentStore {
+            inner: IpfsClient::default(),
+        };
+        let (proxy_addr, proxy_server) = warp::serve(router(
+            client,
+            content_store,
+            1024 * 1024,
+            1024,
+            ReadTimeouts::default(),
+            CircuitBreakerConfig::default(),
+            FetchConfig::default(),
+            None,
+        ))
+        .bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(proxy_server);
+
+        ProxyClient::new(format!("http://{proxy_addr}").parse().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_proxy_client() {
+        let proxy = spawn_proxy(tx_response(0, "")).await;
+        proxy.health().await.unwrap();
+
+        let txn = proxy.tx_status(&TX_HASH.to_lowercase()).await.unwrap();
+        assert_eq!(txn.status, TxStatus::Committed);
+        assert_eq!(txn.hash, TX_HASH);
+        assert_eq!(txn.height, Some(42));
+        assert_eq!(txn.gas_used, Some(80));
+
+        let err = proxy.tx_status("not-a-hash").await.unwrap_err();
+        assert!(err.to_string().contains("invalid transaction hash"));
+    }
+
+    #[tokio::test]
+    async fn test_object_upload_too_large() {
+        let client =
+            FendermintClient::new_http("http://127.0.0.1:26657".parse().unwrap(), None).unwrap();
+        let content_store = IpfsContentStore {
+            inner: IpfsClient::default(),
+        };
+        let max_object_length = 1024;
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/v1/objects")
+            .header("Content-Type", "multipart/form-data; boundary=boundary")
+            .body(vec![0u8; max_object_length as usize + 1])
+            .reply(&router(
+                client,
+                content_store,
+                max_object_length,
+                1024,
+                ReadTimeouts::default(),
+                CircuitBreakerConfig::default(),
+                FetchConfig::default(),
+                None,
+            ))
+            .await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_object_upload_field_too_large() {
+        let client =
+            FendermintClient::new_http("http://127.0.0.1:26657".parse().unwrap(), None).unwrap();
+        let content_store = IpfsContentStore {
+            inner: IpfsClient::default(),
+        };
+        let max_field_length = 64;
+        let boundary = "--abcdef1234--";
+        let upload = |msg: String| {
+            warp::test::request()
+                .method("POST")
+                .path("/v1/objects")
+                .header(
+                    "Content-Type",
+                    format!("multipart/form-data; boundary={}", boundary),
+                )
+                .body(form_body(boundary, &msg, b"hello world"))
+        };
+        let router = router(
+            client,
+            content_store,
+            1024 * 1024,
+            max_field_length,
+            ReadTimeouts::default(),
+            CircuitBreakerConfig::default(),
+            FetchConfig::default(),
+            None,
+        );
+
+        let response = upload("a".repeat(max_field_length as usize + 1))
+            .reply(&router)
+            .await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // A field within the limit is read, and fails to decode.
+        let response = upload("a".repeat(max_field_length as usize))
+            .reply(&router)
+            .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_request_id_preserved() {
+        let request_id = warp::test::request()
+            .header(REQUEST_ID_HEADER, "abc123")
+            .filter(&with_request_id())
+            .await
+            .unwrap();
+        assert_eq!(request_id, "abc123");
+
+        let response = reply_with_request_id(request_id, Ok(warp::reply().into_response())).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get(REQUEST_ID_HEADER).unwrap(), "abc123");
+    }
+
+    #[tokio::test]
+    async fn test_request_id_generated() {
+        let request_id = warp::test::request()
+            .filter(&with_request_id())
+            .await
+            .unwrap();
+        assert_eq!(request_id.len(), 32);
+
+        let response =
+            reply_with_request_id(request_id.clone(), Err(Rejection::from(NotFound))).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            response.headers().get(REQUEST_ID_HEADER).unwrap(),
+            request_id.as_str()
+        );
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let err: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(err["request_id"], request_id);
+    }
+
+    #[test]
+    fn test_listen_addr() {
+        let listen = SocketAddress {
+            host: "127.0.0.1".to_string(),
+            port: 8001,
+        };
+        assert_eq!(
+            listen_addr(&listen).unwrap(),
+            SocketAddr::from(([127, 0, 0, 1], 8001))
+        );
+
+        let listen = SocketAddress {
+            host: "127.0.0.1".to_string(),
+            port: 80010,
+        };
+        let err = listen_addr(&listen).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("invalid listen address '127.0.0.1:80010': "),
+            "unexpected error: {err}"
+        );
+    }
+
+    #[test]
+    fn test_parse_precondition() {
+        let cid = raw_cid(b"hello world");
+        assert_eq!(parse_precondition(None, None).unwrap(), None);
+        assert_eq!(
+            parse_precondition(Some(&format!("\"{}\"", cid)), None).unwrap(),
+            Some(Precondition::Match(cid))
+        );
+        assert_eq!(
+            parse_precondition(Some(&cid.to_string()), None).unwrap(),
+            Some(Precondition::Match(cid))
+        );
+        assert_eq!(
+            parse_precondition(None, Some("*")).unwrap(),
+            Some(Precondition::Absent)
+        );
+        for (if_match, if_none_match) in [
+            (Some("nope"), None),
+            (None, Some("\"etag\"")),
+            (Some("*"), Some("*")),
+        ] {
+            let err = parse_precondition(if_match, if_none_match).unwrap_err();
+            assert!(err.find::<BadRequest>().is_some());
+        }
+    }
+
+    /// Content store serving endless content, which notifies `dropped` once a fetch of it is
+    /// dropped. If `stall` is set, fetches never start instead.
+    #[derive(Clone)]
+    struct EndlessContentStore {
+        dropped: Arc<tokio::sync::Notify>,
+        stall: bool,
+    }
+
+    /// Notifies when dropped along with the stream holding it.
+    struct DropGuard(Arc<tokio::sync::Notify>);
+
+    impl Drop for DropGuard {
+        fn drop(&mut self) {
+            self.0.notify_one();
+        }
+    }
+
+    impl ContentStore for EndlessContentStore {
+        async fn put(&self, _temp_file: TempFile) -> anyhow::Result<Cid> {
+            Err(anyhow!("read only"))
+        }
+
+        async fn get(&self, _cid: Cid, _range: Option<String>) -> anyhow::Result<ObjectRange> {
+            if self.stall {
+                std::future::pending::<()>().await;
+            }
+            let guard = DropGuard(self.dropped.clone());
+            let chunks = futures_util::stream::unfold(guard, |guard| async move {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                Some((Ok::<_, std::io::Error>(vec![0u8; 1024]), guard))
+            });
+            let size = 1 << 30;
+            Ok(ObjectRange {
+                start: 0,
+                end: size - 1,
+                len: size,
+                size,
+                body: Body::wrap_stream(chunks),
+            })
+        }
+
+        async fn has(&self, _cid: Cid) -> anyhow::Result<bool> {
+            Ok(true)
+        }
+
+        async fn fetch(&self, _cid: Cid) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Start a mock CometBFT on `addr`, answering every query with a resolved object.
+    fn serve_object_comet(addr: impl Into<std::net::SocketAddr>) -> std::net::SocketAddr {
+        let object = Object {
+            cid: fvm_ipld_encoding::strict_bytes::ByteBuf(raw_cid(b"endless").to_bytes()),
+            size: 1 << 30,
+            resolved: true,
+            resolved_root: None,
+            failed: false,
+            kind: ObjectKind::External,
+            data: None,
+            created_at: 0,
+            metadata: HashMap::new(),
+        };
+        let response = abci_query_response(&Some(object));
+        let comet = warp::post().map(move || response.clone());
+        let (comet_addr, comet_server) = warp::serve(comet).bind_ephemeral(addr);
+        tokio::spawn(comet_server);
+        comet_addr
+    }
+
+    /// Start a mock CometBFT answering every query with a resolved object.
+    fn object_client() -> FendermintClient {
+        let comet_addr = serve_object_comet(([127, 0, 0, 1], 0));
+        let comet_url = format!("http://{comet_addr}").parse().unwrap();
+        FendermintClient::new_http(comet_url, None).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_dropped_download_cancels_fetch() {
+        let dropped = Arc::new(tokio::sync::Notify::new());
+        let store = EndlessContentStore {
+            dropped: dropped.clone(),
+            stall: false,
+        };
+        let router = router(
+            object_client(),
+            store,
+            1024,
+            1024,
+            ReadTimeouts::default(),
+            CircuitBreakerConfig::default(),
+            FetchConfig::default(),
+            None,
+        );
+        let (proxy_addr, proxy_server) = warp::serve(router).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(proxy_server);
+
+        let mut conn = tokio::net::TcpStream::connect(proxy_addr).await.unwrap();
+        let request = format!(
+            "GET /v1/objects/{}/foo HTTP/1.1\r\nHost: localhost\r\n\r\n",
+            Address::new_id(90)
+        );
+        conn.write_all(request.as_bytes()).await.unwrap();
+
+        // Hang up once the content has started streaming.
+        let mut buf = [0u8; 4096];
+        let n = conn.read(&mut buf).await.unwrap();
+        assert!(buf[..n].starts_with(b"HTTP/1.1 200"));
+        drop(conn);
+
+        tokio::time::timeout(Duration::from_secs(5), dropped.notified())
+            .await
+            .expect("fetch should be cancelled when the client hangs up");
+    }
+
+    #[tokio::test]
+    async fn test_download_timeout() {
+        let store = EndlessContentStore {
+            dropped: Arc::new(tokio::sync::Notify::new()),
+            stall: true,
+        };
+        let timeouts = ReadTimeouts {
+            fetch: Duration::from_millis(50),
+            ..Default::default()
+        };
+        let response = warp::test::request()
+            .method("GET")
+            .path(&format!("/v1/objects/{}/foo", Address::new_id(90)))
+            .reply(&router(
+                object_client(),
+                store,
+                1024,
+                1024,
+                timeouts,
+                CircuitBreakerConfig::default(),
+                FetchConfig::default(),
+                None,
+            ))
+            .await;
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker() {
+        // Reserve an address for the node, which is down until we start it.
+        let comet_addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let comet_url = format!("http://{comet_addr}").parse().unwrap();
+        let client = FendermintClient::new_http(comet_url, None).unwrap();
+        let breaker = CircuitBreakerConfig {
+            threshold: 2,
+            cooldown: Duration::from_millis(200),
+        };
+        let router = router(
+            client,
+            MemoryContentStore::default(),
+            1024,
+            1024,
+            ReadTimeouts::default(),
+            breaker,
+            FetchConfig::default(),
+            None,
+        );
+        let head = || {
+            warp::test::request()
+                .method("HEAD")
+                .path(&format!("/v1/os/{}/foo", Address::new_id(90)))
+                .reply(&router)
+        };
+
+        // Consecutive failures to reach the node open the circuit...
+        assert_eq!(head().await.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(head().await.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(head().await.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        // ...which keeps failing fast for the cooldown, even once the node is back.
+        serve_object_comet(comet_addr);
+        assert_eq!(head().await.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        // After the cooldown a probe goes through, and its success closes the circuit.
+        tokio::time::sleep(breaker.cooldown).await;
+        assert_eq!(head().await.status(), StatusCode::OK);
+        assert_eq!(head().await.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,290 @@
+// Copyright 2024 Textile
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use cid::Cid;
+use fendermint_actor_accumulator::GetWithProofReturn;
+use fendermint_actor_machine::Metadata as MachineMetadata;
+use fendermint_actor_objectstore::{
+    GetParams, HasManyParams, ListByTimeParams, ListParams, Object, ObjectList, ObjectStat,
+    StatParams,
+};
+use fendermint_rpc::{message::GasParams, QueryClient};
+use fendermint_vm_actor_interface::adm::Metadata;
+use fendermint_vm_message::query::FvmQueryHeight;
+use fvm_shared::{address::Address, econ::TokenAmount};
+
+pub async fn os_get<F: QueryClient + Send + Sync>(
+    mut client: F,
+    address: Address,
+    params: GetParams,
+    height: u64,
+) -> anyhow::Result<Option<Object>> {
+    let gas_params = GasParams {
+        gas_limit: Default::default(),
+        gas_fee_cap: Default::default(),
+        gas_premium: Default::default(),
+    };
+    let h = FvmQueryHeight::from(height);
+
+    let return_data = client
+        .os_get_call(address, params, TokenAmount::default(), gas_params, h)
+        .await?;
+
+    Ok(return_data)
+}
+
+pub async fn os_has_many<F: QueryClient + Send + Sync>(
+    mut client: F,
+    address: Address,
+    params: HasManyParams,
+    height: u64,
+) -> anyhow::Result<Vec<bool>> {
+    let gas_params = GasParams {
+        gas_limit: Default::default(),
+        gas_fee_cap: Default::default(),
+        gas_premium: Default::default(),
+    };
+    let h = FvmQueryHeight::from(height);
+
+    let return_data = client
+        .os_has_many_call(address, params, TokenAmount::default(), gas_params, h)
+        .await?;
+
+    Ok(return_data)
+}
+
+pub async fn os_list<F: QueryClient + Send + Sync>(
+    mut client: F,
+    address: Address,
+    params: ListParams,
+    height: u64,
+) -> anyhow::Result<ObjectList> {
+    let gas_params = GasParams {
+        gas_limit: Default::default(),
+        gas_fee_cap: Default::default(),
+        gas_premium: Default::default(),
+    };
+    let h = FvmQueryHeight::from(height);
+
+    let return_data = client
+        .os_list_call(address, params, TokenAmount::default(), gas_params, h)
+        .await?;
+
+    Ok(return_data)
+}
+
+pub async fn os_list_by_time<F: QueryClient + Send + Sync>(
+    mut client: F,
+    address: Address,
+    params: ListByTimeParams,
+    height: u64,
+) -> anyhow::Result<ObjectList> {
+    let gas_params = GasParams {
+        gas_limit: Default::default(),
+        gas_fee_cap: Default::default(),
+        gas_premium: Default::default(),
+    };
+    let h = FvmQueryHeight::from(height);
+
+    let return_data = client
+        .os_list_by_time_call(address, params, TokenAmount::default(), gas_params, h)
+        .await?;
+
+    Ok(return_data)
+}
+
+pub async fn os_stat<F: QueryClient + Send + Sync>(
+    mut client: F,
+    address: Address,
+    params: StatParams,
+    height: u64,
+) -> anyhow::Result<ObjectStat> {
+    let gas_params = GasParams {
+        gas_limit: Default::default(),
+        gas_fee_cap: Default::default(),
+        gas_premium: Default::default(),
+    };
+    let h = FvmQueryHeight::from(height);
+
+    let return_data = client
+        .os_stat_call(address, params, TokenAmount::default(), gas_params, h)
+        .await?;
+
+    Ok(return_data)
+}
+
+pub async fn adm_list_metadata<F: QueryClient + Send + Sync>(
+    mut client: F,
+    owner: Address,
+    height: u64,
+) -> anyhow::Result<Vec<Metadata>> {
+    let gas_params = GasParams {
+        gas_limit: Default::default(),
+        gas_fee_cap: Default::default(),
+        gas_premium: Default::default(),
+    };
+    let h = FvmQueryHeight::from(height);
+
+    let return_data = client
+        .adm_list_metadata_call(owner, TokenAmount::default(), gas_params, h)
+        .await?;
+
+    Ok(return_data)
+}
+
+pub async fn acc_get<F: QueryClient + Send + Sync>(
+    mut client: F,
+    address: Address,
+    index: u64,
+    height: u64,
+) -> anyhow::Result<Option<Vec<u8>>> {
+    let gas_params = GasParams {
+        gas_limit: Default::default(),
+        gas_fee_cap: Default::default(),
+        gas_premium: Default::default(),
+    };
+    let h = FvmQueryHeight::from(height);
+
+    let return_data = client
+        .acc_get_call(address, index, TokenAmount::default(), gas_params, h)
+        .await?;
+
+    Ok(return_data)
+}
+
+pub async fn acc_get_with_proof<F: QueryClient + Send + Sync>(
+    mut client: F,
+    address: Address,
+    index: u64,
+    height: u64,
+) -> anyhow::Result<Option<GetWithProofReturn>> {
+    let gas_params = GasParams {
+        gas_limit: Default::default(),
+        gas_fee_cap: Default::default(),
+        gas_premium: Default::default(),
+    };
+    let h = FvmQueryHeight::from(height);
+
+    let return_data = client
+        .acc_get_with_proof_call(address, index, TokenAmount::default(), gas_params, h)
+        .await?;
+
+    Ok(return_data)
+}
+
+pub async fn acc_get_cid<F: QueryClient + Send + Sync>(
+    mut client: F,
+    address: Address,
+    index: u64,
+    height: u64,
+) -> anyhow::Result<Option<Cid>> {
+    let gas_params = GasParams {
+        gas_limit: Default::default(),
+        gas_fee_cap: Default::default(),
+        gas_premium: Default::default(),
+    };
+    let h = FvmQueryHeight::from(height);
+
+    let return_data = client
+        .acc_get_cid_call(address, index, TokenAmount::default(), gas_params, h)
+        .await?;
+
+    Ok(return_data)
+}
+
+pub async fn acc_root<F: QueryClient + Send + Sync>(
+    mut client: F,
+    address: Address,
+    height: u64,
+) -> anyhow::Result<Cid> {
+    let gas_params = GasParams {
+        gas_limit: Default::default(),
+        gas_fee_cap: Default::default(),
+        gas_premium: Default::default(),
+    };
+    let h = FvmQueryHeight::from(height);
+
+    let return_data = client
+        .acc_root_call(address, TokenAmount::default(), gas_params, h)
+        .await?;
+
+    Ok(return_data)
+}
+
+pub async fn acc_peaks<F: QueryClient + Send + Sync>(
+    mut client: F,
+    address: Address,
+    height: u64,
+) -> anyhow::Result<Vec<Cid>> {
+    let gas_params = GasParams {
+        gas_limit: Default::default(),
+        gas_fee_cap: Default::default(),
+        gas_premium: Default::default(),
+    };
+    let h = FvmQueryHeight::from(height);
+
+    let return_data = client
+        .acc_peaks_call(address, TokenAmount::default(), gas_params, h)
+        .await?;
+
+    Ok(return_data)
+}
+
+pub async fn acc_verify_integrity<F: QueryClient + Send + Sync>(
+    mut client: F,
+    address: Address,
+    height: u64,
+) -> anyhow::Result<Option<String>> {
+    let gas_params = GasParams {
+        gas_limit: Default::default(),
+        gas_fee_cap: Default::default(),
+        gas_premium: Default::default(),
+    };
+    let h = FvmQueryHeight::from(height);
+
+    let return_data = client
+        .acc_verify_integrity_call(address, TokenAmount::default(), gas_params, h)
+        .await?;
+
+    Ok(return_data)
+}
+
+pub async fn machine_get_metadata<F: QueryClient + Send + Sync>(
+    mut client: F,
+    address: Address,
+    height: u64,
+) -> anyhow::Result<MachineMetadata> {
+    let gas_params = GasParams {
+        gas_limit: Default::default(),
+        gas_fee_cap: Default::default(),
+        gas_premium: Default::default(),
+    };
+    let h = FvmQueryHeight::from(height);
+
+    let return_data = client
+        .machine_get_metadata_call(address, TokenAmount::default(), gas_params, h)
+        .await?;
+
+    Ok(return_data)
+}
+
+pub async fn machine_can_write<F: QueryClient + Send + Sync>(
+    mut client: F,
+    address: Address,
+    who: Address,
+    height: u64,
+) -> anyhow::Result<bool> {
+    let gas_params = GasParams {
+        gas_limit: Default::default(),
+        gas_fee_cap: Default::default(),
+        gas_premium: Default::default(),
+    };
+    let h = FvmQueryHeight::from(height);
+
+    let return_data = client
+        .machine_can_write_call(address, who, TokenAmount::default(), gas_params, h)
+        .await?;
+
+    Ok(return_data)
+}
@@ -0,0 +1,297 @@
+// Copyright 2024 Textile
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use fendermint_rpc::{proxy::ErrorMessage, query::CallError};
+use fvm_shared::error::ExitCode;
+use tendermint_rpc::error::ErrorDetail;
+use thiserror::Error;
+use warp::{
+    http::{HeaderValue, StatusCode},
+    Rejection, Reply,
+};
+
+use super::REQUEST_ID_HEADER;
+
+#[derive(Clone, Debug)]
+pub struct BadRequest {
+    pub message: String,
+}
+
+impl warp::reject::Reject for BadRequest {}
+
+#[derive(Debug)]
+pub struct NotFound;
+
+impl warp::reject::Reject for NotFound {}
+
+#[derive(Debug)]
+pub struct NotAcceptable;
+
+impl warp::reject::Reject for NotAcceptable {}
+
+#[derive(Debug)]
+pub struct Timeout;
+
+impl warp::reject::Reject for Timeout {}
+
+/// The node couldn't be reached.
+#[derive(Clone, Debug)]
+pub struct Upstream {
+    pub message: String,
+}
+
+impl warp::reject::Reject for Upstream {}
+
+/// The current value of a key doesn't match the precondition of a request.
+#[derive(Clone, Debug)]
+pub struct PreconditionFailed {
+    pub message: String,
+}
+
+impl warp::reject::Reject for PreconditionFailed {}
+
+/// The circuit breaker is open, so the node isn't even tried.
+#[derive(Debug)]
+pub struct Unavailable;
+
+impl warp::reject::Reject for Unavailable {}
+
+#[derive(Clone, Debug, Error)]
+#[error("form field `{field}` exceeds maximum length of {max} bytes")]
+pub struct FieldTooLarge {
+    pub field: String,
+    pub max: u64,
+}
+
+impl warp::reject::Reject for FieldTooLarge {}
+
+/// A call aborted by the actor with a well-known exit code.
+#[derive(Clone, Debug)]
+struct ActorAbort {
+    status: StatusCode,
+    message: String,
+}
+
+impl warp::reject::Reject for ActorAbort {}
+
+/// The HTTP status matching a well-known actor exit code.
+fn exit_code_status(exit_code: ExitCode) -> Option<StatusCode> {
+    match exit_code {
+        ExitCode::USR_NOT_FOUND => Some(StatusCode::NOT_FOUND),
+        ExitCode::USR_FORBIDDEN => Some(StatusCode::FORBIDDEN),
+        ExitCode::USR_ILLEGAL_ARGUMENT
+        | ExitCode::USR_SERIALIZATION
+        | ExitCode::USR_UNHANDLED_MESSAGE => Some(StatusCode::BAD_REQUEST),
+        ExitCode::USR_INSUFFICIENT_FUNDS | ExitCode::SYS_INSUFFICIENT_FUNDS => {
+            Some(StatusCode::PAYMENT_REQUIRED)
+        }
+        _ => None,
+    }
+}
+
+/// Reject a failed call, with the status matching the exit code if the actor aborted
+/// with a well-known one, as a bad gateway if the node couldn't be reached, and as a bad
+/// request otherwise.
+pub fn call_rejection(e: anyhow::Error, context: &str) -> Rejection {
+    let message = format!("{}: {}", context, e);
+    if e.downcast_ref::<tendermint_rpc::Error>()
+        .is_some_and(is_upstream_error)
+    {
+        return Rejection::from(Upstream { message });
+    }
+    match e
+        .downcast_ref::<CallError>()
+        .and_then(|e| exit_code_status(e.exit_code))
+    {
+        Some(status) => Rejection::from(ActorAbort { status, message }),
+        None => Rejection::from(BadRequest { message }),
+    }
+}
+
+/// Whether the node couldn't be reached, or didn't give a valid response, as opposed to
+/// answering with an error.
+pub fn is_upstream_error(err: &tendermint_rpc::Error) -> bool {
+    !matches!(err.detail(), ErrorDetail::Response(_))
+}
+
+/// Turn rejections into error replies, and echo the request ID in the response headers.
+pub async fn reply_with_request_id(
+    request_id: String,
+    result: Result<warp::reply::Response, Rejection>,
+) -> warp::reply::Response {
+    let mut response = match result {
+        Ok(response) => response,
+        Err(err) => handle_rejection(err, &request_id).into_response(),
+    };
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+pub fn handle_rejection(err: Rejection, request_id: &str) -> impl Reply {
+    let (code, message) = if err.is_not_found() || err.find::<NotFound>().is_some() {
+        (StatusCode::NOT_FOUND, "Not Found".to_string())
+    } else if let Some(e) = err.find::<BadRequest>() {
+        let err = e.to_owned();
+        (StatusCode::BAD_REQUEST, err.message)
+    } else if err.find::<NotAcceptable>().is_some() {
+        (StatusCode::NOT_ACCEPTABLE, "Not Acceptable".to_string())
+    } else if err.find::<Timeout>().is_some() {
+        (StatusCode::GATEWAY_TIMEOUT, "Gateway Timeout".to_string())
+    } else if let Some(e) = err.find::<Upstream>() {
+        (StatusCode::BAD_GATEWAY, e.message.clone())
+    } else if err.find::<Unavailable>().is_some() {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Service Unavailable".to_string(),
+        )
+    } else if err.find::<warp::reject::PayloadTooLarge>().is_some() {
+        (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "Payload too large".to_string(),
+        )
+    } else if let Some(e) = err.find::<FieldTooLarge>() {
+        (StatusCode::PAYLOAD_TOO_LARGE, e.to_string())
+    } else if let Some(e) = err.find::<ActorAbort>() {
+        (e.status, e.message.clone())
+    } else if let Some(e) = err.find::<PreconditionFailed>() {
+        (StatusCode::PRECONDITION_FAILED, e.message.clone())
+    } else {
+        (StatusCode::INTERNAL_SERVER_ERROR, format!("{:?}", err))
+    };
+
+    if code.is_server_error() {
+        tracing::error!(code = code.as_u16(), %message, "request failed");
+    } else {
+        tracing::debug!(code = code.as_u16(), %message, "request rejected");
+    }
+
+    let reply = warp::reply::json(&ErrorMessage {
+        code: code.as_u16(),
+        message,
+        request_id: request_id.to_string(),
+    });
+    let reply = warp::reply::with_header(reply, "Access-Control-Allow-Origin", "*");
+    warp::reply::with_status(reply, code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    use cid::{
+        multihash::{Code, MultihashDigest},
+        Cid,
+    };
+    use fendermint_actor_machine::WriteAccess;
+    use fendermint_actor_objectstore::State as ObjectStoreState;
+    use fvm_ipld_blockstore::MemoryBlockstore;
+    use fvm_ipld_encoding::IPLD_RAW;
+    use fvm_ipld_hamt::BytesKey;
+    use fvm_shared::address::Address;
+
+    #[test]
+    fn test_call_rejection_status() {
+        let cases = [
+            (ExitCode::USR_NOT_FOUND, StatusCode::NOT_FOUND),
+            (ExitCode::USR_FORBIDDEN, StatusCode::FORBIDDEN),
+            (ExitCode::USR_ILLEGAL_ARGUMENT, StatusCode::BAD_REQUEST),
+            (ExitCode::USR_SERIALIZATION, StatusCode::BAD_REQUEST),
+            (ExitCode::USR_UNHANDLED_MESSAGE, StatusCode::BAD_REQUEST),
+            (
+                ExitCode::USR_INSUFFICIENT_FUNDS,
+                StatusCode::PAYMENT_REQUIRED,
+            ),
+            (
+                ExitCode::SYS_INSUFFICIENT_FUNDS,
+                StatusCode::PAYMENT_REQUIRED,
+            ),
+            // Codes without a well-known meaning stay bad requests.
+            (ExitCode::USR_ILLEGAL_STATE, StatusCode::BAD_REQUEST),
+            (ExitCode::new(35), StatusCode::BAD_REQUEST),
+        ];
+        for (exit_code, status) in cases {
+            let e = anyhow::Error::from(CallError {
+                exit_code,
+                info: "aborted".to_string(),
+            });
+            let rejection = call_rejection(e, "objectstore get error");
+            let response = handle_rejection(rejection, "id").into_response();
+            assert_eq!(response.status(), status, "exit code {}", exit_code.value());
+        }
+
+        // Errors other than aborted calls stay bad requests.
+        let rejection = call_rejection(anyhow::anyhow!("failed"), "objectstore get error");
+        let response = handle_rejection(rejection, "id").into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_objectstore_error_status() {
+        use fendermint_actor_objectstore::ObjectStoreError;
+
+        let store = MemoryBlockstore::default();
+        let mut state =
+            ObjectStoreState::new(&store, Address::new_id(100), WriteAccess::Public).unwrap();
+        let key = BytesKey(b"a".to_vec());
+        let cid = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(b"a"));
+        state
+            .add(&store, key.clone(), cid, 1, HashMap::new(), false, 1)
+            .unwrap();
+        let other = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(b"b"));
+
+        // Errors of the store abort the actor with their exit code, which the proxy maps on.
+        let cases = [
+            (
+                state
+                    .swap(
+                        &store,
+                        key.clone(),
+                        Some(other),
+                        other,
+                        1,
+                        HashMap::new(),
+                        1,
+                    )
+                    .unwrap_err(),
+                StatusCode::FORBIDDEN,
+            ),
+            (
+                state
+                    .delete(&store, &BytesKey(b"b".to_vec()), 1)
+                    .unwrap_err(),
+                StatusCode::NOT_FOUND,
+            ),
+            (
+                state
+                    .restore(&store, BytesKey(b"b".to_vec()), 1)
+                    .unwrap_err(),
+                StatusCode::NOT_FOUND,
+            ),
+            (
+                state
+                    .update_metadata(
+                        &store,
+                        key.clone(),
+                        HashMap::from([("k".repeat(257), String::new())]),
+                        false,
+                    )
+                    .unwrap_err(),
+                StatusCode::BAD_REQUEST,
+            ),
+        ];
+        for (e, status) in cases {
+            let exit_code = e.downcast_ref::<ObjectStoreError>().unwrap().exit_code();
+            let e = anyhow::Error::from(CallError {
+                exit_code,
+                info: e.to_string(),
+            });
+            let rejection = call_rejection(e, "objectstore error");
+            let response = handle_rejection(rejection, "id").into_response();
+            assert_eq!(response.status(), status, "exit code {}", exit_code.value());
+        }
+    }
+}
@@ -0,0 +1,204 @@
+// Copyright 2024 Textile
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::{
+    convert::Infallible,
+    io,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::{TcpListener, TcpStream},
+    sync::{OwnedSemaphorePermit, Semaphore},
+};
+use warp::{
+    hyper::{server::accept, service::make_service_fn, Server},
+    Filter, Rejection,
+};
+
+/// Settings of the HTTP server, for clients making many small requests over persistent
+/// connections.
+#[derive(Clone, Copy, Debug)]
+pub struct ServerConfig {
+    /// Whether HTTP/2 is served next to HTTP/1.1. Clients have to use it with prior knowledge,
+    /// as the proxy doesn't terminate TLS.
+    pub http2: bool,
+    /// Interval of the pings on HTTP/2 connections, which are closed if a ping isn't answered
+    /// within another interval. `None` disables keep-alive, so that HTTP/1.1 connections are
+    /// also closed after each response.
+    pub keep_alive: Option<Duration>,
+    /// Maximum number of concurrent requests on an HTTP/2 connection.
+    pub max_concurrent_streams: u32,
+    /// Maximum number of open connections, or 0 for no limit. Connections over the limit are
+    /// only accepted once another one is closed.
+    pub max_connections: usize,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            http2: true,
+            keep_alive: Some(Duration::from_secs(20)),
+            max_concurrent_streams: 256,
+            max_connections: 4096,
+        }
+    }
+}
+
+/// A connection holding its permit of the connection limit until it's closed.
+struct LimitedConn {
+    stream: TcpStream,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl AsyncRead for LimitedConn {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for LimitedConn {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().stream).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().stream).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.stream.is_write_vectored()
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_shutdown(cx)
+    }
+}
+
+/// Serve the router on the listener until the server fails.
+///
+/// `warp::serve` doesn't expose the settings of the underlying hyper server, so the router is
+/// served by hyper directly.
+pub async fn serve<F>(router: F, listener: TcpListener, config: ServerConfig) -> anyhow::Result<()>
+where
+    F: Filter<Extract = (warp::reply::Response,), Error = Rejection>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+{
+    let limit =
+        (config.max_connections > 0).then(|| Arc::new(Semaphore::new(config.max_connections)));
+    let incoming = futures_util::stream::unfold(listener, move |listener| {
+        let limit = limit.clone();
+        async move {
+            let permit = match limit {
+                Some(limit) => Some(
+                    limit
+                        .acquire_owned()
+                        .await
+                        .expect("the connection limit is never closed"),
+                ),
+                None => None,
+            };
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        let conn = LimitedConn {
+                            stream,
+                            _permit: permit,
+                        };
+                        return Some((Ok::<_, io::Error>(conn), listener));
+                    }
+                    // E.g. out of file descriptors, which is hopefully temporary.
+                    Err(e) => {
+                        tracing::warn!(error = %e, "failed to accept connection");
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                    }
+                }
+            }
+        }
+    });
+
+    let service = warp::service(router);
+    let make_service = make_service_fn(move |_: &LimitedConn| {
+        let service = service.clone();
+        async move { Ok::<_, Infallible>(service) }
+    });
+
+    let mut builder = Server::builder(accept::from_stream(incoming))
+        .http1_only(!config.http2)
+        .http1_keepalive(config.keep_alive.is_some())
+        .http2_max_concurrent_streams(config.max_concurrent_streams);
+    if let Some(interval) = config.keep_alive {
+        builder = builder
+            .http2_keep_alive_interval(interval)
+            .http2_keep_alive_timeout(interval);
+    }
+    builder
+        .serve(make_service)
+        .await
+        .map_err(|e| anyhow!("objects proxy failed: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use warp::Reply;
+
+    #[tokio::test]
+    async fn test_serve_limits_connections() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = warp::path!("health").map(|| warp::reply().into_response());
+        let config = ServerConfig {
+            max_connections: 1,
+            ..Default::default()
+        };
+        tokio::spawn(serve(router, listener, config));
+
+        let request = b"GET /health HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        let mut buf = [0u8; 1024];
+        let mut first = TcpStream::connect(addr).await.unwrap();
+        first.write_all(request).await.unwrap();
+        let n = first.read(&mut buf).await.unwrap();
+        assert!(buf[..n].starts_with(b"HTTP/1.1 200"));
+
+        // The first connection is kept alive, so the second one isn't accepted yet.
+        let mut second = TcpStream::connect(addr).await.unwrap();
+        second.write_all(request).await.unwrap();
+        let read = tokio::time::timeout(Duration::from_millis(200), second.read(&mut buf)).await;
+        assert!(read.is_err());
+
+        // Closing the first connection lets the second one in.
+        drop(first);
+        let n = tokio::time::timeout(Duration::from_secs(5), second.read(&mut buf))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(buf[..n].starts_with(b"HTTP/1.1 200"));
+    }
+}
@@ -0,0 +1,158 @@
+// Copyright 2024 Textile
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::{future::Future, num::ParseIntError};
+
+use anyhow::anyhow;
+use async_tempfile::TempFile;
+use cid::Cid;
+use ipfs_api_backend_hyper::{request::Add, IpfsApi, IpfsClient};
+use thiserror::Error;
+use tokio_util::compat::TokioAsyncReadCompatExt;
+use warp::hyper::body::Body;
+
+#[derive(Debug, Error)]
+pub enum ObjectsError {
+    #[error("error parsing range header: `{0}`")]
+    RangeHeaderParseError(ParseIntError),
+    #[error("invalid range header")]
+    RangeHeaderInvalid,
+}
+
+impl From<ParseIntError> for ObjectsError {
+    fn from(err: ParseIntError) -> Self {
+        ObjectsError::RangeHeaderParseError(err)
+    }
+}
+
+/// Backend holding the content of objects served by the proxy.
+///
+/// The CID returned by [`ContentStore::put`] is compared with the one signed by the client,
+/// so a backend has to derive CIDs the same way clients do.
+pub trait ContentStore {
+    /// Store the content of `temp_file`, returning its CID.
+    fn put(&self, temp_file: TempFile) -> impl Future<Output = anyhow::Result<Cid>> + Send;
+
+    /// Get the content of `cid`, or the part of it given by an HTTP `range` header.
+    fn get(
+        &self,
+        cid: Cid,
+        range: Option<String>,
+    ) -> impl Future<Output = anyhow::Result<ObjectRange>> + Send;
+
+    /// Check whether the content of `cid` is stored.
+    fn has(&self, cid: Cid) -> impl Future<Output = anyhow::Result<bool>> + Send;
+
+    /// Fetch the content of `cid` from the network into the store, so it can be served.
+    fn fetch(&self, cid: Cid) -> impl Future<Output = anyhow::Result<()>> + Send;
+}
+
+/// Content store backed by an IPFS node.
+#[derive(Clone)]
+pub struct IpfsContentStore {
+    pub inner: IpfsClient,
+}
+
+impl ContentStore for IpfsContentStore {
+    async fn put(&self, temp_file: TempFile) -> anyhow::Result<Cid> {
+        let res = self
+            .inner
+            .add_async_with_options(
+                temp_file.compat(),
+                Add {
+                    chunker: Some("size-1048576"),
+                    raw_leaves: Some(false),
+                    pin: Some(false),
+                    cid_version: Some(1),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        Ok(Cid::try_from(res.hash)?)
+    }
+
+    async fn get(&self, cid: Cid, range: Option<String>) -> anyhow::Result<ObjectRange> {
+        let stat = self
+            .inner
+            .files_stat(format!("/ipfs/{cid}").as_str())
+            .await?;
+        let size = stat.size;
+        Ok(match range {
+            Some(range) => {
+                let (start, end) = get_range_params(range, size)?;
+                let len = end - start + 1;
+                let body = Body::wrap_stream(self.inner.cat_range(
+                    &cid.to_string(),
+                    start as usize,
+                    len as usize,
+                ));
+                ObjectRange {
+                    start,
+                    end,
+                    len,
+                    size,
+                    body,
+                }
+            }
+            None => ObjectRange {
+                start: 0,
+                end: size - 1,
+                len: size,
+                size,
+                body: Body::wrap_stream(self.inner.cat(&cid.to_string())),
+            },
+        })
+    }
+
+    async fn has(&self, cid: Cid) -> anyhow::Result<bool> {
+        // Resolved content is pinned, like the resolver does. Unlike a stat, checking the pins
+        // doesn't go looking for the content on the network.
+        match self.inner.pin_ls(Some(&cid.to_string()), None).await {
+            Ok(_) => Ok(true),
+            Err(e) if e.to_string().contains("not pinned") => Ok(false),
+            Err(e) => Err(anyhow!(e)),
+        }
+    }
+
+    async fn fetch(&self, cid: Cid) -> anyhow::Result<()> {
+        self.inner.pin_add(&cid.to_string(), true).await?;
+        Ok(())
+    }
+}
+
+pub fn get_range_params(range: String, size: u64) -> Result<(u64, u64), ObjectsError> {
+    let range: Vec<String> = range
+        .replace("bytes=", "")
+        .split('-')
+        .map(|n| n.to_string())
+        .collect();
+    if range.len() != 2 {
+        return Err(ObjectsError::RangeHeaderInvalid);
+    }
+    let (start, end): (u64, u64) = match (!range[0].is_empty(), !range[1].is_empty()) {
+        (true, true) => (range[0].parse::<u64>()?, range[1].parse::<u64>()?),
+        (true, false) => (range[0].parse::<u64>()?, size - 1),
+        (false, true) => {
+            let last = range[1].parse::<u64>()?;
+            if last > size {
+                (0, size - 1)
+            } else {
+                (size - last, size - 1)
+            }
+        }
+        (false, false) => (0, size - 1),
+    };
+    if start > end || end >= size {
+        return Err(ObjectsError::RangeHeaderInvalid);
+    }
+    Ok((start, end))
+}
+
+pub struct ObjectRange {
+    pub start: u64,
+    pub end: u64,
+    pub len: u64,
+    pub size: u64,
+    pub body: warp::hyper::Body,
+}
@@ -0,0 +1,619 @@
+// Copyright 2024 Textile
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::anyhow;
+use async_tempfile::TempFile;
+use base64::{engine::general_purpose, Engine};
+use cid::{
+    multihash::{Code, MultihashDigest},
+    Cid,
+};
+use ethers::core::{k256::ecdsa::SigningKey, rand::rngs::StdRng};
+use fendermint_actor_accumulator::State as AccumulatorState;
+use fendermint_actor_machine::{Kind, Metadata as MachineMetadata, WriteAccess};
+use fendermint_actor_objectstore::{
+    AddParams, HasManyParams,
+    Method::{AddObject, SwapObject},
+    Object, State as ObjectStoreState, SwapParams,
+};
+use fendermint_rpc::{client::FendermintClient, query::CallError, QueryClient};
+use fendermint_vm_message::{
+    conv::from_eth::to_fvm_address,
+    query::{ActorState, FvmQuery, FvmQueryHeight},
+};
+use fvm_ipld_blockstore::MemoryBlockstore;
+use fvm_ipld_encoding::{strict_bytes::ByteBuf, CborStore, RawBytes};
+use fvm_ipld_hamt::BytesKey;
+use fvm_shared::{address::Address, econ::TokenAmount, error::ExitCode};
+use ipfs_api_backend_hyper::IpfsClient;
+use serde::Serialize;
+use tendermint_rpc::endpoint::abci_query::AbciQuery;
+use warp::{hyper::body::Body, Filter};
+
+use super::store::{get_range_params, ContentStore, ObjectRange};
+
+#[derive(Clone)]
+pub struct IpfsMocked {
+    pub _inner: IpfsClient,
+}
+
+impl ContentStore for IpfsMocked {
+    async fn put(&self, temp_file: TempFile) -> anyhow::Result<Cid> {
+        Ok(raw_cid(&read_temp_file(temp_file).await?))
+    }
+
+    async fn get(&self, _cid: Cid, range: Option<String>) -> anyhow::Result<ObjectRange> {
+        let content = "hello world";
+        if let Some(range) = range {
+            let (start, end) = get_range_params(range, content.len() as u64).unwrap();
+            let ranged_content = content[start as usize..=end as usize].to_string();
+            let body = make_request_body(ranged_content);
+            Ok(ObjectRange {
+                start,
+                end,
+                len: end - start + 1,
+                size: content.len() as u64,
+                body,
+            })
+        } else {
+            let body = make_request_body(content.to_string());
+            let size = content.len() as u64;
+            Ok(ObjectRange {
+                start: 0,
+                end: size - 1,
+                len: size,
+                size,
+                body,
+            })
+        }
+    }
+
+    async fn has(&self, _cid: Cid) -> anyhow::Result<bool> {
+        Ok(true)
+    }
+
+    async fn fetch(&self, _cid: Cid) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// Content store keeping objects in memory, under the CID of their raw bytes.
+#[derive(Clone, Default)]
+pub struct MemoryContentStore {
+    pub objects: Arc<Mutex<HashMap<Cid, Vec<u8>>>>,
+    /// Content that can be fetched into the store, as if from other nodes.
+    pub network: Arc<Mutex<HashMap<Cid, Vec<u8>>>>,
+}
+
+impl ContentStore for MemoryContentStore {
+    async fn put(&self, temp_file: TempFile) -> anyhow::Result<Cid> {
+        let content = read_temp_file(temp_file).await?;
+        let cid = raw_cid(&content);
+        self.objects.lock().unwrap().insert(cid, content);
+        Ok(cid)
+    }
+
+    async fn get(&self, cid: Cid, range: Option<String>) -> anyhow::Result<ObjectRange> {
+        let content = self
+            .objects
+            .lock()
+            .unwrap()
+            .get(&cid)
+            .cloned()
+            .ok_or_else(|| anyhow!("object {cid} not found"))?;
+        let size = content.len() as u64;
+        let (start, end) = match range {
+            Some(range) => get_range_params(range, size)?,
+            None => (0, size - 1),
+        };
+        let chunks: Vec<Result<_, std::io::Error>> =
+            vec![Ok(content[start as usize..=end as usize].to_vec())];
+        Ok(ObjectRange {
+            start,
+            end,
+            len: end - start + 1,
+            size,
+            body: Body::wrap_stream(futures_util::stream::iter(chunks)),
+        })
+    }
+
+    async fn has(&self, cid: Cid) -> anyhow::Result<bool> {
+        Ok(self.objects.lock().unwrap().contains_key(&cid))
+    }
+
+    async fn fetch(&self, cid: Cid) -> anyhow::Result<()> {
+        let content = self
+            .network
+            .lock()
+            .unwrap()
+            .get(&cid)
+            .cloned()
+            .ok_or_else(|| anyhow!("object {cid} not found on the network"))?;
+        self.objects.lock().unwrap().insert(cid, content);
+        Ok(())
+    }
+}
+
+async fn read_temp_file(mut temp_file: TempFile) -> anyhow::Result<Vec<u8>> {
+    let mut content = Vec::new();
+    temp_file.read_to_end(&mut content).await?;
+    Ok(content)
+}
+
+pub fn raw_cid(content: &[u8]) -> Cid {
+    Cid::new_v1(
+        fvm_ipld_encoding::IPLD_RAW,
+        Code::Blake2b256.digest(content),
+    )
+}
+
+fn make_request_body(content: String) -> Body {
+    let chunks: Vec<Result<_, std::io::Error>> = vec![Ok(content)];
+    let stream = futures_util::stream::iter(chunks);
+    Body::wrap_stream(stream)
+}
+
+// Used to mocking Actor State
+pub const ABCI_QUERY_RESPONSE_UPLOAD: &str = r#"{
+    "jsonrpc": "2.0",
+    "id": "",
+    "result": {
+     "response": {
+         "code": 0,
+         "log": "",
+         "info": "",
+         "index": "0",
+         "key": "GGQ=",
+         "value": "pWRjb2Rl2CpYJwABVaDkAiB4ZQKaqaSEiu8tIb2Ef7bIWOoxPeNkAEljZabMaAMlaGVzdGF0ZdgqWCcAAXGg5AIgRbDPwiDO7Ft8HGLE1Bk9OOTrpI6IFXKc51+cCrDkwcBoc2VxdWVuY2UAZ2JhbGFuY2VKADY1ya3F3qAAAHFkZWxlZ2F0ZWRfYWRkcmVzc1YECqf8cO8ArRpoWzmcqFtkasWDXCqx",
+         "proof": null,
+         "height": "8",
+         "codespace": ""
+       }
+    }
+ }"#;
+
+pub const ABCI_QUERY_RESPONSE_DOWNLOAD: &str = r#"{
+    "jsonrpc": "2.0",
+    "id": "",
+    "result": {
+     "response": {
+         "code": 0,
+         "log": "",
+         "info": "",
+         "index": "0",
+         "key": "",
+         "value": "mKASGE0YpBhjGGMYaRhkGFgYJAEYcBIYIBilGGgY5AQY2BjqGNoY7BiSGFoYwxi8GBsYNhglGJwPGG0YcAANGHIYnBjZGBgYxBhqGIMYbxhJGHYYVxhkGHMYaRh6GGUGGGgYchhlGHMYbxhsGHYYZRhkGPUYaBhtGGUYdBhhGGQYYRh0GGEYoRhlGF8YcxhpGHoYZRhhGDYYMBiiGNgYbhg6GEsKBxhtGGUYcxhzGGEYZxhlEg0KBBhmGHIYbxhtEgMYdBgwGDAYGAESGDEKAhh0GG8SGCkYdBgyGHcYdRhoGHEYNxh0GGEYMxgzGGUYdxgzGGMYMhhuGGQYbRhnGGIYbBg3GDcYNxh6GDUYeBg0GGQYbBh5GGYYbhhtGHkYbBhqGGkYaBhxGBgB",
+         "proof": null,
+         "height": "6017",
+         "codespace": ""
+       }
+    }
+ }"#;
+
+// Used to mock an object store get that finds no object.
+pub const ABCI_QUERY_RESPONSE_ABSENT: &str = r#"{
+    "jsonrpc": "2.0",
+    "id": "",
+    "result": {
+     "response": {
+         "code": 0,
+         "log": "",
+         "info": "",
+         "index": "0",
+         "key": "",
+         "value": "gxIBGPY=",
+         "proof": null,
+         "height": "6017",
+         "codespace": ""
+       }
+    }
+ }"#;
+
+pub fn form_body(
+    boundary: &str,
+    serialized_signed_message_b64: &str,
+    external_object: &[u8],
+) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(
+        format!(
+            "\
+        --{0}\r\n\
+        content-disposition: form-data; name=\"chain_id\"\r\n\r\n\
+        314159\r\n\
+        --{0}\r\n\
+        content-disposition: form-data; name=\"msg\"\r\n\r\n\
+        {1}\r\n\
+        --{0}\r\n\
+        ",
+            boundary, serialized_signed_message_b64
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(
+        "Content-Disposition: form-data; name=\"object\"; filename=\"example.bin\"\r\n\
+            Content-Type: application/octet-stream\r\n\r\n"
+            .to_string()
+            .as_bytes(),
+    );
+    body.extend_from_slice(external_object);
+    body.extend_from_slice(format!("\r\n--{0}--\r\n", boundary).as_bytes());
+    body
+}
+
+pub async fn multipart_form(
+    serialized_signed_message_b64: &str,
+    external_object: &[u8],
+) -> warp::multipart::FormData {
+    let boundary = "--abcdef1234--";
+    let body = form_body(boundary, serialized_signed_message_b64, external_object);
+    warp::test::request()
+        .method("POST")
+        .header("content-length", body.len())
+        .header(
+            "content-type",
+            format!("multipart/form-data; boundary={}", boundary),
+        )
+        .body(body)
+        .filter(&warp::multipart::form())
+        .await
+        .unwrap()
+}
+
+pub const TX_HASH: &str = "2C0CA3D5C4F4F6E8E7B2F4B1A0E9D0E2C8B5B8B0B2B4B6B8BAB0B2B4B6B8BAB0";
+
+pub fn tx_response(code: u32, log: &str) -> String {
+    format!(
+        r#"{{
+        "jsonrpc": "2.0",
+        "id": "",
+        "result": {{
+            "hash": "{TX_HASH}",
+            "height": "42",
+            "index": 0,
+            "tx_result": {{
+                "code": {code},
+                "data": "",
+                "log": "{log}",
+                "info": "",
+                "gas_wanted": "100",
+                "gas_used": "80",
+                "events": [],
+                "codespace": ""
+            }},
+            "tx": ""
+        }}
+    }}"#
+    )
+}
+
+/// Sign a message adding `object_cid` under `key` to an object store, encoded for an upload.
+pub fn signed_add_message(key: &[u8], object_cid: Cid) -> String {
+    let params = AddParams {
+        key: key.to_vec(),
+        cid: object_cid,
+        size: 11,
+        metadata: HashMap::new(),
+        overwrite: true,
+        data: None,
+    };
+    let to = Address::new_id(90);
+    let object = fendermint_vm_message::signed::Object::new(key.to_vec(), object_cid, to);
+    sign_add_message(params, Some(object))
+}
+
+/// Sign a message adding `data` under `key` as an internal object, encoded for an upload.
+pub fn signed_add_internal_message(key: &[u8], data: &[u8]) -> String {
+    let params = AddParams {
+        key: key.to_vec(),
+        cid: raw_cid(data),
+        size: data.len() as u64,
+        metadata: HashMap::new(),
+        overwrite: true,
+        data: Some(ByteBuf(data.to_vec())),
+    };
+    sign_add_message(params, None)
+}
+
+pub fn sign_add_message(
+    params: AddParams,
+    object: Option<fendermint_vm_message::signed::Object>,
+) -> String {
+    sign_message(AddObject as u64, params, object)
+}
+
+/// Sign a message swapping the object at `key` for `new_cid`, encoded for an upload.
+pub fn signed_swap_message(key: &[u8], expected_cid: Option<Cid>, new_cid: Cid) -> String {
+    let params = SwapParams {
+        key: key.to_vec(),
+        expected_cid,
+        new_cid,
+        size: 11,
+        metadata: HashMap::new(),
+    };
+    let to = Address::new_id(90);
+    let object = fendermint_vm_message::signed::Object::new(key.to_vec(), new_cid, to);
+    sign_message(SwapObject as u64, params, Some(object))
+}
+
+fn sign_message<P: Serialize>(
+    method_num: u64,
+    params: P,
+    object: Option<fendermint_vm_message::signed::Object>,
+) -> String {
+    let params = RawBytes::serialize(params).unwrap();
+    let to = Address::new_id(90);
+    let sk = fendermint_crypto::SecretKey::random(&mut StdRng::from_entropy());
+    let signing_key = SigningKey::from_slice(sk.serialize().as_ref()).unwrap();
+    let from_address = ethers::core::utils::secret_key_to_address(&signing_key);
+    let message = fvm_shared::message::Message {
+        version: Default::default(),
+        from: to_fvm_address(from_address),
+        to,
+        sequence: 0,
+        value: TokenAmount::from_atto(0),
+        method_num,
+        params,
+        gas_limit: 3000000,
+        gas_fee_cap: TokenAmount::from_atto(0),
+        gas_premium: TokenAmount::from_atto(0),
+    };
+    let chain_id = fvm_shared::chainid::ChainID::from(314159);
+    let signed = fendermint_vm_message::signed::SignedMessage::new_secp256k1(
+        message, object, &sk, &chain_id,
+    )
+    .unwrap();
+
+    let serialized_signed_message = fvm_ipld_encoding::to_vec(&signed).unwrap();
+    general_purpose::URL_SAFE.encode(serialized_signed_message)
+}
+
+/// Serve a CometBFT endpoint which finds the object store, and `object` at any key.
+pub fn spawn_object_comet(object: Option<Object>) -> FendermintClient {
+    let object_response = abci_query_response(&object);
+    let comet = warp::post()
+        .and(warp::body::json())
+        .map(move |req: serde_json::Value| {
+            let data = hex::decode(req["params"]["data"].as_str().unwrap()).unwrap();
+            match fvm_ipld_encoding::from_slice::<FvmQuery>(&data).unwrap() {
+                FvmQuery::ActorState(_) => ABCI_QUERY_RESPONSE_UPLOAD.to_string(),
+                _ => object_response.clone(),
+            }
+        });
+    let (comet_addr, comet_server) = warp::serve(comet).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(comet_server);
+    let comet_url = format!("http://{comet_addr}").parse().unwrap();
+    FendermintClient::new_http(comet_url, None).unwrap()
+}
+
+/// Mock an ABCI query response for a read-only call returning `ret`.
+pub fn abci_query_response<T: Serialize>(ret: &T) -> String {
+    use prost::Message;
+    let deliver_tx = tendermint_proto::abci::ResponseDeliverTx {
+        data: fvm_ipld_encoding::to_vec(ret).unwrap().into(),
+        ..Default::default()
+    };
+    let value = fvm_ipld_encoding::to_vec(&deliver_tx.encode_to_vec()).unwrap();
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": "",
+        "result": {
+            "response": {
+                "code": 0,
+                "log": "",
+                "info": "",
+                "index": "0",
+                "key": "",
+                "value": general_purpose::STANDARD.encode(value),
+                "proof": null,
+                "height": "1",
+                "codespace": ""
+            }
+        }
+    })
+    .to_string()
+}
+
+/// Gas every call served by a [`MachineClient`] reports using.
+pub const CALL_GAS_USED: i64 = 1000;
+
+/// A query client serving the state and metadata of a single machine.
+///
+/// Checking objects is served from the state as an object store, and other calls than
+/// getting the metadata from the state as an accumulator.
+#[derive(Clone)]
+pub struct MachineClient {
+    pub store: Arc<MemoryBlockstore>,
+    state: Cid,
+    /// States at specific heights, instead of `state`.
+    states_at: HashMap<u64, Cid>,
+    metadata: MachineMetadata,
+}
+
+impl MachineClient {
+    pub fn new<S: Serialize>(
+        store: MemoryBlockstore,
+        state: &S,
+        metadata: MachineMetadata,
+    ) -> Self {
+        let state = store.put_cbor(state, Code::Blake2b256).unwrap();
+        Self {
+            store: Arc::new(store),
+            state,
+            states_at: HashMap::new(),
+            metadata,
+        }
+    }
+
+    /// Serve `state` at `height`.
+    pub fn with_state_at<S: Serialize>(mut self, height: u64, state: &S) -> Self {
+        let state = self.store.put_cbor(state, Code::Blake2b256).unwrap();
+        self.states_at.insert(height, state);
+        self
+    }
+
+    fn state_at(&self, height: FvmQueryHeight) -> Cid {
+        match height {
+            FvmQueryHeight::Height(height) => {
+                self.states_at.get(&height).copied().unwrap_or(self.state)
+            }
+            _ => self.state,
+        }
+    }
+
+    /// The CBOR encoded return value of a read-only call.
+    fn call(&self, msg: &fvm_shared::message::Message, state: Cid) -> anyhow::Result<Vec<u8>> {
+        use fendermint_actor_accumulator::Method as AccMethod;
+        if msg.method_num == fendermint_actor_machine::GET_METADATA_METHOD {
+            return Ok(fvm_ipld_encoding::to_vec(&self.metadata)?);
+        }
+        if msg.method_num == fendermint_actor_machine::CAN_WRITE_METHOD {
+            // Addresses are compared as given, there is no address resolution here.
+            let params: fendermint_actor_machine::CanWriteParams = msg.params.deserialize()?;
+            let metadata = &self.metadata;
+            let can_write = !metadata.frozen
+                && match metadata.write_access {
+                    WriteAccess::OnlyOwner => params.address == metadata.owner,
+                    WriteAccess::Public => true,
+                };
+            return Ok(fvm_ipld_encoding::to_vec(&can_write)?);
+        }
+        let store = self.store.as_ref();
+        if msg.method_num == fendermint_actor_objectstore::Method::HasMany as u64 {
+            let state: ObjectStoreState = store
+                .get_cbor(&state)?
+                .ok_or_else(|| anyhow!("missing state"))?;
+            let params: HasManyParams = msg.params.deserialize()?;
+            let keys = params
+                .keys
+                .into_iter()
+                .map(|key| BytesKey(key.0))
+                .collect::<Vec<_>>();
+            return Ok(fvm_ipld_encoding::to_vec(&state.has_many(store, &keys)?)?);
+        }
+        let mut state: AccumulatorState = store
+            .get_cbor(&state)?
+            .ok_or_else(|| anyhow!("missing state"))?;
+        if msg.method_num == AccMethod::PushCid as u64 {
+            // Writes are checked like the actor does, but the new state is dropped.
+            let metadata = &self.metadata;
+            if metadata.frozen
+                || (metadata.write_access == WriteAccess::OnlyOwner && msg.from != metadata.owner)
+            {
+                return Err(CallError {
+                    exit_code: ExitCode::USR_FORBIDDEN,
+                    info: format!("{} is not allowed to write", msg.from),
+                }
+                .into());
+            }
+            let params: fendermint_actor_accumulator::PushCidParams = msg.params.deserialize()?;
+            return Ok(fvm_ipld_encoding::to_vec(
+                &state.push_cid(store, params.cid)?,
+            )?);
+        }
+        if msg.method_num == AccMethod::Root as u64 {
+            Ok(fvm_ipld_encoding::to_vec(&state.get_root(store)?)?)
+        } else if msg.method_num == AccMethod::Peaks as u64 {
+            Ok(fvm_ipld_encoding::to_vec(&state.get_peaks(store)?)?)
+        } else if msg.method_num == AccMethod::VerifyIntegrity as u64 {
+            let found = state
+                .verify_integrity(store)
+                .err()
+                .map(|e| format!("{:#}", e));
+            Ok(fvm_ipld_encoding::to_vec(&found)?)
+        } else if msg.method_num == AccMethod::Get as u64 {
+            let params: fendermint_actor_accumulator::GetParams = msg.params.deserialize()?;
+            Ok(fvm_ipld_encoding::to_vec(&state.get_leaf_bytes_in(
+                store,
+                &params.namespace,
+                params.index,
+            )?)?)
+        } else if msg.method_num == AccMethod::GetCid as u64 {
+            let index: u64 = msg.params.deserialize()?;
+            Ok(fvm_ipld_encoding::to_vec(
+                &state.get_leaf_cid_at(store, index)?,
+            )?)
+        } else {
+            Err(anyhow!("unexpected method: {}", msg.method_num))
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl QueryClient for MachineClient {
+    async fn perform(&self, query: FvmQuery, height: FvmQueryHeight) -> anyhow::Result<AbciQuery> {
+        use prost::Message;
+        let state = self.state_at(height);
+        let (key, value) = match query {
+            FvmQuery::ActorState(_) => {
+                let state = ActorState {
+                    code: Cid::default(),
+                    state,
+                    sequence: 0,
+                    balance: TokenAmount::default(),
+                    delegated_address: None,
+                };
+                (
+                    fvm_ipld_encoding::to_vec(&100u64)?,
+                    fvm_ipld_encoding::to_vec(&state)?,
+                )
+            }
+            FvmQuery::Ipld(cid) => (Vec::new(), self.store.get(&cid)?.unwrap_or_default()),
+            FvmQuery::Call(msg) => {
+                // Aborts are answered like the node does, in the result of the call.
+                let deliver_tx = match self.call(&msg, state) {
+                    Ok(data) => tendermint_proto::abci::ResponseDeliverTx {
+                        data: data.into(),
+                        gas_used: CALL_GAS_USED,
+                        ..Default::default()
+                    },
+                    Err(e) => {
+                        let e = e.downcast::<CallError>()?;
+                        tendermint_proto::abci::ResponseDeliverTx {
+                            code: e.exit_code.value(),
+                            info: e.info,
+                            gas_used: CALL_GAS_USED,
+                            ..Default::default()
+                        }
+                    }
+                };
+                (
+                    Vec::new(),
+                    fvm_ipld_encoding::to_vec(&deliver_tx.encode_to_vec())?,
+                )
+            }
+            query => return Err(anyhow!("unexpected query: {:?}", query)),
+        };
+        // Answer as of the queried height, so follow-up queries at it see the same state.
+        let height = match height {
+            FvmQueryHeight::Height(height) => height.try_into()?,
+            _ => 1u32.into(),
+        };
+        Ok(AbciQuery {
+            key,
+            value,
+            height,
+            ..Default::default()
+        })
+    }
+}
+
+pub fn acc_client(state: &AccumulatorState, store: MemoryBlockstore) -> MachineClient {
+    MachineClient::new(
+        store,
+        state,
+        MachineMetadata {
+            kind: Kind::Accumulator,
+            owner: Address::new_id(100),
+            write_access: WriteAccess::OnlyOwner,
+            frozen: false,
+            metadata: HashMap::new(),
+        },
+    )
+}
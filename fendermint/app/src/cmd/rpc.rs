@@ -11,6 +11,7 @@ use bytes::Bytes;
 use fendermint_app_options::genesis::AccountKind;
 use fendermint_crypto::{to_b64, SecretKey};
 use fendermint_rpc::client::BoundFendermintClient;
+use fendermint_rpc::gas::{sample_gas_price, GasSampling};
 use fendermint_rpc::tx::{
     AsyncResponse, BoundClient, CallClient, CommitResponse, SyncResponse, TxAsync, TxClient,
     TxCommit, TxSync,
@@ -124,8 +125,8 @@ where
     G: FnOnce(T) -> serde_json::Value,
     T: Sync + Send,
 {
+    let gas_params = recommended_gas_params(&client, &args).await?;
     let client = TransClient::new(client, &args)?;
-    let gas_params = gas_params(&args);
     let res = f(client, args.value, gas_params).await?;
     let json = match res {
         BroadcastResponse::Async(res) => json!({"response": res.response}),
@@ -161,6 +162,9 @@ async fn transaction(
     method_num: MethodNum,
     params: RawBytes,
 ) -> anyhow::Result<()> {
+    client
+        .check_recipient(&to, method_num, FvmQueryHeight::Committed)
+        .await?;
     broadcast_and_print(
         client,
         args,
@@ -370,12 +374,40 @@ impl TxClient<BroadcastModeWrapper> for TransClient {
     }
 }
 
+/// Gas parameters as given on the command line, with the price defaulting to zero.
 pub fn gas_params(args: &TransArgs) -> GasParams {
     GasParams {
         gas_limit: args.gas_limit,
-        gas_fee_cap: args.gas_fee_cap.clone(),
-        gas_premium: args.gas_premium.clone(),
+        gas_fee_cap: args.gas_fee_cap.clone().unwrap_or_default(),
+        gas_premium: args.gas_premium.clone().unwrap_or_default(),
+    }
+}
+
+/// Gas parameters as given on the command line, with any missing price recommended
+/// from the base fees of recent blocks.
+pub async fn recommended_gas_params(
+    client: &impl QueryClient,
+    args: &TransArgs,
+) -> anyhow::Result<GasParams> {
+    if let (Some(gas_fee_cap), Some(gas_premium)) = (&args.gas_fee_cap, &args.gas_premium) {
+        return Ok(GasParams {
+            gas_limit: args.gas_limit,
+            gas_fee_cap: gas_fee_cap.clone(),
+            gas_premium: gas_premium.clone(),
+        });
     }
+    let sampling = GasSampling {
+        window: args.gas_sample_window,
+        percentile: args.gas_percentile,
+    };
+    let price = sample_gas_price(client, sampling)
+        .await
+        .context("failed to recommend a gas price")?;
+    Ok(GasParams {
+        gas_limit: args.gas_limit,
+        gas_fee_cap: args.gas_fee_cap.clone().unwrap_or(price.gas_fee_cap),
+        gas_premium: args.gas_premium.clone().unwrap_or(price.gas_premium),
+    })
 }
 
 fn to_address(sk: &SecretKey, kind: &AccountKind) -> anyhow::Result<Address> {
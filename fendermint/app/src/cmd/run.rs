@@ -7,7 +7,7 @@ use fendermint_abci::ApplicationService;
 use fendermint_app::events::{ParentFinalityVoteAdded, ParentFinalityVoteIgnored};
 use fendermint_app::ipc::{AppParentFinalityQuery, AppVote};
 use fendermint_app::{App, AppConfig, AppStore, BitswapBlockstore};
-use fendermint_app_settings::AccountKind;
+use fendermint_app_settings::{AccountKind, SocketAddress};
 use fendermint_crypto::SecretKey;
 use fendermint_rocksdb::{blockstore::NamespaceBlockstore, namespaces, RocksDb, RocksDbConfig};
 use fendermint_tracing::emit;
@@ -20,7 +20,7 @@ use fendermint_vm_interpreter::{
     fvm::{Broadcaster, FvmMessageInterpreter, ValidatorContext},
     signed::SignedMessageInterpreter,
 };
-use fendermint_vm_ipfs_resolver::ipfs::IpfsResolver;
+use fendermint_vm_ipfs_resolver::ipfs::{IpfsResolver, ResolverStats};
 use fendermint_vm_resolver::ipld::IpldResolver;
 use fendermint_vm_snapshot::{SnapshotManager, SnapshotParams};
 use fendermint_vm_topdown::proxy::IPCProviderProxy;
@@ -31,7 +31,9 @@ use fvm_shared::address::{current_network, Address, Network};
 use ipc_ipld_resolver::{Event as ResolverEvent, VoteRecord};
 use ipc_provider::config::subnet::{EVMSubnet, SubnetConfig};
 use ipc_provider::IpcProvider;
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::broadcast::error::RecvError;
 use tower::ServiceBuilder;
 use tracing::info;
@@ -155,7 +157,8 @@ async fn run(ipfs_addr: String, settings: Settings) -> anyhow::Result<()> {
     let state_store =
         NamespaceBlockstore::new(db.clone(), ns.state_store).context("error creating state DB")?;
 
-    let checkpoint_pool = CheckpointPool::new();
+    let checkpoint_pool =
+        CheckpointPool::new().with_max_in_flight(settings.resolver.max_tasks_per_subnet);
     let ipfs_pin_pool = ObjectPool::new();
     let parent_finality_votes = VoteTally::empty();
 
@@ -229,9 +232,24 @@ async fn run(ipfs_addr: String, settings: Settings) -> anyhow::Result<()> {
                 parent_finality_votes.clone(),
                 key,
                 own_subnet_id,
-                |value| AppVote::ObjectFinality(IPCObjectFinality { object: value }),
+                |value, failed| {
+                    Ok(AppVote::ObjectFinality(IPCObjectFinality {
+                        object: value,
+                        failed,
+                    }))
+                },
+                settings.resolver.verify_before_vote,
             );
 
+            if let Some(listen) = settings.resolver.stats_listen.clone() {
+                serve_resolver_stats(
+                    ipfs_resolver.stats(),
+                    settings.resolver.retry_delay,
+                    settings.resolver.verify_before_vote,
+                    listen,
+                )?;
+            }
+
             tracing::info!("starting the IPFS Resolver...");
             tokio::spawn(async move { ipfs_resolver.run().await });
         } else {
@@ -332,6 +350,17 @@ async fn run(ipfs_addr: String, settings: Settings) -> anyhow::Result<()> {
         snapshots,
     )?;
 
+    let restored = app
+        .restore_pending_objects()
+        .await
+        .context("failed to restore pending objects")?;
+    if restored > 0 {
+        tracing::info!(
+            count = restored,
+            "restored pending objects to the resolution pool"
+        );
+    }
+
     if let Some((agent_proxy, config)) = ipc_tuple {
         let app_parent_finality_query = AppParentFinalityQuery::new(app.clone());
         tokio::spawn(async move {
@@ -417,6 +446,28 @@ fn open_db(settings: &Settings, ns: &Namespaces) -> anyhow::Result<RocksDb> {
     Ok(db)
 }
 
+/// Serve the settings and live stats of the IPFS Resolver as JSON, for the objects proxy.
+fn serve_resolver_stats(
+    stats: ResolverStats,
+    retry_delay: Duration,
+    verify_before_vote: bool,
+    listen: SocketAddress,
+) -> anyhow::Result<()> {
+    let listen_addr: SocketAddr = listen.try_into()?;
+    let route = warp::path!("v1" / "resolver" / "stats")
+        .and(warp::get())
+        .map(move || warp::reply::json(&stats.snapshot(retry_delay, verify_before_vote)));
+    let (addr, server) = warp::serve(route)
+        .try_bind_ephemeral(listen_addr)
+        .context("failed to start resolver stats server")?;
+    info!(
+        listen_addr = addr.to_string(),
+        "serving IPFS Resolver stats"
+    );
+    tokio::spawn(server);
+    Ok(())
+}
+
 fn make_resolver_service(
     settings: &Settings,
     db: RocksDb,
@@ -618,10 +669,10 @@ async fn dispatch_vote(
             }
         }
         AppVote::ObjectFinality(f) => {
-            tracing::debug!(cid = ?f.object, "received vote for object finality");
+            tracing::debug!(cid = ?f.object, failed = f.failed, "received vote for object finality");
 
             let res = atomically_or_err(|| {
-                parent_finality_votes.add_object_vote(vote.public_key.clone(), f.object.to_bytes())
+                parent_finality_votes.add_object_vote(vote.public_key.clone(), f.vote_key())
             })
             .await;
 
@@ -0,0 +1,192 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Minimal CARv1 framing, used to export and import object store and accumulator contents.
+//!
+//! A CAR is a varint length prefixed DAG-CBOR header followed by varint length prefixed sections,
+//! each holding the CID of a block followed by its data.
+//!
+//! See https://ipld.io/specs/transport/car/carv1/
+
+use std::io::{Read, Write};
+
+use anyhow::{anyhow, Context};
+use cid::Cid;
+use integer_encoding::{VarIntReader, VarIntWriter};
+use serde::{Deserialize, Serialize};
+
+/// The only version of CAR files we write and read.
+pub const CAR_VERSION: u64 = 1;
+
+/// The header block of a CAR file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CarHeader {
+    pub roots: Vec<Cid>,
+    pub version: u64,
+}
+
+impl CarHeader {
+    pub fn new(roots: Vec<Cid>) -> Self {
+        Self {
+            roots,
+            version: CAR_VERSION,
+        }
+    }
+}
+
+/// Writes a CAR file one block at a time.
+pub struct CarWriter<W> {
+    writer: W,
+}
+
+impl<W: Write> CarWriter<W> {
+    /// Write the header and return a writer ready to take the blocks.
+    pub fn new(header: &CarHeader, mut writer: W) -> anyhow::Result<Self> {
+        let bytes = fvm_ipld_encoding::to_vec(header).context("failed to encode CAR header")?;
+        writer.write_varint(bytes.len() as u64)?;
+        writer.write_all(&bytes)?;
+        Ok(Self { writer })
+    }
+
+    /// Append a block to the CAR file.
+    pub fn write_block(&mut self, cid: &Cid, data: &[u8]) -> anyhow::Result<()> {
+        let cid = cid.to_bytes();
+        self.writer.write_varint((cid.len() + data.len()) as u64)?;
+        self.writer.write_all(&cid)?;
+        self.writer.write_all(data)?;
+        Ok(())
+    }
+
+    /// The underlying writer, e.g. to drain what has been written so far.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Flush the underlying writer and give it back.
+    pub fn finish(mut self) -> anyhow::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Reads a CAR file one block at a time.
+pub struct CarReader<R> {
+    reader: R,
+    header: CarHeader,
+}
+
+impl<R: Read> CarReader<R> {
+    /// Read the header and return a reader positioned at the first block.
+    pub fn new(mut reader: R) -> anyhow::Result<Self> {
+        let bytes = read_section(&mut reader)?.ok_or_else(|| anyhow!("missing CAR header"))?;
+        let header: CarHeader =
+            fvm_ipld_encoding::from_slice(&bytes).context("failed to decode CAR header")?;
+        if header.version != CAR_VERSION {
+            return Err(anyhow!("unsupported CAR version {}", header.version));
+        }
+        Ok(Self { reader, header })
+    }
+
+    pub fn header(&self) -> &CarHeader {
+        &self.header
+    }
+
+    /// Read the next block, or `None` at the end of the file.
+    pub fn next_block(&mut self) -> anyhow::Result<Option<(Cid, Vec<u8>)>> {
+        let Some(bytes) = read_section(&mut self.reader)? else {
+            return Ok(None);
+        };
+        let mut cursor = std::io::Cursor::new(bytes);
+        let cid = Cid::read_bytes(&mut cursor).context("failed to decode CAR block CID")?;
+        let offset = cursor.position() as usize;
+        let mut data = cursor.into_inner();
+        data.drain(..offset);
+        Ok(Some((cid, data)))
+    }
+}
+
+impl<R: Read> Iterator for CarReader<R> {
+    type Item = anyhow::Result<(Cid, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_block().transpose()
+    }
+}
+
+/// Read a length prefixed section, or `None` if the reader is exhausted before it starts.
+fn read_section(reader: &mut impl Read) -> anyhow::Result<Option<Vec<u8>>> {
+    let mut first = [0u8; 1];
+    if reader.read(&mut first)? == 0 {
+        return Ok(None);
+    }
+    let len: u64 = (&first[..])
+        .chain(&mut *reader)
+        .read_varint()
+        .context("failed to read CAR section length")?;
+
+    // Not allocating the whole length up front, in case it's bogus.
+    let mut bytes = Vec::new();
+    reader.take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(anyhow!(
+            "truncated CAR section: expected {len} bytes, got {}",
+            bytes.len()
+        ));
+    }
+    Ok(Some(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use cid::{multihash::Code, multihash::MultihashDigest, Cid};
+    use fvm_ipld_encoding::DAG_CBOR;
+
+    use super::{CarHeader, CarReader, CarWriter};
+
+    fn block(data: &[u8]) -> (Cid, Vec<u8>) {
+        let cid = Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(data));
+        (cid, data.to_vec())
+    }
+
+    #[test]
+    fn car_round_trip() {
+        let blocks = vec![block(b"foo"), block(b""), block(&[7; 1000])];
+        let header = CarHeader::new(vec![blocks[0].0]);
+
+        let mut writer = CarWriter::new(&header, Vec::new()).unwrap();
+        for (cid, data) in &blocks {
+            writer.write_block(cid, data).unwrap();
+        }
+        let bytes = writer.finish().unwrap();
+
+        let reader = CarReader::new(bytes.as_slice()).unwrap();
+        assert_eq!(reader.header(), &header);
+        let read = reader.collect::<anyhow::Result<Vec<_>>>().unwrap();
+        assert_eq!(read, blocks);
+    }
+
+    #[test]
+    fn car_truncated_block() {
+        let (cid, data) = block(b"foo");
+        let mut writer = CarWriter::new(&CarHeader::new(vec![cid]), Vec::new()).unwrap();
+        writer.write_block(&cid, &data).unwrap();
+        let mut bytes = writer.finish().unwrap();
+        bytes.pop();
+
+        let mut reader = CarReader::new(bytes.as_slice()).unwrap();
+        assert!(reader.next_block().is_err());
+    }
+
+    #[test]
+    fn car_empty() {
+        let header = CarHeader::new(Vec::new());
+        let bytes = CarWriter::new(&header, Vec::new())
+            .unwrap()
+            .finish()
+            .unwrap();
+
+        let mut reader = CarReader::new(bytes.as_slice()).unwrap();
+        assert_eq!(reader.header(), &header);
+        assert!(reader.next_block().unwrap().is_none());
+    }
+}
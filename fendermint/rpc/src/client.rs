@@ -6,9 +6,11 @@ use std::marker::PhantomData;
 
 use anyhow::{anyhow, Context};
 use async_trait::async_trait;
+use base64::Engine;
 use fendermint_vm_message::chain::ChainMessage;
 use tendermint::abci::response::DeliverTx;
 use tendermint::block::Height;
+use tendermint_rpc::endpoint::broadcast::tx_commit;
 use tendermint_rpc::{endpoint::abci_query::AbciQuery, Client, HttpClient, Scheme, Url};
 use tendermint_rpc::{WebSocketClient, WebSocketClientDriver, WebSocketClientUrl};
 
@@ -19,6 +21,7 @@ use crate::query::QueryClient;
 use crate::tx::{
     AsyncResponse, BoundClient, CommitResponse, SyncResponse, TxAsync, TxClient, TxCommit, TxSync,
 };
+use crate::B64_ENGINE;
 
 // Retrieve the proxy URL with precedence:
 // 1. If supplied, that's the proxy URL used.
@@ -245,6 +248,7 @@ where
             .broadcast_tx_commit(data)
             .await
             .context("broadcast_tx_commit failed")?;
+        log_commit(&msg, &response);
         // We have a fully `DeliverTx` with default fields even if `CheckTx` indicates failure.
         let return_data = if response.check_tx.code.is_err() || response.deliver_tx.code.is_err() {
             None
@@ -261,6 +265,38 @@ where
     }
 }
 
+/// Log a committed transaction with its effects, as an audit trail of writes.
+///
+/// Only the message envelope and the results are logged, never the signature.
+fn log_commit(msg: &ChainMessage, response: &tx_commit::Response) {
+    let ChainMessage::Signed(signed) = msg else {
+        return;
+    };
+    let message = signed.message();
+    let (key, value) = match &signed.object {
+        Some(object) => (
+            Some(String::from_utf8_lossy(&object.key).into_owned()),
+            Some(object.value.to_string()),
+        ),
+        None => (None, None),
+    };
+    tracing::info!(
+        tx_hash = %response.hash,
+        height = response.height.value(),
+        from = %message.from,
+        to = %message.to,
+        method_num = message.method_num,
+        sequence = message.sequence,
+        key,
+        value,
+        check_tx_code = response.check_tx.code.value(),
+        deliver_tx_code = response.deliver_tx.code.value(),
+        gas_used = response.deliver_tx.gas_used,
+        return_data = B64_ENGINE.encode(&response.deliver_tx.data),
+        "transaction committed"
+    );
+}
+
 async fn perform_query<C>(
     client: &C,
     query: FvmQuery,
@@ -368,3 +404,84 @@ mod debug {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    use cid::Cid;
+    use fendermint_crypto::SecretKey;
+    use fendermint_vm_message::signed::Object;
+    use fvm_shared::{address::Address, chainid::ChainID, econ::TokenAmount};
+    use tendermint_rpc::endpoint::broadcast::tx_commit;
+
+    use crate::message::{GasParams, SignedMessageFactory};
+
+    use super::log_commit;
+
+    /// Collects everything written by the subscriber.
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn log_committed_put() {
+        let sk = SecretKey::try_from(vec![1; 32]).unwrap();
+        let mut mf = SignedMessageFactory::new_secp256k1(sk, 0, ChainID::from(1));
+        let store = Address::new_id(100);
+        let value = Cid::default();
+        let msg = mf
+            .transaction(
+                store,
+                2,
+                Default::default(),
+                TokenAmount::from_atto(0),
+                GasParams {
+                    gas_limit: 10_000_000,
+                    gas_fee_cap: TokenAmount::from_atto(100),
+                    gas_premium: TokenAmount::from_atto(100),
+                },
+                Some(Object::new(b"foo/bar".to_vec(), value, store)),
+            )
+            .unwrap();
+
+        let mut response = tx_commit::Response {
+            check_tx: Default::default(),
+            deliver_tx: Default::default(),
+            hash: Default::default(),
+            height: 10u32.into(),
+        };
+        response.deliver_tx.gas_used = 1234;
+
+        let capture = Capture::default();
+        let writer = capture.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .json()
+            .with_writer(move || writer.clone())
+            .finish();
+        tracing::subscriber::with_default(subscriber, || log_commit(&msg, &response));
+
+        let output = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+        let line: serde_json::Value = serde_json::from_str(output.trim()).unwrap();
+        let fields = &line["fields"];
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(fields["message"], "transaction committed");
+        assert_eq!(fields["to"], store.to_string());
+        assert_eq!(fields["key"], "foo/bar");
+        assert_eq!(fields["value"], value.to_string());
+        assert_eq!(fields["gas_used"], 1234);
+        assert_eq!(fields["height"], 10);
+        assert!(fields.get("signature").is_none());
+    }
+}
@@ -0,0 +1,353 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Accumulator exports, for archiving an accumulator offline.
+//!
+//! An export is a CAR file whose root is the root of the accumulator. Its first block is an
+//! [`ExportHeader`] with the leaf count and the settings the leaves were pushed with, followed
+//! by one block per leaf, in order. Replaying the leaves into a fresh accumulator with the same
+//! settings rebuilds it with the same root, which is how an export is verified.
+//!
+//! An export is imported into an accumulator by sending its leaves in batches of
+//! [`ImportLeavesParams`], each carrying the root the accumulator must have after it.
+
+use std::io::{Read, Write};
+
+use anyhow::{anyhow, Context};
+use cid::multihash::{Code, MultihashDigest};
+use cid::Cid;
+use fendermint_actor_accumulator::{ImportLeavesParams, LeafCodec, RootScheme, State};
+use fendermint_actor_machine::WriteAccess;
+use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
+use fvm_ipld_encoding::{strict_bytes::ByteBuf, tuple::*, DAG_CBOR};
+use fvm_shared::address::Address;
+
+use crate::car::{CarHeader, CarReader, CarWriter};
+
+/// Number of leaves in an import batch, unless asked otherwise.
+pub const DEFAULT_IMPORT_BATCH_SIZE: usize = 100;
+
+/// The first block of an accumulator export.
+#[derive(Clone, Debug, PartialEq, Serialize_tuple, Deserialize_tuple)]
+pub struct ExportHeader {
+    /// Number of leaves in the export.
+    pub leaf_count: u64,
+    /// Root of the accumulator after the last leaf.
+    pub root: Cid,
+    /// Codec the leaves were stored with.
+    pub leaf_codec: LeafCodec,
+    /// How the peaks are bagged into the root.
+    pub root_scheme: RootScheme,
+}
+
+impl ExportHeader {
+    fn block(&self) -> anyhow::Result<(Cid, Vec<u8>)> {
+        let data = fvm_ipld_encoding::to_vec(self).context("failed to encode export header")?;
+        Ok((Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&data)), data))
+    }
+}
+
+/// Writes an accumulator export one leaf at a time.
+pub struct ExportWriter<W> {
+    car: CarWriter<W>,
+    header: ExportHeader,
+    written: u64,
+}
+
+impl<W: Write> ExportWriter<W> {
+    /// Write the header and return a writer ready to take the leaves.
+    pub fn new(header: ExportHeader, writer: W) -> anyhow::Result<Self> {
+        let mut car = CarWriter::new(&CarHeader::new(vec![header.root]), writer)?;
+        let (cid, data) = header.block()?;
+        car.write_block(&cid, &data)?;
+        Ok(Self {
+            car,
+            header,
+            written: 0,
+        })
+    }
+
+    /// Append the next leaf, as the bytes that were pushed.
+    pub fn write_leaf(&mut self, bytes: &[u8]) -> anyhow::Result<()> {
+        if self.written == self.header.leaf_count {
+            return Err(anyhow!(
+                "export already has all {} leaves",
+                self.header.leaf_count
+            ));
+        }
+        let (cid, data) = self.header.leaf_codec.encode_leaf(bytes)?;
+        self.car.write_block(&cid, &data)?;
+        self.written += 1;
+        Ok(())
+    }
+
+    /// The underlying writer, e.g. to drain what has been written so far.
+    pub fn get_mut(&mut self) -> &mut W {
+        self.car.get_mut()
+    }
+
+    /// Check that every leaf was written, flush the underlying writer and give it back.
+    pub fn finish(self) -> anyhow::Result<W> {
+        if self.written != self.header.leaf_count {
+            return Err(anyhow!(
+                "export has {} of {} leaves",
+                self.written,
+                self.header.leaf_count
+            ));
+        }
+        self.car.finish()
+    }
+}
+
+/// Read the header of an export, returning it with the reader positioned at the first leaf.
+fn open_export<R: Read>(reader: R) -> anyhow::Result<(ExportHeader, CarReader<R>)> {
+    let mut car = CarReader::new(reader)?;
+    let (cid, data) = car
+        .next_block()?
+        .ok_or_else(|| anyhow!("missing export header"))?;
+    let header: ExportHeader =
+        fvm_ipld_encoding::from_slice(&data).context("failed to decode export header")?;
+    if header.block()?.0 != cid {
+        return Err(anyhow!("export header does not match its cid {}", cid));
+    }
+    if car.header().roots != [header.root] {
+        return Err(anyhow!("export roots do not match the header root"));
+    }
+    Ok((header, car))
+}
+
+/// Decode the leaf at `index` from its block, checking that the block matches its CID.
+fn read_leaf(header: &ExportHeader, index: u64, cid: Cid, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let bytes = header.leaf_codec.decode_leaf(data)?;
+    let (leaf, _) = header.leaf_codec.encode_leaf(&bytes)?;
+    if leaf != cid {
+        return Err(anyhow!("leaf {} does not match its cid {}", index, cid));
+    }
+    Ok(bytes)
+}
+
+/// Replay an export into a fresh accumulator owned by `owner`, checking that it ends up with
+/// the exported leaf count and root.
+pub fn replay_export<BS: Blockstore, R: Read>(
+    store: &BS,
+    owner: Address,
+    reader: R,
+) -> anyhow::Result<State> {
+    let (header, car) = open_export(reader)?;
+    let mut state = replay_state(store, owner, &header)?;
+    for block in car {
+        let (cid, data) = block?;
+        let bytes = read_leaf(&header, state.leaf_count(), cid, &data)?;
+        state.push_bytes(store, bytes)?;
+    }
+    check_replay(&header, &state, store)?;
+    Ok(state)
+}
+
+/// Verify an export and split its leaves into batches of at most `batch_size` leaves,
+/// to be sent in order as `ImportLeaves` messages.
+///
+/// The export is replayed in memory, so that every batch carries the root the accumulator
+/// must have after it, and a corrupted export is rejected before anything is sent.
+pub fn import_batches<R: Read>(
+    reader: R,
+    batch_size: usize,
+    force: bool,
+) -> anyhow::Result<(ExportHeader, Vec<ImportLeavesParams>)> {
+    if batch_size == 0 {
+        return Err(anyhow!("batch size must be positive"));
+    }
+    let (header, car) = open_export(reader)?;
+    let store = MemoryBlockstore::default();
+    let mut state = replay_state(&store, Address::new_id(0), &header)?;
+
+    let mut batches = Vec::new();
+    let mut leaves = Vec::new();
+    let mut blocks = car.peekable();
+    while let Some(block) = blocks.next() {
+        let (cid, data) = block?;
+        let bytes = read_leaf(&header, state.leaf_count(), cid, &data)?;
+        state.push_bytes(&store, bytes.clone())?;
+        leaves.push(ByteBuf(bytes));
+        if leaves.len() == batch_size || blocks.peek().is_none() {
+            batches.push(ImportLeavesParams {
+                start: state.leaf_count() - leaves.len() as u64,
+                leaves: std::mem::take(&mut leaves),
+                leaf_codec: header.leaf_codec,
+                root_scheme: header.root_scheme,
+                expected_root: state.get_root(&store)?,
+                force,
+            });
+        }
+    }
+    check_replay(&header, &state, &store)?;
+    Ok((header, batches))
+}
+
+/// Create an empty accumulator with the leaf settings of an export, to replay it into.
+fn replay_state<BS: Blockstore>(
+    store: &BS,
+    owner: Address,
+    header: &ExportHeader,
+) -> anyhow::Result<State> {
+    let mut state = State::new(store, owner, WriteAccess::OnlyOwner)?;
+    state.set_leaf_codec(header.leaf_codec)?;
+    state.set_root_scheme(header.root_scheme)?;
+    Ok(state)
+}
+
+/// Check that a replayed accumulator ended up with the exported leaf count and root.
+fn check_replay<BS: Blockstore>(
+    header: &ExportHeader,
+    state: &State,
+    store: &BS,
+) -> anyhow::Result<()> {
+    if state.leaf_count() != header.leaf_count {
+        return Err(anyhow!(
+            "export has {} of {} leaves",
+            state.leaf_count(),
+            header.leaf_count
+        ));
+    }
+    let root = state.get_root(store)?;
+    if root != header.root {
+        return Err(anyhow!(
+            "replayed root {} does not match exported root {}",
+            root,
+            header.root
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use fendermint_actor_accumulator::{LeafCodec, RootScheme, State};
+    use fendermint_actor_machine::WriteAccess;
+    use fvm_ipld_blockstore::MemoryBlockstore;
+    use fvm_shared::address::Address;
+
+    use super::{import_batches, replay_export, ExportHeader, ExportWriter};
+
+    /// Build an accumulator of `n` leaves, returning it with the pushed leaves.
+    fn build(store: &MemoryBlockstore, n: u8, codec: LeafCodec) -> (State, Vec<Vec<u8>>) {
+        let mut state = State::new(store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        state.set_leaf_codec(codec).unwrap();
+        let leaves = (0..n).map(|i| vec![i; i as usize + 1]).collect::<Vec<_>>();
+        for leaf in &leaves {
+            state.push_bytes(store, leaf.clone()).unwrap();
+        }
+        (state, leaves)
+    }
+
+    fn export(state: &State, store: &MemoryBlockstore, leaves: &[Vec<u8>]) -> Vec<u8> {
+        let header = ExportHeader {
+            leaf_count: state.leaf_count(),
+            root: state.get_root(store).unwrap(),
+            leaf_codec: state.leaf_codec,
+            root_scheme: state.root_scheme,
+        };
+        let mut writer = ExportWriter::new(header, Vec::new()).unwrap();
+        for leaf in leaves {
+            writer.write_leaf(leaf).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn export_replay_round_trip() {
+        for codec in [LeafCodec::DagCbor, LeafCodec::Raw] {
+            for n in [0, 1, 11] {
+                let store = MemoryBlockstore::default();
+                let (state, leaves) = build(&store, n, codec);
+                let bytes = export(&state, &store, &leaves);
+
+                let fresh = MemoryBlockstore::default();
+                let replayed =
+                    replay_export(&fresh, Address::new_id(101), bytes.as_slice()).unwrap();
+                assert_eq!(replayed.leaf_count(), state.leaf_count());
+                assert_eq!(
+                    replayed.get_root(&fresh).unwrap(),
+                    state.get_root(&store).unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn export_replay_uniform_root() {
+        let store = MemoryBlockstore::default();
+        let mut state = State::new(&store, Address::new_id(100), WriteAccess::OnlyOwner).unwrap();
+        state.set_root_scheme(RootScheme::Uniform).unwrap();
+        let leaves = vec![vec![1, 2, 3]];
+        state.push_bytes(&store, leaves[0].clone()).unwrap();
+        let bytes = export(&state, &store, &leaves);
+
+        let fresh = MemoryBlockstore::default();
+        let replayed = replay_export(&fresh, Address::new_id(101), bytes.as_slice()).unwrap();
+        assert_eq!(replayed.root_scheme, RootScheme::Uniform);
+        assert_eq!(
+            replayed.get_root(&fresh).unwrap(),
+            state.get_root(&store).unwrap()
+        );
+    }
+
+    #[test]
+    fn export_import_batches() {
+        let store = MemoryBlockstore::default();
+        let (source, leaves) = build(&store, 11, LeafCodec::DagCbor);
+        let bytes = export(&source, &store, &leaves);
+
+        let (header, batches) = import_batches(bytes.as_slice(), 4, false).unwrap();
+        assert_eq!(header.leaf_count, 11);
+        assert_eq!(
+            batches.iter().map(|b| b.leaves.len()).collect::<Vec<_>>(),
+            vec![4, 4, 3]
+        );
+
+        let fresh = MemoryBlockstore::default();
+        let mut state = State::new(&fresh, Address::new_id(101), WriteAccess::OnlyOwner).unwrap();
+        for batch in batches {
+            state.import_leaves(&fresh, batch).unwrap();
+        }
+        assert_eq!(state.get_root(&fresh).unwrap(), header.root);
+    }
+
+    #[test]
+    fn export_replay_tampered() {
+        let store = MemoryBlockstore::default();
+        let (state, mut leaves) = build(&store, 5, LeafCodec::Raw);
+        leaves[2] = b"tampered".to_vec();
+        let bytes = export(&state, &store, &leaves);
+
+        let err = replay_export(
+            &MemoryBlockstore::default(),
+            Address::new_id(101),
+            &bytes[..],
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("does not match exported root"));
+    }
+
+    #[test]
+    fn export_leaf_count_checked() {
+        let store = MemoryBlockstore::default();
+        let (state, leaves) = build(&store, 3, LeafCodec::Raw);
+        let header = ExportHeader {
+            leaf_count: state.leaf_count(),
+            root: state.get_root(&store).unwrap(),
+            leaf_codec: state.leaf_codec,
+            root_scheme: state.root_scheme,
+        };
+
+        let mut writer = ExportWriter::new(header.clone(), Vec::new()).unwrap();
+        writer.write_leaf(&leaves[0]).unwrap();
+        assert!(writer.finish().is_err());
+
+        let mut writer = ExportWriter::new(header, Vec::new()).unwrap();
+        for leaf in &leaves {
+            writer.write_leaf(leaf).unwrap();
+        }
+        assert!(writer.write_leaf(&leaves[0]).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Gas price recommendations derived from the base fees of recent blocks.
+
+use anyhow::Context;
+use fendermint_vm_message::query::FvmQueryHeight;
+use fvm_shared::econ::TokenAmount;
+
+use crate::query::QueryClient;
+
+/// Settings for sampling the base fees of recent blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasSampling {
+    /// Number of most recent blocks to sample.
+    pub window: u64,
+    /// Percentile of the block-to-block base fee increases to pay as premium, from 0 to 100.
+    pub percentile: u8,
+}
+
+impl Default for GasSampling {
+    fn default() -> Self {
+        Self {
+            window: 20,
+            percentile: 60,
+        }
+    }
+}
+
+/// Recommended gas price of a message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GasPrice {
+    pub gas_fee_cap: TokenAmount,
+    pub gas_premium: TokenAmount,
+}
+
+/// Recommend a gas price given the base fees of recent blocks, oldest first.
+///
+/// The premium is the given percentile of the increases of the base fee from one block to the next,
+/// so that a message keeps up with the fee going up as it has been lately. Like EIP-1559, the fee cap
+/// leaves room for the latest base fee to double before the message gets stuck.
+///
+/// Returns `None` if there is no history.
+pub fn recommend_gas_price(base_fees: &[TokenAmount], percentile: u8) -> Option<GasPrice> {
+    let latest = base_fees.last()?;
+
+    let mut increases = base_fees
+        .windows(2)
+        .map(|w| {
+            if w[1] > w[0] {
+                w[1].clone() - &w[0]
+            } else {
+                TokenAmount::from_atto(0)
+            }
+        })
+        .collect::<Vec<_>>();
+    increases.sort();
+
+    let gas_premium = if increases.is_empty() {
+        TokenAmount::from_atto(0)
+    } else {
+        // Nearest-rank percentile.
+        let percentile = percentile.min(100) as usize;
+        let rank = (percentile * increases.len()).div_ceil(100);
+        increases[rank.saturating_sub(1)].clone()
+    };
+    let gas_fee_cap = TokenAmount::from_atto(latest.atto() * 2u32) + &gas_premium;
+
+    Some(GasPrice {
+        gas_fee_cap,
+        gas_premium,
+    })
+}
+
+/// Sample the base fees of the last committed blocks and recommend a gas price from them.
+pub async fn sample_gas_price(
+    client: &impl QueryClient,
+    sampling: GasSampling,
+) -> anyhow::Result<GasPrice> {
+    let latest = client
+        .state_params(FvmQueryHeight::Committed)
+        .await
+        .context("failed to get the latest base fee")?;
+    let height = latest.height.value();
+    let start = height
+        .saturating_sub(sampling.window.saturating_sub(1))
+        .max(1);
+
+    let mut base_fees = Vec::new();
+    for h in start..height {
+        let res = client
+            .state_params(FvmQueryHeight::Height(h))
+            .await
+            .with_context(|| format!("failed to get the base fee at height {h}"))?;
+        base_fees.push(res.value.base_fee);
+    }
+    base_fees.push(latest.value.base_fee);
+
+    Ok(recommend_gas_price(&base_fees, sampling.percentile)
+        .expect("the latest base fee is always sampled"))
+}
+
+#[cfg(test)]
+mod tests {
+    use fvm_shared::econ::TokenAmount;
+
+    use super::{recommend_gas_price, GasPrice};
+
+    fn atto(fees: &[u64]) -> Vec<TokenAmount> {
+        fees.iter().map(|f| TokenAmount::from_atto(*f)).collect()
+    }
+
+    #[test]
+    fn recommend_from_base_fee_history() {
+        // Increases are [0, 20, 0, 40, 0].
+        let base_fees = atto(&[100, 100, 120, 110, 150, 150]);
+
+        let price = recommend_gas_price(&base_fees, 80).unwrap();
+        assert_eq!(
+            price,
+            GasPrice {
+                gas_fee_cap: TokenAmount::from_atto(320),
+                gas_premium: TokenAmount::from_atto(20),
+            }
+        );
+
+        let price = recommend_gas_price(&base_fees, 100).unwrap();
+        assert_eq!(price.gas_premium, TokenAmount::from_atto(40));
+
+        let price = recommend_gas_price(&base_fees, 50).unwrap();
+        assert_eq!(price.gas_premium, TokenAmount::from_atto(0));
+        assert_eq!(price.gas_fee_cap, TokenAmount::from_atto(300));
+    }
+
+    #[test]
+    fn recommend_from_short_history() {
+        assert_eq!(recommend_gas_price(&[], 60), None);
+
+        let price = recommend_gas_price(&atto(&[100]), 60).unwrap();
+        assert_eq!(price.gas_premium, TokenAmount::from_atto(0));
+        assert_eq!(price.gas_fee_cap, TokenAmount::from_atto(200));
+    }
+}
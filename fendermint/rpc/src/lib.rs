@@ -6,8 +6,12 @@ use base64::{
     engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
 };
 
+pub mod car;
 pub mod client;
+pub mod export;
+pub mod gas;
 pub mod message;
+pub mod proxy;
 pub mod query;
 pub mod response;
 pub mod tx;
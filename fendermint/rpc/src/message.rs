@@ -3,12 +3,30 @@
 
 use std::path::Path;
 
-use anyhow::Context;
+use anyhow::{anyhow, Context};
 use base64::Engine;
+use bls_signatures::Serialize;
 use bytes::Bytes;
-use fendermint_actor_objectstore::{GetParams, Method::GetObject};
+use fendermint_actor_accumulator::{
+    GetParams as AccGetParams, ImportLeavesParams,
+    Method::{
+        Get as AccGet, GetCid as AccGetCid, GetWithProof as AccGetWithProof, ImportLeaves,
+        Peaks as AccPeaks, PushCid, Root as AccRoot, VerifyIntegrity as AccVerifyIntegrity,
+    },
+    PushCidParams, RootParams as AccRootParams,
+};
+use fendermint_actor_machine::{CanWriteParams, CAN_WRITE_METHOD, GET_METADATA_METHOD};
+use fendermint_actor_objectstore::{
+    AddParams, ClaimFirstParams, ClaimParams, CopyParams, GetParams, HasManyParams, HasParams,
+    ListByTimeParams, ListParams,
+    Method::{
+        AddObject, ClaimFirst, ClaimObject, CopyObject, GetObject, HasMany, HasObject, ListByTime,
+        ListObjects, StatPrefix, SwapObject, UpdateMetadata,
+    },
+    StatParams, SwapParams, UpdateMetadataParams,
+};
 use fendermint_crypto::SecretKey;
-use fendermint_vm_actor_interface::{eam, evm};
+use fendermint_vm_actor_interface::{adm, eam, evm};
 use fendermint_vm_message::signed::Object;
 use fendermint_vm_message::{chain::ChainMessage, signed::SignedMessage};
 use fvm_ipld_encoding::{BytesSer, RawBytes};
@@ -36,11 +54,23 @@ impl MessageFactory {
         &self.addr
     }
 
+    /// The sequence of the next message.
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
     /// Set the sequence to an arbitrary value.
     pub fn set_sequence(&mut self, sequence: u64) {
         self.sequence = sequence;
     }
 
+    /// Create a message to an actor.
+    ///
+    /// The recipient isn't checked, because the factory has no access to the chain. Sending
+    /// [`METHOD_SEND`] to an `f410` address which has no actor yet creates a placeholder actor
+    /// for it, but calling any other method on such an address fails; use
+    /// [`QueryClient::check_recipient`](crate::query::QueryClient::check_recipient) to catch
+    /// that before sending.
     pub fn transaction(
         &mut self,
         to: Address,
@@ -114,6 +144,199 @@ impl MessageFactory {
         Ok(self.transaction(address, GetObject as u64, params, value, gas_params))
     }
 
+    /// List objects in an object store. This will not create a transaction.
+    pub fn os_list(
+        &mut self,
+        address: Address,
+        params: ListParams,
+        value: TokenAmount,
+        gas_params: GasParams,
+    ) -> anyhow::Result<Message> {
+        let params = RawBytes::serialize(params)?;
+        Ok(self.transaction(address, ListObjects as u64, params, value, gas_params))
+    }
+
+    /// List objects in an object store by the epoch they were added at. This will not create a transaction.
+    pub fn os_list_by_time(
+        &mut self,
+        address: Address,
+        params: ListByTimeParams,
+        value: TokenAmount,
+        gas_params: GasParams,
+    ) -> anyhow::Result<Message> {
+        let params = RawBytes::serialize(params)?;
+        Ok(self.transaction(address, ListByTime as u64, params, value, gas_params))
+    }
+
+    /// Check whether an object exists in an object store. This will not create a transaction.
+    pub fn os_has(
+        &mut self,
+        address: Address,
+        params: HasParams,
+        value: TokenAmount,
+        gas_params: GasParams,
+    ) -> anyhow::Result<Message> {
+        let params = RawBytes::serialize(params)?;
+        Ok(self.transaction(address, HasObject as u64, params, value, gas_params))
+    }
+
+    /// Check which of a set of objects exist in an object store. This will not create a transaction.
+    pub fn os_has_many(
+        &mut self,
+        address: Address,
+        params: HasManyParams,
+        value: TokenAmount,
+        gas_params: GasParams,
+    ) -> anyhow::Result<Message> {
+        let params = RawBytes::serialize(params)?;
+        Ok(self.transaction(address, HasMany as u64, params, value, gas_params))
+    }
+
+    /// Get the object count and total size under a prefix in an object store. This will not create a transaction.
+    pub fn os_stat(
+        &mut self,
+        address: Address,
+        params: StatParams,
+        value: TokenAmount,
+        gas_params: GasParams,
+    ) -> anyhow::Result<Message> {
+        let params = RawBytes::serialize(params)?;
+        Ok(self.transaction(address, StatPrefix as u64, params, value, gas_params))
+    }
+
+    /// List the machines owned by an address. This will not create a transaction.
+    pub fn adm_list_metadata(
+        &mut self,
+        owner: Address,
+        value: TokenAmount,
+        gas_params: GasParams,
+    ) -> anyhow::Result<Message> {
+        let params = RawBytes::serialize(adm::ListMetadataParams { owner })?;
+        Ok(self.transaction(
+            adm::ADM_ACTOR_ADDR,
+            adm::Method::ListMetadata as u64,
+            params,
+            value,
+            gas_params,
+        ))
+    }
+
+    /// Get the metadata of a machine, e.g. an object store or an accumulator.
+    /// This will not create a transaction.
+    pub fn machine_get_metadata(
+        &mut self,
+        address: Address,
+        value: TokenAmount,
+        gas_params: GasParams,
+    ) -> anyhow::Result<Message> {
+        Ok(self.transaction(
+            address,
+            GET_METADATA_METHOD,
+            RawBytes::default(),
+            value,
+            gas_params,
+        ))
+    }
+
+    /// Check whether an address may currently write to a machine.
+    /// This will not create a transaction.
+    pub fn machine_can_write(
+        &mut self,
+        address: Address,
+        who: Address,
+        value: TokenAmount,
+        gas_params: GasParams,
+    ) -> anyhow::Result<Message> {
+        let params = RawBytes::serialize(CanWriteParams { address: who })?;
+        Ok(self.transaction(address, CAN_WRITE_METHOD, params, value, gas_params))
+    }
+
+    /// Get a leaf from the default namespace of an accumulator.
+    /// This will not create a transaction.
+    pub fn acc_get(
+        &mut self,
+        address: Address,
+        index: u64,
+        value: TokenAmount,
+        gas_params: GasParams,
+    ) -> anyhow::Result<Message> {
+        let params = RawBytes::serialize(AccGetParams {
+            index,
+            namespace: Vec::new(),
+        })?;
+        Ok(self.transaction(address, AccGet as u64, params, value, gas_params))
+    }
+
+    /// Get a leaf from an accumulator with its inclusion proof. This will not create a transaction.
+    pub fn acc_get_with_proof(
+        &mut self,
+        address: Address,
+        index: u64,
+        value: TokenAmount,
+        gas_params: GasParams,
+    ) -> anyhow::Result<Message> {
+        let params = RawBytes::serialize(index)?;
+        Ok(self.transaction(address, AccGetWithProof as u64, params, value, gas_params))
+    }
+
+    /// Get the CID of a leaf from an accumulator. This will not create a transaction.
+    pub fn acc_get_cid(
+        &mut self,
+        address: Address,
+        index: u64,
+        value: TokenAmount,
+        gas_params: GasParams,
+    ) -> anyhow::Result<Message> {
+        let params = RawBytes::serialize(index)?;
+        Ok(self.transaction(address, AccGetCid as u64, params, value, gas_params))
+    }
+
+    /// Get the root of the default namespace of an accumulator.
+    /// This will not create a transaction.
+    pub fn acc_root(
+        &mut self,
+        address: Address,
+        value: TokenAmount,
+        gas_params: GasParams,
+    ) -> anyhow::Result<Message> {
+        let params = RawBytes::serialize(AccRootParams::default())?;
+        Ok(self.transaction(address, AccRoot as u64, params, value, gas_params))
+    }
+
+    /// Get the peaks of the default namespace of an accumulator.
+    /// This will not create a transaction.
+    pub fn acc_peaks(
+        &mut self,
+        address: Address,
+        value: TokenAmount,
+        gas_params: GasParams,
+    ) -> anyhow::Result<Message> {
+        Ok(self.transaction(
+            address,
+            AccPeaks as u64,
+            RawBytes::default(),
+            value,
+            gas_params,
+        ))
+    }
+
+    /// Check the leaf counts of an accumulator against its peaks.
+    /// This will not create a transaction.
+    pub fn acc_verify_integrity(
+        &mut self,
+        address: Address,
+        value: TokenAmount,
+        gas_params: GasParams,
+    ) -> anyhow::Result<Message> {
+        Ok(self.transaction(
+            address,
+            AccVerifyIntegrity as u64,
+            RawBytes::default(),
+            value,
+            gas_params,
+        ))
+    }
+
     pub fn fevm_call(
         &mut self,
         contract: Address,
@@ -129,13 +352,20 @@ impl MessageFactory {
         Ok(msg)
     }
 }
+
+/// Secret key of the sender of the messages generated by a [`SignedMessageFactory`].
+enum SigningKey {
+    Secp256k1(SecretKey),
+    Bls(bls_signatures::PrivateKey),
+}
+
 /// Wrapper for MessageFactory which generates signed messages
 ///
-/// It assumes the sender is an `f1` type address, it won't work with `f410` addresses.
+/// It assumes the sender is an `f1` or `f3` type address, it won't work with `f410` addresses.
 /// For those one must use the Ethereum API, with a suitable client library such as [ethers].
 pub struct SignedMessageFactory {
     inner: MessageFactory,
-    sk: SecretKey,
+    sk: SigningKey,
     chain_id: ChainID,
 }
 
@@ -144,7 +374,7 @@ impl SignedMessageFactory {
     pub fn new(sk: SecretKey, addr: Address, sequence: u64, chain_id: ChainID) -> Self {
         Self {
             inner: MessageFactory::new(addr, sequence),
-            sk,
+            sk: SigningKey::Secp256k1(sk),
             chain_id,
         }
     }
@@ -156,6 +386,17 @@ impl SignedMessageFactory {
         Self::new(sk, addr, sequence, chain_id)
     }
 
+    /// Treat the secret key as an f3 type account.
+    pub fn new_bls(sk: bls_signatures::PrivateKey, sequence: u64, chain_id: ChainID) -> Self {
+        let pk = sk.public_key();
+        let addr = Address::new_bls(&pk.as_bytes()).expect("public key is 48 bytes");
+        Self {
+            inner: MessageFactory::new(addr, sequence),
+            sk: SigningKey::Bls(sk),
+            chain_id,
+        }
+    }
+
     /// Convenience method to read the secret key from a file, expected to be in Base64 format.
     pub fn read_secret_key(sk: &Path) -> anyhow::Result<SecretKey> {
         let b64 = std::fs::read_to_string(sk).context("failed to read secret key")?;
@@ -166,6 +407,25 @@ impl SignedMessageFactory {
         Ok(sk)
     }
 
+    /// Convenience method to read the secret key from an encrypted file, expected to be in the
+    /// Ethereum v3 keystore format.
+    ///
+    /// A wrong passphrase is reported separately from a malformed keystore.
+    pub fn read_secret_key_encrypted(sk: &Path, passphrase: &str) -> anyhow::Result<SecretKey> {
+        let bz = match eth_keystore::decrypt_key(sk, passphrase) {
+            Ok(bz) => bz,
+            Err(eth_keystore::KeystoreError::MacMismatch) => {
+                return Err(anyhow!("wrong passphrase for keystore"))
+            }
+            Err(eth_keystore::KeystoreError::StdIo(e)) => {
+                return Err(anyhow!("failed to read keystore: {e}"))
+            }
+            Err(e) => return Err(anyhow!("corrupt keystore file: {e}")),
+        };
+        let sk = SecretKey::try_from(bz).context("failed to parse secret key")?;
+        Ok(sk)
+    }
+
     /// Convenience method to serialize a [`ChainMessage`] for inclusion in a Tendermint transaction.
     pub fn serialize(message: &ChainMessage) -> anyhow::Result<Vec<u8>> {
         Ok(fvm_ipld_encoding::to_vec(message)?)
@@ -176,7 +436,27 @@ impl SignedMessageFactory {
         self.inner.address()
     }
 
+    /// The sequence of the next message.
+    pub fn sequence(&self) -> u64 {
+        self.inner.sequence()
+    }
+
+    /// Start a batch of messages with consecutive sequences.
+    ///
+    /// The sequence is rolled back if the batch is dropped without being finished.
+    pub fn batch(&mut self) -> MessageBatch<'_> {
+        let start = self.inner.sequence();
+        MessageBatch {
+            factory: self,
+            start,
+            messages: Vec::new(),
+        }
+    }
+
     /// Transfer tokens to another account.
+    ///
+    /// The recipient can be an `f410` address which doesn't exist yet, in which case
+    /// a placeholder actor is created for it when the message is executed.
     pub fn transfer(
         &mut self,
         to: Address,
@@ -187,6 +467,8 @@ impl SignedMessageFactory {
     }
 
     /// Send a message to an actor.
+    ///
+    /// See [`MessageFactory::transaction`] about sending to `f410` addresses.
     pub fn transaction(
         &mut self,
         to: Address,
@@ -199,11 +481,174 @@ impl SignedMessageFactory {
         let message = self
             .inner
             .transaction(to, method_num, params, value, gas_params);
-        let signed = SignedMessage::new_secp256k1(message, object, &self.sk, &self.chain_id)?;
+        let signed = match &self.sk {
+            SigningKey::Secp256k1(sk) => {
+                SignedMessage::new_secp256k1(message, object, sk, &self.chain_id)?
+            }
+            SigningKey::Bls(sk) => SignedMessage::new_bls(message, object, sk, &self.chain_id)?,
+        };
         let chain = ChainMessage::Signed(signed);
         Ok(chain)
     }
 
+    /// Add an object to an object store. Use [`fendermint_actor_objectstore::cid_key`] as the key
+    /// to store the object under its CID.
+    ///
+    /// Objects carrying their content in `params.data` are stored internally, so they aren't
+    /// handed to the resolver.
+    pub fn os_add(
+        &mut self,
+        address: Address,
+        params: AddParams,
+        value: TokenAmount,
+        gas_params: GasParams,
+    ) -> anyhow::Result<ChainMessage> {
+        let object = params
+            .data
+            .is_none()
+            .then(|| Object::new(params.key.clone(), params.cid, address));
+        let params = RawBytes::serialize(params)?;
+        let message =
+            self.transaction(address, AddObject as u64, params, value, gas_params, object)?;
+        Ok(message)
+    }
+
+    /// Swap an object in an object store, only if its current value matches the expected CID.
+    pub fn os_swap(
+        &mut self,
+        address: Address,
+        params: SwapParams,
+        value: TokenAmount,
+        gas_params: GasParams,
+    ) -> anyhow::Result<ChainMessage> {
+        let object = Object::new(params.key.clone(), params.new_cid, address);
+        let params = RawBytes::serialize(params)?;
+        let message = self.transaction(
+            address,
+            SwapObject as u64,
+            params,
+            value,
+            gas_params,
+            Some(object),
+        )?;
+        Ok(message)
+    }
+
+    /// Update the metadata of an object in an object store, without changing its value.
+    pub fn os_update_metadata(
+        &mut self,
+        address: Address,
+        params: UpdateMetadataParams,
+        value: TokenAmount,
+        gas_params: GasParams,
+    ) -> anyhow::Result<ChainMessage> {
+        let params = RawBytes::serialize(params)?;
+        let message = self.transaction(
+            address,
+            UpdateMetadata as u64,
+            params,
+            value,
+            gas_params,
+            None,
+        )?;
+        Ok(message)
+    }
+
+    /// Copy or move a resolved object in an object store to another key.
+    pub fn os_copy(
+        &mut self,
+        address: Address,
+        params: CopyParams,
+        value: TokenAmount,
+        gas_params: GasParams,
+    ) -> anyhow::Result<ChainMessage> {
+        let params = RawBytes::serialize(params)?;
+        let message =
+            self.transaction(address, CopyObject as u64, params, value, gas_params, None)?;
+        Ok(message)
+    }
+
+    /// Get and delete an object from an object store at once.
+    pub fn os_claim(
+        &mut self,
+        address: Address,
+        params: ClaimParams,
+        value: TokenAmount,
+        gas_params: GasParams,
+    ) -> anyhow::Result<ChainMessage> {
+        let params = RawBytes::serialize(params)?;
+        let message =
+            self.transaction(address, ClaimObject as u64, params, value, gas_params, None)?;
+        Ok(message)
+    }
+
+    /// Get and delete the object with the lexicographically first key under a prefix
+    /// from an object store at once.
+    pub fn os_claim_first(
+        &mut self,
+        address: Address,
+        params: ClaimFirstParams,
+        value: TokenAmount,
+        gas_params: GasParams,
+    ) -> anyhow::Result<ChainMessage> {
+        let params = RawBytes::serialize(params)?;
+        let message =
+            self.transaction(address, ClaimFirst as u64, params, value, gas_params, None)?;
+        Ok(message)
+    }
+
+    /// Import a batch of leaves into an accumulator, e.g. one of [`crate::export::import_batches`].
+    pub fn acc_import_leaves(
+        &mut self,
+        address: Address,
+        params: ImportLeavesParams,
+        value: TokenAmount,
+        gas_params: GasParams,
+    ) -> anyhow::Result<ChainMessage> {
+        let params = RawBytes::serialize(params)?;
+        let message = self.transaction(
+            address,
+            ImportLeaves as u64,
+            params,
+            value,
+            gas_params,
+            None,
+        )?;
+        Ok(message)
+    }
+
+    /// Push the CID of content stored elsewhere, e.g. in IPFS, as a leaf of an accumulator.
+    pub fn acc_push_cid(
+        &mut self,
+        address: Address,
+        params: PushCidParams,
+        value: TokenAmount,
+        gas_params: GasParams,
+    ) -> anyhow::Result<ChainMessage> {
+        let params = RawBytes::serialize(params)?;
+        let message = self.transaction(address, PushCid as u64, params, value, gas_params, None)?;
+        Ok(message)
+    }
+
+    /// Create a new machine, e.g. an object store or an accumulator, owned by the sender.
+    pub fn adm_create(
+        &mut self,
+        params: adm::CreateExternalParams,
+        value: TokenAmount,
+        gas_params: GasParams,
+    ) -> anyhow::Result<ChainMessage> {
+        let params = RawBytes::serialize(params)?;
+        let message = self.transaction(
+            adm::ADM_ACTOR_ADDR,
+            adm::Method::CreateExternal as u64,
+            params,
+            value,
+            gas_params,
+            None,
+        )?;
+        Ok(message)
+    }
+
     /// Deploy a FEVM contract.
     pub fn fevm_create(
         &mut self,
@@ -268,6 +713,60 @@ impl SignedMessageFactory {
     }
 }
 
+/// Messages built by a [`SignedMessageFactory`] to be broadcast in order.
+pub struct MessageBatch<'a> {
+    factory: &'a mut SignedMessageFactory,
+    /// Sequence of the factory when the batch was started.
+    start: u64,
+    messages: Vec<ChainMessage>,
+}
+
+impl MessageBatch<'_> {
+    /// Add a message built by the factory, e.g. `batch.add(|f| f.os_swap(...))`.
+    ///
+    /// If building the message fails, the sequence is left as it was before the call.
+    pub fn add<F>(&mut self, f: F) -> anyhow::Result<&mut Self>
+    where
+        F: FnOnce(&mut SignedMessageFactory) -> anyhow::Result<ChainMessage>,
+    {
+        let sequence = self.factory.sequence();
+        match f(self.factory) {
+            Ok(message) => {
+                self.messages.push(message);
+                Ok(self)
+            }
+            Err(e) => {
+                self.factory.inner.set_sequence(sequence);
+                Err(e)
+            }
+        }
+    }
+
+    /// Number of messages in the batch.
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// Whether the batch has no messages.
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Finish the batch, keeping the sequences used by its messages.
+    pub fn finish(mut self) -> Vec<ChainMessage> {
+        let messages = std::mem::take(&mut self.messages);
+        // The messages are handed over, so the drop doesn't need to roll anything back.
+        self.start = self.factory.sequence();
+        messages
+    }
+}
+
+impl Drop for MessageBatch<'_> {
+    fn drop(&mut self) {
+        self.factory.inner.set_sequence(self.start);
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct GasParams {
     /// Maximum amount of gas that can be charged.
@@ -280,3 +779,184 @@ pub struct GasParams {
     /// Gas premium.
     pub gas_premium: TokenAmount,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    use bls_signatures::Serialize;
+    use fendermint_actor_machine::WriteAccess;
+    use fendermint_crypto::SecretKey;
+    use fendermint_vm_actor_interface::adm;
+    use fendermint_vm_message::chain::ChainMessage;
+    use fvm_shared::{
+        address::{Address, Protocol},
+        chainid::ChainID,
+        econ::TokenAmount,
+        METHOD_SEND,
+    };
+
+    use super::{GasParams, SignedMessageFactory};
+
+    fn factory(sequence: u64) -> SignedMessageFactory {
+        let sk = SecretKey::try_from(vec![1; 32]).unwrap();
+        SignedMessageFactory::new_secp256k1(sk, sequence, ChainID::from(1))
+    }
+
+    fn gas_params() -> GasParams {
+        GasParams {
+            gas_limit: 10_000_000,
+            gas_fee_cap: TokenAmount::from_atto(100),
+            gas_premium: TokenAmount::from_atto(100),
+        }
+    }
+
+    fn sequence(message: &ChainMessage) -> u64 {
+        match message {
+            ChainMessage::Signed(signed) => signed.message().sequence,
+            other => panic!("unexpected message: {other:?}"),
+        }
+    }
+
+    fn keystore_path() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/keystore.json")
+    }
+
+    #[test]
+    fn read_secret_key_encrypted() {
+        let sk = SignedMessageFactory::read_secret_key_encrypted(&keystore_path(), "testpassword")
+            .expect("failed to decrypt keystore");
+        assert_eq!(
+            hex::encode(*sk.serialize()),
+            "7a28b5ba57c53603b0b07b56bba752f7784bf506fa95edc395f5cf6c7514fe9d"
+        );
+    }
+
+    #[test]
+    fn read_secret_key_encrypted_wrong_passphrase() {
+        let err = SignedMessageFactory::read_secret_key_encrypted(&keystore_path(), "wrong")
+            .expect_err("wrong passphrase should be rejected");
+        assert_eq!(err.to_string(), "wrong passphrase for keystore");
+    }
+
+    #[test]
+    fn sign_with_bls_key() {
+        let sk = bls_signatures::PrivateKey::new([1u8; 32]);
+        let addr = Address::new_bls(&sk.public_key().as_bytes()).unwrap();
+        let chain_id = ChainID::from(1);
+        let mut factory = SignedMessageFactory::new_bls(sk, 0, chain_id);
+        assert_eq!(*factory.address(), addr);
+        assert_eq!(addr.protocol(), Protocol::BLS);
+
+        let to = Address::new_id(100);
+        let message = factory
+            .transfer(to, TokenAmount::from_atto(1), gas_params())
+            .unwrap();
+        match message {
+            ChainMessage::Signed(signed) => {
+                assert!(signed.is_bls());
+                assert_eq!(signed.message().from, *factory.address());
+                signed.verify(&chain_id).expect("signature should be valid");
+                assert!(signed.verify(&ChainID::from(2)).is_err());
+            }
+            other => panic!("unexpected message: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn transfer_to_uninitialized_delegated_address() {
+        let to = Address::new_delegated(10, &[1u8; 20]).unwrap();
+        let message = factory(0)
+            .transfer(to, TokenAmount::from_atto(1), gas_params())
+            .unwrap();
+        match message {
+            ChainMessage::Signed(signed) => {
+                let message = signed.message();
+                assert_eq!(message.to, to);
+                assert_eq!(message.method_num, METHOD_SEND);
+            }
+            other => panic!("unexpected message: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn batch_assigns_consecutive_sequences() {
+        let mut factory = factory(5);
+        let mut batch = factory.batch();
+        for i in 0..3 {
+            batch
+                .add(|f| {
+                    f.transfer(
+                        Address::new_id(100 + i),
+                        TokenAmount::from_atto(1),
+                        gas_params(),
+                    )
+                })
+                .unwrap();
+        }
+        let messages = batch.finish();
+
+        let sequences: Vec<u64> = messages.iter().map(sequence).collect();
+        assert_eq!(sequences, vec![5, 6, 7]);
+        assert_eq!(factory.sequence(), 8);
+    }
+
+    #[test]
+    fn dropped_batch_restores_sequence() {
+        let mut factory = factory(5);
+        {
+            let mut batch = factory.batch();
+            batch
+                .add(|f| {
+                    f.transfer(
+                        Address::new_id(100),
+                        TokenAmount::from_atto(1),
+                        gas_params(),
+                    )
+                })
+                .unwrap();
+            batch
+                .add(|f| {
+                    f.transfer(
+                        Address::new_id(101),
+                        TokenAmount::from_atto(1),
+                        gas_params(),
+                    )
+                })
+                .unwrap();
+        }
+        assert_eq!(factory.sequence(), 5);
+
+        let message = factory
+            .transfer(
+                Address::new_id(100),
+                TokenAmount::from_atto(1),
+                gas_params(),
+            )
+            .unwrap();
+        assert_eq!(sequence(&message), 5);
+    }
+
+    #[test]
+    fn adm_create_targets_adm() {
+        let mut factory = factory(0);
+        let params = adm::CreateExternalParams {
+            kind: adm::Kind::Accumulator,
+            write_access: WriteAccess::OnlyOwner,
+            metadata: HashMap::from([("name".to_string(), "events".to_string())]),
+        };
+        let message = match factory
+            .adm_create(params, TokenAmount::default(), gas_params())
+            .unwrap()
+        {
+            ChainMessage::Signed(signed) => signed.into_message(),
+            other => panic!("unexpected message: {other:?}"),
+        };
+        assert_eq!(message.to, adm::ADM_ACTOR_ADDR);
+        assert_eq!(message.method_num, adm::Method::CreateExternal as u64);
+        let params: adm::CreateExternalParams = message.params.deserialize().unwrap();
+        assert!(matches!(params.kind, adm::Kind::Accumulator));
+        assert_eq!(params.metadata["name"], "events");
+    }
+}
@@ -0,0 +1,1121 @@
+// Copyright 2024 Textile
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Typed client for the objects proxy HTTP API.
+//!
+//! The proxy only serves object uploads, object and accumulator reads, simulations of writes,
+//! and prepares accumulator imports. Other object store and accumulator operations are messages signed by the sender,
+//! sent with a [`crate::TxClient`], and queries made with a [`crate::QueryClient`].
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{anyhow, Context};
+use base64::{engine::general_purpose, Engine};
+use bytes::Bytes;
+use cid::Cid;
+use fendermint_actor_accumulator::{
+    GetWithProofReturn, ImportLeavesParams, LeafCodec, Method as AccumulatorMethod, Proof,
+    RootScheme,
+};
+use fendermint_actor_objectstore::{Method as ObjectStoreMethod, ObjectKind};
+use fendermint_vm_message::signed::SignedMessage;
+use fvm_shared::{address::Address, chainid::ChainID, clock::ChainEpoch};
+use reqwest::{multipart, RequestBuilder, Url};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// Aggregates over the objects under a key prefix.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StatResponse {
+    /// Number of objects.
+    pub count: u64,
+    /// Sum of the object sizes in bytes.
+    pub total_bytes: u64,
+    /// Whether only the store's maximum of keys were scanned, so objects are left out.
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+/// Keys to check the existence of in an object store.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct HasManyRequest {
+    /// Object keys, in `key_encoding`.
+    pub keys: Vec<String>,
+    /// Encoding of the keys. Defaults to UTF-8.
+    pub key_encoding: Option<KeyEncoding>,
+}
+
+/// A signed `ClaimObject` or `ClaimFirst` message, for the proxy to broadcast.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ClaimRequest {
+    pub chain_id: u64,
+    /// The signed message, DAG-CBOR encoded and URL-safe base64 encoded, like in uploads.
+    pub msg: String,
+    /// Encoding of the key of the claimed object in the response. Defaults to UTF-8.
+    pub key_encoding: Option<KeyEncoding>,
+}
+
+impl ClaimRequest {
+    pub fn new(chain_id: ChainID, signed_msg: &SignedMessage) -> anyhow::Result<Self> {
+        let msg = fvm_ipld_encoding::to_vec(signed_msg)?;
+        Ok(Self {
+            chain_id: chain_id.into(),
+            msg: general_purpose::URL_SAFE.encode(msg),
+            key_encoding: None,
+        })
+    }
+}
+
+/// The kind of write a [`SimulateRequest`] stands in for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SimulateTarget {
+    /// Adding an object to an object store.
+    OsPut,
+    /// Deleting an object from an object store.
+    OsDelete,
+    /// Claiming an object from an object store, by key or under a prefix.
+    OsClaim,
+    /// Pushing a leaf into an accumulator, as data or as a CID.
+    AccPush,
+}
+
+impl SimulateTarget {
+    /// The methods a message for the target can call.
+    pub fn methods(&self) -> &'static [u64] {
+        match self {
+            Self::OsPut => &[ObjectStoreMethod::AddObject as u64],
+            Self::OsDelete => &[ObjectStoreMethod::DeleteObject as u64],
+            Self::OsClaim => &[
+                ObjectStoreMethod::ClaimObject as u64,
+                ObjectStoreMethod::ClaimFirst as u64,
+            ],
+            Self::AccPush => &[
+                AccumulatorMethod::Push as u64,
+                AccumulatorMethod::PushCid as u64,
+            ],
+        }
+    }
+}
+
+/// A signed write message, for the proxy to run without broadcasting it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SimulateRequest {
+    pub chain_id: u64,
+    /// The signed message, DAG-CBOR encoded and URL-safe base64 encoded, like in uploads.
+    pub msg: String,
+    /// The write the message is for, which its method has to match.
+    pub target: SimulateTarget,
+}
+
+impl SimulateRequest {
+    pub fn new(
+        chain_id: ChainID,
+        signed_msg: &SignedMessage,
+        target: SimulateTarget,
+    ) -> anyhow::Result<Self> {
+        let msg = fvm_ipld_encoding::to_vec(signed_msg)?;
+        Ok(Self {
+            chain_id: chain_id.into(),
+            msg: general_purpose::URL_SAFE.encode(msg),
+            target,
+        })
+    }
+}
+
+/// Outcome of running a write message against the latest committed state.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SimulateResponse {
+    pub would_succeed: bool,
+    pub gas_used: i64,
+    /// Why the message would fail, if it would.
+    pub error: Option<String>,
+}
+
+/// Whether each of the requested keys has an object, in the order of the request.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HasManyResponse {
+    pub has: Vec<bool>,
+}
+
+/// How object keys are represented in a listing.
+///
+/// Object stores hold keys as raw bytes, which may not be valid UTF-8. Listing such keys
+/// as UTF-8 fails, rather than returning altered keys.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyEncoding {
+    /// Keys as UTF-8 strings.
+    #[default]
+    Utf8,
+    /// Keys as standard base64 of their bytes.
+    Base64,
+}
+
+impl KeyEncoding {
+    /// Encode a key, or `None` if it can't be represented in this encoding.
+    pub fn encode(&self, key: &[u8]) -> Option<String> {
+        match self {
+            KeyEncoding::Utf8 => std::str::from_utf8(key).ok().map(String::from),
+            KeyEncoding::Base64 => Some(general_purpose::STANDARD.encode(key)),
+        }
+    }
+
+    /// Decode a key encoded with [`KeyEncoding::encode`].
+    pub fn decode(&self, key: &str) -> anyhow::Result<Vec<u8>> {
+        match self {
+            KeyEncoding::Utf8 => Ok(key.as_bytes().to_vec()),
+            KeyEncoding::Base64 => general_purpose::STANDARD
+                .decode(key)
+                .context("failed to decode base64 key"),
+        }
+    }
+}
+
+/// An object in a listing.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ListedObject {
+    /// Object key, in the key encoding of the listing.
+    pub key: String,
+    #[serde(with = "cid_string")]
+    pub cid: Cid,
+    pub size: u64,
+    /// Whether the content is available. Internal objects always are.
+    pub resolved: bool,
+    /// Whether the content is stored in IPFS, or inline in the object store.
+    #[serde(default)]
+    pub kind: ObjectKind,
+    pub metadata: HashMap<String, String>,
+    /// The epoch at which the object was added.
+    #[serde(default)]
+    pub created_at: ChainEpoch,
+}
+
+/// How much of each object a listing includes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListDetail {
+    /// Everything about the object, as a [`ListedObject`].
+    #[default]
+    Full,
+    /// Only the key and CID, as a [`MinimalListedObject`], to save bandwidth.
+    Minimal,
+}
+
+/// An object in a listing with [`ListDetail::Minimal`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MinimalListedObject {
+    /// Object key, in the key encoding of the listing.
+    pub key: String,
+    #[serde(with = "cid_string")]
+    pub cid: Cid,
+}
+
+impl From<ListedObject> for MinimalListedObject {
+    fn from(object: ListedObject) -> Self {
+        Self {
+            key: object.key,
+            cid: object.cid,
+        }
+    }
+}
+
+/// Objects under a key prefix, as [`ListedObject`]s or [`MinimalListedObject`]s.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ListResponse<O = ListedObject> {
+    pub objects: Vec<O>,
+    /// When a delimiter is given, the distinct key prefixes up to and including the next delimiter,
+    /// in the key encoding of the listing.
+    pub common_prefixes: Vec<String>,
+    /// Whether more objects match beyond this page.
+    #[serde(default)]
+    pub truncated: bool,
+    /// Whether a glob was only matched against the store's maximum of keys, so matching keys
+    /// may be missing. Paging further doesn't find them.
+    #[serde(default)]
+    pub scan_truncated: bool,
+}
+
+impl ListResponse {
+    /// The same listing with only the key and CID of each object.
+    pub fn into_minimal(self) -> ListResponse<MinimalListedObject> {
+        ListResponse {
+            objects: self.objects.into_iter().map(Into::into).collect(),
+            common_prefixes: self.common_prefixes,
+            truncated: self.truncated,
+            scan_truncated: self.scan_truncated,
+        }
+    }
+}
+
+/// A machine in a listing.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ListedMachine {
+    /// Machine kind, e.g. `objectstore` or `accumulator`.
+    pub kind: String,
+    /// Machine robust address.
+    pub address: String,
+}
+
+/// Settings shared by all machines.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MachineMetadataResponse {
+    /// Machine kind, e.g. `objectstore` or `accumulator`.
+    pub kind: String,
+    /// Machine owner robust address.
+    pub owner: String,
+    /// Who can write to the machine, `onlyowner` or `public`.
+    pub write_access: String,
+    /// Whether the machine is frozen and no longer accepts writes.
+    pub frozen: bool,
+    /// Application-defined metadata, e.g. a name or a schema ID.
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+}
+
+/// Settings of an object store.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ObjectStoreMetadataResponse {
+    #[serde(flatten)]
+    pub machine: MachineMetadataResponse,
+    /// Bit width of the HAMT holding the objects.
+    pub bit_width: u32,
+    /// Maximum object size in bytes, if limited.
+    pub max_object_size: Option<u64>,
+    /// Accumulator whose root is recorded when an object is resolved, if any.
+    pub accumulator: Option<String>,
+    /// Maximum number of objects returned by a list query, if not the default.
+    pub max_list_limit: Option<u64>,
+    /// Number of epochs a deleted object can be restored for, if deletes are soft.
+    pub soft_delete_grace_period: Option<ChainEpoch>,
+    /// Whether prior versions of objects are kept.
+    pub versioning: bool,
+    /// Whether object CIDs are stored as CIDv0.
+    pub cid_v0: bool,
+    /// Maximum number of metadata entries on an object, if not the default.
+    pub max_metadata_entries: Option<u64>,
+    /// Maximum length in bytes of a metadata key, if not the default.
+    pub max_metadata_key_length: Option<u64>,
+    /// Maximum total length in bytes of the metadata of an object, if not the default.
+    pub max_metadata_size: Option<u64>,
+    /// Maximum size in bytes of an object stored internally, if not the default.
+    pub max_internal_object_size: Option<u64>,
+    /// Maximum number of keys a list query matches against its pattern, if not the default.
+    pub max_pattern_scan: Option<u64>,
+    /// Accumulator every change to the objects is pushed to, if any.
+    pub change_log: Option<String>,
+}
+
+/// Settings of an accumulator.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AccumulatorMetadataResponse {
+    #[serde(flatten)]
+    pub machine: MachineMetadataResponse,
+    /// Bit width of the AMT holding the peaks.
+    pub bit_width: u32,
+    /// Number of leaves pushed so far.
+    pub leaf_count: u64,
+    pub leaf_codec: LeafCodec,
+    /// How the peaks are bagged into the root, needed to verify proofs against it.
+    pub root_scheme: RootScheme,
+    /// Maximum number of pushes accepted in an epoch, if limited.
+    pub max_pushes_per_epoch: Option<u64>,
+    /// Number of most recent leaves pushes are deduplicated against, 0 if they aren't.
+    pub dedup_window: u64,
+}
+
+/// Result of checking the leaf counts of an accumulator against its peaks.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IntegrityResponse {
+    /// Whether the check passed.
+    pub ok: bool,
+    /// What the check found wrong, if it didn't pass.
+    pub error: Option<String>,
+}
+
+/// Settings and live stats of the IPFS Resolver of the node behind the proxy.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResolverStatsResponse {
+    /// Seconds to wait between attempts to resolve a CID.
+    pub retry_delay_secs: u64,
+    /// Whether content is checked to still be pinned right before voting on it.
+    pub verify_before_vote: bool,
+    /// Tasks being resolved.
+    pub in_flight: u64,
+    /// Tasks waiting out the retry delay before going back to the queue.
+    pub queued: u64,
+    /// Tasks resolved since the node started.
+    pub resolved: u64,
+    /// Tasks failed for good since the node started.
+    pub failed: u64,
+    /// Retries scheduled since the node started.
+    pub retried: u64,
+}
+
+/// The root, leaf count and peaks of an accumulator, all as of the same height.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AccumulatorSummaryResponse {
+    /// The height the summary was read at.
+    pub height: u64,
+    pub root: String,
+    pub count: u64,
+    pub peaks: Vec<String>,
+}
+
+/// Leaves appended to an accumulator between two heights.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AccumulatorDiffResponse {
+    /// Number of leaves at the start height, which is the index of the first leaf of the diff.
+    pub from_leaf_count: u64,
+    /// Number of leaves at the end height.
+    pub to_leaf_count: u64,
+    /// The appended leaves, in order.
+    pub leaves: Vec<ListedLeaf>,
+}
+
+/// An accumulator leaf in a listing.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ListedLeaf {
+    pub index: u64,
+    /// The leaf bytes, base64 encoded.
+    pub leaf: String,
+}
+
+impl ListedLeaf {
+    /// The decoded leaf bytes.
+    pub fn leaf_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        general_purpose::STANDARD
+            .decode(&self.leaf)
+            .context("failed to decode leaf")
+    }
+}
+
+/// Whether the content addressed by an accumulator leaf has been resolved.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LeafStatusResponse {
+    pub index: u64,
+    /// The CID of the leaf.
+    #[serde(with = "cid_string")]
+    pub leaf: Cid,
+    /// Whether the content is pinned by the IPFS node of the proxy, which is where the
+    /// resolver puts it once resolved.
+    pub resolved: bool,
+}
+
+/// Whether an address may currently write to a machine.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CanWriteResponse {
+    pub can_write: bool,
+}
+
+/// The result of uploading a content-addressed object.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ContentUploadResponse {
+    /// The CID computed by IPFS.
+    #[serde(with = "cid_string")]
+    pub cid: Cid,
+    /// The key the object is added under, derived from its CID.
+    pub key: String,
+}
+
+/// A verified accumulator export, split into batches of leaves to import.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ImportResponse {
+    /// Number of leaves in the export.
+    pub leaf_count: u64,
+    /// Root of the accumulator once every batch is imported.
+    #[serde(with = "cid_string")]
+    pub root: Cid,
+    /// The DAG-CBOR encoded `ImportLeaves` parameters of each batch, base64 encoded.
+    pub batches: Vec<String>,
+}
+
+impl ImportResponse {
+    /// Decode the batches, to be signed and sent in order.
+    pub fn params(&self) -> anyhow::Result<Vec<ImportLeavesParams>> {
+        self.batches
+            .iter()
+            .map(|batch| {
+                let bytes = general_purpose::STANDARD
+                    .decode(batch)
+                    .context("failed to decode import batch")?;
+                fvm_ipld_encoding::from_slice(&bytes).context("failed to decode import batch")
+            })
+            .collect()
+    }
+}
+
+/// An accumulator leaf with its inclusion proof, enough to verify the leaf locally.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LeafProofResponse {
+    /// The leaf bytes, base64 encoded.
+    pub leaf: String,
+    pub index: u64,
+    /// The number of leaves in the accumulator the proof was built against.
+    pub leaf_count: u64,
+    /// The root of the accumulator, which the proof verifies against.
+    #[serde(with = "cid_string")]
+    pub root: Cid,
+    /// The CID of the leaf.
+    #[serde(with = "cid_string")]
+    pub leaf_cid: Cid,
+    /// Sibling hashes on the path from the leaf up to its peak, bottom first.
+    #[serde(with = "cid_strings")]
+    pub siblings: Vec<Cid>,
+    #[serde(with = "cid_strings")]
+    pub peaks: Vec<Cid>,
+}
+
+impl LeafProofResponse {
+    /// The decoded leaf bytes.
+    pub fn leaf_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        general_purpose::STANDARD
+            .decode(&self.leaf)
+            .context("failed to decode leaf")
+    }
+
+    /// The inclusion proof, to check with [`fendermint_actor_accumulator::verify_proof`].
+    pub fn proof(&self) -> Proof {
+        Proof {
+            index: self.index,
+            leaf_count: self.leaf_count,
+            leaf: self.leaf_cid,
+            siblings: self.siblings.clone(),
+            peaks: self.peaks.clone(),
+        }
+    }
+}
+
+impl From<GetWithProofReturn> for LeafProofResponse {
+    fn from(ret: GetWithProofReturn) -> Self {
+        LeafProofResponse {
+            leaf: general_purpose::STANDARD.encode(&ret.leaf),
+            index: ret.proof.index,
+            leaf_count: ret.leaf_count,
+            root: ret.root,
+            leaf_cid: ret.proof.leaf,
+            siblings: ret.proof.siblings,
+            peaks: ret.proof.peaks,
+        }
+    }
+}
+
+/// Status of a transaction broadcast to the chain.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TxStatus {
+    /// The transaction is not in a block yet.
+    Pending,
+    /// The transaction was included in a block and succeeded.
+    Committed,
+    /// The transaction was included in a block but failed.
+    Failed,
+}
+
+/// Result of a transaction, as far as the proxy knows it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Txn {
+    pub hash: String,
+    pub status: TxStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas_used: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub info: Option<String>,
+}
+
+/// Error returned by the proxy.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ErrorMessage {
+    pub code: u16,
+    pub message: String,
+    pub request_id: String,
+}
+
+/// HTTP client for the objects proxy.
+#[derive(Clone)]
+pub struct ProxyClient {
+    http: reqwest::Client,
+    url: Url,
+}
+
+impl ProxyClient {
+    pub fn new(url: Url) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            url,
+        }
+    }
+
+    /// Check that the proxy is up.
+    pub async fn health(&self) -> anyhow::Result<()> {
+        self.send(self.http.get(self.endpoint("health")?)).await?;
+        Ok(())
+    }
+
+    /// Upload the content of an object whose CID is in a signed `AddObject` or `SwapObject`
+    /// message, returning the CID computed by IPFS.
+    ///
+    /// The message itself is not broadcast by the proxy.
+    pub async fn os_upload(
+        &self,
+        chain_id: ChainID,
+        signed_msg: &SignedMessage,
+        object: Vec<u8>,
+    ) -> anyhow::Result<Cid> {
+        let msg = fvm_ipld_encoding::to_vec(signed_msg)?;
+        let form = multipart::Form::new()
+            .text("chain_id", u64::from(chain_id).to_string())
+            .text("msg", general_purpose::URL_SAFE.encode(msg))
+            .part("object", multipart::Part::bytes(object).file_name("object"));
+        self.upload(self.http.post(self.endpoint("v1/objects")?).multipart(form))
+            .await
+    }
+
+    /// Upload the content of an object whose CID is in a signed `SwapObject` message expecting
+    /// `expected_cid`, only if the key currently has that value, `None` meaning it's absent.
+    ///
+    /// The message itself is not broadcast by the proxy.
+    pub async fn os_upload_swap(
+        &self,
+        chain_id: ChainID,
+        signed_msg: &SignedMessage,
+        object: Vec<u8>,
+        expected_cid: Option<Cid>,
+    ) -> anyhow::Result<Cid> {
+        let msg = fvm_ipld_encoding::to_vec(signed_msg)?;
+        let form = multipart::Form::new()
+            .text("chain_id", u64::from(chain_id).to_string())
+            .text("msg", general_purpose::URL_SAFE.encode(msg))
+            .part("object", multipart::Part::bytes(object).file_name("object"));
+        let req = self.http.post(self.endpoint("v1/objects")?).multipart(form);
+        let req = match expected_cid {
+            Some(cid) => req.header("If-Match", format!("\"{}\"", cid)),
+            None => req.header("If-None-Match", "*"),
+        };
+        self.upload(req).await
+    }
+
+    async fn upload(&self, req: RequestBuilder) -> anyhow::Result<Cid> {
+        let body = self.send(req).await?.text().await?;
+        Cid::try_from(body.as_str()).context("failed to parse object cid")
+    }
+
+    /// Upload the content of an object added under its CID, see
+    /// [`fendermint_actor_objectstore::cid_key`], in a signed `AddObject` message.
+    ///
+    /// The message itself is not broadcast by the proxy.
+    pub async fn os_upload_content(
+        &self,
+        chain_id: ChainID,
+        signed_msg: &SignedMessage,
+        object: Vec<u8>,
+    ) -> anyhow::Result<ContentUploadResponse> {
+        let msg = fvm_ipld_encoding::to_vec(signed_msg)?;
+        let form = multipart::Form::new()
+            .text("chain_id", u64::from(chain_id).to_string())
+            .text("msg", general_purpose::URL_SAFE.encode(msg))
+            .part("object", multipart::Part::bytes(object).file_name("object"));
+        self.json(self.http.put(self.endpoint("v1/os")?).multipart(form))
+            .await
+    }
+
+    /// Download the content of the object at `key`.
+    pub async fn os_get(
+        &self,
+        address: Address,
+        key: &str,
+        height: Option<u64>,
+    ) -> anyhow::Result<Bytes> {
+        let req = self
+            .http
+            .get(self.endpoint(&format!("v1/objects/{address}/{key}"))?)
+            .query(&HeightQuery { height });
+        Ok(self.send(req).await?.bytes().await?)
+    }
+
+    /// Aggregate the objects under a key prefix.
+    pub async fn os_stat(
+        &self,
+        address: Address,
+        prefix: &str,
+        height: Option<u64>,
+    ) -> anyhow::Result<StatResponse> {
+        let req = self
+            .http
+            .get(self.endpoint(&format!("v1/stat/{address}"))?)
+            .query(&StatQuery { prefix, height });
+        self.json(req).await
+    }
+
+    /// Check which of a set of keys have an object, with a single query.
+    pub async fn os_has_many(
+        &self,
+        address: Address,
+        request: &HasManyRequest,
+        height: Option<u64>,
+    ) -> anyhow::Result<HasManyResponse> {
+        let req = self
+            .http
+            .post(self.endpoint(&format!("v1/os/{address}/has-many"))?)
+            .query(&HeightQuery { height })
+            .json(request);
+        self.json(req).await
+    }
+
+    /// Claim an object with a signed `ClaimObject` or `ClaimFirst` message, which the proxy
+    /// broadcasts, returning the object it got and deleted.
+    ///
+    /// Fails with a 404 error if there was no object to claim.
+    pub async fn os_claim(
+        &self,
+        address: Address,
+        request: &ClaimRequest,
+    ) -> anyhow::Result<ListedObject> {
+        let req = self
+            .http
+            .post(self.endpoint(&format!("v1/os/{address}/claim"))?)
+            .json(request);
+        self.json(req).await
+    }
+
+    /// List the objects under a key prefix, grouping keys by `delimiter` if it's not empty.
+    /// The `detail` of the query is ignored, everything is listed.
+    pub async fn os_list(
+        &self,
+        address: Address,
+        query: &ListQuery,
+    ) -> anyhow::Result<ListResponse> {
+        self.list(address, query, ListDetail::Full).await
+    }
+
+    /// Like [`ProxyClient::os_list`], but only listing the key and CID of each object.
+    pub async fn os_list_minimal(
+        &self,
+        address: Address,
+        query: &ListQuery,
+    ) -> anyhow::Result<ListResponse<MinimalListedObject>> {
+        self.list(address, query, ListDetail::Minimal).await
+    }
+
+    async fn list<O: DeserializeOwned>(
+        &self,
+        address: Address,
+        query: &ListQuery,
+        detail: ListDetail,
+    ) -> anyhow::Result<ListResponse<O>> {
+        let query = ListQuery {
+            detail: Some(detail),
+            ..query.clone()
+        };
+        let req = self
+            .http
+            .get(self.endpoint(&format!("v1/list/{address}"))?)
+            .query(&query);
+        self.json(req).await
+    }
+
+    /// List the objects added between two epochs, oldest first.
+    pub async fn os_list_by_time(
+        &self,
+        address: Address,
+        query: &ListByTimeQuery,
+    ) -> anyhow::Result<ListResponse> {
+        let req = self
+            .http
+            .get(self.endpoint(&format!("v1/list/{address}/by_time"))?)
+            .query(query);
+        self.json(req).await
+    }
+
+    /// Get the settings of an object store.
+    pub async fn os_metadata(
+        &self,
+        address: Address,
+        height: Option<u64>,
+    ) -> anyhow::Result<ObjectStoreMetadataResponse> {
+        let req = self
+            .http
+            .get(self.endpoint(&format!("v1/os/{address}/metadata"))?)
+            .query(&HeightQuery { height });
+        self.json(req).await
+    }
+
+    /// Get the settings of an accumulator.
+    pub async fn acc_metadata(
+        &self,
+        address: Address,
+        height: Option<u64>,
+    ) -> anyhow::Result<AccumulatorMetadataResponse> {
+        let req = self
+            .http
+            .get(self.endpoint(&format!("v1/acc/{address}/metadata"))?)
+            .query(&HeightQuery { height });
+        self.json(req).await
+    }
+
+    /// Get the root, leaf count and peaks of an accumulator in one request.
+    pub async fn acc_summary(
+        &self,
+        address: Address,
+        height: Option<u64>,
+    ) -> anyhow::Result<AccumulatorSummaryResponse> {
+        let req = self
+            .http
+            .get(self.endpoint(&format!("v1/acc/{address}/summary"))?)
+            .query(&HeightQuery { height });
+        self.json(req).await
+    }
+
+    /// Check the leaf counts of an accumulator against its peaks.
+    pub async fn acc_verify(
+        &self,
+        address: Address,
+        height: Option<u64>,
+    ) -> anyhow::Result<IntegrityResponse> {
+        let req = self
+            .http
+            .get(self.endpoint(&format!("v1/acc/{address}/verify"))?)
+            .query(&HeightQuery { height });
+        self.json(req).await
+    }
+
+    /// Get the settings and live stats of the IPFS Resolver of the node.
+    pub async fn resolver_stats(&self) -> anyhow::Result<ResolverStatsResponse> {
+        self.json(self.http.get(self.endpoint("v1/resolver/stats")?))
+            .await
+    }
+
+    /// Check whether `addr` may currently write to an object store.
+    pub async fn os_can_write(
+        &self,
+        address: Address,
+        addr: Address,
+        height: Option<u64>,
+    ) -> anyhow::Result<CanWriteResponse> {
+        self.can_write(&format!("v1/os/{address}/can-write"), addr, height)
+            .await
+    }
+
+    /// Check whether `addr` may currently write to an accumulator.
+    pub async fn acc_can_write(
+        &self,
+        address: Address,
+        addr: Address,
+        height: Option<u64>,
+    ) -> anyhow::Result<CanWriteResponse> {
+        self.can_write(&format!("v1/acc/{address}/can-write"), addr, height)
+            .await
+    }
+
+    async fn can_write(
+        &self,
+        path: &str,
+        addr: Address,
+        height: Option<u64>,
+    ) -> anyhow::Result<CanWriteResponse> {
+        let req = self.http.get(self.endpoint(path)?).query(&CanWriteQuery {
+            addr: addr.to_string(),
+            height,
+        });
+        self.json(req).await
+    }
+
+    /// Get the bytes of the accumulator leaf at `index`.
+    pub async fn acc_get(
+        &self,
+        address: Address,
+        index: u64,
+        height: Option<u64>,
+    ) -> anyhow::Result<Bytes> {
+        let req = self
+            .http
+            .get(self.endpoint(&format!("v1/acc/{address}/{index}"))?)
+            .header(reqwest::header::ACCEPT, "application/octet-stream")
+            .query(&HeightQuery { height });
+        Ok(self.send(req).await?.bytes().await?)
+    }
+
+    /// Get the accumulator leaf at `index`, decoded from DAG-CBOR into JSON by the proxy.
+    pub async fn acc_get_json(
+        &self,
+        address: Address,
+        index: u64,
+        height: Option<u64>,
+    ) -> anyhow::Result<serde_json::Value> {
+        let req = self
+            .http
+            .get(self.endpoint(&format!("v1/acc/{address}/{index}"))?)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .query(&HeightQuery { height });
+        self.json(req).await
+    }
+
+    /// Get the accumulator leaf at `index` with its inclusion proof.
+    pub async fn acc_get_with_proof(
+        &self,
+        address: Address,
+        index: u64,
+        height: Option<u64>,
+    ) -> anyhow::Result<LeafProofResponse> {
+        let req = self
+            .http
+            .get(self.endpoint(&format!("v1/acc/{address}/{index}"))?)
+            .query(&ProofQuery {
+                height,
+                proof: true,
+            });
+        self.json(req).await
+    }
+
+    /// Get the CID of the accumulator leaf at `index`, and whether its content is resolved.
+    pub async fn acc_leaf_status(
+        &self,
+        address: Address,
+        index: u64,
+        height: Option<u64>,
+    ) -> anyhow::Result<LeafStatusResponse> {
+        let req = self
+            .http
+            .get(self.endpoint(&format!("v1/acc/{address}/{index}/status"))?)
+            .query(&HeightQuery { height });
+        self.json(req).await
+    }
+
+    /// Get the roots of an accumulator at each of `heights`.
+    pub async fn acc_roots(
+        &self,
+        address: Address,
+        heights: &[u64],
+    ) -> anyhow::Result<BTreeMap<u64, Cid>> {
+        let heights = heights
+            .iter()
+            .map(|h| h.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        let req = self
+            .http
+            .get(self.endpoint(&format!("v1/acc/{address}/roots"))?)
+            .query(&[("heights", heights)]);
+        let roots: BTreeMap<u64, String> = self.json(req).await?;
+        roots
+            .into_iter()
+            .map(|(height, root)| {
+                let root = Cid::try_from(root.as_str()).context("failed to parse root cid")?;
+                Ok((height, root))
+            })
+            .collect()
+    }
+
+    /// Get the leaves appended to an accumulator after `from_height`, up to `to_height`.
+    pub async fn acc_diff(
+        &self,
+        address: Address,
+        from_height: u64,
+        to_height: u64,
+    ) -> anyhow::Result<AccumulatorDiffResponse> {
+        let req = self
+            .http
+            .get(self.endpoint(&format!("v1/acc/{address}/diff"))?)
+            .query(&DiffQuery {
+                from_height,
+                to_height,
+            });
+        self.json(req).await
+    }
+
+    /// Verify an accumulator export and split it into `ImportLeaves` batches.
+    ///
+    /// The proxy holds no keys, so the batches are returned to be signed and sent in order,
+    /// see [`crate::message::SignedMessageFactory::acc_import_leaves`].
+    pub async fn acc_import(
+        &self,
+        address: Address,
+        export: Vec<u8>,
+        query: &ImportQuery,
+    ) -> anyhow::Result<ImportResponse> {
+        let req = self
+            .http
+            .post(self.endpoint(&format!("v1/acc/{address}/import"))?)
+            .header(reqwest::header::CONTENT_TYPE, "application/vnd.ipld.car")
+            .query(query)
+            .body(export);
+        self.json(req).await
+    }
+
+    /// List the machines owned by an address.
+    pub async fn list_machines(&self, query: &MachinesQuery) -> anyhow::Result<Vec<ListedMachine>> {
+        let req = self.http.get(self.endpoint("v1/machines")?).query(query);
+        self.json(req).await
+    }
+
+    /// Run a signed write message against the latest committed state, without broadcasting it,
+    /// to find out if it would succeed and how much gas it would use.
+    pub async fn simulate(&self, request: &SimulateRequest) -> anyhow::Result<SimulateResponse> {
+        let req = self.http.post(self.endpoint("v1/simulate")?).json(request);
+        self.json(req).await
+    }
+
+    /// Look up the status of a transaction by its hash.
+    pub async fn tx_status(&self, hash: &str) -> anyhow::Result<Txn> {
+        let req = self.http.get(self.endpoint(&format!("v1/tx/{hash}"))?);
+        self.json(req).await
+    }
+
+    fn endpoint(&self, path: &str) -> anyhow::Result<Url> {
+        self.url
+            .join(path)
+            .with_context(|| format!("invalid proxy path: {path}"))
+    }
+
+    async fn json<T: DeserializeOwned>(&self, req: RequestBuilder) -> anyhow::Result<T> {
+        let res = self.send(req).await?;
+        res.json().await.context("failed to parse proxy response")
+    }
+
+    /// Send a request, turning error responses into errors.
+    async fn send(&self, req: RequestBuilder) -> anyhow::Result<reqwest::Response> {
+        let res = req.send().await.context("failed to send proxy request")?;
+        if res.status().is_success() {
+            return Ok(res);
+        }
+        let status = res.status();
+        match res.json::<ErrorMessage>().await {
+            Ok(e) => Err(anyhow!(
+                "proxy error {}: {} (request id {})",
+                e.code,
+                e.message,
+                e.request_id
+            )),
+            Err(_) => Err(anyhow!("proxy error {status}")),
+        }
+    }
+}
+
+/// Query parameters for listing objects.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ListQuery {
+    pub prefix: Option<String>,
+    pub delimiter: Option<String>,
+    pub offset: Option<u64>,
+    pub limit: Option<u64>,
+    pub height: Option<u64>,
+    /// Encoding of the keys and common prefixes in the response. Defaults to UTF-8.
+    pub key_encoding: Option<KeyEncoding>,
+    /// List objects in descending key order, rather than in the order of the key hashes.
+    /// Only the store's maximum of keys under the prefix are sorted, see
+    /// [`ListResponse::scan_truncated`]. Defaults to false.
+    pub reverse: Option<bool>,
+    /// Only list the keys matching this glob pattern, e.g. `logs/2024-*/error.json`.
+    /// Matching stops once the store's maximum of keys were matched against it, see
+    /// [`ListResponse::scan_truncated`].
+    pub glob: Option<String>,
+    /// How much of each object to include. Defaults to everything.
+    pub detail: Option<ListDetail>,
+}
+
+/// Query parameters for listing objects by the epoch they were added at.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ListByTimeQuery {
+    /// The first epoch to list objects from. Defaults to 0.
+    pub start_epoch: Option<ChainEpoch>,
+    /// The last epoch to list objects from, inclusive. Defaults to no bound.
+    pub end_epoch: Option<ChainEpoch>,
+    pub limit: Option<u64>,
+    pub height: Option<u64>,
+    /// Encoding of the keys in the response. Defaults to UTF-8.
+    pub key_encoding: Option<KeyEncoding>,
+}
+
+/// Query parameters for listing machines.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MachinesQuery {
+    /// Owner address of the machines.
+    pub owner: Option<String>,
+    /// Only list machines of this kind, e.g. `objectstore` or `accumulator`.
+    pub kind: Option<String>,
+    pub height: Option<u64>,
+}
+
+/// Query parameters for importing an accumulator export.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ImportQuery {
+    /// Replace the leaves of a non-empty accumulator. Defaults to false.
+    pub force: Option<bool>,
+    /// Maximum number of leaves in a batch. Defaults to
+    /// [`crate::export::DEFAULT_IMPORT_BATCH_SIZE`].
+    pub batch_size: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct HeightQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    height: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct CanWriteQuery {
+    addr: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    height: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct DiffQuery {
+    from_height: u64,
+    to_height: u64,
+}
+
+#[derive(Serialize)]
+struct ProofQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    height: Option<u64>,
+    proof: bool,
+}
+
+#[derive(Serialize)]
+struct StatQuery<'a> {
+    prefix: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    height: Option<u64>,
+}
+
+/// Serialize CIDs as strings in JSON, rather than as IPLD links.
+mod cid_string {
+    use std::str::FromStr;
+
+    use cid::Cid;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(cid: &Cid, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(cid)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Cid, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Cid::from_str(&s).map_err(D::Error::custom)
+    }
+}
+
+/// Serialize lists of CIDs as strings in JSON, rather than as IPLD links.
+mod cid_strings {
+    use std::str::FromStr;
+
+    use cid::Cid;
+    use serde::{de::Error, ser::SerializeSeq, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(cids: &[Cid], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(cids.len()))?;
+        for cid in cids {
+            seq.serialize_element(&cid.to_string())?;
+        }
+        seq.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Cid>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|s| Cid::from_str(s).map_err(D::Error::custom))
+            .collect()
+    }
+}
@@ -1,13 +1,23 @@
 // Copyright 2022-2024 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+use std::fmt;
+
 use anyhow::{anyhow, Context};
 use async_trait::async_trait;
-use fendermint_actor_objectstore::{GetParams, Object};
+use fendermint_actor_accumulator::GetWithProofReturn;
+use fendermint_actor_machine::Metadata as MachineMetadata;
+use fendermint_actor_objectstore::{
+    GetParams, HasManyParams, HasParams, ListByTimeParams, ListParams, Object, ObjectList,
+    ObjectStat, StatParams,
+};
+use fendermint_vm_actor_interface::adm::Metadata;
 use fendermint_vm_actor_interface::system::SYSTEM_ACTOR_ADDR;
-use fvm_ipld_encoding::serde::Serialize;
+use fvm_ipld_encoding::serde::{de::DeserializeOwned, Serialize};
+use fvm_shared::address::Protocol;
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::message::Message;
+use fvm_shared::{MethodNum, METHOD_SEND};
 use prost::Message as ProstMessage;
 use tendermint::abci::response::DeliverTx;
 use tendermint::block::Height;
@@ -23,8 +33,13 @@ use fendermint_vm_message::query::{
 };
 
 use crate::message::{GasParams, MessageFactory};
-use crate::response::decode_os_get;
 use crate::response::encode_data;
+use crate::response::{
+    decode_acc_get, decode_acc_get_cid, decode_acc_get_with_proof, decode_acc_peaks,
+    decode_acc_root, decode_acc_verify_integrity, decode_adm_list_metadata,
+    decode_machine_can_write, decode_machine_metadata, decode_os_get, decode_os_has,
+    decode_os_has_many, decode_os_list, decode_os_stat,
+};
 
 #[derive(Serialize, Debug, Clone)]
 /// The parsed value from a query, along with the height at which the query was performed.
@@ -33,6 +48,31 @@ pub struct QueryResponse<T> {
     pub value: T,
 }
 
+/// A read-only call which was executed but aborted by the actor.
+#[derive(Debug, Clone)]
+pub struct CallError {
+    /// The exit code the actor aborted with.
+    pub exit_code: ExitCode,
+    pub info: String,
+}
+
+impl From<&DeliverTx> for CallError {
+    fn from(deliver_tx: &DeliverTx) -> Self {
+        Self {
+            exit_code: ExitCode::new(deliver_tx.code.value()),
+            info: deliver_tx.info.clone(),
+        }
+    }
+}
+
+impl fmt::Display for CallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.info)
+    }
+}
+
+impl std::error::Error for CallError {}
+
 /// Fendermint client for submitting queries.
 #[async_trait]
 pub trait QueryClient: Sync {
@@ -60,6 +100,55 @@ pub trait QueryClient: Sync {
         Ok(QueryResponse { height, value })
     }
 
+    /// Query the state of an actor, decoded as `T`.
+    async fn actor_state_as<T: DeserializeOwned + Send>(
+        &self,
+        address: &Address,
+        height: FvmQueryHeight,
+    ) -> anyhow::Result<QueryResponse<Option<T>>> {
+        let res = self.actor_state(address, height).await?;
+        let height = res.height;
+        let Some((_, actor)) = res.value else {
+            return Ok(QueryResponse {
+                height,
+                value: None,
+            });
+        };
+        // Read the state as of the same height as the actor.
+        let bytes = self
+            .ipld(&actor.state, FvmQueryHeight::Height(height.value()))
+            .await?
+            .ok_or_else(|| anyhow!("actor state {} not found", actor.state))?;
+        let state =
+            fvm_ipld_encoding::from_slice(&bytes).context("failed to decode actor state")?;
+        Ok(QueryResponse {
+            height,
+            value: Some(state),
+        })
+    }
+
+    /// Check that a message calling `method_num` can be delivered to `to`.
+    ///
+    /// A plain transfer to an `f410` address without an actor creates a placeholder actor
+    /// for it, but any other method would fail, so for those the recipient has to exist already.
+    async fn check_recipient(
+        &self,
+        to: &Address,
+        method_num: MethodNum,
+        height: FvmQueryHeight,
+    ) -> anyhow::Result<()> {
+        if to.protocol() != Protocol::Delegated || method_num == METHOD_SEND {
+            return Ok(());
+        }
+        let res = self.actor_state(to, height).await?;
+        if res.value.is_none() {
+            return Err(anyhow!(
+                "delegated address {to} is not initialized; send it a plain transfer first to create its account"
+            ));
+        }
+        Ok(())
+    }
+
     /// Run a message in a read-only fashion.
     async fn call(
         &self,
@@ -147,7 +236,7 @@ pub trait QueryClient: Sync {
 
         let response = self.call(msg, height).await?;
         if response.value.code.is_err() {
-            return Err(anyhow!("{}", response.value.info));
+            return Err(CallError::from(&response.value).into());
         }
         let return_data = decode_os_get(&response.value)
             .context("error decoding data from deliver_tx in call")?;
@@ -155,6 +244,313 @@ pub trait QueryClient: Sync {
         Ok(return_data)
     }
 
+    /// List objects in an object store without including a transaction on the blockchain.
+    async fn os_list_call(
+        &mut self,
+        address: Address,
+        params: ListParams,
+        value: TokenAmount,
+        gas_params: GasParams,
+        height: FvmQueryHeight,
+    ) -> anyhow::Result<ObjectList> {
+        let msg = MessageFactory::new(SYSTEM_ACTOR_ADDR, 0)
+            .os_list(address, params, value, gas_params)?;
+
+        let response = self.call(msg, height).await?;
+        if response.value.code.is_err() {
+            return Err(CallError::from(&response.value).into());
+        }
+        let return_data = decode_os_list(&response.value)
+            .context("error decoding data from deliver_tx in call")?;
+
+        Ok(return_data)
+    }
+
+    /// List objects in an object store by the epoch they were added at, without including
+    /// a transaction on the blockchain.
+    async fn os_list_by_time_call(
+        &mut self,
+        address: Address,
+        params: ListByTimeParams,
+        value: TokenAmount,
+        gas_params: GasParams,
+        height: FvmQueryHeight,
+    ) -> anyhow::Result<ObjectList> {
+        let msg = MessageFactory::new(SYSTEM_ACTOR_ADDR, 0)
+            .os_list_by_time(address, params, value, gas_params)?;
+
+        let response = self.call(msg, height).await?;
+        if response.value.code.is_err() {
+            return Err(CallError::from(&response.value).into());
+        }
+        let return_data = decode_os_list(&response.value)
+            .context("error decoding data from deliver_tx in call")?;
+
+        Ok(return_data)
+    }
+
+    /// Check whether an object exists in an object store without including a transaction on the blockchain.
+    async fn os_has_call(
+        &mut self,
+        address: Address,
+        params: HasParams,
+        value: TokenAmount,
+        gas_params: GasParams,
+        height: FvmQueryHeight,
+    ) -> anyhow::Result<bool> {
+        let msg =
+            MessageFactory::new(SYSTEM_ACTOR_ADDR, 0).os_has(address, params, value, gas_params)?;
+
+        let response = self.call(msg, height).await?;
+        if response.value.code.is_err() {
+            return Err(CallError::from(&response.value).into());
+        }
+        let return_data = decode_os_has(&response.value)
+            .context("error decoding data from deliver_tx in call")?;
+
+        Ok(return_data)
+    }
+
+    /// Check which of a set of objects exist in an object store without including a transaction on the blockchain.
+    async fn os_has_many_call(
+        &mut self,
+        address: Address,
+        params: HasManyParams,
+        value: TokenAmount,
+        gas_params: GasParams,
+        height: FvmQueryHeight,
+    ) -> anyhow::Result<Vec<bool>> {
+        let msg = MessageFactory::new(SYSTEM_ACTOR_ADDR, 0)
+            .os_has_many(address, params, value, gas_params)?;
+
+        let response = self.call(msg, height).await?;
+        if response.value.code.is_err() {
+            return Err(CallError::from(&response.value).into());
+        }
+        let return_data = decode_os_has_many(&response.value)
+            .context("error decoding data from deliver_tx in call")?;
+
+        Ok(return_data)
+    }
+
+    /// Get the object count and total size under a prefix in an object store without including a transaction on the blockchain.
+    async fn os_stat_call(
+        &mut self,
+        address: Address,
+        params: StatParams,
+        value: TokenAmount,
+        gas_params: GasParams,
+        height: FvmQueryHeight,
+    ) -> anyhow::Result<ObjectStat> {
+        let msg = MessageFactory::new(SYSTEM_ACTOR_ADDR, 0)
+            .os_stat(address, params, value, gas_params)?;
+
+        let response = self.call(msg, height).await?;
+        if response.value.code.is_err() {
+            return Err(CallError::from(&response.value).into());
+        }
+        let return_data = decode_os_stat(&response.value)
+            .context("error decoding data from deliver_tx in call")?;
+
+        Ok(return_data)
+    }
+
+    /// List the machines owned by an address without including a transaction on the blockchain.
+    async fn adm_list_metadata_call(
+        &mut self,
+        owner: Address,
+        value: TokenAmount,
+        gas_params: GasParams,
+        height: FvmQueryHeight,
+    ) -> anyhow::Result<Vec<Metadata>> {
+        let msg = MessageFactory::new(SYSTEM_ACTOR_ADDR, 0)
+            .adm_list_metadata(owner, value, gas_params)?;
+
+        let response = self.call(msg, height).await?;
+        if response.value.code.is_err() {
+            return Err(CallError::from(&response.value).into());
+        }
+        let return_data = decode_adm_list_metadata(&response.value)
+            .context("error decoding data from deliver_tx in call")?;
+
+        Ok(return_data)
+    }
+
+    /// Get the metadata of a machine without including a transaction on the blockchain.
+    async fn machine_get_metadata_call(
+        &mut self,
+        address: Address,
+        value: TokenAmount,
+        gas_params: GasParams,
+        height: FvmQueryHeight,
+    ) -> anyhow::Result<MachineMetadata> {
+        let msg = MessageFactory::new(SYSTEM_ACTOR_ADDR, 0)
+            .machine_get_metadata(address, value, gas_params)?;
+
+        let response = self.call(msg, height).await?;
+        if response.value.code.is_err() {
+            return Err(CallError::from(&response.value).into());
+        }
+        let return_data = decode_machine_metadata(&response.value)
+            .context("error decoding data from deliver_tx in call")?;
+
+        Ok(return_data)
+    }
+
+    /// Check whether an address may write to a machine without including a transaction
+    /// on the blockchain.
+    async fn machine_can_write_call(
+        &mut self,
+        address: Address,
+        who: Address,
+        value: TokenAmount,
+        gas_params: GasParams,
+        height: FvmQueryHeight,
+    ) -> anyhow::Result<bool> {
+        let msg = MessageFactory::new(SYSTEM_ACTOR_ADDR, 0)
+            .machine_can_write(address, who, value, gas_params)?;
+
+        let response = self.call(msg, height).await?;
+        if response.value.code.is_err() {
+            return Err(CallError::from(&response.value).into());
+        }
+        let return_data = decode_machine_can_write(&response.value)
+            .context("error decoding data from deliver_tx in call")?;
+
+        Ok(return_data)
+    }
+
+    /// Get a leaf from an accumulator without including a transaction on the blockchain.
+    async fn acc_get_call(
+        &mut self,
+        address: Address,
+        index: u64,
+        value: TokenAmount,
+        gas_params: GasParams,
+        height: FvmQueryHeight,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        let msg =
+            MessageFactory::new(SYSTEM_ACTOR_ADDR, 0).acc_get(address, index, value, gas_params)?;
+
+        let response = self.call(msg, height).await?;
+        if response.value.code.is_err() {
+            return Err(CallError::from(&response.value).into());
+        }
+        let return_data = decode_acc_get(&response.value)
+            .context("error decoding data from deliver_tx in call")?;
+
+        Ok(return_data)
+    }
+
+    /// Get a leaf from an accumulator with its inclusion proof, without including a transaction
+    /// on the blockchain.
+    async fn acc_get_with_proof_call(
+        &mut self,
+        address: Address,
+        index: u64,
+        value: TokenAmount,
+        gas_params: GasParams,
+        height: FvmQueryHeight,
+    ) -> anyhow::Result<Option<GetWithProofReturn>> {
+        let msg = MessageFactory::new(SYSTEM_ACTOR_ADDR, 0)
+            .acc_get_with_proof(address, index, value, gas_params)?;
+
+        let response = self.call(msg, height).await?;
+        if response.value.code.is_err() {
+            return Err(CallError::from(&response.value).into());
+        }
+        let return_data = decode_acc_get_with_proof(&response.value)
+            .context("error decoding data from deliver_tx in call")?;
+
+        Ok(return_data)
+    }
+
+    /// Get the CID of a leaf from an accumulator without including a transaction on the
+    /// blockchain.
+    async fn acc_get_cid_call(
+        &mut self,
+        address: Address,
+        index: u64,
+        value: TokenAmount,
+        gas_params: GasParams,
+        height: FvmQueryHeight,
+    ) -> anyhow::Result<Option<Cid>> {
+        let msg = MessageFactory::new(SYSTEM_ACTOR_ADDR, 0)
+            .acc_get_cid(address, index, value, gas_params)?;
+
+        let response = self.call(msg, height).await?;
+        if response.value.code.is_err() {
+            return Err(CallError::from(&response.value).into());
+        }
+        let return_data = decode_acc_get_cid(&response.value)
+            .context("error decoding data from deliver_tx in call")?;
+
+        Ok(return_data)
+    }
+
+    /// Get the root of an accumulator without including a transaction on the blockchain.
+    async fn acc_root_call(
+        &mut self,
+        address: Address,
+        value: TokenAmount,
+        gas_params: GasParams,
+        height: FvmQueryHeight,
+    ) -> anyhow::Result<Cid> {
+        let msg = MessageFactory::new(SYSTEM_ACTOR_ADDR, 0).acc_root(address, value, gas_params)?;
+
+        let response = self.call(msg, height).await?;
+        if response.value.code.is_err() {
+            return Err(CallError::from(&response.value).into());
+        }
+        let return_data = decode_acc_root(&response.value)
+            .context("error decoding data from deliver_tx in call")?;
+
+        Ok(return_data)
+    }
+
+    /// Get the peaks of an accumulator without including a transaction on the blockchain.
+    async fn acc_peaks_call(
+        &mut self,
+        address: Address,
+        value: TokenAmount,
+        gas_params: GasParams,
+        height: FvmQueryHeight,
+    ) -> anyhow::Result<Vec<Cid>> {
+        let msg =
+            MessageFactory::new(SYSTEM_ACTOR_ADDR, 0).acc_peaks(address, value, gas_params)?;
+
+        let response = self.call(msg, height).await?;
+        if response.value.code.is_err() {
+            return Err(CallError::from(&response.value).into());
+        }
+        let return_data = decode_acc_peaks(&response.value)
+            .context("error decoding data from deliver_tx in call")?;
+
+        Ok(return_data)
+    }
+
+    /// Check the leaf counts of an accumulator against its peaks, returning the problem found,
+    /// if any, without including a transaction on the blockchain.
+    async fn acc_verify_integrity_call(
+        &mut self,
+        address: Address,
+        value: TokenAmount,
+        gas_params: GasParams,
+        height: FvmQueryHeight,
+    ) -> anyhow::Result<Option<String>> {
+        let msg = MessageFactory::new(SYSTEM_ACTOR_ADDR, 0)
+            .acc_verify_integrity(address, value, gas_params)?;
+
+        let response = self.call(msg, height).await?;
+        if response.value.code.is_err() {
+            return Err(CallError::from(&response.value).into());
+        }
+        let return_data = decode_acc_verify_integrity(&response.value)
+            .context("error decoding data from deliver_tx in call")?;
+
+        Ok(return_data)
+    }
+
     /// Run an ABCI query.
     async fn perform(&self, query: FvmQuery, height: FvmQueryHeight) -> anyhow::Result<AbciQuery>;
 }
@@ -219,11 +615,56 @@ fn parse_deliver_tx(res: AbciQuery) -> anyhow::Result<DeliverTx> {
 
 #[cfg(test)]
 mod tests {
+    use async_trait::async_trait;
+    use fendermint_vm_message::query::{FvmQuery, FvmQueryHeight};
+    use fvm_shared::address::Address;
+    use fvm_shared::error::ExitCode;
+    use fvm_shared::METHOD_SEND;
+    use tendermint::abci::Code;
     use tendermint_rpc::endpoint::abci_query::AbciQuery;
 
     use crate::response::decode_fevm_invoke;
 
-    use super::parse_deliver_tx;
+    use super::{parse_deliver_tx, QueryClient};
+
+    /// A client which doesn't know about any actor.
+    struct EmptyClient;
+
+    #[async_trait]
+    impl QueryClient for EmptyClient {
+        async fn perform(
+            &self,
+            _query: FvmQuery,
+            _height: FvmQueryHeight,
+        ) -> anyhow::Result<AbciQuery> {
+            Ok(AbciQuery {
+                code: Code::from(ExitCode::USR_NOT_FOUND.value()),
+                ..Default::default()
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn check_recipient_uninitialized_delegated() {
+        let to = Address::new_delegated(10, &[1u8; 20]).unwrap();
+        let height = FvmQueryHeight::Committed;
+
+        EmptyClient
+            .check_recipient(&to, METHOD_SEND, height)
+            .await
+            .expect("transfers create the account");
+
+        let err = EmptyClient
+            .check_recipient(&to, 2, height)
+            .await
+            .expect_err("other methods need an existing actor");
+        assert!(err.to_string().contains("is not initialized"));
+
+        EmptyClient
+            .check_recipient(&Address::new_id(100), 2, height)
+            .await
+            .expect("only delegated addresses are checked");
+    }
 
     #[test]
     fn parse_call_query_response() {
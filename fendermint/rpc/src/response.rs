@@ -3,8 +3,11 @@
 use anyhow::{anyhow, Context};
 use base64::Engine;
 use bytes::Bytes;
-use fendermint_actor_objectstore::Object;
-use fendermint_vm_actor_interface::eam;
+use cid::Cid;
+use fendermint_actor_accumulator::GetWithProofReturn;
+use fendermint_actor_machine::Metadata as MachineMetadata;
+use fendermint_actor_objectstore::{Object, ObjectList, ObjectStat};
+use fendermint_vm_actor_interface::{adm, eam};
 use fvm_ipld_encoding::{BytesDe, RawBytes};
 use tendermint::abci::response::DeliverTx;
 
@@ -40,6 +43,20 @@ pub fn decode_fevm_create(deliver_tx: &DeliverTx) -> anyhow::Result<eam::CreateR
         .map_err(|e| anyhow!("error parsing as CreateReturn: {e}"))
 }
 
+/// Parse what Tendermint returns in the `data` field of [`DeliverTx`] as [`adm::CreateExternalReturn`].
+pub fn decode_adm_create(deliver_tx: &DeliverTx) -> anyhow::Result<adm::CreateExternalReturn> {
+    let data = decode_data(&deliver_tx.data)?;
+    fvm_ipld_encoding::from_slice::<adm::CreateExternalReturn>(&data)
+        .map_err(|e| anyhow!("error parsing as CreateExternalReturn: {e}"))
+}
+
+/// Parse what Tendermint returns in the `data` field of [`DeliverTx`] as a list of [`adm::Metadata`].
+pub fn decode_adm_list_metadata(deliver_tx: &DeliverTx) -> anyhow::Result<Vec<adm::Metadata>> {
+    let data = decode_data(&deliver_tx.data)?;
+    fvm_ipld_encoding::from_slice::<Vec<adm::Metadata>>(&data)
+        .map_err(|e| anyhow!("error parsing as Vec<Metadata>: {e}"))
+}
+
 /// Parse what Tendermint returns in the `data` field of [`DeliverTx`] as raw ABI return value.
 pub fn decode_fevm_invoke(deliver_tx: &DeliverTx) -> anyhow::Result<Vec<u8>> {
     let data = decode_data(&deliver_tx.data)?;
@@ -66,3 +83,99 @@ pub fn decode_os_get(deliver_tx: &DeliverTx) -> anyhow::Result<Option<Object>> {
     fvm_ipld_encoding::from_slice::<Option<Object>>(&data)
         .map_err(|e| anyhow!("error parsing as Option<Object>: {e}"))
 }
+
+/// Parse what Tendermint returns in the `data` field of [`DeliverTx`] as an [`Object`] claimed
+/// with its key.
+pub fn decode_os_claim_first(deliver_tx: &DeliverTx) -> anyhow::Result<Option<(Vec<u8>, Object)>> {
+    let data = decode_data(&deliver_tx.data)?;
+    fvm_ipld_encoding::from_slice::<Option<(Vec<u8>, Object)>>(&data)
+        .map_err(|e| anyhow!("error parsing as Option<(Vec<u8>, Object)>: {e}"))
+}
+
+/// Parse what Tendermint returns in the `data` field of [`DeliverTx`] as an [`ObjectList`].
+pub fn decode_os_list(deliver_tx: &DeliverTx) -> anyhow::Result<ObjectList> {
+    let data = decode_data(&deliver_tx.data)?;
+    fvm_ipld_encoding::from_slice::<ObjectList>(&data)
+        .map_err(|e| anyhow!("error parsing as ObjectList: {e}"))
+}
+
+/// Parse what Tendermint returns in the `data` field of [`DeliverTx`] as a `bool`.
+pub fn decode_os_has(deliver_tx: &DeliverTx) -> anyhow::Result<bool> {
+    let data = decode_data(&deliver_tx.data)?;
+    fvm_ipld_encoding::from_slice::<bool>(&data).map_err(|e| anyhow!("error parsing as bool: {e}"))
+}
+
+/// Parse what Tendermint returns in the `data` field of [`DeliverTx`] as whether each of
+/// a set of objects exists.
+pub fn decode_os_has_many(deliver_tx: &DeliverTx) -> anyhow::Result<Vec<bool>> {
+    let data = decode_data(&deliver_tx.data)?;
+    fvm_ipld_encoding::from_slice::<Vec<bool>>(&data)
+        .map_err(|e| anyhow!("error parsing as Vec<bool>: {e}"))
+}
+
+/// Parse what Tendermint returns in the `data` field of [`DeliverTx`] as an accumulator leaf.
+pub fn decode_acc_get(deliver_tx: &DeliverTx) -> anyhow::Result<Option<Vec<u8>>> {
+    let data = decode_data(&deliver_tx.data)?;
+    fvm_ipld_encoding::from_slice::<Option<Vec<u8>>>(&data)
+        .map_err(|e| anyhow!("error parsing as Option<Vec<u8>>: {e}"))
+}
+
+/// Parse what Tendermint returns in the `data` field of [`DeliverTx`] as an accumulator leaf
+/// with its inclusion proof.
+pub fn decode_acc_get_with_proof(
+    deliver_tx: &DeliverTx,
+) -> anyhow::Result<Option<GetWithProofReturn>> {
+    let data = decode_data(&deliver_tx.data)?;
+    fvm_ipld_encoding::from_slice::<Option<GetWithProofReturn>>(&data)
+        .map_err(|e| anyhow!("error parsing as Option<GetWithProofReturn>: {e}"))
+}
+
+/// Parse what Tendermint returns in the `data` field of [`DeliverTx`] as the CID of an
+/// accumulator leaf.
+pub fn decode_acc_get_cid(deliver_tx: &DeliverTx) -> anyhow::Result<Option<Cid>> {
+    let data = decode_data(&deliver_tx.data)?;
+    fvm_ipld_encoding::from_slice::<Option<Cid>>(&data)
+        .map_err(|e| anyhow!("error parsing as Option<Cid>: {e}"))
+}
+
+/// Parse what Tendermint returns in the `data` field of [`DeliverTx`] as an accumulator root.
+pub fn decode_acc_root(deliver_tx: &DeliverTx) -> anyhow::Result<Cid> {
+    let data = decode_data(&deliver_tx.data)?;
+    fvm_ipld_encoding::from_slice::<Cid>(&data).map_err(|e| anyhow!("error parsing as Cid: {e}"))
+}
+
+/// Parse what Tendermint returns in the `data` field of [`DeliverTx`] as accumulator peaks.
+pub fn decode_acc_peaks(deliver_tx: &DeliverTx) -> anyhow::Result<Vec<Cid>> {
+    let data = decode_data(&deliver_tx.data)?;
+    fvm_ipld_encoding::from_slice::<Vec<Cid>>(&data)
+        .map_err(|e| anyhow!("error parsing as Vec<Cid>: {e}"))
+}
+
+/// Parse what Tendermint returns in the `data` field of [`DeliverTx`] as the problem found by
+/// an accumulator integrity check, if any.
+pub fn decode_acc_verify_integrity(deliver_tx: &DeliverTx) -> anyhow::Result<Option<String>> {
+    let data = decode_data(&deliver_tx.data)?;
+    fvm_ipld_encoding::from_slice::<Option<String>>(&data)
+        .map_err(|e| anyhow!("error parsing as Option<String>: {e}"))
+}
+
+/// Parse what Tendermint returns in the `data` field of [`DeliverTx`] as machine metadata.
+pub fn decode_machine_metadata(deliver_tx: &DeliverTx) -> anyhow::Result<MachineMetadata> {
+    let data = decode_data(&deliver_tx.data)?;
+    fvm_ipld_encoding::from_slice::<MachineMetadata>(&data)
+        .map_err(|e| anyhow!("error parsing as Metadata: {e}"))
+}
+
+/// Parse what Tendermint returns in the `data` field of [`DeliverTx`] as whether an address
+/// may write to a machine.
+pub fn decode_machine_can_write(deliver_tx: &DeliverTx) -> anyhow::Result<bool> {
+    let data = decode_data(&deliver_tx.data)?;
+    fvm_ipld_encoding::from_slice::<bool>(&data).map_err(|e| anyhow!("error parsing as bool: {e}"))
+}
+
+/// Parse what Tendermint returns in the `data` field of [`DeliverTx`] as an [`ObjectStat`].
+pub fn decode_os_stat(deliver_tx: &DeliverTx) -> anyhow::Result<ObjectStat> {
+    let data = decode_data(&deliver_tx.data)?;
+    fvm_ipld_encoding::from_slice::<ObjectStat>(&data)
+        .map_err(|e| anyhow!("error parsing as ObjectStat: {e}"))
+}
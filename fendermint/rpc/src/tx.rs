@@ -15,12 +15,12 @@ use fvm_shared::address::Address;
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::MethodNum;
 
-use fendermint_vm_actor_interface::eam;
+use fendermint_vm_actor_interface::{adm, eam};
 use fendermint_vm_message::chain::ChainMessage;
 
 use crate::message::{GasParams, SignedMessageFactory};
 use crate::query::{QueryClient, QueryResponse};
-use crate::response::{decode_bytes, decode_fevm_create, decode_fevm_invoke};
+use crate::response::{decode_adm_create, decode_bytes, decode_fevm_create, decode_fevm_invoke};
 
 /// Abstracting away what the return value is based on whether
 /// we broadcast transactions in sync, async or commit mode.
@@ -69,6 +69,20 @@ pub trait TxClient<M: BroadcastMode = TxCommit>: BoundClient + Send + Sync {
         Ok(res)
     }
 
+    /// Create a new machine owned by the sender.
+    async fn adm_create(
+        &mut self,
+        params: adm::CreateExternalParams,
+        value: TokenAmount,
+        gas_params: GasParams,
+    ) -> anyhow::Result<M::Response<adm::CreateExternalReturn>> {
+        let mf = self.message_factory_mut();
+        let msg = mf.adm_create(params, value, gas_params)?;
+        let fut = self.perform(msg, decode_adm_create);
+        let res = fut.await?;
+        Ok(res)
+    }
+
     /// Deploy a FEVM contract.
     async fn fevm_create(
         &mut self,
@@ -0,0 +1,61 @@
+// Copyright 2022-2024 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use cid::Cid;
+use fvm_ipld_blockstore::Blockstore;
+
+/// Blockstore counting the reads and writes made through it, to put bounds on
+/// the store accesses of an operation in tests.
+///
+/// `has` is counted as a read, and every block of a batch write as a write.
+#[derive(Debug, Default)]
+pub struct CountingBlockstore<BS> {
+    inner: BS,
+    gets: AtomicUsize,
+    puts: AtomicUsize,
+}
+
+impl<BS> CountingBlockstore<BS> {
+    pub fn new(inner: BS) -> Self {
+        Self {
+            inner,
+            gets: Default::default(),
+            puts: Default::default(),
+        }
+    }
+
+    /// Number of blocks read so far.
+    pub fn gets(&self) -> usize {
+        self.gets.load(Ordering::Relaxed)
+    }
+
+    /// Number of blocks written so far.
+    pub fn puts(&self) -> usize {
+        self.puts.load(Ordering::Relaxed)
+    }
+
+    /// Reset the counters, returning the number of reads and writes before the reset.
+    pub fn reset(&self) -> (usize, usize) {
+        (
+            self.gets.swap(0, Ordering::Relaxed),
+            self.puts.swap(0, Ordering::Relaxed),
+        )
+    }
+
+    pub fn inner(&self) -> &BS {
+        &self.inner
+    }
+}
+
+impl<BS: Blockstore> Blockstore for CountingBlockstore<BS> {
+    fn get(&self, k: &Cid) -> anyhow::Result<Option<Vec<u8>>> {
+        self.gets.fetch_add(1, Ordering::Relaxed);
+        self.inner.get(k)
+    }
+
+    fn put_keyed(&self, k: &Cid, block: &[u8]) -> anyhow::Result<()> {
+        self.puts.fetch_add(1, Ordering::Relaxed);
+        self.inner.put_keyed(k, block)
+    }
+}
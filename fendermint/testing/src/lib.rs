@@ -2,6 +2,8 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 #[cfg(feature = "arb")]
 pub mod arb;
+#[cfg(feature = "blockstore")]
+pub mod blockstore;
 #[cfg(feature = "golden")]
 pub mod golden;
 #[cfg(feature = "smt")]
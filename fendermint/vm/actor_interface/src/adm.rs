@@ -5,6 +5,7 @@ use fendermint_actor_machine::WriteAccess;
 use fvm_ipld_encoding::tuple::{Deserialize_tuple, Serialize_tuple};
 use fvm_shared::{address::Address, ActorID, METHOD_CONSTRUCTOR};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt::Display;
 
 define_singleton!(ADM {
@@ -56,6 +57,9 @@ pub struct Metadata {
 pub struct CreateExternalParams {
     pub kind: Kind,
     pub write_access: WriteAccess,
+    /// Application-defined metadata of the machine, passed on to its constructor.
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
 }
 
 /// Helper to read return value from machine creation.
@@ -5,8 +5,9 @@ use anyhow::{anyhow, bail, Context};
 use async_stm::atomically;
 use async_trait::async_trait;
 use fendermint_actor_objectstore::{
-    GetParams,
-    Method::{GetObject, ResolveObject},
+    AddParams, GetParams,
+    Method::{AddObject, GetObject, ResolveObject, SwapObject},
+    SwapParams,
 };
 use fendermint_tracing::emit;
 use fendermint_vm_actor_interface::{ipc, system};
@@ -24,7 +25,8 @@ use fendermint_vm_resolver::pool::{ResolveKey, ResolvePool};
 use fendermint_vm_topdown::proxy::IPCProviderProxy;
 use fendermint_vm_topdown::voting::{ValidatorKey, VoteTally};
 use fendermint_vm_topdown::{
-    CachedFinalityProvider, IPCParentFinality, ParentFinalityProvider, ParentViewProvider, Toggle,
+    object_vote_key, CachedFinalityProvider, IPCParentFinality, ParentFinalityProvider,
+    ParentViewProvider, Toggle,
 };
 use fvm_ipld_blockstore::Blockstore;
 use fvm_ipld_encoding::RawBytes;
@@ -32,6 +34,7 @@ use fvm_shared::clock::ChainEpoch;
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::message::Message;
 use num_traits::Zero;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio_util::bytes;
 
@@ -49,6 +52,8 @@ use crate::{
 pub type CheckpointPool = ResolvePool<CheckpointPoolItem>;
 pub type TopDownFinalityProvider = Arc<Toggle<CachedFinalityProvider<IPCProviderProxy>>>;
 pub type ObjectPool = IpfsResolvePool<ObjectPoolItem>;
+/// Objects left in the [`ObjectPool`] with their declared size, persisted across restarts.
+pub type PendingObjects = Vec<(ObjectPoolItem, Option<u64>)>;
 
 /// These are the extra state items that the chain interpreter needs,
 /// a sort of "environment" supporting IPC.
@@ -82,7 +87,7 @@ impl From<&CheckpointPoolItem> for ResolveKey {
     }
 }
 
-#[derive(Clone, Hash, PartialEq, Eq)]
+#[derive(Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ObjectPoolItem {
     obj: Object,
 }
@@ -199,39 +204,53 @@ where
         // Append at the end - if we run out of block space, these are going to be reproposed in the next block.
         msgs.extend(ckpts);
 
-        // Collect locally resolved objects from the pool. We're relying on the proposer's local
-        // view of object resolution, rather than considering those that _might_ have a quorum,
-        // but have not yet been resolved by _this_ proposer. However, an object like this will get
-        // picked up by a different proposer who _does_ consider it resolved.
-        let local_resolved_objects = atomically(|| env.object_pool.collect_resolved()).await;
+        // Collect locally resolved objects from the pool, and those found invalid. We're relying
+        // on the proposer's local view of object resolution, rather than considering those that
+        // _might_ have a quorum, but have not yet been resolved by _this_ proposer. However, an
+        // object like this will get picked up by a different proposer who _does_ consider it resolved.
+        let (local_resolved_objects, local_failed_objects) = atomically(|| {
+            Ok((
+                env.object_pool.collect_resolved()?,
+                env.object_pool.collect_failed()?,
+            ))
+        })
+        .await;
+        let local_objects = local_resolved_objects
+            .iter()
+            .map(|item| (item, false))
+            .chain(local_failed_objects.iter().map(|item| (item, true)))
+            .collect::<Vec<_>>();
 
         // Create transactions ready to be included on the chain. These are from locally resolved
-        // objects that have reached a global quorum and are not yet finalized.
+        // or failed objects that have reached a global quorum and are not yet finalized.
         //
         // If the object has already been finalized, i.e., it was proposed in an earlier block with
         // a quorum that did not include _this_ proposer, we can just remove it from the local
         // resolve pool. If we were to propose it, it would be rejected in the process step.
-        if !local_resolved_objects.is_empty() {
+        if !local_objects.is_empty() {
             let mut objects: Vec<ChainMessage> = vec![];
             // We start a blockstore transaction that can be reverted
             state.state_tree_mut().begin_transaction();
-            for item in local_resolved_objects.iter() {
+            for (item, failed) in local_objects {
                 if is_object_finalized(&mut state, item)? {
                     tracing::debug!(cid = ?item.obj.value, "object already finalized; removing from pool");
                     atomically(|| env.object_pool.remove(item)).await;
                     continue;
                 }
 
-                let is_globally_resolved = atomically(|| {
+                let has_quorum = atomically(|| {
                     env.parent_finality_votes
-                        .find_object_quorum(&item.obj.value.to_bytes())
+                        .find_object_quorum(&object_vote_key(&item.obj.value, failed))
                 })
                 .await;
-                if is_globally_resolved {
-                    tracing::debug!(cid = ?item.obj.value, "object has quorum; adding tx to chain");
-                    objects.push(ChainMessage::Ipc(IpcMessage::ObjectResolved(
-                        item.obj.clone(),
-                    )));
+                if has_quorum {
+                    tracing::debug!(cid = ?item.obj.value, failed, "object has quorum; adding tx to chain");
+                    let obj = item.obj.clone();
+                    objects.push(ChainMessage::Ipc(if failed {
+                        IpcMessage::ObjectFailed(obj)
+                    } else {
+                        IpcMessage::ObjectResolved(obj)
+                    }));
                 }
             }
             state
@@ -292,45 +311,14 @@ where
                     }
                 }
                 ChainMessage::Ipc(IpcMessage::ObjectResolved(obj)) => {
-                    let item = ObjectPoolItem { obj };
-
-                    // Ensure that the object is ready to be included on-chain.
-                    // We can accept the proposal if the object has reached a global quorum and is
-                    // not yet finalized.
-                    // Start a blockstore transaction that can be reverted.
-                    state.state_tree_mut().begin_transaction();
-                    if is_object_finalized(&mut state, &item)? {
-                        tracing::debug!(cid = ?item.obj.value, "object is already finalized; rejecting proposal");
+                    if !process_object(&env, &mut state, ObjectPoolItem { obj }, false).await? {
                         return Ok(false);
                     }
-                    state
-                        .state_tree_mut()
-                        .end_transaction(true)
-                        .expect("we just started a transaction");
-
-                    let is_globally_resolved = atomically(|| {
-                        env.parent_finality_votes
-                            .find_object_quorum(&item.obj.value.to_bytes())
-                    })
-                    .await;
-                    if !is_globally_resolved {
-                        tracing::debug!(cid = ?item.obj.value, "object is not globally resolved; rejecting proposal");
+                }
+                ChainMessage::Ipc(IpcMessage::ObjectFailed(obj)) => {
+                    if !process_object(&env, &mut state, ObjectPoolItem { obj }, true).await? {
                         return Ok(false);
                     }
-
-                    // Remove from pool if locally resolved
-                    let is_locally_resolved =
-                        atomically(|| match env.object_pool.get_status(&item)? {
-                            None => Ok(false),
-                            Some(status) => status.is_resolved(),
-                        })
-                        .await;
-                    if is_locally_resolved {
-                        tracing::debug!(cid = ?item.obj.value, "object is locally resolved; removing from pool");
-                        atomically(|| env.object_pool.remove(&item)).await;
-                    } else {
-                        tracing::debug!(cid = ?item.obj.value, "object is not locally resolved");
-                    }
                 }
                 _ => {}
             };
@@ -374,8 +362,12 @@ where
 
                 if ret.is_ok() {
                     if let Some(obj) = msg.object {
-                        atomically(|| env.object_pool.add(ObjectPoolItem { obj: obj.clone() }))
-                            .await;
+                        let size = object_declared_size(&msg.message);
+                        atomically(|| {
+                            env.object_pool
+                                .add_with_size(ObjectPoolItem { obj: obj.clone() }, size)
+                        })
+                        .await;
                         tracing::debug!(cid = ?obj.value, store = ?obj.address, "object added to pool");
                     }
                 }
@@ -511,61 +503,11 @@ where
                     Ok(((env, state), ChainMessageApplyRet::Ipc(ret)))
                 }
                 IpcMessage::ObjectResolved(obj) => {
-                    let from = system::SYSTEM_ACTOR_ADDR;
-                    let to = obj.address;
-                    let method_num = ResolveObject as u64;
-                    let gas_limit = fvm_shared::BLOCK_GAS_LIMIT;
-
-                    let params = fendermint_actor_objectstore::ResolveParams {
-                        key: obj.key,
-                        value: obj.value,
-                    };
-                    let params = RawBytes::serialize(params)?;
-                    let msg = Message {
-                        version: Default::default(),
-                        from,
-                        to,
-                        sequence: 0, // We will use implicit execution which doesn't check or modify this.
-                        value: Default::default(),
-                        method_num,
-                        params,
-                        gas_limit,
-                        gas_fee_cap: Default::default(),
-                        gas_premium: Default::default(),
-                    };
-
-                    let (apply_ret, emitters) = state.execute_implicit(msg)?;
-
-                    let info = apply_ret
-                        .failure_info
-                        .clone()
-                        .map(|i| i.to_string())
-                        .filter(|s| !s.is_empty());
-                    tracing::info!(
-                        exit_code = apply_ret.msg_receipt.exit_code.value(),
-                        from = from.to_string(),
-                        to = to.to_string(),
-                        method_num = method_num,
-                        gas_limit = gas_limit,
-                        gas_used = apply_ret.msg_receipt.gas_used,
-                        info = info.unwrap_or_default(),
-                        "implicit tx delivered"
-                    );
-
-                    tracing::debug!(
-                        cid = ?obj.value,
-                        "chain interpreter has finalized object"
-                    );
-
-                    let ret = FvmApplyRet {
-                        apply_ret,
-                        from: system::SYSTEM_ACTOR_ADDR,
-                        to,
-                        method_num,
-                        gas_limit,
-                        emitters,
-                    };
-
+                    let ret = deliver_object(&mut state, obj, false)?;
+                    Ok(((env, state), ChainMessageApplyRet::Ipc(ret)))
+                }
+                IpcMessage::ObjectFailed(obj) => {
+                    let ret = deliver_object(&mut state, obj, true)?;
                     Ok(((env, state), ChainMessageApplyRet::Ipc(ret)))
                 }
             },
@@ -650,7 +592,8 @@ where
                     }
                     IpcMessage::TopDownExec(_)
                     | IpcMessage::BottomUpExec(_)
-                    | IpcMessage::ObjectResolved(_) => {
+                    | IpcMessage::ObjectResolved(_)
+                    | IpcMessage::ObjectFailed(_) => {
                         // Users cannot send these messages, only validators can propose them in blocks.
                         Ok((state, Err(IllegalMessage)))
                     }
@@ -728,6 +671,25 @@ fn relayed_bottom_up_ckpt_to_fvm(
     Ok(msg)
 }
 
+/// Get the object size declared by the sender from the params of an objectstore message.
+///
+/// The resolver checks this against the size of the content it fetches.
+fn object_declared_size(msg: &FvmMessage) -> Option<u64> {
+    if msg.method_num == AddObject as u64 {
+        msg.params
+            .deserialize::<AddParams>()
+            .ok()
+            .map(|params| params.size)
+    } else if msg.method_num == SwapObject as u64 {
+        msg.params
+            .deserialize::<SwapParams>()
+            .ok()
+            .map(|params| params.size)
+    } else {
+        None
+    }
+}
+
 /// Check if an object has been finalized (resolved) by reading its on-chain state.
 /// This approach uses an implicit FVM transaction to query a read-only blockstore.
 fn is_object_finalized<DB>(
@@ -761,7 +723,7 @@ where
             .map_err(|e| anyhow!("error parsing as Option<Object>: {e}"))?;
 
     Ok(match object {
-        Some(object) => object.resolved,
+        Some(object) => object.resolved || object.failed,
         None => {
             // The object was deleted before it was resolved.
             // We can return true here because the objectstore actor will ignore the final implicit
@@ -771,3 +733,120 @@ where
         }
     })
 }
+
+/// Check whether an object has reached a global quorum, as resolved or failed, and is not yet
+/// finalized, so that a proposal including it can be accepted.
+/// The object is removed from the local pool when it has reached the same state locally.
+async fn process_object<DB>(
+    env: &ChainEnv,
+    state: &mut FvmExecState<ReadOnlyBlockstore<DB>>,
+    item: ObjectPoolItem,
+    failed: bool,
+) -> anyhow::Result<bool>
+where
+    DB: Blockstore + Clone + 'static + Send + Sync,
+{
+    // Start a blockstore transaction that can be reverted.
+    state.state_tree_mut().begin_transaction();
+    if is_object_finalized(state, &item)? {
+        tracing::debug!(cid = ?item.obj.value, "object is already finalized; rejecting proposal");
+        return Ok(false);
+    }
+    state
+        .state_tree_mut()
+        .end_transaction(true)
+        .expect("we just started a transaction");
+
+    let has_quorum = atomically(|| {
+        env.parent_finality_votes
+            .find_object_quorum(&object_vote_key(&item.obj.value, failed))
+    })
+    .await;
+    if !has_quorum {
+        tracing::debug!(cid = ?item.obj.value, failed, "object has no global quorum; rejecting proposal");
+        return Ok(false);
+    }
+
+    // Remove from pool if it reached the same state locally.
+    let is_local = atomically(|| match env.object_pool.get_status(&item)? {
+        None => Ok(false),
+        Some(status) if failed => status.is_failed(),
+        Some(status) => status.is_resolved(),
+    })
+    .await;
+    if is_local {
+        tracing::debug!(cid = ?item.obj.value, failed, "object is finalized locally; removing from pool");
+        atomically(|| env.object_pool.remove(&item)).await;
+    } else {
+        tracing::debug!(cid = ?item.obj.value, failed, "object is not finalized locally");
+    }
+    Ok(true)
+}
+
+/// Resolve an object, or mark it failed, in its objectstore with an implicit message.
+fn deliver_object<DB>(
+    state: &mut FvmExecState<DB>,
+    obj: Object,
+    failed: bool,
+) -> anyhow::Result<FvmApplyRet>
+where
+    DB: Blockstore + Clone + 'static,
+{
+    let from = system::SYSTEM_ACTOR_ADDR;
+    let to = obj.address;
+    let method_num = ResolveObject as u64;
+    let gas_limit = fvm_shared::BLOCK_GAS_LIMIT;
+
+    let params = fendermint_actor_objectstore::ResolveParams {
+        key: obj.key,
+        value: obj.value,
+        failed,
+    };
+    let params = RawBytes::serialize(params)?;
+    let msg = Message {
+        version: Default::default(),
+        from,
+        to,
+        sequence: 0, // We will use implicit execution which doesn't check or modify this.
+        value: Default::default(),
+        method_num,
+        params,
+        gas_limit,
+        gas_fee_cap: Default::default(),
+        gas_premium: Default::default(),
+    };
+
+    let (apply_ret, emitters) = state.execute_implicit(msg)?;
+
+    let info = apply_ret
+        .failure_info
+        .clone()
+        .map(|i| i.to_string())
+        .filter(|s| !s.is_empty());
+    tracing::info!(
+        exit_code = apply_ret.msg_receipt.exit_code.value(),
+        from = from.to_string(),
+        to = to.to_string(),
+        method_num = method_num,
+        gas_limit = gas_limit,
+        gas_used = apply_ret.msg_receipt.gas_used,
+        info = info.unwrap_or_default(),
+        "implicit tx delivered"
+    );
+
+    tracing::debug!(
+        cid = ?obj.value,
+        failed,
+        "chain interpreter has finalized object"
+    );
+
+    let ret = FvmApplyRet {
+        apply_ret,
+        from: system::SYSTEM_ACTOR_ADDR,
+        to,
+        method_num,
+        gas_limit,
+        emitters,
+    };
+    Ok(ret)
+}
@@ -2,43 +2,172 @@
 // Copyright 2022-2024 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 
 use async_stm::{atomically, atomically_or_err, queues::TQueueLike};
 use cid::Cid;
+use fendermint_vm_topdown::object_vote_key;
 use fendermint_vm_topdown::voting::VoteTally;
 use ipc_api::subnet_id::SubnetID;
-use ipc_ipld_resolver::{Client, ResolverIpfs, ValidatorKey, VoteRecord};
+use ipc_ipld_resolver::{Client, ResolverIpfs, SignedVoteRecord, ValidatorKey, VoteRecord};
 use libp2p::identity::Keypair;
 use serde::de::DeserializeOwned;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::pool::{ResolveQueue, ResolveTask};
 
+/// The capabilities of the [ipc_ipld_resolver] client needed by the [IpfsResolver].
+pub trait IpfsClient<V>: ResolverIpfs + Clone + Send + Sync + 'static {
+    /// Publish a signed vote into a topic based on its subnet.
+    fn publish_vote(&self, vote: SignedVoteRecord<V>) -> anyhow::Result<()>;
+}
+
+impl<V> IpfsClient<V> for Client<V>
+where
+    V: Clone + Send + Sync + 'static,
+{
+    fn publish_vote(&self, vote: SignedVoteRecord<V>) -> anyhow::Result<()> {
+        Client::publish_vote(self, vote)
+    }
+}
+
+/// Whether a failed resolution is worth retrying.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// The error might go away, e.g. the IPFS node is temporarily unreachable.
+    Transient,
+    /// The error will never go away, e.g. the CID is malformed.
+    Permanent,
+}
+
+/// Decides whether a resolution error is transient or permanent.
+pub type ErrorClassifier = fn(&anyhow::Error) -> ErrorClass;
+
+/// Turns the CID of resolved content into the payload of a vote for it. The flag tells
+/// whether the vote is for the content having failed instead, e.g. for being of another
+/// size than declared.
+///
+/// Building the vote can fail, e.g. if it needs to look something up, in which case the
+/// content isn't voted for.
+pub type VoteFactory<V> = Arc<dyn Fn(Cid, bool) -> anyhow::Result<V> + Send + Sync>;
+
+/// Treat errors about malformed CIDs and unsupported codecs as permanent, and everything else as transient.
+pub fn default_error_classifier(e: &anyhow::Error) -> ErrorClass {
+    const PERMANENT: [&str; 4] = [
+        "invalid cid",
+        "invalid path",
+        "unsupported codec",
+        "unknown codec",
+    ];
+    let msg = e.to_string().to_lowercase();
+    if PERMANENT.iter().any(|p| msg.contains(p)) {
+        ErrorClass::Permanent
+    } else {
+        ErrorClass::Transient
+    }
+}
+
+/// Live counters of the [IpfsResolver], shared by its clones.
+#[derive(Clone, Default)]
+pub struct ResolverStats {
+    inner: Arc<StatsInner>,
+}
+
+#[derive(Default)]
+struct StatsInner {
+    in_flight: AtomicU64,
+    queued: AtomicU64,
+    resolved: AtomicU64,
+    failed: AtomicU64,
+    retried: AtomicU64,
+}
+
+/// The settings and counters of the [IpfsResolver] at a point in time.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResolverStatsSnapshot {
+    /// Seconds to wait between attempts to resolve a CID.
+    pub retry_delay_secs: u64,
+    /// Whether content is checked to still be pinned right before voting on it.
+    pub verify_before_vote: bool,
+    /// Tasks being resolved.
+    pub in_flight: u64,
+    /// Tasks waiting out the retry delay before going back to the queue. New tasks are
+    /// taken off the queue as soon as they arrive, so these make up the queue depth.
+    pub queued: u64,
+    /// Tasks resolved since start.
+    pub resolved: u64,
+    /// Tasks failed for good since start.
+    pub failed: u64,
+    /// Retries scheduled since start.
+    pub retried: u64,
+}
+
+impl ResolverStats {
+    /// Take a snapshot of the counters, along with the settings of the resolver.
+    pub fn snapshot(
+        &self,
+        retry_delay: Duration,
+        verify_before_vote: bool,
+    ) -> ResolverStatsSnapshot {
+        let inner = &self.inner;
+        ResolverStatsSnapshot {
+            retry_delay_secs: retry_delay.as_secs(),
+            verify_before_vote,
+            in_flight: inner.in_flight.load(Ordering::Relaxed),
+            queued: inner.queued.load(Ordering::Relaxed),
+            resolved: inner.resolved.load(Ordering::Relaxed),
+            failed: inner.failed.load(Ordering::Relaxed),
+            retried: inner.retried.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Count a task as in flight until the returned guard is dropped.
+    fn start(&self) -> InFlight {
+        self.inner.in_flight.fetch_add(1, Ordering::Relaxed);
+        InFlight(self.clone())
+    }
+}
+
+/// Guard counting a task as in flight, however its resolution ends.
+struct InFlight(ResolverStats);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.inner.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
 /// The IPFS Resolver takes resolution tasks from the [ResolvePool] and
 /// uses the [ipc_ipld_resolver] to fetch the content from the local IPFS node.
-pub struct IpfsResolver<V> {
-    client: Client<V>,
+pub struct IpfsResolver<C, V> {
+    client: C,
     queue: ResolveQueue,
     retry_delay: Duration,
     vote_tally: VoteTally,
     key: Keypair,
     subnet_id: SubnetID,
-    to_vote: fn(Cid) -> V,
+    to_vote: VoteFactory<V>,
+    verify_before_vote: bool,
+    classify_error: ErrorClassifier,
+    stats: ResolverStats,
 }
 
-impl<V> IpfsResolver<V>
+impl<C, V> IpfsResolver<C, V>
 where
+    C: IpfsClient<V>,
     V: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
 {
     pub fn new(
-        client: Client<V>,
+        client: C,
         queue: ResolveQueue,
         retry_delay: Duration,
         vote_tally: VoteTally,
         key: Keypair,
         subnet_id: SubnetID,
-        to_vote: fn(Cid) -> V,
+        to_vote: impl Fn(Cid, bool) -> anyhow::Result<V> + Send + Sync + 'static,
+        verify_before_vote: bool,
     ) -> Self {
         Self {
             client,
@@ -47,10 +176,24 @@ where
             vote_tally,
             key,
             subnet_id,
-            to_vote,
+            to_vote: Arc::new(to_vote),
+            verify_before_vote,
+            classify_error: default_error_classifier,
+            stats: ResolverStats::default(),
         }
     }
 
+    /// The live counters of the resolver, which keep being updated once it runs.
+    pub fn stats(&self) -> ResolverStats {
+        self.stats.clone()
+    }
+
+    /// Replace the function deciding which resolution errors are worth retrying.
+    pub fn with_error_classifier(mut self, classify_error: ErrorClassifier) -> Self {
+        self.classify_error = classify_error;
+        self
+    }
+
     /// Start taking tasks from the resolver pool and resolving them using the IPFS Resolver.
     pub async fn run(self) {
         loop {
@@ -68,7 +211,10 @@ where
                 self.vote_tally.clone(),
                 self.key.clone(),
                 self.subnet_id.clone(),
-                self.to_vote,
+                self.to_vote.clone(),
+                self.verify_before_vote,
+                self.classify_error,
+                self.stats.clone(),
             );
         }
     }
@@ -77,85 +223,255 @@ where
 /// Run task resolution in the background, so as not to block items from other
 /// subnets being tried.
 #[allow(clippy::too_many_arguments)]
-fn start_resolve<V>(
+fn start_resolve<C, V>(
     task: ResolveTask,
-    client: Client<V>,
+    client: C,
     queue: ResolveQueue,
     retry_delay: Duration,
     vote_tally: VoteTally,
     key: Keypair,
     subnet_id: SubnetID,
-    to_vote: fn(Cid) -> V,
+    to_vote: VoteFactory<V>,
+    verify_before_vote: bool,
+    classify_error: ErrorClassifier,
+    stats: ResolverStats,
 ) where
+    C: IpfsClient<V>,
     V: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
 {
-    tokio::spawn(async move {
-        tracing::debug!(cid = ?task.cid(), "starting ipfs content resolve");
-        let res = client.resolve_ipfs(task.cid()).await;
+    tokio::spawn(resolve(
+        task,
+        client,
+        queue,
+        retry_delay,
+        vote_tally,
+        key,
+        subnet_id,
+        to_vote,
+        verify_before_vote,
+        classify_error,
+        stats,
+    ));
+}
+
+/// Resolve the content of a task, check it against the declared size, then vote on it.
+///
+/// If `verify_before_vote` is set, the content is checked to still be pinned right before voting.
+/// Content that fails for good is voted failed instead, so that once a quorum agrees it is
+/// marked failed on chain and removed from the pool.
+#[allow(clippy::too_many_arguments)]
+async fn resolve<C, V>(
+    task: ResolveTask,
+    client: C,
+    queue: ResolveQueue,
+    retry_delay: Duration,
+    vote_tally: VoteTally,
+    key: Keypair,
+    subnet_id: SubnetID,
+    to_vote: VoteFactory<V>,
+    verify_before_vote: bool,
+    classify_error: ErrorClassifier,
+    stats: ResolverStats,
+) where
+    C: IpfsClient<V>,
+    V: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+{
+    let _in_flight = stats.start();
+    tracing::debug!(cid = ?task.cid(), "starting ipfs content resolve");
+    let res = client.resolve_ipfs(task.cid()).await;
 
-        let err = match res {
+    let err = match res {
+        Err(e) => {
+            tracing::error!(
+                error = e.to_string(),
+                "failed to submit ipfs resolution task"
+            );
+            // The service is no longer listening, we might as well stop taking new tasks from the queue.
+            // By not quitting we should see this error every time there is a new task, which is at least is a constant reminder.
+            return;
+        }
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => Some(e),
+    };
+
+    if let Some(e) = err {
+        match classify_error(&e) {
+            ErrorClass::Transient => {
+                tracing::error!(
+                    cid = ?task.cid(),
+                    error = e.to_string(),
+                    "ipfs content resolution failed; retrying later"
+                );
+                schedule_retry(task, queue, retry_delay, &stats);
+            }
+            ErrorClass::Permanent => {
+                tracing::error!(
+                    cid = ?task.cid(),
+                    error = e.to_string(),
+                    "ipfs content resolution failed permanently; voting it failed"
+                );
+                atomically(|| task.set_failed()).await;
+                stats.inner.failed.fetch_add(1, Ordering::Relaxed);
+                vote(
+                    task.cid(),
+                    true,
+                    &client,
+                    &vote_tally,
+                    &key,
+                    subnet_id,
+                    &to_vote,
+                )
+                .await;
+            }
+        }
+        return;
+    }
+
+    tracing::debug!(cid = ?task.cid(), "ipfs content resolved");
+
+    // Check the content against the size declared by the sender,
+    // so that nobody can dodge size-based accounting by lying about it.
+    if let Some(declared) = task.size() {
+        match client.stat_ipfs(task.cid()).await {
             Err(e) => {
                 tracing::error!(
                     error = e.to_string(),
-                    "failed to submit ipfs resolution task"
+                    "failed to submit ipfs stat task; retrying later"
                 );
-                // The service is no longer listening, we might as well stop taking new tasks from the queue.
-                // By not quitting we should see this error every time there is a new task, which is at least is a constant reminder.
+                schedule_retry(task, queue, retry_delay, &stats);
                 return;
             }
-            Ok(Ok(())) => None,
-            Ok(Err(e)) => Some(e),
-        };
+            Ok(Err(e)) => {
+                tracing::error!(
+                    cid = ?task.cid(),
+                    error = e.to_string(),
+                    "ipfs content stat failed; retrying later"
+                );
+                schedule_retry(task, queue, retry_delay, &stats);
+                return;
+            }
+            Ok(Ok(actual)) if actual != declared => {
+                tracing::error!(
+                    cid = ?task.cid(),
+                    declared,
+                    actual,
+                    "ipfs content size does not match declared size; voting it failed"
+                );
+                atomically(|| task.set_failed()).await;
+                stats.inner.failed.fetch_add(1, Ordering::Relaxed);
+                vote(
+                    task.cid(),
+                    true,
+                    &client,
+                    &vote_tally,
+                    &key,
+                    subnet_id,
+                    &to_vote,
+                )
+                .await;
+                return;
+            }
+            Ok(Ok(_)) => {}
+        }
+    }
 
-        match err {
-            None => {
-                tracing::debug!(cid = ?task.cid(), "ipfs content resolved");
-
-                // Mark task as resolved
-                atomically(|| task.set_resolved()).await;
-
-                let vote = to_vote(task.cid());
-                match VoteRecord::signed(&key, subnet_id, vote) {
-                    Ok(vote) => {
-                        // Add our own vote
-                        let validator_key = ValidatorKey::from(key.public());
-                        let res = atomically_or_err(|| {
-                            vote_tally.add_object_vote(validator_key.clone(), task.cid().to_bytes())
-                        })
-                        .await;
-
-                        match res {
-                            Ok(added) => {
-                                if added {
-                                    // Send own vote to peers
-                                    if let Err(e) = client.publish_vote(vote) {
-                                        tracing::error!(
-                                            error = e.to_string(),
-                                            "failed to publish vote"
-                                        );
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                tracing::error!(error = e.to_string(), "failed to handle own vote");
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        tracing::error!(error = e.to_string(), "failed to sign vote");
-                    }
-                }
+    // The content could have been unpinned since it was resolved, and we shouldn't vote for
+    // content we can no longer serve.
+    if verify_before_vote {
+        match client.has_ipfs(task.cid()).await {
+            Err(e) => {
+                tracing::error!(error = e.to_string(), "failed to submit ipfs has task");
+                return;
             }
-            Some(e) => {
+            Ok(Err(e)) => {
                 tracing::error!(
                     cid = ?task.cid(),
                     error = e.to_string(),
-                    "ipfs content resolution failed; retrying later"
+                    "ipfs content check failed; retrying later"
                 );
-                schedule_retry(task, queue, retry_delay);
+                schedule_retry(task, queue, retry_delay, &stats);
+                return;
+            }
+            Ok(Ok(false)) => {
+                tracing::warn!(
+                    cid = ?task.cid(),
+                    "ipfs content no longer pinned; retrying later"
+                );
+                schedule_retry(task, queue, retry_delay, &stats);
+                return;
             }
+            Ok(Ok(true)) => {}
         }
-    });
+    }
+
+    // Mark task as resolved
+    atomically(|| task.set_resolved()).await;
+    stats.inner.resolved.fetch_add(1, Ordering::Relaxed);
+
+    vote(
+        task.cid(),
+        false,
+        &client,
+        &vote_tally,
+        &key,
+        subnet_id,
+        &to_vote,
+    )
+    .await;
+}
+
+/// Vote for content as resolved, or as failed, and publish the vote to peers.
+async fn vote<C, V>(
+    cid: Cid,
+    failed: bool,
+    client: &C,
+    vote_tally: &VoteTally,
+    key: &Keypair,
+    subnet_id: SubnetID,
+    to_vote: &VoteFactory<V>,
+) where
+    C: IpfsClient<V>,
+    V: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+{
+    // The content is resolved or failed either way, but we can't vote for it without a payload.
+    let vote = match to_vote(cid, failed) {
+        Ok(vote) => vote,
+        Err(e) => {
+            tracing::error!(
+                cid = ?cid,
+                error = e.to_string(),
+                "failed to create vote; not voting"
+            );
+            return;
+        }
+    };
+    match VoteRecord::signed(key, subnet_id, vote) {
+        Ok(vote) => {
+            // Add our own vote
+            let validator_key = ValidatorKey::from(key.public());
+            let res = atomically_or_err(|| {
+                vote_tally.add_object_vote(validator_key.clone(), object_vote_key(&cid, failed))
+            })
+            .await;
+
+            match res {
+                Ok(added) => {
+                    if added {
+                        // Send own vote to peers
+                        if let Err(e) = client.publish_vote(vote) {
+                            tracing::error!(error = e.to_string(), "failed to publish vote");
+                        }
+                    }
+                }
+                Err(e) => {
+                    tracing::error!(error = e.to_string(), "failed to handle own vote");
+                }
+            }
+        }
+        Err(e) => {
+            tracing::error!(error = e.to_string(), "failed to sign vote");
+        }
+    }
 }
 
 /// Part of error handling.
@@ -168,10 +484,386 @@ fn start_resolve<V>(
 /// such as having no peers currently, but that might change.
 ///
 /// For now, let's retry the same task later.
-fn schedule_retry(task: ResolveTask, queue: ResolveQueue, retry_delay: Duration) {
+fn schedule_retry(
+    task: ResolveTask,
+    queue: ResolveQueue,
+    retry_delay: Duration,
+    stats: &ResolverStats,
+) {
+    stats.inner.retried.fetch_add(1, Ordering::Relaxed);
+    stats.inner.queued.fetch_add(1, Ordering::Relaxed);
+    let stats = stats.clone();
     tokio::spawn(async move {
         tokio::time::sleep(retry_delay).await;
         tracing::debug!(cid = ?task.cid(), "retrying content resolution");
         atomically(move || queue.write(task.clone())).await;
+        stats.inner.queued.fetch_sub(1, Ordering::Relaxed);
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use async_stm::atomically;
+    use async_trait::async_trait;
+    use cid::Cid;
+    use fendermint_vm_topdown::object_vote_key;
+    use fendermint_vm_topdown::voting::VoteTally;
+    use ipc_api::subnet_id::SubnetID;
+    use ipc_ipld_resolver::{HasResult, ResolverIpfs, SignedVoteRecord, StatResult, ValidatorKey};
+    use libp2p::identity::Keypair;
+
+    use super::{default_error_classifier, resolve, IpfsClient, ResolverStats, VoteFactory};
+    use crate::pool::{ResolveKey, ResolvePool, ResolveStatus};
+
+    #[derive(Clone, Hash, Eq, PartialEq, Debug)]
+    struct TestItem {
+        cid: Cid,
+    }
+
+    impl From<&TestItem> for ResolveKey {
+        fn from(value: &TestItem) -> Self {
+            value.cid
+        }
+    }
+
+    /// Client pretending that all content in IPFS has the same size.
+    #[derive(Clone)]
+    struct TestClient {
+        size: u64,
+        /// Error to fail resolution with, if any.
+        resolve_error: Option<&'static str>,
+        /// Whether content is still pinned after it has been resolved.
+        pinned: bool,
+        /// Whether the stat task can't be submitted to the service.
+        stat_unavailable: bool,
+        published: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl ResolverIpfs for TestClient {
+        async fn resolve_ipfs(&self, _cid: Cid) -> anyhow::Result<anyhow::Result<()>> {
+            match self.resolve_error {
+                Some(e) => Ok(Err(anyhow::anyhow!(e))),
+                None => Ok(Ok(())),
+            }
+        }
+
+        async fn stat_ipfs(&self, _cid: Cid) -> anyhow::Result<StatResult> {
+            if self.stat_unavailable {
+                return Err(anyhow::anyhow!("service unavailable"));
+            }
+            Ok(Ok(self.size))
+        }
+
+        async fn has_ipfs(&self, _cid: Cid) -> anyhow::Result<HasResult> {
+            Ok(Ok(self.pinned))
+        }
+    }
+
+    impl IpfsClient<Vec<u8>> for TestClient {
+        fn publish_vote(&self, _vote: SignedVoteRecord<Vec<u8>>) -> anyhow::Result<()> {
+            self.published.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    const RETRY_DELAY: Duration = Duration::from_millis(10);
+
+    /// Resolve an item declared with one size against content of another size.
+    async fn resolve_sized(
+        declared: u64,
+        actual: u64,
+    ) -> (Cid, ResolveStatus<TestItem>, VoteTally, TestClient) {
+        let client = TestClient {
+            size: actual,
+            resolve_error: None,
+            pinned: true,
+            stat_unavailable: false,
+            published: Default::default(),
+        };
+        resolve_with(client, &ResolvePool::new(), declared, false).await
+    }
+
+    /// Resolve an item declared with the given size using the given client.
+    async fn resolve_with(
+        client: TestClient,
+        pool: &ResolvePool<TestItem>,
+        declared: u64,
+        verify_before_vote: bool,
+    ) -> (Cid, ResolveStatus<TestItem>, VoteTally, TestClient) {
+        resolve_counted(
+            client,
+            pool,
+            declared,
+            verify_before_vote,
+            &Default::default(),
+        )
+        .await
+    }
+
+    /// Resolve an item declared with the given size using the given client, counting it in `stats`.
+    async fn resolve_counted(
+        client: TestClient,
+        pool: &ResolvePool<TestItem>,
+        declared: u64,
+        verify_before_vote: bool,
+        stats: &ResolverStats,
+    ) -> (Cid, ResolveStatus<TestItem>, VoteTally, TestClient) {
+        let to_vote: VoteFactory<Vec<u8>> = Arc::new(|cid, _| Ok(cid.to_bytes()));
+        resolve_voting(
+            client,
+            pool,
+            Cid::default(),
+            declared,
+            verify_before_vote,
+            stats,
+            to_vote,
+        )
+        .await
+    }
+
+    /// Resolve the item with `cid`, building the vote for it with `to_vote`.
+    async fn resolve_voting(
+        client: TestClient,
+        pool: &ResolvePool<TestItem>,
+        cid: Cid,
+        declared: u64,
+        verify_before_vote: bool,
+        stats: &ResolverStats,
+        to_vote: VoteFactory<Vec<u8>>,
+    ) -> (Cid, ResolveStatus<TestItem>, VoteTally, TestClient) {
+        let key = Keypair::generate_secp256k1();
+        let vote_tally = VoteTally::new(vec![(ValidatorKey::from(key.public()), 1)], (0, vec![]));
+
+        let item = TestItem { cid };
+        let status = atomically(|| pool.add_with_size(item.clone(), Some(declared))).await;
+        let task = atomically(|| pool.next()).await;
+
+        resolve(
+            task,
+            client.clone(),
+            pool.queue(),
+            RETRY_DELAY,
+            vote_tally.clone(),
+            key,
+            SubnetID::default(),
+            to_vote,
+            verify_before_vote,
+            default_error_classifier,
+            stats.clone(),
+        )
+        .await;
+
+        (item.cid, status, vote_tally, client)
+    }
+
+    #[tokio::test]
+    async fn resolve_matching_size() {
+        let (cid, status, vote_tally, client) = resolve_sized(42, 42).await;
+
+        atomically(|| {
+            assert!(status.is_resolved()?);
+            assert!(!status.is_failed()?);
+            assert!(vote_tally.find_object_quorum(&cid.to_bytes())?);
+            Ok(())
+        })
+        .await;
+        assert_eq!(client.published.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn resolve_mismatched_size() {
+        let (cid, status, vote_tally, client) = resolve_sized(42, 1024).await;
+
+        // The content isn't voted resolved, but failed, so it can be marked failed on chain.
+        atomically(|| {
+            assert!(!status.is_resolved()?);
+            assert!(status.is_failed()?);
+            assert!(!vote_tally.find_object_quorum(&object_vote_key(&cid, false))?);
+            assert!(vote_tally.find_object_quorum(&object_vote_key(&cid, true))?);
+            Ok(())
+        })
+        .await;
+        assert_eq!(client.published.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn resolve_stat_unavailable_retried() {
+        let client = TestClient {
+            size: 42,
+            resolve_error: None,
+            pinned: true,
+            stat_unavailable: true,
+            published: Default::default(),
+        };
+        let pool = ResolvePool::new();
+        let (_, status, _, client) = resolve_with(client, &pool, 42, false).await;
+
+        atomically(|| {
+            assert!(!status.is_resolved()?);
+            assert!(!status.is_failed()?);
+            Ok(())
+        })
+        .await;
+        assert_eq!(client.published.load(Ordering::SeqCst), 0);
+
+        let retried = tokio::time::timeout(RETRY_DELAY * 10, atomically(|| pool.next())).await;
+        assert!(retried.is_ok(), "unsubmitted stat tasks should be retried");
+    }
+
+    #[tokio::test]
+    async fn resolve_unpinned_before_vote() {
+        let client = TestClient {
+            size: 42,
+            resolve_error: None,
+            pinned: false,
+            stat_unavailable: false,
+            published: Default::default(),
+        };
+        let (cid, status, vote_tally, client) =
+            resolve_with(client, &ResolvePool::new(), 42, true).await;
+
+        atomically(|| {
+            assert!(!status.is_resolved()?);
+            assert!(!status.is_failed()?);
+            assert!(!vote_tally.find_object_quorum(&cid.to_bytes())?);
+            Ok(())
+        })
+        .await;
+        assert_eq!(client.published.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn resolve_vote_creation_failed() {
+        let bad = Cid::default();
+        let good = Cid::try_from("bafkqaaa").unwrap();
+        let to_vote: VoteFactory<Vec<u8>> = Arc::new(move |cid, _| {
+            if cid == bad {
+                Err(anyhow::anyhow!("no vote for {cid}"))
+            } else {
+                Ok(cid.to_bytes())
+            }
+        });
+        for (cid, votes) in [(bad, 0), (good, 1)] {
+            let client = TestClient {
+                size: 42,
+                resolve_error: None,
+                pinned: true,
+                stat_unavailable: false,
+                published: Default::default(),
+            };
+            let (cid, status, vote_tally, client) = resolve_voting(
+                client,
+                &ResolvePool::new(),
+                cid,
+                42,
+                false,
+                &Default::default(),
+                to_vote.clone(),
+            )
+            .await;
+
+            // The content is resolved even if it can't be voted for.
+            atomically(|| {
+                assert!(status.is_resolved()?);
+                assert_eq!(vote_tally.find_object_quorum(&cid.to_bytes())?, votes > 0);
+                Ok(())
+            })
+            .await;
+            assert_eq!(client.published.load(Ordering::SeqCst), votes);
+        }
+    }
+
+    /// Resolve an item with a client failing resolution with the given error.
+    async fn resolve_failing(
+        error: &'static str,
+    ) -> (ResolvePool<TestItem>, ResolveStatus<TestItem>, TestClient) {
+        let client = TestClient {
+            size: 42,
+            resolve_error: Some(error),
+            pinned: true,
+            stat_unavailable: false,
+            published: Default::default(),
+        };
+        let pool = ResolvePool::new();
+        let (_, status, _, client) = resolve_with(client, &pool, 42, false).await;
+        (pool, status, client)
+    }
+
+    #[tokio::test]
+    async fn resolve_permanent_error_dropped() {
+        let (pool, status, client) = resolve_failing("invalid cid: unknown base").await;
+
+        atomically(|| {
+            assert!(!status.is_resolved()?);
+            assert!(status.is_failed()?);
+            Ok(())
+        })
+        .await;
+        // Only the vote for the content having failed is published.
+        assert_eq!(client.published.load(Ordering::SeqCst), 1);
+
+        let retried = tokio::time::timeout(RETRY_DELAY * 10, atomically(|| pool.next())).await;
+        assert!(retried.is_err(), "permanent failures should not be retried");
+    }
+
+    #[tokio::test]
+    async fn resolve_transient_error_retried() {
+        let (pool, status, client) = resolve_failing("connection refused").await;
+
+        atomically(|| {
+            assert!(!status.is_resolved()?);
+            assert!(!status.is_failed()?);
+            Ok(())
+        })
+        .await;
+        assert_eq!(client.published.load(Ordering::SeqCst), 0);
+
+        let retried = tokio::time::timeout(RETRY_DELAY * 10, atomically(|| pool.next())).await;
+        assert!(retried.is_ok(), "transient failures should be retried");
+    }
+
+    #[tokio::test]
+    async fn resolve_stats_counted() {
+        let stats = ResolverStats::default();
+        let client = |resolve_error| TestClient {
+            size: 42,
+            resolve_error,
+            pinned: true,
+            stat_unavailable: false,
+            published: Default::default(),
+        };
+
+        // One of each outcome: resolved, wrong size, permanent error, and transient error.
+        resolve_counted(client(None), &ResolvePool::new(), 42, false, &stats).await;
+        resolve_counted(client(None), &ResolvePool::new(), 1024, false, &stats).await;
+        let failing = Some("invalid cid: unknown base");
+        resolve_counted(client(failing), &ResolvePool::new(), 42, false, &stats).await;
+        let pool = ResolvePool::new();
+        let failing = Some("connection refused");
+        resolve_counted(client(failing), &pool, 42, false, &stats).await;
+
+        let snapshot = stats.snapshot(RETRY_DELAY, true);
+        assert_eq!(snapshot.retry_delay_secs, 0);
+        assert!(snapshot.verify_before_vote);
+        assert_eq!(snapshot.in_flight, 0);
+        assert_eq!(snapshot.resolved, 1);
+        assert_eq!(snapshot.failed, 2);
+        assert_eq!(snapshot.retried, 1);
+
+        // The retried task leaves the count of queued ones once it's back in the queue.
+        let retried = tokio::time::timeout(RETRY_DELAY * 10, atomically(|| pool.next())).await;
+        assert!(retried.is_ok());
+        tokio::time::timeout(RETRY_DELAY * 10, async {
+            while stats.snapshot(RETRY_DELAY, true).queued > 0 {
+                tokio::time::sleep(RETRY_DELAY).await;
+            }
+        })
+        .await
+        .expect("the retried task should no longer be queued");
+    }
+}
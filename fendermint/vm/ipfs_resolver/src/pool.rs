@@ -21,11 +21,15 @@ pub type ResolveKey = Cid;
 #[derive(Clone)]
 pub struct ResolveStatus<T> {
     /// Indicate whether the content has been resolved.
-    ///
-    /// If needed we can expand on this to include failure states.
     is_resolved: TVar<bool>,
+    /// Indicate whether the content was found to be invalid, e.g. its size
+    /// did not match the declared one. Failed items are collected apart from resolved ones,
+    /// to be marked failed on chain.
+    is_failed: TVar<bool>,
     /// The collection of items that all resolve to the same root CID and subnet.
     items: TVar<im::HashSet<T>>,
+    /// Size of the content in bytes, as declared by the sender, if known.
+    size: Option<u64>,
 }
 
 impl<T> ResolveStatus<T>
@@ -33,17 +37,27 @@ where
     T: Clone + Hash + Eq + PartialEq + Sync + Send + 'static,
 {
     pub fn new(item: T) -> Self {
+        Self::new_with_size(item, None)
+    }
+
+    pub fn new_with_size(item: T, size: Option<u64>) -> Self {
         let mut items = im::HashSet::new();
         items.insert(item);
         Self {
             is_resolved: TVar::new(false),
+            is_failed: TVar::new(false),
             items: TVar::new(items),
+            size,
         }
     }
 
     pub fn is_resolved(&self) -> Stm<bool> {
         self.is_resolved.read_clone()
     }
+
+    pub fn is_failed(&self) -> Stm<bool> {
+        self.is_failed.read_clone()
+    }
 }
 
 /// Tasks emitted by the pool for background resolution.
@@ -51,8 +65,12 @@ where
 pub struct ResolveTask {
     /// Content to resolve.
     key: ResolveKey,
+    /// Size of the content in bytes, as declared by the sender, if known.
+    size: Option<u64>,
     /// Flag to flip when the task is done.
     is_resolved: TVar<bool>,
+    /// Flag to flip when the content turns out to be invalid.
+    is_failed: TVar<bool>,
 }
 
 impl ResolveTask {
@@ -60,9 +78,17 @@ impl ResolveTask {
         self.key
     }
 
+    pub fn size(&self) -> Option<u64> {
+        self.size
+    }
+
     pub fn set_resolved(&self) -> Stm<()> {
         self.is_resolved.write(true)
     }
+
+    pub fn set_failed(&self) -> Stm<()> {
+        self.is_failed.write(true)
+    }
 }
 
 pub type ResolveQueue = TChan<ResolveTask>;
@@ -114,6 +140,13 @@ where
     ///
     /// If the item is new, enqueue it from background resolution, otherwise just return its existing status.
     pub fn add(&self, item: T) -> Stm<ResolveStatus<T>> {
+        self.add_with_size(item, None)
+    }
+
+    /// Add an item to the resolution targets along with the size of its content, as declared by the sender.
+    ///
+    /// The resolver checks the declared size against the resolved content, and fails the task on mismatch.
+    pub fn add_with_size(&self, item: T, size: Option<u64>) -> Stm<ResolveStatus<T>> {
         let key = ResolveKey::from(&item);
         let mut items = self.items.read_clone()?;
 
@@ -124,12 +157,14 @@ where
             })?;
             Ok(status)
         } else {
-            let status = ResolveStatus::new(item);
+            let status = ResolveStatus::new_with_size(item, size);
             items.insert(key, status.clone());
             self.items.write(items)?;
             self.queue.write(ResolveTask {
                 key,
+                size,
                 is_resolved: status.is_resolved.clone(),
+                is_failed: status.is_failed.clone(),
             })?;
             Ok(status)
         }
@@ -161,6 +196,46 @@ where
         Ok(resolved)
     }
 
+    /// Collect failed items, ready to be marked failed on chain.
+    ///
+    /// Like resolved items, they are not removed until they are.
+    pub fn collect_failed(&self) -> Stm<HashSet<T>> {
+        let mut failed = HashSet::new();
+        let items = self.items.read()?;
+        for item in items.values() {
+            if item.is_failed()? {
+                let items = item.items.read()?;
+                failed.extend(items.iter().cloned());
+            }
+        }
+        Ok(failed)
+    }
+
+    /// Collect the items which have not failed, along with their declared size.
+    ///
+    /// These are the items still waiting for resolution or execution; the pool only lives
+    /// in memory, so they can be persisted and added back with [`ResolvePool::add_pending`]
+    /// after a restart, to get them resolved and voted on again.
+    pub fn pending(&self) -> Stm<Vec<(T, Option<u64>)>> {
+        let mut pending = Vec::new();
+        let items = self.items.read()?;
+        for status in items.values() {
+            if !status.is_failed()? {
+                let items = status.items.read()?;
+                pending.extend(items.iter().cloned().map(|item| (item, status.size)));
+            }
+        }
+        Ok(pending)
+    }
+
+    /// Add items collected with [`ResolvePool::pending`], enqueueing them for resolution.
+    pub fn add_pending(&self, pending: Vec<(T, Option<u64>)>) -> Stm<()> {
+        for (item, size) in pending {
+            self.add_with_size(item, size)?;
+        }
+        Ok(())
+    }
+
     /// Await the next item to be resolved.
     pub fn next(&self) -> Stm<ResolveTask> {
         self.queue.read()
@@ -178,6 +253,8 @@ where
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
     use async_stm::{atomically, queues::TQueueLike};
     use cid::Cid;
 
@@ -291,4 +368,84 @@ mod tests {
         })
         .await;
     }
+
+    #[tokio::test]
+    async fn restore_pending() {
+        let pool = ResolvePool::new();
+        let resolved = TestItem {
+            cid: Cid::default(),
+        };
+        let unresolved = TestItem {
+            cid: Cid::new_v1(0x55, resolved.cid.hash().to_owned()),
+        };
+
+        // Resolve one item and leave the other in the queue, as if the node stopped mid-way.
+        atomically(|| {
+            pool.add_with_size(resolved.clone(), Some(1))?;
+            pool.add_with_size(unresolved.clone(), Some(2))?;
+            let task = pool.queue.read()?;
+            task.set_resolved()
+        })
+        .await;
+
+        let pending = atomically(|| pool.pending()).await;
+        assert_eq!(pending.len(), 2);
+
+        // Start over with an empty pool, the way the node would after a restart.
+        let pool = ResolvePool::new();
+        atomically(|| pool.add_pending(pending.clone())).await;
+
+        atomically(|| {
+            let mut sizes = HashMap::new();
+            while !pool.queue.is_empty()? {
+                let task = pool.queue.read()?;
+                sizes.insert(task.key, task.size);
+            }
+            assert_eq!(sizes.len(), 2);
+            assert_eq!(sizes.get(&resolved.cid), Some(&Some(1)));
+            assert_eq!(sizes.get(&unresolved.cid), Some(&Some(2)));
+            assert!(pool.get_status(&unresolved)?.is_some());
+            Ok(())
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn pending_skips_failed() {
+        let pool = ResolvePool::new();
+        let item = TestItem::dummy();
+
+        atomically(|| {
+            pool.add(item.clone())?;
+            let task = pool.queue.read()?;
+            task.set_failed()?;
+            assert!(pool.pending()?.is_empty());
+            Ok(())
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn collect_failed() {
+        let pool = ResolvePool::new();
+        let item = TestItem::dummy();
+
+        atomically(|| {
+            pool.add(item.clone())?;
+            assert!(pool.collect_failed()?.is_empty());
+            let task = pool.queue.read()?;
+            task.set_failed()?;
+
+            let failed = pool.collect_failed()?;
+            assert!(failed.contains(&item));
+            assert!(pool.collect_resolved()?.is_empty());
+
+            // Failed items stay in the pool until they are removed once marked failed on chain.
+            assert_eq!(pool.count()?, 1);
+            pool.remove(&item)?;
+            assert!(pool.collect_failed()?.is_empty());
+            Ok(())
+        })
+        .await;
+    }
 }
@@ -32,6 +32,10 @@ pub enum IpcMessage {
 
     /// Proposed by validators when an object accompanying a message has been resolved and is ready to be executed.
     ObjectResolved(Object),
+
+    /// Proposed by validators when the content of an object accompanying a message was found invalid,
+    /// e.g. of another size than declared, so that it's marked failed rather than resolved.
+    ObjectFailed(Object),
 }
 
 /// A message relayed by a user on the current subnet.
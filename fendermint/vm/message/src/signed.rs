@@ -3,6 +3,7 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use anyhow::anyhow;
+use bls_signatures::Serialize;
 use cid::multihash::MultihashDigest;
 use cid::Cid;
 use ethers_core::types as et;
@@ -121,6 +122,31 @@ impl SignedMessage {
         })
     }
 
+    /// Create a signed message from a BLS secret key, for an `f3` sender.
+    ///
+    /// Delegated senders can only sign with secp256k1 keys, so they are rejected.
+    pub fn new_bls(
+        message: Message,
+        object: Option<Object>,
+        sk: &bls_signatures::PrivateKey,
+        chain_id: &ChainID,
+    ) -> Result<Self, SignedMessageError> {
+        let signature = match Self::signable(&message, &object, chain_id)? {
+            Signable::Regular(data) => sign_bls(sk, &data),
+            Signable::Ethereum(_) | Signable::RegularFromEth(_) => {
+                return Err(SignedMessageError::InvalidSignature(format!(
+                    "BLS keys cannot sign for the delegated address {}",
+                    message.from
+                )))
+            }
+        };
+        Ok(Self {
+            message,
+            object,
+            signature,
+        })
+    }
+
     /// Calculate the CID of an FVM message.
     pub fn cid(message: &Message) -> Result<Cid, fvm_ipld_encoding::Error> {
         crate::cid(message)
@@ -300,6 +326,12 @@ fn sign_eth(sk: &SecretKey, hash: et::H256) -> Signature {
     sign_secp256k1(sk, &hash.0)
 }
 
+/// Sign a transaction pre-image with a BLS key. Unlike secp256k1, BLS signs the data itself,
+/// which is what [Signature::verify] expects.
+fn sign_bls(sk: &bls_signatures::PrivateKey, data: &[u8]) -> Signature {
+    Signature::new_bls(sk.sign(data).as_bytes())
+}
+
 /// Turn a [`ChainID`] into bytes. Uses big-endian encoding.
 pub fn chain_id_bytes(chain_id: &ChainID) -> [u8; 8] {
     u64::from(*chain_id).to_be_bytes()
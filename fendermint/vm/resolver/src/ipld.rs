@@ -62,7 +62,8 @@ where
 }
 
 /// Run task resolution in the background, so as not to block items from other
-/// subnets being tried.
+/// subnets being tried. The queue takes turns between subnets, so a backlog in
+/// one subnet doesn't hold up the tasks of the others.
 fn start_resolve<C>(
     task: ResolveTask,
     client: C,
@@ -83,6 +84,7 @@ fn start_resolve<C>(
                 tracing::error!(error = e.to_string(), "failed to submit resolution task");
                 // The service is no longer listening, we might as well stop taking new tasks from the queue.
                 // By not quitting we should see this error every time there is a new task, which is at least is a constant reminder.
+                atomically(|| queue.done(&task)).await;
                 return;
             }
             (Ok(Ok(())), _) | (_, Some(Ok(Ok(())))) => None,
@@ -92,7 +94,11 @@ fn start_resolve<C>(
         match err {
             None => {
                 tracing::debug!(cid = ?task.cid(), "content resolved");
-                atomically(|| task.set_resolved()).await;
+                atomically(|| {
+                    task.set_resolved()?;
+                    queue.done(&task)
+                })
+                .await;
             }
             Some(e) => {
                 tracing::error!(
@@ -100,6 +106,7 @@ fn start_resolve<C>(
                     error = e.to_string(),
                     "content resolution failed; retrying later"
                 );
+                atomically(|| queue.done(&task)).await;
                 schedule_retry(task, queue, retry_delay);
             }
         }
@@ -3,10 +3,7 @@
 
 use std::{collections::HashSet, hash::Hash};
 
-use async_stm::{
-    queues::{tchan::TChan, TQueueLike},
-    Stm, TVar,
-};
+use async_stm::{queues::TQueueLike, retry, Stm, TVar};
 use cid::Cid;
 use ipc_api::subnet_id::SubnetID;
 
@@ -79,7 +76,106 @@ impl ResolveTask {
     }
 }
 
-pub type ResolveQueue = TChan<ResolveTask>;
+/// Queue of resolution tasks, served round-robin across their source subnets,
+/// so that a backlog of tasks from one subnet doesn't hold up the others.
+#[derive(Clone, Default)]
+pub struct ResolveQueue {
+    /// Tasks waiting to be resolved, per source subnet.
+    tasks: TVar<im::HashMap<SubnetID, im::Vector<ResolveTask>>>,
+    /// Subnets with waiting tasks, in the order they are served.
+    turns: TVar<im::Vector<SubnetID>>,
+    /// Number of tasks read from each subnet and not done yet.
+    in_flight: TVar<im::HashMap<SubnetID, usize>>,
+    /// Maximum number of tasks of a single subnet resolved at the same time, if limited.
+    max_in_flight: Option<usize>,
+}
+
+impl ResolveQueue {
+    pub fn new(max_in_flight: Option<usize>) -> Self {
+        Self {
+            max_in_flight,
+            ..Default::default()
+        }
+    }
+
+    /// Mark a task read from the queue as done, making room for the next task of its subnet.
+    ///
+    /// Must be called once for every task read, whatever the outcome, if the number of tasks
+    /// in flight is limited.
+    pub fn done(&self, task: &ResolveTask) -> Stm<()> {
+        if self.max_in_flight.is_none() {
+            return Ok(());
+        }
+        self.in_flight.update_mut(|in_flight| {
+            if let Some(count) = in_flight.get_mut(&task.key.0) {
+                *count = count.saturating_sub(1);
+                if *count == 0 {
+                    in_flight.remove(&task.key.0);
+                }
+            }
+        })
+    }
+
+    /// Whether another task of the subnet can be resolved.
+    fn has_capacity(&self, in_flight: &im::HashMap<SubnetID, usize>, subnet_id: &SubnetID) -> bool {
+        match self.max_in_flight {
+            Some(max) => in_flight.get(subnet_id).copied().unwrap_or_default() < max,
+            None => true,
+        }
+    }
+}
+
+impl TQueueLike<ResolveTask> for ResolveQueue {
+    fn write(&self, task: ResolveTask) -> Stm<()> {
+        let subnet_id = task.key.0.clone();
+        let mut tasks = self.tasks.read_clone()?;
+        let queue = tasks
+            .entry(subnet_id.clone())
+            .or_insert_with(im::Vector::new);
+        if queue.is_empty() {
+            self.turns.update_mut(|turns| turns.push_back(subnet_id))?;
+        }
+        queue.push_back(task);
+        self.tasks.write(tasks)
+    }
+
+    /// Take the next task of the first subnet in turn with capacity to spare,
+    /// which then goes to the back of the line.
+    fn read(&self) -> Stm<ResolveTask> {
+        let mut in_flight = self.in_flight.read_clone()?;
+        let mut turns = self.turns.read_clone()?;
+        let Some(turn) = turns
+            .iter()
+            .position(|subnet_id| self.has_capacity(&in_flight, subnet_id))
+        else {
+            return retry();
+        };
+        let subnet_id = turns.remove(turn);
+
+        let mut tasks = self.tasks.read_clone()?;
+        let queue = tasks
+            .get_mut(&subnet_id)
+            .expect("subnets in turn have tasks");
+        let task = queue.pop_front().expect("subnets in turn have tasks");
+        if queue.is_empty() {
+            tasks.remove(&subnet_id);
+        } else {
+            turns.push_back(subnet_id.clone());
+        }
+        self.tasks.write(tasks)?;
+        self.turns.write(turns)?;
+
+        if self.max_in_flight.is_some() {
+            *in_flight.entry(subnet_id).or_insert(0) += 1;
+            self.in_flight.write(in_flight)?;
+        }
+        Ok(task)
+    }
+
+    fn is_empty(&self) -> Stm<bool> {
+        Ok(self.turns.read()?.is_empty())
+    }
+}
 
 /// A data structure used to communicate resolution requirements and outcomes
 /// between the resolver running in the background and the application waiting
@@ -117,6 +213,12 @@ where
         }
     }
 
+    /// Limit the number of tasks of a single source subnet being resolved at the same time.
+    pub fn with_max_in_flight(mut self, max_in_flight: Option<usize>) -> Self {
+        self.queue = ResolveQueue::new(max_in_flight);
+        self
+    }
+
     /// Queue to consume for task items.
     ///
     /// Exposed as-is to allow re-queueing items.
@@ -182,6 +284,8 @@ where
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use async_stm::{atomically, queues::TQueueLike};
     use cid::Cid;
     use ipc_api::subnet_id::SubnetID;
@@ -299,4 +403,79 @@ mod tests {
         })
         .await;
     }
+
+    /// An item of the given subnet, told apart from others by `n`.
+    fn numbered_item(root_id: u64, n: u64) -> TestItem {
+        TestItem {
+            subnet_id: SubnetID::new_root(root_id),
+            cid: Cid::new_v1(n, Cid::default().hash().to_owned()),
+        }
+    }
+
+    #[tokio::test]
+    async fn subnets_interleaved() {
+        let pool = ResolvePool::new();
+
+        // One subnet has a backlog before the other subnet's tasks come in.
+        atomically(|| {
+            for n in 0..3 {
+                pool.add(numbered_item(1, n), false)?;
+            }
+            for n in 0..2 {
+                pool.add(numbered_item(2, n), false)?;
+            }
+            Ok(())
+        })
+        .await;
+
+        let order = atomically(|| {
+            let mut order = Vec::new();
+            while !pool.queue.is_empty()? {
+                let task = pool.next()?;
+                order.push((task.subnet_id(), task.cid().codec()));
+            }
+            Ok(order)
+        })
+        .await;
+
+        let (a, b) = (SubnetID::new_root(1), SubnetID::new_root(2));
+        assert_eq!(
+            order,
+            vec![
+                (a.clone(), 0),
+                (b.clone(), 0),
+                (a.clone(), 1),
+                (b, 1),
+                (a, 2)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn subnet_max_in_flight() {
+        let pool = ResolvePool::new().with_max_in_flight(Some(1));
+
+        atomically(|| {
+            pool.add(numbered_item(1, 0), false)?;
+            pool.add(numbered_item(1, 1), false)?;
+            pool.add(numbered_item(2, 0), false)?;
+            Ok(())
+        })
+        .await;
+
+        let first = atomically(|| pool.next()).await;
+        let second = atomically(|| pool.next()).await;
+        assert_eq!(first.subnet_id(), SubnetID::new_root(1));
+        assert_eq!(second.subnet_id(), SubnetID::new_root(2));
+
+        // Both subnets are at capacity, even though the first one has a task waiting.
+        let blocked =
+            tokio::time::timeout(Duration::from_millis(50), atomically(|| pool.next())).await;
+        assert!(blocked.is_err());
+
+        atomically(|| pool.queue.done(&first)).await;
+        let third = atomically(|| pool.next()).await;
+        assert_eq!(third.subnet_id(), SubnetID::new_root(1));
+        assert_eq!(third.cid().codec(), 1);
+    }
 }
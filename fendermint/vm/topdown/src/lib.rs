@@ -141,18 +141,45 @@ impl Display for IPCParentFinality {
 pub struct IPCObjectFinality {
     /// The Cid of the object
     pub object: Cid,
+    /// Whether the content was found invalid rather than resolved, e.g. its size didn't
+    /// match the declared one.
+    #[serde(default)]
+    pub failed: bool,
 }
 
 impl IPCObjectFinality {
     pub fn new(value: Cid) -> Self {
-        Self { object: value }
+        Self {
+            object: value,
+            failed: false,
+        }
+    }
+
+    /// The key the vote is tallied under, see [`object_vote_key`].
+    pub fn vote_key(&self) -> Object {
+        object_vote_key(&self.object, self.failed)
     }
 }
 
 impl Display for IPCObjectFinality {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "IPCObjectFinality(value: {})", self.object)
+        write!(
+            f,
+            "IPCObjectFinality(value: {}, failed: {})",
+            self.object, self.failed
+        )
+    }
+}
+
+/// The key votes for an object are tallied under. Votes for content found invalid are
+/// tallied apart from votes for resolved content, by appending a byte to the CID. CIDs are
+/// self-delimiting, so that can't make the key of another CID.
+pub fn object_vote_key(object: &Cid, failed: bool) -> Object {
+    let mut key = object.to_bytes();
+    if failed {
+        key.push(0);
     }
+    key
 }
 
 #[async_trait]
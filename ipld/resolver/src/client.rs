@@ -8,7 +8,7 @@ use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::oneshot;
 
 use crate::{
-    service::{Request, ResolveResult},
+    service::{HasResult, Request, ResolveResult, StatResult},
     vote_record::SignedVoteRecord,
 };
 
@@ -121,6 +121,12 @@ pub trait ResolverIpfs {
     ///
     /// Upon success, the data should be pinned in the local IPFS node.
     async fn resolve_ipfs(&self, cid: Cid) -> anyhow::Result<ResolveResult>;
+
+    /// Look up the size in bytes of content in the local IPFS node.
+    async fn stat_ipfs(&self, cid: Cid) -> anyhow::Result<StatResult>;
+
+    /// Check whether content is still pinned in the local IPFS node.
+    async fn has_ipfs(&self, cid: Cid) -> anyhow::Result<HasResult>;
 }
 
 #[async_trait]
@@ -139,4 +145,22 @@ where
         let res = rx.await?;
         Ok(res)
     }
+
+    /// Look up the size in bytes of content in the local IPFS node.
+    async fn stat_ipfs(&self, cid: Cid) -> anyhow::Result<StatResult> {
+        let (tx, rx) = oneshot::channel();
+        let req = Request::StatIpfs(cid, tx);
+        self.send_request(req)?;
+        let res = rx.await?;
+        Ok(res)
+    }
+
+    /// Check whether content is still pinned in the local IPFS node.
+    async fn has_ipfs(&self, cid: Cid) -> anyhow::Result<HasResult> {
+        let (tx, rx) = oneshot::channel();
+        let req = Request::HasIpfs(cid, tx);
+        self.send_request(req)?;
+        let res = rx.await?;
+        Ok(res)
+    }
 }
@@ -21,6 +21,6 @@ pub mod missing_blocks;
 
 pub use behaviour::{ContentConfig, DiscoveryConfig, MembershipConfig, NetworkConfig};
 pub use client::{Client, Resolver, ResolverIpfs};
-pub use service::{Config, ConnectionConfig, Event, NoKnownPeers, Service};
+pub use service::{Config, ConnectionConfig, Event, HasResult, NoKnownPeers, Service, StatResult};
 pub use timestamp::Timestamp;
-pub use vote_record::{ValidatorKey, VoteRecord};
+pub use vote_record::{SignedVoteRecord, ValidatorKey, VoteRecord};
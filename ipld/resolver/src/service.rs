@@ -40,6 +40,12 @@ use crate::vote_record::{SignedVoteRecord, VoteRecord};
 /// Result of attempting to resolve a CID.
 pub type ResolveResult = anyhow::Result<()>;
 
+/// Result of querying the size of content in the local IPFS node.
+pub type StatResult = anyhow::Result<u64>;
+
+/// Result of checking whether content is pinned in the local IPFS node.
+pub type HasResult = anyhow::Result<bool>;
+
 /// Channel to complete the results with.
 type ResponseChannel = oneshot::Sender<ResolveResult>;
 
@@ -108,6 +114,8 @@ pub(crate) enum Request<V> {
     UnpinSubnet(SubnetID),
     Resolve(Cid, SubnetID, ResponseChannel),
     ResolveIpfs(Cid, ResponseChannel),
+    StatIpfs(Cid, oneshot::Sender<StatResult>),
+    HasIpfs(Cid, oneshot::Sender<HasResult>),
     RateLimitUsed(PeerId, usize),
     UpdateRateLimit(u32),
 }
@@ -484,6 +492,8 @@ where
             Request::ResolveIpfs(cid, response_channel) => {
                 self.start_ipfs_query(cid, response_channel)
             }
+            Request::StatIpfs(cid, response_channel) => self.start_ipfs_stat(cid, response_channel),
+            Request::HasIpfs(cid, response_channel) => self.start_ipfs_has(cid, response_channel),
             Request::RateLimitUsed(peer_id, bytes) => {
                 self.content_mut().rate_limit_used(peer_id, bytes)
             }
@@ -539,6 +549,36 @@ where
         });
     }
 
+    /// Look up the byte size of content in the local IPFS node.
+    fn start_ipfs_stat(&mut self, cid: Cid, response_channel: oneshot::Sender<StatResult>) {
+        let ipfs = self.ipfs_client.clone();
+        tokio::spawn(async move {
+            let res = ipfs
+                .files_stat(format!("/ipfs/{cid}").as_str())
+                .await
+                .map(|stat| stat.size)
+                .map_err(|e| anyhow!(e));
+            if response_channel.send(res).is_err() {
+                error!("error sending stat result; listener closed")
+            }
+        });
+    }
+
+    /// Check whether content is still pinned in the local IPFS node.
+    fn start_ipfs_has(&mut self, cid: Cid, response_channel: oneshot::Sender<HasResult>) {
+        let ipfs = self.ipfs_client.clone();
+        tokio::spawn(async move {
+            let res = match ipfs.pin_ls(Some(&cid.to_string()), None).await {
+                Ok(_) => Ok(true),
+                Err(e) if e.to_string().contains("not pinned") => Ok(false),
+                Err(e) => Err(anyhow!(e)),
+            };
+            if response_channel.send(res).is_err() {
+                error!("error sending has result; listener closed")
+            }
+        });
+    }
+
     /// Handle the results from a resolve attempt. If it succeeded, notify the
     /// listener. Otherwise if we have fallback peers to try, start another
     /// query and send the result to them. By default these are the peers